@@ -1,7 +1,5 @@
-/**
- * Integration test for dogbox.moe
- * Tests file uploads, post uploads, appending, and markdown support
- */
+//! Integration test for dogbox.moe
+//! Tests file uploads, post uploads, appending, and markdown support
 
 use reqwest::multipart;
 use serde_json::json;
@@ -248,7 +246,7 @@ async fn test_post_append(base_url: &str) -> Result<(), Box<dyn Error>> {
     println!("  ✅ Initial post created: {}", post_id);
 
     // Append multiple entries
-    let append_contents = vec![
+    let append_contents = [
         "Second entry - appended!",
         "Third entry - another append!",
         "Fourth entry - final append!",
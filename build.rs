@@ -0,0 +1,12 @@
+//! Generates the gRPC service (src/grpc.rs) from proto/dogbox.proto
+//!
+//! protox compiles the schema in Rust, so building doesn't need `protoc`.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto");
+    let descriptors = protox::compile(["dogbox.proto"], ["proto"])?;
+    tonic_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)?;
+    Ok(())
+}
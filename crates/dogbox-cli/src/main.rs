@@ -0,0 +1,335 @@
+//! `dogbox-cli`: upload, download, paste and append from the command line
+//!
+//! Everything is encrypted and decrypted locally with `dogbox-client`, so share
+//! links work in the browser as usual. Deletion tokens and append keys of
+//! uploads made here are kept in a local store (see `store.rs`).
+
+mod store;
+mod sync;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use dogbox_client::{AppendContent, Client, PostContentType, PostType, ShareLink, UploadOptions, Uploaded};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use store::{Store, StoredUpload};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[derive(Debug, Parser)]
+#[command(name = "dogbox-cli", version, about = "End-to-end encrypted uploads to dogbox.moe")]
+struct Cli {
+    /// Instance to upload to
+    #[arg(long, global = true, env = "DOGBOX_SERVER", default_value = "https://dogbox.moe")]
+    server: String,
+
+    /// Trusted API key (issued by the instance's admin)
+    #[arg(long, global = true, env = "DOGBOX_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Where deletion tokens and append keys are kept
+    #[arg(long, global = true)]
+    store: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Encrypt and upload files, printing a share link for each
+    Upload {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[command(flatten)]
+        options: UploadArgs,
+    },
+    /// Download and decrypt a share link
+    Download {
+        link: String,
+        /// Output file (`-` for stdout); defaults to `<id><ext>`, or the
+        /// current directory for a post's attachments
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Post text (or stdin) as an appendable markdown post
+    Paste {
+        /// Text to post; read from stdin when omitted
+        text: Option<String>,
+        #[command(flatten)]
+        options: UploadArgs,
+    },
+    /// Append text (or stdin) or a file to a post
+    Append {
+        link: String,
+        /// Markdown to append; read from stdin when neither this nor --file is given
+        text: Option<String>,
+        /// Append a file attachment instead of text
+        #[arg(long, conflicts_with = "text")]
+        file: Option<PathBuf>,
+        /// Append key, if the post wasn't created from this machine
+        #[arg(long, env = "DOGBOX_APPEND_KEY", hide_env_values = true)]
+        append_key: Option<String>,
+    },
+    /// Delete an upload made from this machine (or pass its --token)
+    Delete {
+        /// Share link or file ID
+        target: String,
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// List uploads in the local store
+    List,
+    /// Upload new and changed files in a directory, then keep watching it
+    Sync {
+        dir: PathBuf,
+        /// Seconds between scans; a file is uploaded once it stops changing for one
+        #[arg(long, default_value_t = 2, value_name = "SECONDS")]
+        interval: u64,
+        /// Scan once and exit instead of watching
+        #[arg(long)]
+        once: bool,
+        /// Where the directory's uploads are tracked; defaults to `sync/` next to the store
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        /// Delete the previous upload of a file that changed
+        #[arg(long)]
+        delete_replaced: bool,
+        #[command(flatten)]
+        options: UploadArgs,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+struct UploadArgs {
+    /// Hours until the upload expires (instance default if omitted)
+    #[arg(long)]
+    expiry_hours: Option<i64>,
+    /// Never expire (if the instance allows it)
+    #[arg(long)]
+    permanent: bool,
+    /// Delete after this many downloads
+    #[arg(long)]
+    max_downloads: Option<i64>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut client = Client::new(&cli.server)?;
+    if let Some(api_key) = &cli.api_key {
+        client = client.with_api_key(api_key);
+    }
+    let store_path = match cli.store {
+        Some(path) => path,
+        None => Store::default_path()?,
+    };
+
+    match cli.command {
+        Command::Upload { files, options } => {
+            for path in files {
+                let data = tokio::fs::read(&path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let upload_options = UploadOptions {
+                    mime_type: Some(mime_guess::from_path(&path).first_or_octet_stream().to_string()),
+                    file_extension: path.extension().map(|ext| format!(".{}", ext.to_string_lossy())),
+                    ..options.to_upload_options(PostType::File)
+                };
+                let uploaded = client.upload(&data, upload_options).await?;
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                remember(&store_path, &uploaded, name).await?;
+                println!("{}", uploaded.link);
+            }
+        }
+        Command::Download { link, output } => {
+            let link = ShareLink::parse(&link)?;
+            match link.post_type {
+                PostType::File => download_file(&client, &link, output).await?,
+                PostType::Post => download_post(&client, &link, output).await?,
+            }
+        }
+        Command::Paste { text, options } => {
+            let text = text_or_stdin(text).await?;
+            let upload_options = UploadOptions {
+                mime_type: Some("text/plain".to_string()),
+                file_extension: Some(".md".to_string()),
+                ..options.to_upload_options(PostType::Post)
+            };
+            let uploaded = client.upload(text.as_bytes(), upload_options).await?;
+            remember(&store_path, &uploaded, "paste".to_string()).await?;
+            if let Some(append_key) = &uploaded.response.post_append_key {
+                eprintln!("Append key: {}", append_key);
+            }
+            println!("{}", uploaded.link);
+        }
+        Command::Append {
+            link,
+            text,
+            file,
+            append_key,
+        } => {
+            let link = ShareLink::parse(&link)?;
+            let append_key = match append_key {
+                Some(key) => key,
+                None => Store::load(&store_path)
+                    .await?
+                    .find(&link.id)
+                    .and_then(|upload| upload.post_append_key.clone())
+                    .context("No append key stored for this post; pass --append-key")?,
+            };
+            let content = match file {
+                Some(path) => AppendContent::File {
+                    data: tokio::fs::read(&path)
+                        .await
+                        .with_context(|| format!("Failed to read {}", path.display()))?,
+                    mime_type: Some(mime_guess::from_path(&path).first_or_octet_stream().to_string()),
+                    file_extension: path.extension().map(|ext| format!(".{}", ext.to_string_lossy())),
+                },
+                None => AppendContent::Markdown(text_or_stdin(text).await?),
+            };
+            let order = client.append(&link, &append_key, content).await?;
+            eprintln!("Appended entry {} to {}", order, link.id);
+        }
+        Command::Delete { target, token } => {
+            // The last path segment of a link (with or without its #key), or the ID itself
+            let file_id = target
+                .split('#')
+                .next()
+                .and_then(|location| location.trim_end_matches('/').rsplit('/').next())
+                .unwrap_or_default()
+                .to_string();
+            let mut store = Store::load(&store_path).await?;
+            let token = match token {
+                Some(token) => token,
+                None => store
+                    .find(&file_id)
+                    .map(|upload| upload.deletion_token.clone())
+                    .context("No deletion token stored for this upload; pass --token")?,
+            };
+            client.delete(&file_id, &token).await?;
+            store.remove(&file_id);
+            store.save(&store_path).await?;
+            eprintln!("Deleted {}", file_id);
+        }
+        Command::List => {
+            let store = Store::load(&store_path).await?;
+            for upload in &store.uploads {
+                let expires = upload
+                    .expires_at
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "never".to_string());
+                println!("{}\t{}\texpires {}\t{}", upload.file_id, upload.name, expires, upload.link);
+            }
+        }
+        Command::Sync {
+            dir,
+            interval,
+            once,
+            manifest,
+            delete_replaced,
+            options,
+        } => {
+            let options = sync::SyncOptions {
+                interval: (!once).then(|| Duration::from_secs(interval.max(1))),
+                manifest,
+                delete_replaced,
+                upload: options,
+            };
+            sync::run(&client, &store_path, &dir, options).await?;
+        }
+    }
+    Ok(())
+}
+
+impl UploadArgs {
+    fn to_upload_options(&self, post_type: PostType) -> UploadOptions {
+        UploadOptions {
+            post_type,
+            expiry_hours: self.expiry_hours,
+            is_permanent: self.permanent,
+            max_downloads: self.max_downloads,
+            ..Default::default()
+        }
+    }
+}
+
+/// Record an upload's tokens and link in the local store
+async fn remember(store_path: &Path, uploaded: &Uploaded, name: String) -> Result<()> {
+    let mut store = Store::load(store_path).await?;
+    store.uploads.push(StoredUpload {
+        file_id: uploaded.response.file_id.clone(),
+        link: uploaded.link.to_string(),
+        deletion_token: uploaded.response.deletion_token.clone(),
+        post_append_key: uploaded.response.post_append_key.clone(),
+        name,
+        uploaded_at: chrono::Utc::now(),
+        expires_at: uploaded.response.expires_at,
+    });
+    store.save(store_path).await
+}
+
+async fn download_file(client: &Client, link: &ShareLink, output: Option<PathBuf>) -> Result<()> {
+    let file = client.download(link).await?;
+    let output = output.unwrap_or_else(|| PathBuf::from(local_name(&link.id, file.file_extension.as_deref())));
+    write_output(&output, &file.data).await
+}
+
+/// Print a post's markdown entries and save its attachments next to `output`
+async fn download_post(client: &Client, link: &ShareLink, output: Option<PathBuf>) -> Result<()> {
+    let post = client.view_post(link).await?;
+    let dir = output.unwrap_or_else(|| PathBuf::from("."));
+    let mut stdout = tokio::io::stdout();
+    for entry in post.entries {
+        match entry.content_type {
+            PostContentType::Markdown => {
+                stdout.write_all(&entry.data).await?;
+                stdout.write_all(b"\n\n").await?;
+            }
+            PostContentType::File => {
+                let name = local_name(&format!("{}-{}", post.post_id, entry.order), entry.file_extension.as_deref());
+                write_output(&dir.join(name), &entry.data).await?;
+            }
+        }
+    }
+    stdout.flush().await?;
+    Ok(())
+}
+
+/// `{stem}{extension}`, dropping anything in either that could leave the directory
+fn local_name(stem: &str, extension: Option<&str>) -> String {
+    let stem: String = stem.chars().filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')).collect();
+    let extension = extension
+        .and_then(|ext| ext.strip_prefix('.'))
+        .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|ext| format!(".{}", ext))
+        .unwrap_or_default();
+    format!("{}{}", stem, extension)
+}
+
+async fn write_output(path: &Path, data: &[u8]) -> Result<()> {
+    if path == Path::new("-") {
+        let mut stdout = tokio::io::stdout();
+        stdout.write_all(data).await?;
+        stdout.flush().await?;
+        return Ok(());
+    }
+    if tokio::fs::try_exists(path).await? {
+        bail!("{} already exists", path.display());
+    }
+    tokio::fs::write(path, data)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    eprintln!("Saved {}", path.display());
+    Ok(())
+}
+
+async fn text_or_stdin(text: Option<String>) -> Result<String> {
+    match text {
+        Some(text) => Ok(text),
+        None => {
+            let mut text = String::new();
+            tokio::io::stdin().read_to_string(&mut text).await?;
+            Ok(text)
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Local record of uploads, so they can be deleted or appended to later
+//!
+//! Kept as JSON in `$DOGBOX_STORE`, else `$XDG_DATA_HOME/dogbox/uploads.json`
+//! or `~/.local/share/dogbox/uploads.json`. It holds share links (with their
+//! keys), deletion tokens and append keys, so it is written owner-only.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredUpload {
+    pub file_id: String,
+    /// Share link including the `#key` fragment
+    pub link: String,
+    pub deletion_token: String,
+    pub post_append_key: Option<String>,
+    /// Local file name or `paste`, for `dogbox-cli list`
+    pub name: String,
+    pub uploaded_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Store {
+    pub uploads: Vec<StoredUpload>,
+}
+
+impl Store {
+    pub fn default_path() -> Result<PathBuf> {
+        if let Some(path) = std::env::var_os("DOGBOX_STORE") {
+            return Ok(PathBuf::from(path));
+        }
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME").context("HOME is not set; pass --store")?)
+                .join(".local/share"),
+        };
+        Ok(data_home.join("dogbox/uploads.json"))
+    }
+
+    /// Load the store, or an empty one if it doesn't exist yet
+    pub async fn load(path: &Path) -> Result<Self> {
+        load_json(path).await
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        save_json(self, path).await
+    }
+
+    pub fn find(&self, file_id: &str) -> Option<&StoredUpload> {
+        self.uploads.iter().find(|upload| upload.file_id == file_id)
+    }
+
+    pub fn remove(&mut self, file_id: &str) {
+        self.uploads.retain(|upload| upload.file_id != file_id);
+    }
+}
+
+/// Read a JSON file, or the default value if it doesn't exist yet
+pub async fn load_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).with_context(|| format!("Failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Write a JSON file readable only by the owner
+pub async fn save_json<T: Serialize>(value: &T, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    // Written to a temporary file first so an interrupted save can't truncate it
+    let tmp = path.with_extension("json.tmp");
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&tmp).await?;
+    file.write_all(&serde_json::to_vec_pretty(value)?).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
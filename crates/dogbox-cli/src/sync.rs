@@ -0,0 +1,239 @@
+//! `dogbox-cli sync`: keep a directory uploaded, e.g. a screenshot folder
+//!
+//! The directory is polled rather than watched with filesystem notifications,
+//! which behaves the same everywhere, network drives included. A file is only
+//! uploaded once its size and modification time have held still for a whole
+//! interval, so half-written files aren't sent. What was uploaded for each path
+//! is kept in a manifest, written owner-only like the store since it holds the
+//! share links' keys and the deletion tokens.
+
+use crate::store::{load_json, save_json, Store};
+use crate::{remember, UploadArgs};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use dogbox_client::{Client, PostType, UploadOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Suffixes of files that are still being written by editors and browsers
+const TEMPORARY_SUFFIXES: &[&str] = &["~", ".tmp", ".part", ".crdownload", ".swp"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncedFile {
+    pub file_id: String,
+    /// Share link including the `#key` fragment
+    pub link: String,
+    pub deletion_token: String,
+    pub size: u64,
+    /// Modification time in milliseconds since the epoch
+    pub modified: i64,
+    /// BLAKE3 of the plaintext, so files that were only touched aren't uploaded again
+    pub blake3: String,
+    pub uploaded_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub dir: PathBuf,
+    /// Keyed by path relative to `dir`, with `/` separators
+    pub files: BTreeMap<String, SyncedFile>,
+}
+
+impl Manifest {
+    /// `sync/<hash of the directory>.json` next to the store
+    pub fn default_path(store_path: &Path, dir: &Path) -> PathBuf {
+        let hash = blake3::hash(dir.as_os_str().as_encoded_bytes()).to_hex();
+        store_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join("sync")
+            .join(format!("{}.json", &hash[..16]))
+    }
+}
+
+pub struct SyncOptions {
+    /// `None` scans once, uploading everything that isn't up to date
+    pub interval: Option<Duration>,
+    pub manifest: Option<PathBuf>,
+    /// Delete the previous upload of a file that changed
+    pub delete_replaced: bool,
+    pub upload: UploadArgs,
+}
+
+/// A regular file found while scanning
+struct Found {
+    relative: String,
+    path: PathBuf,
+    size: u64,
+    modified: i64,
+}
+
+pub async fn run(client: &Client, store_path: &Path, dir: &Path, options: SyncOptions) -> Result<()> {
+    let dir = tokio::fs::canonicalize(dir)
+        .await
+        .with_context(|| format!("Failed to open {}", dir.display()))?;
+    let manifest_path = match &options.manifest {
+        Some(path) => path.clone(),
+        None => Manifest::default_path(store_path, &dir),
+    };
+    let mut manifest: Manifest = load_json(&manifest_path).await?;
+    manifest.dir = dir.clone();
+
+    // The manifest and store may live inside the directory; they are never uploaded
+    let mut skip = Vec::new();
+    for path in [manifest_path.as_path(), store_path] {
+        skip.extend(resolve(path).await);
+    }
+
+    // Size and modification time of changed files on the previous scan
+    let mut pending: HashMap<String, (u64, i64)> = HashMap::new();
+    loop {
+        let mut changed = HashMap::new();
+        for found in scan(&dir, &skip).await? {
+            let up_to_date = manifest
+                .files
+                .get(&found.relative)
+                .is_some_and(|synced| synced.size == found.size && synced.modified == found.modified);
+            if up_to_date {
+                continue;
+            }
+            let settled = pending.get(&found.relative) == Some(&(found.size, found.modified));
+            if options.interval.is_some() && !settled {
+                changed.insert(found.relative, (found.size, found.modified));
+                continue;
+            }
+            // Failures are retried on a later scan
+            if let Err(e) = sync_file(client, store_path, &mut manifest, &found, &options).await {
+                eprintln!("Failed to upload {}: {:#}", found.relative, e);
+                continue;
+            }
+            save_json(&manifest, &manifest_path).await?;
+        }
+        pending = changed;
+
+        match options.interval {
+            Some(interval) => tokio::time::sleep(interval).await,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Upload `found` unless its contents match what was last uploaded for it
+async fn sync_file(
+    client: &Client,
+    store_path: &Path,
+    manifest: &mut Manifest,
+    found: &Found,
+    options: &SyncOptions,
+) -> Result<()> {
+    let data = tokio::fs::read(&found.path)
+        .await
+        .with_context(|| format!("Failed to read {}", found.path.display()))?;
+    let hash = blake3::hash(&data).to_hex().to_string();
+    if let Some(synced) = manifest.files.get_mut(&found.relative).filter(|synced| synced.blake3 == hash) {
+        synced.size = found.size;
+        synced.modified = found.modified;
+        return Ok(());
+    }
+
+    let upload_options = UploadOptions {
+        mime_type: Some(mime_guess::from_path(&found.path).first_or_octet_stream().to_string()),
+        file_extension: found.path.extension().map(|ext| format!(".{}", ext.to_string_lossy())),
+        ..options.upload.to_upload_options(PostType::File)
+    };
+    let uploaded = client.upload(&data, upload_options).await?;
+    remember(store_path, &uploaded, found.relative.clone()).await?;
+    let previous = manifest.files.insert(
+        found.relative.clone(),
+        SyncedFile {
+            file_id: uploaded.response.file_id.clone(),
+            link: uploaded.link.to_string(),
+            deletion_token: uploaded.response.deletion_token.clone(),
+            size: found.size,
+            modified: found.modified,
+            blake3: hash,
+            uploaded_at: Utc::now(),
+            expires_at: uploaded.response.expires_at,
+        },
+    );
+    println!("{}\t{}", found.relative, uploaded.link);
+
+    if let Some(previous) = previous.filter(|_| options.delete_replaced) {
+        match client.delete(&previous.file_id, &previous.deletion_token).await {
+            Ok(()) => {
+                let mut store = Store::load(store_path).await?;
+                store.remove(&previous.file_id);
+                store.save(store_path).await?;
+            }
+            Err(e) => eprintln!("Failed to delete the previous upload of {}: {}", found.relative, e),
+        }
+    }
+    Ok(())
+}
+
+/// Regular files under `dir`, leaving out hidden and temporary ones and not following symlinks
+async fn scan(dir: &Path, skip: &[PathBuf]) -> Result<Vec<Found>> {
+    let mut found = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        let mut entries = match tokio::fs::read_dir(&current).await {
+            Ok(entries) => entries,
+            Err(e) if current == dir => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+            // A subdirectory removed or locked down since it was listed
+            Err(_) => continue,
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || TEMPORARY_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+                continue;
+            }
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type().await else {
+                continue;
+            };
+            if file_type.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if !file_type.is_file() || skip.contains(&path) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as i64)
+                .unwrap_or_default();
+            let relative = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            found.push(Found {
+                relative,
+                path,
+                size: metadata.len(),
+                modified,
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// `path` with its directory canonicalized, to compare against scanned paths
+async fn resolve(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(tokio::fs::canonicalize(parent).await.ok()?.join(path.file_name()?))
+}
@@ -0,0 +1,263 @@
+//! Client-side encryption, compatible with the web frontend (static/js/crypto.js)
+//!
+//! A fresh ML-KEM-1024 key pair is generated per upload and a shared secret is
+//! encapsulated to it; the shared secret is the AES-256-GCM key. The share link
+//! carries `DOGBOX_KEY_SYMMETRIC_<base64url(secret key || ciphertext)>` in its
+//! fragment, so whoever has the link can decapsulate the same AES key again.
+//!
+//! Blobs come in two layouts:
+//! - single: `[IV (12)][ciphertext + tag]`, used for post entries
+//! - chunked: `[magic "DOGC" (u32 LE)][chunk count (u32 LE)]` then per chunk
+//!   `[length (u32 LE)][IV (12)][ciphertext + tag]`, used for file uploads so
+//!   large files aren't one giant AES-GCM message
+
+use crate::error::{Error, Result};
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use kem::{Decapsulate, Encapsulate};
+use ml_kem::{EncodedSizeUser, KemCore, MlKem1024};
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+/// Prefix of exported keys (the part of a share link after `#`)
+pub const KEY_PREFIX: &str = "DOGBOX_KEY_SYMMETRIC_";
+
+/// Plaintext bytes per chunk of the chunked layout (64 MiB, as in the browser)
+pub const CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// "DOGC" (DOGbox Chunked), little-endian, at the start of chunked blobs
+pub const MAGIC_CHUNKED: u32 = 0x444F_4743;
+
+const KEM_SECRET_KEY_LEN: usize = 3168;
+const KEM_CIPHERTEXT_LEN: usize = 1568;
+const IV_LEN: usize = 12;
+
+type DecapsulationKey = <MlKem1024 as KemCore>::DecapsulationKey;
+
+/// Key for one upload: the ML-KEM material that goes into the share link and
+/// the AES-256-GCM key derived from it
+pub struct Key {
+    kem_secret_key: Zeroizing<Vec<u8>>,
+    kem_ciphertext: Vec<u8>,
+    aes_key: Zeroizing<[u8; 32]>,
+}
+
+impl Key {
+    /// Generate a fresh key (one per upload, like the web frontend)
+    pub fn generate() -> Self {
+        let mut rng = rand::thread_rng();
+        let (decapsulation_key, encapsulation_key) = MlKem1024::generate(&mut rng);
+        let (ciphertext, shared_secret) = encapsulation_key
+            .encapsulate(&mut rng)
+            .expect("ML-KEM encapsulation is infallible");
+
+        Self {
+            kem_secret_key: Zeroizing::new(decapsulation_key.as_bytes().to_vec()),
+            kem_ciphertext: ciphertext.to_vec(),
+            aes_key: Zeroizing::new(shared_secret.into()),
+        }
+    }
+
+    /// Parse an exported key, with or without the `DOGBOX_KEY_SYMMETRIC_` prefix
+    pub fn import(exported: &str) -> Result<Self> {
+        let encoded = exported.strip_prefix(KEY_PREFIX).unwrap_or(exported);
+        let combined = Zeroizing::new(
+            URL_SAFE_NO_PAD
+                .decode(encoded.trim_end_matches('='))
+                .map_err(|e| Error::InvalidKey(format!("not base64url: {}", e)))?,
+        );
+        if combined.len() != KEM_SECRET_KEY_LEN + KEM_CIPHERTEXT_LEN {
+            return Err(Error::InvalidKey(format!(
+                "expected {} bytes, got {}",
+                KEM_SECRET_KEY_LEN + KEM_CIPHERTEXT_LEN,
+                combined.len()
+            )));
+        }
+
+        let (secret_key, ciphertext) = combined.split_at(KEM_SECRET_KEY_LEN);
+        let decapsulation_key = DecapsulationKey::from_bytes(
+            secret_key.try_into().expect("length checked above"),
+        );
+        let shared_secret = decapsulation_key
+            .decapsulate(ciphertext.try_into().expect("length checked above"))
+            .expect("ML-KEM decapsulation is infallible");
+
+        Ok(Self {
+            kem_secret_key: Zeroizing::new(secret_key.to_vec()),
+            kem_ciphertext: ciphertext.to_vec(),
+            aes_key: Zeroizing::new(shared_secret.into()),
+        })
+    }
+
+    /// The key as it appears in share links: `DOGBOX_KEY_SYMMETRIC_<base64url>`
+    pub fn export(&self) -> String {
+        let mut combined = Zeroizing::new(Vec::with_capacity(KEM_SECRET_KEY_LEN + KEM_CIPHERTEXT_LEN));
+        combined.extend_from_slice(&self.kem_secret_key);
+        combined.extend_from_slice(&self.kem_ciphertext);
+        format!("{}{}", KEY_PREFIX, URL_SAFE_NO_PAD.encode(&combined))
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new_from_slice(self.aes_key.as_slice()).expect("AES-256 key is 32 bytes")
+    }
+
+    /// Encrypt into the single layout (`[IV][ciphertext]`), as post entries are
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        seal(&self.cipher(), plaintext)
+    }
+
+    /// Encrypt into the chunked (`DOGC`) layout, as file uploads are
+    pub fn encrypt_chunked(&self, plaintext: &[u8]) -> Vec<u8> {
+        let cipher = self.cipher();
+        let chunks: Vec<Vec<u8>> = if plaintext.is_empty() {
+            Vec::new()
+        } else {
+            plaintext.chunks(CHUNK_SIZE).map(|chunk| seal(&cipher, chunk)).collect()
+        };
+
+        let total: usize = chunks.iter().map(|chunk| 4 + chunk.len()).sum();
+        let mut blob = Vec::with_capacity(8 + total);
+        blob.extend_from_slice(&MAGIC_CHUNKED.to_le_bytes());
+        blob.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        for chunk in &chunks {
+            blob.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            blob.extend_from_slice(chunk);
+        }
+        blob
+    }
+
+    /// Decrypt a blob in either layout
+    pub fn decrypt(&self, blob: &[u8]) -> Result<Vec<u8>> {
+        let cipher = self.cipher();
+        if blob.len() < 8 || read_u32(blob, 0) != Some(MAGIC_CHUNKED) {
+            return open(&cipher, blob);
+        }
+
+        let count = read_u32(blob, 4).unwrap_or_default();
+        let mut offset = 8;
+        let mut plaintext = Vec::new();
+        for _ in 0..count {
+            let len = read_u32(blob, offset).ok_or(Error::Decryption)? as usize;
+            offset += 4;
+            let chunk = blob.get(offset..offset + len).ok_or(Error::Decryption)?;
+            plaintext.extend(open(&cipher, chunk)?);
+            offset += len;
+        }
+        Ok(plaintext)
+    }
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Vec<u8> {
+    let mut iv = [0u8; IV_LEN];
+    rand::thread_rng().fill_bytes(&mut iv);
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(iv), plaintext)
+        .expect("AES-GCM encryption of an in-memory buffer");
+
+    let mut sealed = Vec::with_capacity(IV_LEN + ciphertext.len());
+    sealed.extend_from_slice(&iv);
+    sealed.extend(ciphertext);
+    sealed
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < IV_LEN {
+        return Err(Error::Decryption);
+    }
+    let (iv, ciphertext) = sealed.split_at(IV_LEN);
+    let iv: [u8; IV_LEN] = iv.try_into().expect("split at IV_LEN");
+    cipher.decrypt(&Nonce::from(iv), ciphertext).map_err(|_| Error::Decryption)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"Encrypted in the browser by static/js/crypto.js\n";
+
+    #[test]
+    fn single_layout_round_trips() {
+        let key = Key::generate();
+        for plaintext in [&b""[..], b"x", PLAINTEXT] {
+            let blob = key.encrypt(plaintext);
+            assert_eq!(blob.len(), IV_LEN + plaintext.len() + 16);
+            assert_eq!(key.decrypt(&blob).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn chunked_layout_round_trips() {
+        let key = Key::generate();
+
+        let blob = key.encrypt_chunked(b"");
+        assert_eq!(blob, [&MAGIC_CHUNKED.to_le_bytes()[..], &0u32.to_le_bytes()].concat());
+        assert_eq!(key.decrypt(&blob).unwrap(), b"");
+
+        let blob = key.encrypt_chunked(PLAINTEXT);
+        assert_eq!(read_u32(&blob, 0), Some(MAGIC_CHUNKED));
+        assert_eq!(read_u32(&blob, 4), Some(1));
+        assert_eq!(read_u32(&blob, 8), Some((IV_LEN + PLAINTEXT.len() + 16) as u32));
+        assert_eq!(key.decrypt(&blob).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn exported_key_decrypts_after_import() {
+        let key = Key::generate();
+        let exported = key.export();
+        assert!(exported.starts_with(KEY_PREFIX));
+        let blob = key.encrypt_chunked(PLAINTEXT);
+
+        let imported = Key::import(&exported).unwrap();
+        assert_eq!(imported.export(), exported);
+        assert_eq!(imported.decrypt(&blob).unwrap(), PLAINTEXT);
+        // The prefix is optional
+        let bare = Key::import(exported.strip_prefix(KEY_PREFIX).unwrap()).unwrap();
+        assert_eq!(bare.decrypt(&blob).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn rejects_tampered_blobs_and_other_keys() {
+        let key = Key::generate();
+        let mut blob = key.encrypt_chunked(PLAINTEXT);
+        assert!(matches!(Key::generate().decrypt(&blob), Err(Error::Decryption)));
+
+        let last = blob.len() - 1;
+        blob[last] ^= 1;
+        assert!(matches!(key.decrypt(&blob), Err(Error::Decryption)));
+        // Chunk runs past the end of the blob
+        assert!(matches!(key.decrypt(&blob[..blob.len() - 4]), Err(Error::Decryption)));
+        assert!(matches!(key.decrypt(&[0; 4]), Err(Error::Decryption)));
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(matches!(Key::import("DOGBOX_KEY_SYMMETRIC_not*base64"), Err(Error::InvalidKey(_))));
+        assert!(matches!(Key::import("DOGBOX_KEY_SYMMETRIC_AAAA"), Err(Error::InvalidKey(_))));
+    }
+
+    /// A key exported and blobs encrypted by static/js/crypto.js (`exportKey`,
+    /// `encryptFileWithProgress` and `encryptFile`), run under Node
+    #[test]
+    fn decrypts_what_the_browser_encrypted() {
+        let exported = include_str!("../tests/fixtures/browser-key.txt");
+        let key = Key::import(exported.trim()).unwrap();
+
+        let file = include_bytes!("../tests/fixtures/browser-file.bin");
+        assert_eq!(key.decrypt(file).unwrap(), PLAINTEXT);
+        let post = include_bytes!("../tests/fixtures/browser-post.bin");
+        assert_eq!(key.decrypt(post).unwrap(), PLAINTEXT);
+        let empty = include_bytes!("../tests/fixtures/browser-empty.bin");
+        assert_eq!(key.decrypt(empty).unwrap(), b"");
+    }
+}
@@ -0,0 +1,29 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with an error status; `message` is its `error` field
+    #[error("Server returned {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Invalid share link: {0}")]
+    InvalidLink(String),
+
+    /// Wrong key, or the blob was tampered with
+    #[error("Decryption failed")]
+    Decryption,
+
+    #[error("Downloaded blob does not match its BLAKE3 hash (expected {expected}, got {actual})")]
+    HashMismatch { expected: String, actual: String },
+
+    #[error("Invalid post entry: {0}")]
+    InvalidEntry(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
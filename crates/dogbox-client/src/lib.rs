@@ -0,0 +1,268 @@
+//! Client for dogbox.moe instances
+//!
+//! Encrypts before uploading and decrypts after downloading, exactly like the
+//! web frontend (ML-KEM-1024 + AES-256-GCM, see [`crypto`]), so links made here
+//! open in the browser and the other way around. The server only ever sees
+//! ciphertext.
+//!
+//! ```no_run
+//! # async fn example() -> dogbox_client::Result<()> {
+//! use dogbox_client::{Client, ShareLink, UploadOptions};
+//!
+//! let client = Client::new("https://dogbox.moe")?;
+//! let uploaded = client.upload(b"hello", UploadOptions::default()).await?;
+//! println!("{}", uploaded.link);
+//!
+//! let link = ShareLink::parse(&uploaded.link.to_string())?;
+//! let file = client.download(&link).await?;
+//! assert_eq!(file.data, b"hello");
+//!
+//! client.delete(&uploaded.response.file_id, &uploaded.response.deletion_token).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod crypto;
+pub mod error;
+pub mod models;
+
+pub use crypto::Key;
+pub use error::{Error, Result};
+pub use models::{
+    AppendContent, Downloaded, Post, PostContentType, PostEntry, PostType, ShareLink, UploadOptions, UploadResponse,
+    Uploaded,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use models::{AppendRequest, AppendResponse, ErrorResponse, PostViewResponse};
+use reqwest::{header, multipart, RequestBuilder, Response};
+
+/// Header carrying a trusted API key (`POST /api/admin/api-keys`)
+const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Header carrying the BLAKE3 hash of a downloaded blob
+const BLAKE3_HEADER: &str = "X-Content-Blake3";
+
+/// A dogbox instance
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    http: reqwest::Client,
+    api_key: Option<String>,
+}
+
+impl Client {
+    /// Client for the instance at `base_url`, e.g. `https://dogbox.moe`
+    pub fn new(base_url: &str) -> Result<Self> {
+        // The server's CSRF check wants a custom header on state-changing requests
+        let mut headers = header::HeaderMap::new();
+        headers.insert("X-Requested-With", header::HeaderValue::from_static("dogbox-client"));
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("dogbox-client/", env!("CARGO_PKG_VERSION")))
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http,
+            api_key: None,
+        })
+    }
+
+    /// Send a trusted API key with every request (higher limits, no rate limiting)
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Encrypt `data` with a fresh key and upload it
+    ///
+    /// Files use the chunked layout and posts the single one, matching what the
+    /// browser's download and post pages decrypt.
+    pub async fn upload(&self, data: &[u8], options: UploadOptions) -> Result<Uploaded> {
+        let key = Key::generate();
+        let blob = match options.post_type {
+            PostType::File => key.encrypt_chunked(data),
+            PostType::Post => key.encrypt(data),
+        };
+
+        let mut form = multipart::Form::new()
+            .part("file", multipart::Part::bytes(blob).file_name("encrypted.bin"))
+            .text("post_type", options.post_type.as_str())
+            .text("is_permanent", options.is_permanent.to_string());
+        if let Some(mime_type) = options.mime_type {
+            form = form.text("mime_type", mime_type);
+        }
+        if let Some(file_extension) = options.file_extension {
+            form = form.text("file_extension", file_extension);
+        }
+        if let Some(expiry_hours) = options.expiry_hours.filter(|_| !options.is_permanent) {
+            form = form.text("expiry_hours", expiry_hours.to_string());
+        }
+        if let Some(max_downloads) = options.max_downloads {
+            form = form.text("max_downloads", max_downloads.to_string());
+        }
+        if let Some(slug) = options.slug {
+            form = form.text("slug", slug);
+        }
+
+        let response: UploadResponse = self
+            .send(self.http.post(self.url("/api/upload")).multipart(form))
+            .await?
+            .json()
+            .await?;
+        let link = ShareLink {
+            base_url: self.base_url.clone(),
+            post_type: response.post_type,
+            id: response.file_id.clone(),
+            key,
+        };
+        Ok(Uploaded { response, link })
+    }
+
+    /// Download a file, check its BLAKE3 hash and decrypt it
+    pub async fn download(&self, link: &ShareLink) -> Result<Downloaded> {
+        let response = self.send(self.http.get(self.url(&format!("/api/files/{}", link.id)))).await?;
+        let headers = response.headers().clone();
+        let blob = response.bytes().await?;
+
+        if let Some(expected) = headers.get(BLAKE3_HEADER).and_then(|v| v.to_str().ok()) {
+            let actual = blake3::hash(&blob).to_hex().to_string();
+            if actual != expected {
+                return Err(Error::HashMismatch {
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+
+        let mime_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        Ok(Downloaded {
+            data: link.key.decrypt(&blob)?,
+            mime_type,
+            file_extension: headers
+                .get(header::CONTENT_DISPOSITION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(disposition_extension),
+        })
+    }
+
+    /// Fetch a post and decrypt all of its entries
+    pub async fn view_post(&self, link: &ShareLink) -> Result<Post> {
+        let post: PostViewResponse = self
+            .send(self.http.get(self.url(&format!("/api/posts/{}", link.id))))
+            .await?
+            .json()
+            .await?;
+
+        let entries = post
+            .content
+            .into_iter()
+            .map(|entry| {
+                let blob = BASE64
+                    .decode(&entry.content_encrypted)
+                    .map_err(|e| Error::InvalidEntry(format!("entry {} is not base64: {}", entry.order, e)))?;
+                Ok(PostEntry {
+                    order: entry.order,
+                    appended_at: entry.appended_at,
+                    content_type: entry.content_type,
+                    mime_type: entry.mime_type,
+                    file_extension: entry.file_extension,
+                    data: link.key.decrypt(&blob)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Post {
+            post_id: post.post_id,
+            slug: post.slug,
+            is_locked: post.is_locked,
+            is_permanent: post.is_permanent,
+            uploaded_at: post.uploaded_at,
+            expires_at: post.expires_at,
+            entries,
+        })
+    }
+
+    /// Encrypt `content` with the post's key and append it; returns the entry's position
+    pub async fn append(&self, link: &ShareLink, append_key: &str, content: AppendContent) -> Result<i64> {
+        let request = match content {
+            AppendContent::Markdown(text) => AppendRequest {
+                append_key,
+                content: BASE64.encode(link.key.encrypt(text.as_bytes())),
+                content_type: PostContentType::Markdown,
+                mime_type: Some("text/plain".to_string()),
+                file_extension: None,
+                file_size: None,
+            },
+            AppendContent::File {
+                data,
+                mime_type,
+                file_extension,
+            } => AppendRequest {
+                append_key,
+                content: BASE64.encode(link.key.encrypt(&data)),
+                content_type: PostContentType::File,
+                mime_type: Some(mime_type.unwrap_or_else(|| "application/octet-stream".to_string())),
+                file_extension,
+                file_size: Some(data.len() as i64),
+            },
+        };
+
+        let response: AppendResponse = self
+            .send(self.http.post(self.url(&format!("/api/posts/{}/append", link.id))).json(&request))
+            .await?
+            .json()
+            .await?;
+        Ok(response.content_order)
+    }
+
+    /// Delete an upload with the deletion token it was issued
+    pub async fn delete(&self, file_id: &str, deletion_token: &str) -> Result<()> {
+        self.send(
+            self.http
+                .delete(self.url(&format!("/api/files/{}", file_id)))
+                .query(&[("token", deletion_token)]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Send a request, turning error statuses into `Error::Api`
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = match &self.api_key {
+            Some(api_key) => request.header(API_KEY_HEADER, api_key),
+            None => request,
+        };
+        let response = request.send().await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<ErrorResponse>(&body)
+            .map(|e| e.error)
+            .unwrap_or(body);
+        Err(Error::Api { status, message })
+    }
+}
+
+/// The extension of a download's `filename="file.ext"`
+fn disposition_extension(disposition: &str) -> Option<String> {
+    let filename = disposition.split("filename=\"").nth(1)?.split('"').next()?;
+    let extension = filename.strip_prefix("file.")?;
+    // Only plain extensions, since callers may build local file names from them
+    let plain = !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric());
+    plain.then(|| format!(".{}", extension))
+}
@@ -0,0 +1,216 @@
+use crate::crypto::Key;
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostType {
+    /// A single encrypted file, shared as `/f/{id}`
+    #[default]
+    File,
+    /// An appendable post, shared as `/p/{id}`
+    Post,
+}
+
+impl PostType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostType::File => "file",
+            PostType::Post => "post",
+        }
+    }
+
+    /// First path segment of share links (`f` or `p`)
+    pub fn path_segment(&self) -> &'static str {
+        match self {
+            PostType::File => "f",
+            PostType::Post => "p",
+        }
+    }
+}
+
+/// Options for `Client::upload`; everything but the data is optional
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    pub post_type: PostType,
+    /// Declared MIME type (sent unencrypted)
+    pub mime_type: Option<String>,
+    /// Extension including the dot, e.g. `.png` (sent unencrypted)
+    pub file_extension: Option<String>,
+    /// Hours until the upload expires (server default if unset)
+    pub expiry_hours: Option<i64>,
+    pub is_permanent: bool,
+    /// Delete the file after this many downloads
+    pub max_downloads: Option<i64>,
+    /// Vanity slug for posts (`/p/{slug}`)
+    pub slug: Option<String>,
+}
+
+/// The server's reply to an upload
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadResponse {
+    pub file_id: String,
+    /// Needed to delete the upload before it expires
+    pub deletion_token: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Path of the share page, without the key
+    pub url: String,
+    pub post_type: PostType,
+    /// Needed to append to a post
+    pub post_append_key: Option<String>,
+    pub is_permanent: bool,
+    pub downloads_remaining: Option<i64>,
+}
+
+/// A finished upload: what the server returned and the link to share
+#[derive(Debug)]
+pub struct Uploaded {
+    pub response: UploadResponse,
+    pub link: ShareLink,
+}
+
+/// A share link, `{base}/f/{id}#DOGBOX_KEY_SYMMETRIC_...` or `{base}/p/{id}#...`
+#[derive(Debug)]
+pub struct ShareLink {
+    /// Instance URL without a trailing slash, e.g. `https://dogbox.moe`
+    pub base_url: String,
+    pub post_type: PostType,
+    /// File ID, or a post's slug
+    pub id: String,
+    pub key: Key,
+}
+
+impl ShareLink {
+    /// Parse a link as the web frontend prints it
+    pub fn parse(url: &str) -> Result<Self> {
+        let (location, fragment) = url
+            .split_once('#')
+            .ok_or_else(|| Error::InvalidLink("missing #key fragment".to_string()))?;
+        let key = Key::import(fragment)?;
+
+        let location = location.trim_end_matches('/');
+        let (rest, id) = location
+            .rsplit_once('/')
+            .ok_or_else(|| Error::InvalidLink("missing file ID".to_string()))?;
+        let (base_url, segment) = rest
+            .rsplit_once('/')
+            .ok_or_else(|| Error::InvalidLink("missing /f/ or /p/ path".to_string()))?;
+        let post_type = match segment {
+            "f" => PostType::File,
+            "p" => PostType::Post,
+            other => return Err(Error::InvalidLink(format!("unknown path /{}/", other))),
+        };
+        if id.is_empty() || base_url.is_empty() {
+            return Err(Error::InvalidLink("missing instance or file ID".to_string()));
+        }
+
+        Ok(Self {
+            base_url: base_url.to_string(),
+            post_type,
+            id: id.to_string(),
+            key,
+        })
+    }
+}
+
+impl fmt::Display for ShareLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}#{}", self.base_url, self.post_type.path_segment(), self.id, self.key.export())
+    }
+}
+
+/// A downloaded and decrypted file
+#[derive(Debug, Clone)]
+pub struct Downloaded {
+    pub data: Vec<u8>,
+    pub mime_type: Option<String>,
+    /// Extension including the dot, if the uploader declared one
+    pub file_extension: Option<String>,
+}
+
+/// What to append to a post
+#[derive(Debug, Clone)]
+pub enum AppendContent {
+    Markdown(String),
+    File {
+        data: Vec<u8>,
+        mime_type: Option<String>,
+        /// Extension including the dot
+        file_extension: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostContentType {
+    Markdown,
+    File,
+}
+
+/// A post with its entries decrypted
+#[derive(Debug, Clone)]
+pub struct Post {
+    pub post_id: String,
+    pub slug: Option<String>,
+    pub is_locked: bool,
+    pub is_permanent: bool,
+    pub uploaded_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub entries: Vec<PostEntry>,
+}
+
+/// One decrypted post entry
+#[derive(Debug, Clone)]
+pub struct PostEntry {
+    pub order: i64,
+    pub appended_at: DateTime<Utc>,
+    pub content_type: PostContentType,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub data: Vec<u8>,
+}
+
+// Wire formats of the post endpoints
+
+#[derive(Deserialize)]
+pub(crate) struct PostViewResponse {
+    pub post_id: String,
+    pub is_permanent: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub uploaded_at: DateTime<Utc>,
+    pub is_locked: bool,
+    pub slug: Option<String>,
+    pub content: Vec<PostContentView>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PostContentView {
+    pub content_encrypted: String,
+    pub appended_at: DateTime<Utc>,
+    pub order: i64,
+    pub content_type: PostContentType,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct AppendRequest<'a> {
+    pub append_key: &'a str,
+    pub content: String,
+    pub content_type: PostContentType,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub file_size: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AppendResponse {
+    pub content_order: i64,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ErrorResponse {
+    pub error: String,
+}
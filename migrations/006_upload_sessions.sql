@@ -0,0 +1,24 @@
+-- Resumable upload sessions (tus protocol)
+-- Tracks partially uploaded encrypted blobs so clients can resume after a dropped connection
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4 (part of the upload URL)
+    upload_length INTEGER NOT NULL,            -- Declared total size of the encrypted blob
+    upload_offset INTEGER NOT NULL DEFAULT 0,  -- Bytes received so far
+
+    -- Upload options (from tus Upload-Metadata)
+    filename_encrypted TEXT,
+    mime_type TEXT,
+    file_extension TEXT,
+    expiry_hours INTEGER,
+    is_permanent BOOLEAN NOT NULL DEFAULT 0,
+
+    created_at INTEGER NOT NULL,               -- Unix timestamp
+    expires_at INTEGER NOT NULL,               -- Unix timestamp; unfinished sessions are discarded after this
+
+    -- Set once the upload completes, so a client that lost the final response can recover it
+    file_id TEXT,
+    deletion_token TEXT
+);
+
+-- Index for cleanup queries
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_expires_at ON upload_sessions(expires_at);
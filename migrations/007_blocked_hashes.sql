@@ -0,0 +1,7 @@
+-- Denylist of BLAKE3 hashes (of encrypted blobs) that may not be uploaded or served
+-- Lets operators block known-abusive content even though they cannot decrypt it
+CREATE TABLE IF NOT EXISTS blocked_hashes (
+    blake3_hash TEXT PRIMARY KEY NOT NULL,     -- Lowercase hex BLAKE3 hash
+    reason TEXT,                               -- Optional operator note
+    created_at INTEGER NOT NULL                -- Unix timestamp
+);
@@ -0,0 +1,6 @@
+-- Optional download limits ("burn after N downloads")
+-- NULL means unlimited; 0 means exhausted (blob deleted, row kept so downloads return 410 until expiry)
+ALTER TABLE files ADD COLUMN downloads_remaining INTEGER;
+
+-- Carried by resumable uploads until the file is committed
+ALTER TABLE upload_sessions ADD COLUMN max_downloads INTEGER;
@@ -0,0 +1,3 @@
+-- Optional view limit for dogpastes ("burn after reading")
+-- NULL means unlimited; the paste is deleted by the view that reaches the limit
+ALTER TABLE dogpaste ADD COLUMN max_views INTEGER;
@@ -0,0 +1,14 @@
+-- Collaborator append keys for posts
+-- Issued by the holder of the post's master append key; each can be revoked individually
+CREATE TABLE IF NOT EXISTS post_keys (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4 (used to revoke the key)
+    post_id TEXT NOT NULL,
+    append_key TEXT NOT NULL UNIQUE,           -- Collaborator's append key
+    label TEXT,                                -- Optional plaintext note (e.g. who holds the key)
+    created_at INTEGER NOT NULL,               -- Unix timestamp
+
+    FOREIGN KEY (post_id) REFERENCES files(id) ON DELETE CASCADE
+);
+
+-- Index for key checks on append
+CREATE INDEX IF NOT EXISTS idx_post_keys_post_id ON post_keys(post_id);
@@ -0,0 +1,22 @@
+-- Collections (albums): share several separately uploaded files under one link
+CREATE TABLE IF NOT EXISTS collections (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4
+    collection_key TEXT NOT NULL,              -- Secret required to add/remove members
+    title_encrypted TEXT,                      -- Optional client-encrypted title
+    created_at INTEGER NOT NULL                -- Unix timestamp
+);
+
+CREATE TABLE IF NOT EXISTS collection_files (
+    collection_id TEXT NOT NULL,
+    file_id TEXT NOT NULL,
+    position INTEGER NOT NULL,                 -- Display order within the collection
+    added_at INTEGER NOT NULL,                 -- Unix timestamp
+
+    PRIMARY KEY (collection_id, file_id),
+    -- Members disappear with their file (expiry or deletion)
+    FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
+
+-- Index for cascading file deletions
+CREATE INDEX IF NOT EXISTS idx_collection_files_file_id ON collection_files(file_id);
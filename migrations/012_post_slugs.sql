@@ -0,0 +1,4 @@
+-- Optional vanity slugs for posts (/p/my-changelog); the UUID keeps working too
+ALTER TABLE files ADD COLUMN slug TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_files_slug ON files(slug);
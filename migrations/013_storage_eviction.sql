@@ -0,0 +1,9 @@
+-- Least-recently-downloaded eviction when the storage quota is full (STORAGE_FULL_POLICY=evict)
+-- NULL means never downloaded; eviction falls back to uploaded_at
+ALTER TABLE files ADD COLUMN last_downloaded_at TIMESTAMP;
+
+-- Running totals that outlive restarts (e.g. files evicted to make room)
+CREATE TABLE IF NOT EXISTS counters (
+    name TEXT PRIMARY KEY NOT NULL,
+    value INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,7 @@
+-- Instance access keys for private mode (PRIVATE_MODE=uploads|all), issued via the admin API
+CREATE TABLE IF NOT EXISTS access_keys (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4 (used to revoke the key)
+    key_hash TEXT NOT NULL UNIQUE,             -- BLAKE3 of the key; the key itself is only shown once
+    label TEXT,                                -- Optional plaintext note (e.g. who holds the key)
+    created_at INTEGER NOT NULL                -- Unix timestamp
+);
@@ -0,0 +1,8 @@
+-- Trusted API keys for the operator's own automation: skip rate limiting and get elevated limits
+CREATE TABLE IF NOT EXISTS api_keys (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4 (used to revoke the key)
+    key_hash TEXT NOT NULL UNIQUE,             -- BLAKE3 of the key; the key itself is only shown once
+    label TEXT,                                -- Optional plaintext note (e.g. which bot uses it)
+    created_at INTEGER NOT NULL,               -- Unix timestamp
+    last_used_at INTEGER                       -- Unix timestamp of the last request presenting the key
+);
@@ -0,0 +1,11 @@
+-- Pre-authorized upload tokens minted by the admin ("send me a file" invites)
+CREATE TABLE IF NOT EXISTS upload_tokens (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4 (used to revoke the token)
+    token_hash TEXT NOT NULL UNIQUE,           -- BLAKE3 of the token; the token itself is only shown once
+    label TEXT,                                -- Optional plaintext note (e.g. who was invited)
+    max_size_bytes INTEGER,                    -- Size cap for uploads with this token (NULL = instance default)
+    max_expiry_hours INTEGER,                  -- Expiry cap for uploads with this token (NULL = instance default)
+    uses_remaining INTEGER,                    -- Uploads left (NULL = unlimited until expires_at)
+    expires_at INTEGER,                        -- Unix timestamp after which the token stops working (NULL = never)
+    created_at INTEGER NOT NULL                -- Unix timestamp
+);
@@ -0,0 +1,8 @@
+-- Expiry warnings registered by uploaders (with their deletion token)
+-- The cleanup task calls callback_url once the file is within hours_before of expiring
+CREATE TABLE IF NOT EXISTS expiry_warnings (
+    file_id TEXT PRIMARY KEY NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    callback_url TEXT,                         -- NULL = poll-only (GET /api/files/:id/expiry-warning)
+    hours_before INTEGER NOT NULL,             -- How long before expiry the warning is due
+    notified_at INTEGER                        -- Unix timestamp the callback was sent (reset when the expiry changes)
+);
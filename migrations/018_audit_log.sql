@@ -0,0 +1,22 @@
+-- Append-only log of administrative and destructive actions
+-- Records who (admin or the server itself) did what to which ID; never IPs, keys or content
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL,               -- Unix timestamp
+    actor TEXT NOT NULL,                       -- 'admin' or 'system'
+    action TEXT NOT NULL,                      -- e.g. 'file.delete', 'hash.block', 'test_mode.wipe'
+    target TEXT,                               -- File ID, hash or key ID the action applied to
+    details TEXT                               -- Free-form context (e.g. block reason, counts)
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
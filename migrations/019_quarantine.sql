@@ -0,0 +1,3 @@
+-- Legal hold: quarantined files can't be downloaded, deleted or cleaned up until released
+ALTER TABLE files ADD COLUMN quarantined_at INTEGER;   -- Unix timestamp the hold was placed (NULL = not held)
+ALTER TABLE files ADD COLUMN quarantine_reason TEXT;   -- Operator's note (e.g. case reference)
@@ -0,0 +1,13 @@
+-- Hourly totals recorded by the cleanup task, for charting growth on the stats page
+CREATE TABLE IF NOT EXISTS stats_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL,               -- Unix timestamp
+    total_uploads INTEGER NOT NULL,
+    total_posts INTEGER NOT NULL,
+    total_files INTEGER NOT NULL,
+    total_dogpastes INTEGER NOT NULL,
+    total_views INTEGER NOT NULL,
+    storage_bytes INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_stats_snapshots_created_at ON stats_snapshots(created_at);
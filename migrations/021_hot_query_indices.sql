@@ -0,0 +1,13 @@
+-- Indices for the queries run on every cleanup pass, upload and stats refresh
+-- files(blake3_hash) and posts_content(file_id, content_order) already exist (001, 002)
+
+-- Expiry lookups by time, with is_permanent available without touching the row
+-- (supersedes the single-column index from 001)
+DROP INDEX IF EXISTS idx_files_expires_at;
+CREATE INDEX IF NOT EXISTS idx_files_expires_at_permanent ON files(expires_at, is_permanent);
+
+-- Cleanup compares datetime(expires_at), which a plain column index can't serve
+CREATE INDEX IF NOT EXISTS idx_files_expiry_datetime ON files(datetime(expires_at)) WHERE is_permanent = 0;
+
+-- Per-type counts for stats
+CREATE INDEX IF NOT EXISTS idx_files_post_type ON files(post_type);
@@ -0,0 +1,6 @@
+-- Settings changed at runtime through the admin API, overriding the config
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL                -- Unix timestamp
+);
@@ -0,0 +1,2 @@
+-- Uploads answered with an existing file instead of storing another copy (admin stats)
+ALTER TABLE files ADD COLUMN dedup_hits INTEGER NOT NULL DEFAULT 0;
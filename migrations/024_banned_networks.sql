@@ -0,0 +1,9 @@
+-- IP / CIDR bans managed through the admin API
+CREATE TABLE IF NOT EXISTS banned_networks (
+    id TEXT PRIMARY KEY NOT NULL,              -- UUID v4 (used to lift the ban)
+    network TEXT NOT NULL UNIQUE,              -- Canonical CIDR, e.g. 203.0.113.0/24 (single addresses are /32 or /128)
+    scope TEXT NOT NULL DEFAULT 'uploads',     -- 'uploads' (content creation only) or 'all' (every request)
+    reason TEXT,                               -- Optional operator note
+    expires_at INTEGER,                        -- Unix timestamp the ban lapses (NULL = never)
+    created_at INTEGER NOT NULL                -- Unix timestamp
+);
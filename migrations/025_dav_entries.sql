@@ -0,0 +1,15 @@
+-- WebDAV namespace: each trusted API key sees its uploads under the paths it PUT them at
+CREATE TABLE IF NOT EXISTS dav_entries (
+    api_key_id TEXT NOT NULL,
+    path TEXT NOT NULL,                        -- e.g. backups/2025-01-01.tar.age (no leading slash)
+    file_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,               -- Unix timestamp
+
+    PRIMARY KEY (api_key_id, path),
+    -- Entries disappear with their file (expiry or deletion) or when the key is revoked
+    FOREIGN KEY (api_key_id) REFERENCES api_keys(id) ON DELETE CASCADE,
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
+
+-- Index for cascading file deletions
+CREATE INDEX IF NOT EXISTS idx_dav_entries_file_id ON dav_entries(file_id);
@@ -0,0 +1,4 @@
+-- Secret access keys for the S3 API. SigV4 signatures are checked by recomputing them,
+-- so unlike the API key itself the secret can't be stored hashed. Keys issued before
+-- this migration have none and can't use the S3 API.
+ALTER TABLE api_keys ADD COLUMN s3_secret_key TEXT;
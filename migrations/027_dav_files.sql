@@ -0,0 +1,8 @@
+-- Files created through the WebDAV/S3 namespace. Identical content deduplicates into
+-- one file, so a path may also point at an upload made some other way; only files
+-- listed here are deleted when their last path goes away. Files mounted before this
+-- migration aren't listed and are left to expire.
+CREATE TABLE IF NOT EXISTS dav_files (
+    file_id TEXT PRIMARY KEY NOT NULL,
+    FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+);
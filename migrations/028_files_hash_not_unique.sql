@@ -0,0 +1,49 @@
+-- Identical content may be stored more than once: uploads with a download limit and
+-- WebDAV/S3 files never deduplicate into other files. SQLite can't drop a column
+-- constraint, so the table is rebuilt without UNIQUE on blake3_hash. Migrations run
+-- with foreign keys off, so dropping the old table doesn't cascade.
+CREATE TABLE files_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    filename_encrypted TEXT,
+    size_bytes INTEGER NOT NULL,
+    mime_type TEXT,
+    uploaded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    deletion_token TEXT NOT NULL UNIQUE,
+    storage_path TEXT NOT NULL,
+    blake3_hash TEXT NOT NULL,                 -- BLAKE3 of the encrypted blob (not unique)
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    post_type TEXT NOT NULL DEFAULT 'file',
+    post_append_key TEXT,
+    is_permanent BOOLEAN NOT NULL DEFAULT 0,
+    view_count INTEGER NOT NULL DEFAULT 0,
+    file_extension TEXT,
+    downloads_remaining INTEGER,
+    slug TEXT,
+    last_downloaded_at TIMESTAMP,
+    quarantined_at INTEGER,
+    quarantine_reason TEXT,
+    dedup_hits INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO files_new (
+    id, filename_encrypted, size_bytes, mime_type, uploaded_at, expires_at,
+    deletion_token, storage_path, blake3_hash, created_at, post_type, post_append_key,
+    is_permanent, view_count, file_extension, downloads_remaining, slug,
+    last_downloaded_at, quarantined_at, quarantine_reason, dedup_hits
+)
+SELECT
+    id, filename_encrypted, size_bytes, mime_type, uploaded_at, expires_at,
+    deletion_token, storage_path, blake3_hash, created_at, post_type, post_append_key,
+    is_permanent, view_count, file_extension, downloads_remaining, slug,
+    last_downloaded_at, quarantined_at, quarantine_reason, dedup_hits
+FROM files;
+
+DROP TABLE files;
+ALTER TABLE files_new RENAME TO files;
+
+CREATE INDEX IF NOT EXISTS idx_files_blake3_hash ON files(blake3_hash);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_files_slug ON files(slug);
+CREATE INDEX IF NOT EXISTS idx_files_expires_at_permanent ON files(expires_at, is_permanent);
+CREATE INDEX IF NOT EXISTS idx_files_expiry_datetime ON files(datetime(expires_at)) WHERE is_permanent = 0;
+CREATE INDEX IF NOT EXISTS idx_files_post_type ON files(post_type);
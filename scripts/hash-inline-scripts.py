@@ -2,7 +2,7 @@
 """
 Calculate SHA256 hashes of inline scripts for Content Security Policy.
 
-This script scans all HTML files in the static/ directory and generates
+This script scans all HTML page templates in the templates/ directory and generates
 SHA256 hashes for inline <script> tags. These hashes are used in the CSP
 to allow specific inline scripts without 'unsafe-inline'.
 
@@ -12,7 +12,8 @@ Usage:
 Or via justfile:
     just hash-scripts
 
-After running, update src/middleware.rs with the generated hashes.
+After running, update DEFAULT_CSP_SCRIPT_HASHES in src/constants.rs with the
+generated hashes, or set CSP_SCRIPT_HASHES for a modified frontend.
 """
 
 import hashlib
@@ -36,19 +37,19 @@ def calculate_sha256(script_content):
     return base64.b64encode(sha256_hash).decode('ascii')
 
 def main():
-    # Find the project root (where static/ is)
+    # Find the project root (where templates/ is)
     script_path = Path(__file__).resolve()
     project_root = script_path.parent.parent
-    static_dir = project_root / 'static'
+    templates_dir = project_root / 'templates'
 
-    if not static_dir.exists():
-        print(f"Error: static/ directory not found at {static_dir}")
+    if not templates_dir.exists():
+        print(f"Error: templates/ directory not found at {templates_dir}")
         return 1
 
-    html_files = list(static_dir.glob('*.html'))
+    html_files = list(templates_dir.glob('*.html'))
 
     if not html_files:
-        print(f"Error: No HTML files found in {static_dir}")
+        print(f"Error: No HTML files found in {templates_dir}")
         return 1
 
     all_hashes = set()
@@ -76,26 +77,26 @@ def main():
                 })
 
     print("\n" + "=" * 80)
-    print("🔐 CSP script-src directive (copy this to src/middleware.rs):")
+    print("🔐 CSP script hashes (copy these to DEFAULT_CSP_SCRIPT_HASHES in src/constants.rs):")
     print("=" * 80)
     print()
 
-    # Format with proper line breaks for Rust
+    # Format as Rust string literals
     hashes_list = sorted(all_hashes)
-    print("script-src 'self' 'wasm-unsafe-eval' \\")
-    for i, h in enumerate(hashes_list):
-        if i < len(hashes_list) - 1:
-            print(f"  'sha256-{h}=' \\")
-        else:
-            print(f"  'sha256-{h}=';")
+    for h in hashes_list:
+        print(f'    "sha256-{h}",')
+
+    print()
+    print("Or, for a modified frontend, set in .env:")
+    print("CSP_SCRIPT_HASHES=" + ",".join(f"sha256-{h}" for h in hashes_list))
 
     print("\n" + "=" * 80)
     print(f"✅ Total unique script hashes: {len(all_hashes)}")
     print("=" * 80)
     print()
     print("📝 Next steps:")
-    print("1. Copy the CSP directive above")
-    print("2. Update src/middleware.rs in the content-security-policy header")
+    print("1. Copy the hashes above")
+    print("2. Update DEFAULT_CSP_SCRIPT_HASHES in src/constants.rs (or set CSP_SCRIPT_HASHES)")
     print("3. Rebuild: cargo build")
     print("4. Restart server: just dev")
     print()
@@ -0,0 +1,914 @@
+//! Operator-only API under /api/admin, guarded by `middleware::admin_auth`
+
+use crate::constants::{
+    ADMIN_DEFAULT_PAGE_SIZE, ADMIN_MAX_PAGE_SIZE, ADMIN_MESSAGE_SETTING, ADMIN_STATS_DEFAULT_DAYS,
+    ADMIN_STATS_DEFAULT_TOP, ADMIN_STATS_MAX_DAYS, ADMIN_STATS_MAX_TOP, MAX_ADMIN_MESSAGE_LENGTH,
+    MAX_POST_KEY_LABEL_LENGTH, MAX_UPLOAD_SIZE,
+};
+use crate::error::{AppError, Result};
+use crate::middleware;
+use crate::models::*;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware as axum_middleware,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+
+/// Build the admin router (nested at /api/admin)
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/files", get(list_files))
+        .route("/files/:id", delete(delete_file))
+        .route("/files/:id/quarantine", post(quarantine_file).delete(release_quarantine))
+        .route("/storage", get(storage))
+        .route("/stats", get(stats))
+        .route("/blocked-hashes", get(list_blocked_hashes).post(block_hash))
+        .route("/blocked-hashes/:hash", delete(unblock_hash))
+        .route("/bans", get(list_bans).post(ban_network))
+        .route("/bans/:id", delete(unban_network))
+        .route("/access-keys", get(list_access_keys).post(create_access_key))
+        .route("/access-keys/:id", delete(revoke_access_key))
+        .route("/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/api-keys/:id", delete(revoke_api_key))
+        .route("/upload-tokens", get(list_upload_tokens).post(create_upload_token))
+        .route("/upload-tokens/:id", delete(revoke_upload_token))
+        .route("/reload-config", post(reload_config))
+        .route("/maintenance", post(run_maintenance))
+        .route("/cleanup", post(run_cleanup))
+        .route("/motd", put(set_admin_message).delete(reset_admin_message))
+        .route("/audit-log", get(audit_log))
+        .route_layer(axum_middleware::from_fn_with_state(state, middleware::admin_auth))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileListQuery {
+    offset: Option<i64>,
+    limit: Option<i64>,
+    post_type: Option<PostType>,
+    min_size: Option<i64>,
+    expired: Option<bool>,
+}
+
+/// List stored files and posts (metadata only, newest first)
+#[utoipa::path(
+    get,
+    path = "/api/admin/files",
+    tag = "admin",
+    params(
+        ("offset" = Option<i64>, Query, description = "Rows to skip (default 0)"),
+        ("limit" = Option<i64>, Query, description = "Page size (default 50, max 500)"),
+        ("post_type" = Option<PostType>, Query, description = "Only files or only posts"),
+        ("min_size" = Option<i64>, Query, description = "Only rows of at least this many bytes"),
+        ("expired" = Option<bool>, Query, description = "Only rows past their expiry that cleanup hasn't removed yet (true) or only live rows (false)")
+    ),
+    responses(
+        (status = 200, description = "Page of file metadata", body = AdminFileListResponse),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn list_files(
+    State(state): State<AppState>,
+    Query(query): Query<FileListQuery>,
+) -> Result<Json<AdminFileListResponse>> {
+    let offset = query.offset.unwrap_or(0).max(0);
+    let limit = query
+        .limit
+        .unwrap_or(ADMIN_DEFAULT_PAGE_SIZE)
+        .clamp(1, ADMIN_MAX_PAGE_SIZE);
+
+    let post_type = query.post_type.map(|post_type| post_type.to_string());
+    let (mut files, total) = state
+        .db
+        .list_files(post_type.as_deref(), query.min_size, query.expired, offset, limit)
+        .await?;
+    if !state.config.load().analytics_enabled {
+        files.iter_mut().for_each(|file| file.view_count = None);
+    }
+
+    Ok(Json(AdminFileListResponse {
+        files,
+        total,
+        offset,
+        limit,
+    }))
+}
+
+/// Force-delete a file or post without its deletion token
+#[utoipa::path(
+    delete,
+    path = "/api/admin/files/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 200, description = "File deleted", body = DeleteResponse),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "File not found"),
+        (status = 409, description = "File is quarantined")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn delete_file(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<DeleteResponse>> {
+    state.file_service.force_delete_file(&id).await?;
+
+    Ok(Json(DeleteResponse {
+        success: true,
+        message: "File deleted successfully".to_string(),
+    }))
+}
+
+/// Place a file or post under a legal hold
+///
+/// Downloads return 451 and the blob and metadata are kept (no cleanup, eviction
+/// or deletion) until the hold is released.
+#[utoipa::path(
+    post,
+    path = "/api/admin/files/{id}/quarantine",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "File or post ID")
+    ),
+    request_body = QuarantineRequest,
+    responses(
+        (status = 204, description = "File quarantined"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "File not found")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn quarantine_file(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<QuarantineRequest>,
+) -> Result<StatusCode> {
+    state.file_service.quarantine_file(&id, req.reason.as_deref()).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Release a legal hold
+#[utoipa::path(
+    delete,
+    path = "/api/admin/files/{id}/quarantine",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "File or post ID")
+    ),
+    responses(
+        (status = 204, description = "Hold released"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "File not found or not quarantined")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn release_quarantine(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    state.file_service.release_quarantine(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Storage totals by upload type
+#[utoipa::path(
+    get,
+    path = "/api/admin/storage",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Storage totals", body = AdminStorageResponse),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn storage(State(state): State<AppState>) -> Result<Json<AdminStorageResponse>> {
+    Ok(Json(state.db.get_storage_totals().await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    top: Option<i64>,
+    days: Option<i64>,
+}
+
+/// Detailed statistics the public /api/stats omits: largest and most viewed files,
+/// uploads per day, storage per post type and deduplication savings
+#[utoipa::path(
+    get,
+    path = "/api/admin/stats",
+    tag = "admin",
+    params(
+        ("top" = Option<i64>, Query, description = "Length of the top-N lists (default 10, max 100)"),
+        ("days" = Option<i64>, Query, description = "Days of uploads per day (default 30, max 365)")
+    ),
+    responses(
+        (status = 200, description = "Detailed statistics", body = AdminStatsResponse),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn stats(
+    State(state): State<AppState>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<AdminStatsResponse>> {
+    let top = query.top.unwrap_or(ADMIN_STATS_DEFAULT_TOP).clamp(1, ADMIN_STATS_MAX_TOP);
+    let days = query.days.unwrap_or(ADMIN_STATS_DEFAULT_DAYS).clamp(1, ADMIN_STATS_MAX_DAYS);
+    let mut stats = state.db.get_admin_stats(top, days).await?;
+    if !state.config.load().analytics_enabled {
+        stats.top_by_views = None;
+        stats.dedup = None;
+        stats.top_by_size.iter_mut().for_each(|file| file.view_count = None);
+    }
+    Ok(Json(stats))
+}
+
+/// Normalize and validate a BLAKE3 hex digest
+fn parse_blake3_hash(hash: &str) -> Result<String> {
+    let hash = hash.trim().to_ascii_lowercase();
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::BadRequest(
+            "blake3_hash must be 64 hexadecimal characters".to_string(),
+        ));
+    }
+    Ok(hash)
+}
+
+/// List blocked BLAKE3 hashes
+#[utoipa::path(
+    get,
+    path = "/api/admin/blocked-hashes",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Blocked hashes, newest first", body = Vec<BlockedHash>),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn list_blocked_hashes(State(state): State<AppState>) -> Result<Json<Vec<BlockedHash>>> {
+    Ok(Json(state.db.list_blocked_hashes().await?))
+}
+
+/// Block a BLAKE3 hash
+///
+/// New uploads with this hash are rejected (403) and existing copies return 410.
+#[utoipa::path(
+    post,
+    path = "/api/admin/blocked-hashes",
+    tag = "admin",
+    request_body = BlockHashRequest,
+    responses(
+        (status = 201, description = "Hash blocked"),
+        (status = 400, description = "Invalid hash"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn block_hash(
+    State(state): State<AppState>,
+    Json(req): Json<BlockHashRequest>,
+) -> Result<StatusCode> {
+    let hash = parse_blake3_hash(&req.blake3_hash)?;
+    state.db.block_hash(&hash, req.reason.as_deref()).await?;
+
+    tracing::warn!("Admin blocked hash {}", hash);
+    state.db.audit(AuditActor::Admin, "hash.block", Some(&hash), req.reason.as_deref()).await;
+    Ok(StatusCode::CREATED)
+}
+
+/// Remove a BLAKE3 hash from the denylist
+#[utoipa::path(
+    delete,
+    path = "/api/admin/blocked-hashes/{hash}",
+    tag = "admin",
+    params(
+        ("hash" = String, Path, description = "BLAKE3 hash")
+    ),
+    responses(
+        (status = 204, description = "Hash unblocked"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Hash was not blocked")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn unblock_hash(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+) -> Result<StatusCode> {
+    let hash = parse_blake3_hash(&hash)?;
+    if !state.db.unblock_hash(&hash).await? {
+        return Err(AppError::NotFound);
+    }
+
+    tracing::warn!("Admin unblocked hash {}", hash);
+    state.db.audit(AuditActor::Admin, "hash.unblock", Some(&hash), None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reload admin message, expiry defaults, storage limits and rate limits (same as SIGHUP)
+///
+/// Other settings still require a restart. An invalid config is rejected and
+/// the running config is kept.
+#[utoipa::path(
+    post,
+    path = "/api/admin/reload-config",
+    tag = "admin",
+    responses(
+        (status = 204, description = "Configuration reloaded"),
+        (status = 400, description = "New configuration is invalid"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn reload_config(State(state): State<AppState>) -> Result<StatusCode> {
+    state
+        .reload_config()
+        .map_err(|e| AppError::BadRequest(format!("Config reload failed: {}", e)))?;
+
+    tracing::warn!("Admin reloaded configuration");
+    state.db.audit(AuditActor::Admin, "config.reload", None, None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run database maintenance now (incremental vacuum + ANALYZE)
+///
+/// The first run on an older database does a full VACUUM, which blocks writes
+/// until it finishes.
+#[utoipa::path(
+    post,
+    path = "/api/admin/maintenance",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Maintenance finished", body = MaintenanceReport),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn run_maintenance(State(state): State<AppState>) -> Result<Json<MaintenanceReport>> {
+    tracing::warn!("Admin triggered database maintenance");
+    Ok(Json(crate::maintenance::run(&state.db, AuditActor::Admin).await?))
+}
+
+/// Run expired-content cleanup and orphan collection now
+///
+/// Same cleanup the background task runs every CLEANUP_INTERVAL_SECS, plus removal of
+/// blobs no file refers to and staged uploads abandoned by interrupted requests.
+#[utoipa::path(
+    post,
+    path = "/api/admin/cleanup",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cleanup finished", body = AdminCleanupResponse),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn run_cleanup(State(state): State<AppState>) -> Result<Json<AdminCleanupResponse>> {
+    tracing::warn!("Admin triggered cleanup");
+    let expired = state.file_service.cleanup_expired().await?;
+    *crate::cleanup::LAST_CLEANUP.write().await = Some(chrono::Utc::now());
+    crate::cleanup::log_report(&expired);
+
+    let orphans = state.file_service.collect_orphans().await?;
+    tracing::info!(
+        "🗑️  Orphans: {} blobs, {} stale uploads, {} KB reclaimed",
+        orphans.blobs,
+        orphans.stale_uploads,
+        orphans.bytes_reclaimed / 1024
+    );
+
+    state.db.audit(AuditActor::Admin, "cleanup.run", None, None).await;
+    Ok(Json(AdminCleanupResponse { expired, orphans }))
+}
+
+/// Set or clear the admin message (MOTD) without a restart
+///
+/// Stored in the database, so it survives restarts and config reloads and takes
+/// precedence over ADMIN_MESSAGE until reset with DELETE.
+#[utoipa::path(
+    put,
+    path = "/api/admin/motd",
+    tag = "admin",
+    request_body = SetAdminMessageRequest,
+    responses(
+        (status = 200, description = "Message updated", body = AdminMessageResponse),
+        (status = 400, description = "Message too long or contains disallowed characters"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn set_admin_message(
+    State(state): State<AppState>,
+    Json(req): Json<SetAdminMessageRequest>,
+) -> Result<Json<AdminMessageResponse>> {
+    let message = req.message.map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+    if let Some(message) = &message {
+        if message.chars().count() > MAX_ADMIN_MESSAGE_LENGTH {
+            return Err(AppError::InvalidField {
+                field: "message",
+                message: format!("Message must be at most {} characters", MAX_ADMIN_MESSAGE_LENGTH),
+            });
+        }
+        if !crate::config::is_safe_admin_message(message) {
+            return Err(AppError::InvalidField {
+                field: "message",
+                message: "Only alphanumeric characters, spaces, commas, periods, hyphens, and apostrophes are allowed"
+                    .to_string(),
+            });
+        }
+    }
+
+    // An empty value records "cleared", which still overrides ADMIN_MESSAGE
+    state
+        .db
+        .set_setting(ADMIN_MESSAGE_SETTING, message.as_deref().unwrap_or_default())
+        .await?;
+    state.set_admin_message(message.clone());
+
+    tracing::warn!("Admin {} the admin message", if message.is_some() { "set" } else { "cleared" });
+    state.db.audit(AuditActor::Admin, "motd.set", None, message.as_deref()).await;
+    Ok(Json(AdminMessageResponse { message, from_database: true }))
+}
+
+/// Drop the stored admin message and go back to ADMIN_MESSAGE from the config
+#[utoipa::path(
+    delete,
+    path = "/api/admin/motd",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Message reset to the configured one", body = AdminMessageResponse),
+        (status = 400, description = "Configuration could not be re-read"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn reset_admin_message(State(state): State<AppState>) -> Result<Json<AdminMessageResponse>> {
+    state.db.delete_setting(ADMIN_MESSAGE_SETTING).await?;
+
+    let mut config = crate::config::Config::clone(&state.config.load());
+    config.admin_message_from_db = false;
+    let config = config
+        .reloaded()
+        .map_err(|e| AppError::BadRequest(format!("Config reload failed: {}", e)))?;
+    let message = config.admin_message.clone();
+    state.config.store(std::sync::Arc::new(config));
+
+    tracing::warn!("Admin reset the admin message");
+    state.db.audit(AuditActor::Admin, "motd.reset", None, None).await;
+    Ok(Json(AdminMessageResponse { message, from_database: false }))
+}
+
+/// List IP / CIDR bans, newest first (including lapsed ones cleanup hasn't removed yet)
+#[utoipa::path(
+    get,
+    path = "/api/admin/bans",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Banned networks", body = Vec<BannedNetwork>),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn list_bans(State(state): State<AppState>) -> Result<Json<Vec<BannedNetwork>>> {
+    Ok(Json(state.db.list_bans(true).await?))
+}
+
+/// Ban an IP address or CIDR range
+///
+/// Matching clients (resolved through TRUSTED_PROXIES like the rate limiter) get 403
+/// for uploads, or for every request outside the admin API with scope `all`.
+/// Banning a network that is already banned updates that ban.
+#[utoipa::path(
+    post,
+    path = "/api/admin/bans",
+    tag = "admin",
+    request_body = BanNetworkRequest,
+    responses(
+        (status = 201, description = "Network banned", body = BannedNetwork),
+        (status = 400, description = "Invalid network or duration"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn ban_network(
+    State(state): State<AppState>,
+    Json(req): Json<BanNetworkRequest>,
+) -> Result<(StatusCode, Json<BannedNetwork>)> {
+    let network = req.network.trim();
+    let network = network
+        .parse::<ipnet::IpNet>()
+        .or_else(|_| network.parse::<std::net::IpAddr>().map(ipnet::IpNet::from))
+        .map_err(|_| AppError::InvalidField {
+            field: "network",
+            message: "Must be an IP address or CIDR range".to_string(),
+        })?;
+    if req.valid_hours.is_some_and(|h| h < 1) {
+        return Err(AppError::BadRequest("valid_hours must be at least 1".to_string()));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let ban = BannedNetwork {
+        id: uuid::Uuid::new_v4().to_string(),
+        // Host bits are dropped so 203.0.113.7/24 and 203.0.113.0/24 are the same ban
+        network: network.trunc().to_string(),
+        scope: req.scope.unwrap_or_default().to_string(),
+        reason: req.reason,
+        expires_at: req.valid_hours.map(|h| now + h * 60 * 60),
+        created_at: now,
+    };
+    let ban = state.db.ban_network(&ban).await?;
+    state.bans.reload(&state.db).await?;
+
+    tracing::warn!("Admin banned {} ({})", ban.network, ban.scope);
+    state
+        .db
+        .audit(AuditActor::Admin, "ban.create", Some(&ban.network), ban.reason.as_deref())
+        .await;
+    Ok((StatusCode::CREATED, Json(ban)))
+}
+
+/// Lift an IP / CIDR ban
+#[utoipa::path(
+    delete,
+    path = "/api/admin/bans/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Ban ID")
+    ),
+    responses(
+        (status = 204, description = "Ban lifted"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Ban not found")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn unban_network(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let network = state.db.unban_network(&id).await?.ok_or(AppError::NotFound)?;
+    state.bans.reload(&state.db).await?;
+
+    tracing::warn!("Admin lifted the ban on {}", network);
+    state.db.audit(AuditActor::Admin, "ban.delete", Some(&network), None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List instance access keys (private mode)
+#[utoipa::path(
+    get,
+    path = "/api/admin/access-keys",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Access keys, newest first (keys themselves are never shown again)", body = Vec<AccessKey>),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn list_access_keys(State(state): State<AppState>) -> Result<Json<Vec<AccessKey>>> {
+    Ok(Json(state.db.list_access_keys().await?))
+}
+
+/// Issue an instance access key for PRIVATE_MODE
+#[utoipa::path(
+    post,
+    path = "/api/admin/access-keys",
+    tag = "admin",
+    request_body = CreateAccessKeyRequest,
+    responses(
+        (status = 201, description = "Key issued", body = CreateAccessKeyResponse),
+        (status = 400, description = "Label too long"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn create_access_key(
+    State(state): State<AppState>,
+    Json(req): Json<CreateAccessKeyRequest>,
+) -> Result<(StatusCode, Json<CreateAccessKeyResponse>)> {
+    if req.label.as_ref().is_some_and(|l| l.chars().count() > MAX_POST_KEY_LABEL_LENGTH) {
+        return Err(AppError::BadRequest(format!(
+            "Label must be at most {} characters",
+            MAX_POST_KEY_LABEL_LENGTH
+        )));
+    }
+
+    let key = AccessKey {
+        id: uuid::Uuid::new_v4().to_string(),
+        label: req.label,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    let access_key = FileRecord::generate_access_key();
+    let key_hash = blake3::hash(access_key.as_bytes()).to_hex().to_string();
+    state.db.create_access_key(&key, &key_hash).await?;
+
+    tracing::warn!("Admin issued access key {}", key.id);
+    state.db.audit(AuditActor::Admin, "access_key.create", Some(&key.id), None).await;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateAccessKeyResponse {
+            key_id: key.id,
+            access_key,
+            label: key.label,
+        }),
+    ))
+}
+
+/// Revoke an instance access key
+#[utoipa::path(
+    delete,
+    path = "/api/admin/access-keys/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Key ID")
+    ),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Key not found")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn revoke_access_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.db.revoke_access_key(&id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    tracing::warn!("Admin revoked access key {}", id);
+    state.db.audit(AuditActor::Admin, "access_key.revoke", Some(&id), None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List trusted API keys
+#[utoipa::path(
+    get,
+    path = "/api/admin/api-keys",
+    tag = "admin",
+    responses(
+        (status = 200, description = "API keys, newest first (keys themselves are never shown again)", body = Vec<ApiKey>),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn list_api_keys(State(state): State<AppState>) -> Result<Json<Vec<ApiKey>>> {
+    Ok(Json(state.db.list_api_keys().await?))
+}
+
+/// Issue a trusted API key
+///
+/// Requests sending it as `X-Api-Key` skip rate limiting and get the
+/// API_KEY_MAX_UPLOAD_BYTES / API_KEY_MAX_EXPIRY_HOURS limits.
+#[utoipa::path(
+    post,
+    path = "/api/admin/api-keys",
+    tag = "admin",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "Key issued", body = CreateApiKeyResponse),
+        (status = 400, description = "Label too long"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>)> {
+    if req.label.as_ref().is_some_and(|l| l.chars().count() > MAX_POST_KEY_LABEL_LENGTH) {
+        return Err(AppError::BadRequest(format!(
+            "Label must be at most {} characters",
+            MAX_POST_KEY_LABEL_LENGTH
+        )));
+    }
+
+    let key = ApiKey {
+        id: uuid::Uuid::new_v4().to_string(),
+        label: req.label,
+        created_at: chrono::Utc::now().timestamp(),
+        last_used_at: None,
+    };
+    let api_key = FileRecord::generate_api_key();
+    let key_hash = blake3::hash(api_key.as_bytes()).to_hex().to_string();
+    let s3_secret_key = FileRecord::generate_s3_secret_key();
+    state.db.create_api_key(&key, &key_hash, &s3_secret_key).await?;
+
+    tracing::warn!("Admin issued API key {}", key.id);
+    state.db.audit(AuditActor::Admin, "api_key.create", Some(&key.id), None).await;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            key_id: key.id,
+            api_key,
+            s3_secret_key,
+            label: key.label,
+        }),
+    ))
+}
+
+/// Revoke a trusted API key
+#[utoipa::path(
+    delete,
+    path = "/api/admin/api-keys/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Key ID")
+    ),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Key not found")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.db.revoke_api_key(&id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    tracing::warn!("Admin revoked API key {}", id);
+    state.db.audit(AuditActor::Admin, "api_key.revoke", Some(&id), None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List upload tokens
+#[utoipa::path(
+    get,
+    path = "/api/admin/upload-tokens",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Upload tokens, newest first (tokens themselves are never shown again)", body = Vec<UploadToken>),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn list_upload_tokens(State(state): State<AppState>) -> Result<Json<Vec<UploadToken>>> {
+    Ok(Json(state.db.list_upload_tokens().await?))
+}
+
+/// Mint a pre-authorized upload token
+///
+/// The invitee sends it as `X-Upload-Token` to `/api/upload` or `/api/raw`, which
+/// also works on private instances. Single-use unless `max_uses` or `valid_hours` is set.
+#[utoipa::path(
+    post,
+    path = "/api/admin/upload-tokens",
+    tag = "admin",
+    request_body = CreateUploadTokenRequest,
+    responses(
+        (status = 201, description = "Token minted", body = CreateUploadTokenResponse),
+        (status = 400, description = "Invalid limits or label too long"),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn create_upload_token(
+    State(state): State<AppState>,
+    Json(req): Json<CreateUploadTokenRequest>,
+) -> Result<(StatusCode, Json<CreateUploadTokenResponse>)> {
+    if req.label.as_ref().is_some_and(|l| l.chars().count() > MAX_POST_KEY_LABEL_LENGTH) {
+        return Err(AppError::BadRequest(format!(
+            "Label must be at most {} characters",
+            MAX_POST_KEY_LABEL_LENGTH
+        )));
+    }
+    if req.max_uses.is_some_and(|n| n < 1) {
+        return Err(AppError::BadRequest("max_uses must be at least 1".to_string()));
+    }
+    if req.valid_hours.is_some_and(|h| h < 1) {
+        return Err(AppError::BadRequest("valid_hours must be at least 1".to_string()));
+    }
+    if req.max_expiry_hours.is_some_and(|h| h < 1) {
+        return Err(AppError::BadRequest("max_expiry_hours must be at least 1".to_string()));
+    }
+    // Request bodies are capped at startup, so a token can't allow more than that
+    let config = state.config.load();
+    let body_limit = (MAX_UPLOAD_SIZE as u64).max(config.api_key_max_upload_bytes);
+    if req.max_size_bytes.is_some_and(|n| n < 1 || n as u64 > body_limit) {
+        return Err(AppError::BadRequest(format!(
+            "max_size_bytes must be between 1 and {}",
+            body_limit
+        )));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let token = UploadToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        label: req.label,
+        max_size_bytes: req.max_size_bytes,
+        max_expiry_hours: req.max_expiry_hours,
+        // A token needs some bound: single-use unless it expires on its own
+        uses_remaining: match (req.max_uses, req.valid_hours) {
+            (Some(n), _) => Some(n),
+            (None, Some(_)) => None,
+            (None, None) => Some(1),
+        },
+        expires_at: req.valid_hours.map(|h| now + h * 60 * 60),
+        created_at: now,
+    };
+    let upload_token = FileRecord::generate_upload_token();
+    let token_hash = blake3::hash(upload_token.as_bytes()).to_hex().to_string();
+    state.db.create_upload_token(&token, &token_hash).await?;
+
+    tracing::warn!("Admin minted upload token {}", token.id);
+    state.db.audit(AuditActor::Admin, "upload_token.create", Some(&token.id), None).await;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateUploadTokenResponse {
+            token_id: token.id,
+            upload_token,
+            uses_remaining: token.uses_remaining,
+            expires_at: token.expires_at,
+        }),
+    ))
+}
+
+/// Revoke an upload token
+#[utoipa::path(
+    delete,
+    path = "/api/admin/upload-tokens/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Token ID")
+    ),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Token not found")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn revoke_upload_token(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.db.revoke_upload_token(&id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    tracing::warn!("Admin revoked upload token {}", id);
+    state.db.audit(AuditActor::Admin, "upload_token.revoke", Some(&id), None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    action: Option<String>,
+    actor: Option<String>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// Query the audit log (newest first)
+///
+/// Records administrative and destructive actions with their actor and target ID.
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit-log",
+    tag = "admin",
+    params(
+        ("action" = Option<String>, Query, description = "Only this action (e.g. file.delete, hash.block)"),
+        ("actor" = Option<String>, Query, description = "Only this actor (admin or system)"),
+        ("offset" = Option<i64>, Query, description = "Rows to skip (default 0)"),
+        ("limit" = Option<i64>, Query, description = "Page size (default 50, max 500)")
+    ),
+    responses(
+        (status = 200, description = "Page of audit log entries", body = AuditLogResponse),
+        (status = 401, description = "Missing or invalid admin token")
+    ),
+    security(("admin_token" = []))
+)]
+pub async fn audit_log(
+    State(state): State<AppState>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<AuditLogResponse>> {
+    let offset = query.offset.unwrap_or(0).max(0);
+    let limit = query
+        .limit
+        .unwrap_or(ADMIN_DEFAULT_PAGE_SIZE)
+        .clamp(1, ADMIN_MAX_PAGE_SIZE);
+
+    let (entries, total) = state
+        .db
+        .list_audit_log(query.action.as_deref(), query.actor.as_deref(), offset, limit)
+        .await?;
+
+    Ok(Json(AuditLogResponse {
+        entries,
+        total,
+        offset,
+        limit,
+    }))
+}
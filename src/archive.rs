@@ -0,0 +1,107 @@
+//! Tar archives of whole posts for backup (`GET /api/posts/{id}/archive`)
+//!
+//! The archive holds `manifest.json` followed by one `entries/NNNN.enc` member per
+//! appended entry. Entries are the raw encrypted bytes exactly as uploaded, so the
+//! archive is as opaque to the server as the post itself.
+
+use crate::models::{FileRecord, PostContent};
+use axum::body::Bytes;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Tar block size (members are padded to a multiple of this)
+const BLOCK_SIZE: usize = 512;
+
+/// Describes the post and maps archive members back to entries
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArchiveManifest {
+    pub post_id: String,
+    pub is_permanent: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub uploaded_at: DateTime<Utc>,
+    pub entries: Vec<ArchiveManifestEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArchiveManifestEntry {
+    /// Archive member holding the encrypted entry
+    pub path: String,
+    pub order: i64,
+    pub content_type: String,
+    pub appended_at: DateTime<Utc>,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    /// Plaintext size reported by the uploader (for file entries)
+    pub file_size: Option<i64>,
+    /// Size of the encrypted member in bytes
+    pub size_bytes: u64,
+}
+
+/// Build the tar stream for a post and its entries
+pub fn post_archive(
+    post: &FileRecord,
+    entries: Vec<PostContent>,
+) -> serde_json::Result<impl Stream<Item = std::io::Result<Bytes>>> {
+    let mut manifest = ArchiveManifest {
+        post_id: post.id.clone(),
+        is_permanent: post.is_permanent,
+        expires_at: if post.is_permanent { None } else { Some(post.expires_at) },
+        uploaded_at: post.uploaded_at,
+        entries: Vec::with_capacity(entries.len()),
+    };
+
+    let mut members = Vec::with_capacity(entries.len() + 1);
+    for entry in entries {
+        // Entries are stored base64-encoded; archive the encrypted bytes themselves
+        // (anything that isn't valid base64 is archived exactly as stored)
+        let data = BASE64
+            .decode(&entry.content_encrypted)
+            .unwrap_or_else(|_| entry.content_encrypted.clone().into_bytes());
+        let path = format!("entries/{:04}.enc", entry.content_order);
+
+        manifest.entries.push(ArchiveManifestEntry {
+            path: path.clone(),
+            order: entry.content_order,
+            content_type: entry.content_type,
+            appended_at: entry.appended_at,
+            mime_type: entry.mime_type,
+            file_extension: entry.file_extension,
+            file_size: entry.file_size,
+            size_bytes: data.len() as u64,
+        });
+        members.push((path, entry.appended_at.timestamp(), data));
+    }
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    members.insert(0, ("manifest.json".to_string(), Utc::now().timestamp(), manifest_json));
+
+    let chunks = members
+        .into_iter()
+        .flat_map(|(path, mtime, data)| tar_member(&path, mtime, data))
+        .chain(std::iter::once(Bytes::from(vec![0u8; BLOCK_SIZE * 2]))) // end-of-archive marker
+        .map(Ok);
+
+    Ok(stream::iter(chunks))
+}
+
+/// Header, data and padding for one regular-file member
+fn tar_member(path: &str, mtime: i64, data: Vec<u8>) -> Vec<Bytes> {
+    let mut header = tar::Header::new_ustar();
+    // Paths are generated above and always fit the ustar name field
+    let _ = header.set_path(path);
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime.max(0) as u64);
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_cksum();
+
+    let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+    vec![
+        Bytes::copy_from_slice(header.as_bytes()),
+        Bytes::from(data),
+        Bytes::from(vec![0u8; padding]),
+    ]
+}
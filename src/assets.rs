@@ -0,0 +1,84 @@
+//! Static assets
+//!
+//! Everything under `static/` is compiled into the binary, so a release build
+//! runs as a single file from any working directory. Setting STATIC_DIR serves
+//! that directory from disk instead, for working on the frontend without
+//! rebuilding. HTML pages are templates, see [`crate::pages`].
+
+use crate::state::AppState;
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+use std::borrow::Cow;
+use tower_http::services::ServeDir;
+
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Embedded;
+
+/// Read a file under `static/`, from STATIC_DIR when it is set
+async fn load(state: &AppState, name: &str) -> Option<Cow<'static, [u8]>> {
+    let static_dir = state.config.load().static_dir.clone();
+    match static_dir {
+        Some(dir) => tokio::fs::read(std::path::Path::new(&dir).join(name)).await.ok().map(Cow::Owned),
+        None => Embedded::get(name).map(|file| file.data),
+    }
+}
+
+/// `/favicon.ico`, falling back to the embedded icon when STATIC_DIR doesn't have one
+///
+/// Browsers request it on every page load, so it is cacheable for a day.
+pub async fn favicon(state: &AppState) -> Response {
+    let content = match load(state, "favicon.ico").await {
+        Some(content) => Some(content),
+        None => Embedded::get("favicon.ico").map(|file| file.data),
+    };
+    match content {
+        Some(content) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "image/x-icon"),
+                (header::CACHE_CONTROL, "public, max-age=86400"),
+            ],
+            content,
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, "Favicon not found").into_response(),
+    }
+}
+
+/// Routes for `/static/*`
+pub fn router(static_dir: Option<&str>) -> Router<AppState> {
+    match static_dir {
+        Some(dir) => Router::new().fallback_service(ServeDir::new(dir)),
+        None => Router::new().route("/*path", get(serve_embedded)),
+    }
+}
+
+/// Embedded lookups are by exact name, so `..` can't escape the asset set
+async fn serve_embedded(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let Some(file) = Embedded::get(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    // Assets only change with the binary, so their content hash makes a stable ETag
+    let etag = format!("\"{}\"", &hex::encode(file.metadata.sha256_hash())[..16]);
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if headers.get(header::IF_NONE_MATCH).is_some_and(|value| value.as_bytes() == etag.as_bytes()) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (
+        cache_headers,
+        [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+        file.data,
+    )
+        .into_response()
+}
@@ -0,0 +1,336 @@
+//! Whole-instance backups (`dogbox backup --out <path>`, `dogbox restore <archive>`)
+//!
+//! The backup is a tar archive holding `dogbox.db`, a consistent SQLite snapshot,
+//! one `blobs/<file id>` member per stored blob, and `manifest.json` (written last,
+//! once it's known which blobs made it in). It is safe to take while the server is
+//! running: files that expire between the snapshot and copying their blob are
+//! listed as missing in the manifest instead of failing the backup.
+//!
+//! Restoring extracts and verifies everything (blob sizes and BLAKE3 hashes) in a
+//! staging directory before touching the target database or storage, and only
+//! restores into an empty database and upload directory.
+
+use crate::config::Config;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::storage;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio_util::io::StreamReader;
+
+/// Bumped whenever the archive layout changes incompatibly
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Archive member holding the database snapshot
+pub const DATABASE_MEMBER: &str = "dogbox.db";
+
+/// Archive member holding the manifest
+pub const MANIFEST_MEMBER: &str = "manifest.json";
+
+/// Tar block size (members are padded to a multiple of this)
+const BLOCK_SIZE: u64 = 512;
+
+/// Describes a backup and maps archive members back to file rows
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    /// Version of dogbox that wrote the backup (and the migrations it had applied)
+    pub dogbox_version: String,
+    pub created_at: DateTime<Utc>,
+    /// Archive member holding the SQLite snapshot
+    pub database: String,
+    pub blobs: Vec<BackupBlob>,
+    /// Files in the snapshot whose blobs were deleted before they could be copied
+    pub missing_blobs: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupBlob {
+    pub file_id: String,
+    /// Storage key recorded in `files.storage_path`
+    pub storage_path: String,
+    /// Archive member holding the encrypted blob
+    pub path: String,
+    pub size_bytes: u64,
+    /// BLAKE3 hash of the encrypted blob, as recorded in the database
+    pub blake3_hash: String,
+}
+
+/// Write a backup of the database and every stored blob to `out`
+pub async fn create(config: &Config, out: &Path) -> anyhow::Result<BackupManifest> {
+    let partial = sibling(out, "partial");
+    let snapshot = sibling(out, "db.partial");
+
+    let result = write_backup(config, out, &partial, &snapshot).await;
+    let _ = fs::remove_file(&snapshot).await;
+    if result.is_err() {
+        let _ = fs::remove_file(&partial).await;
+    }
+    result
+}
+
+async fn write_backup(
+    config: &Config,
+    out: &Path,
+    partial: &Path,
+    snapshot: &Path,
+) -> anyhow::Result<BackupManifest> {
+    // VACUUM INTO refuses to overwrite, so clear anything an earlier failed run left behind
+    let _ = fs::remove_file(snapshot).await;
+
+    let db = Database::new(&config.database_url, &config.sqlite).await?;
+    db.snapshot_into(snapshot).await?;
+    tracing::info!("📸 Snapshotted database");
+
+    // Blob list comes from the snapshot so it matches the rows being backed up
+    let blobs = Database::open_read_only(snapshot).await?.list_stored_blobs().await?;
+    let storage = storage::from_config(config)?;
+
+    let mut archive = BufWriter::new(fs::File::create(partial).await?);
+    let created_at = Utc::now();
+    let mtime = created_at.timestamp().max(0) as u64;
+
+    let db_file = fs::File::open(snapshot).await?;
+    let db_size = db_file.metadata().await?.len();
+    write_member(&mut archive, DATABASE_MEMBER, mtime, db_size, db_file).await?;
+
+    let mut manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        dogbox_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at,
+        database: DATABASE_MEMBER.to_string(),
+        blobs: Vec::with_capacity(blobs.len()),
+        missing_blobs: Vec::new(),
+    };
+
+    for blob in blobs {
+        let (size, stream) = match storage.stream(&blob.storage_path).await {
+            Ok(opened) => opened,
+            Err(e) if is_missing(&e) => {
+                tracing::warn!("⚠️  Blob for {} disappeared before it was copied (expired?)", blob.id);
+                manifest.missing_blobs.push(blob.id);
+                continue;
+            }
+            Err(e) => anyhow::bail!("Failed to read blob {}: {}", blob.storage_path, e),
+        };
+
+        let path = format!("blobs/{}", blob.id);
+        let reader = StreamReader::new(stream.map_err(std::io::Error::other));
+        write_member(&mut archive, &path, mtime, size, reader).await?;
+
+        manifest.blobs.push(BackupBlob {
+            file_id: blob.id,
+            storage_path: blob.storage_path,
+            path,
+            size_bytes: size,
+            blake3_hash: blob.blake3_hash,
+        });
+    }
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    write_member(&mut archive, MANIFEST_MEMBER, mtime, manifest_json.len() as u64, &manifest_json[..]).await?;
+
+    // End-of-archive marker
+    archive.write_all(&[0u8; BLOCK_SIZE as usize * 2]).await?;
+    archive.flush().await?;
+    archive.into_inner().sync_all().await?;
+
+    fs::rename(partial, out).await?;
+    Ok(manifest)
+}
+
+/// S3 reports a vanished blob as `NotFound`, local storage as an IO error
+pub fn is_missing(e: &AppError) -> bool {
+    match e {
+        AppError::NotFound => true,
+        AppError::Io(io) => io.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// `<out>.<suffix>` next to the output, so the final rename stays on one filesystem
+fn sibling(out: &Path, suffix: &str) -> PathBuf {
+    let mut name = out.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    out.with_file_name(name)
+}
+
+/// Header, exactly `size` bytes from `data`, and padding for one regular-file member
+async fn write_member<W, R>(out: &mut W, path: &str, mtime: u64, size: u64, data: R) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let mut header = tar::Header::new_gnu();
+    header.set_path(path)?;
+    header.set_size(size);
+    header.set_mode(0o600);
+    header.set_mtime(mtime);
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_cksum();
+    out.write_all(header.as_bytes()).await?;
+
+    let copied = tokio::io::copy(&mut data.take(size), out).await?;
+    if copied != size {
+        anyhow::bail!("{} changed while it was being backed up ({} of {} bytes read)", path, copied, size);
+    }
+
+    let padding = (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE;
+    out.write_all(&vec![0u8; padding as usize]).await?;
+    Ok(())
+}
+
+/// What a restore brought back
+#[derive(Debug)]
+pub struct RestoreSummary {
+    pub backup_created_at: DateTime<Utc>,
+    pub blobs: usize,
+    /// File rows dropped because the backup had no blob for them
+    pub dropped_files: usize,
+}
+
+/// Archive member extracted to the staging directory
+struct StagedMember {
+    path: PathBuf,
+    size_bytes: u64,
+    blake3_hash: String,
+}
+
+/// Restore a backup written by `create` into the configured (empty) database and storage
+pub async fn restore(config: &Config, archive: &Path) -> anyhow::Result<RestoreSummary> {
+    let db_path = Database::file_path(&config.database_url)?;
+    if fs::metadata(&db_path).await.is_ok_and(|m| m.len() > 0) {
+        anyhow::bail!(
+            "Refusing to restore over existing database {}; point DATABASE_URL at a new file",
+            db_path.display()
+        );
+    }
+
+    fs::create_dir_all(&config.upload_dir).await?;
+    let mut entries = fs::read_dir(&config.upload_dir).await?;
+    if entries.next_entry().await?.is_some() {
+        anyhow::bail!("Refusing to restore into non-empty upload directory {}", config.upload_dir);
+    }
+
+    // Staged inside the upload directory so committing a blob is a rename
+    let staging = Path::new(&config.upload_dir).join(format!(".restore-{}", uuid::Uuid::new_v4()));
+    fs::create_dir(&staging).await?;
+
+    let result = restore_staged(config, archive, &db_path, &staging).await;
+    let _ = fs::remove_dir_all(&staging).await;
+    result
+}
+
+async fn restore_staged(
+    config: &Config,
+    archive: &Path,
+    db_path: &Path,
+    staging: &Path,
+) -> anyhow::Result<RestoreSummary> {
+    let (archive, staging_dir) = (archive.to_path_buf(), staging.to_path_buf());
+    let mut members = tokio::task::spawn_blocking(move || extract(&archive, &staging_dir)).await??;
+
+    let manifest_member = members
+        .remove(MANIFEST_MEMBER)
+        .ok_or_else(|| anyhow::anyhow!("Not a dogbox backup: {} is missing", MANIFEST_MEMBER))?;
+    let manifest: BackupManifest = serde_json::from_slice(&fs::read(&manifest_member.path).await?)?;
+    if manifest.format_version > FORMAT_VERSION {
+        anyhow::bail!(
+            "Backup format {} is newer than this dogbox supports ({}); upgrade first",
+            manifest.format_version,
+            FORMAT_VERSION
+        );
+    }
+
+    let database = members
+        .remove(&manifest.database)
+        .ok_or_else(|| anyhow::anyhow!("Backup is missing its database ({})", manifest.database))?;
+
+    // Verify every blob before anything is committed
+    let mut verified = Vec::with_capacity(manifest.blobs.len());
+    for blob in &manifest.blobs {
+        let staged = members
+            .remove(&blob.path)
+            .ok_or_else(|| anyhow::anyhow!("Backup is missing blob {} for file {}", blob.path, blob.file_id))?;
+        if staged.size_bytes != blob.size_bytes || staged.blake3_hash != blob.blake3_hash {
+            anyhow::bail!("Blob {} for file {} is corrupt (size or BLAKE3 hash mismatch)", blob.path, blob.file_id);
+        }
+        verified.push((blob, staged));
+    }
+    tracing::info!("✓ Verified {} blobs", verified.len());
+
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::copy(&database.path, db_path).await?;
+    let db = Database::new(&config.database_url, &config.sqlite).await?;
+    if config.run_migrations {
+        db.migrate().await?;
+    }
+
+    // Rows whose blobs expired while the backup was being taken would only 404
+    let mut dropped_files = 0;
+    for file_id in &manifest.missing_blobs {
+        if db.force_delete_file(file_id).await?.is_some() {
+            dropped_files += 1;
+        }
+    }
+
+    let storage = storage::from_config(config)?;
+    for (blob, staged) in &verified {
+        storage.put(&blob.storage_path, &staged.path).await?;
+    }
+
+    Ok(RestoreSummary {
+        backup_created_at: manifest.created_at,
+        blobs: verified.len(),
+        dropped_files,
+    })
+}
+
+/// Extract every member of `archive` into `staging`, hashing as it goes
+///
+/// Member names are untrusted, so staged files get generated names.
+fn extract(archive: &Path, staging: &Path) -> anyhow::Result<HashMap<String, StagedMember>> {
+    let mut tar = tar::Archive::new(std::io::BufReader::new(std::fs::File::open(archive)?));
+    let mut members = HashMap::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    for (index, entry) in tar.entries()?.enumerate() {
+        let mut entry = entry?;
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+
+        let path = staging.join(format!("{}.part", index));
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        let mut hasher = blake3::Hasher::new();
+        let mut size_bytes = 0u64;
+        loop {
+            let n = entry.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            file.write_all(&buf[..n])?;
+            size_bytes += n as u64;
+        }
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+        members.insert(name, StagedMember {
+            path,
+            size_bytes,
+            blake3_hash: hasher.finalize().to_hex().to_string(),
+        });
+    }
+
+    Ok(members)
+}
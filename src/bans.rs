@@ -0,0 +1,73 @@
+//! IP / CIDR bans
+//!
+//! Bans live in the `banned_networks` table and are mirrored in memory so every
+//! request can be checked without a query. The copy is reloaded whenever the admin
+//! API changes a ban, and periodically so bans made through another instance sharing
+//! the database are picked up. Expired bans stop matching right away and are
+//! deleted by the cleanup task.
+
+use crate::constants::BAN_REFRESH_SECS;
+use crate::database::Database;
+use crate::models::BanScope;
+use crate::state::AppState;
+use arc_swap::ArcSwap;
+use ipnet::IpNet;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Ban {
+    network: IpNet,
+    scope: BanScope,
+    expires_at: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct BanList {
+    bans: ArcSwap<Vec<Ban>>,
+}
+
+impl BanList {
+    /// Replace the in-memory copy with the unexpired bans in the database
+    pub async fn reload(&self, db: &Database) -> anyhow::Result<()> {
+        let bans = db
+            .list_bans(false)
+            .await?
+            .into_iter()
+            .filter_map(|ban| {
+                Some(Ban {
+                    network: ban.network.parse().ok()?,
+                    scope: ban.scope.parse().ok()?,
+                    expires_at: ban.expires_at,
+                })
+            })
+            .collect();
+        self.bans.store(Arc::new(bans));
+        Ok(())
+    }
+
+    /// The widest scope `ip` is banned from, if any
+    pub fn scope_for(&self, ip: IpAddr) -> Option<BanScope> {
+        // Dual-stack listeners report IPv4 clients as ::ffff:a.b.c.d
+        let ip = ip.to_canonical();
+        let now = chrono::Utc::now().timestamp();
+        self.bans
+            .load()
+            .iter()
+            .filter(|ban| ban.expires_at.is_none_or(|expires_at| expires_at > now))
+            .filter(|ban| ban.network.contains(&ip))
+            .map(|ban| ban.scope)
+            .max()
+    }
+}
+
+/// Background task picking up bans changed by other instances
+pub async fn start_refresh_task(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(BAN_REFRESH_SECS));
+    loop {
+        interval.tick().await;
+        if let Err(e) = state.bans.reload(&state.db).await {
+            tracing::error!("❌ Reloading IP bans failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Captcha verification for uploads (Cloudflare Turnstile or hCaptcha)
+//!
+//! The upload pages render the provider's widget; its token is sent as
+//! `X-Captcha-Token` and checked against the provider's siteverify API, which
+//! both providers implement the same way.
+
+use crate::config::CaptchaConfig;
+use crate::constants::CAPTCHA_VERIFY_TIMEOUT_SECS;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+struct VerifyResponse {
+    success: bool,
+    #[serde(rename = "error-codes", default)]
+    error_codes: Vec<String>,
+}
+
+pub struct Captcha {
+    config: CaptchaConfig,
+    client: reqwest::Client,
+}
+
+impl Captcha {
+    pub fn new(config: CaptchaConfig) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(CAPTCHA_VERIFY_TIMEOUT_SECS))
+            .user_agent(concat!("dogbox/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self { config, client })
+    }
+
+    /// Whether the provider accepts `token` (Err when the provider can't be reached)
+    pub async fn verify(&self, token: &str, remote_ip: Option<IpAddr>) -> anyhow::Result<bool> {
+        let mut form = vec![("secret", self.config.secret.clone()), ("response", token.to_string())];
+        if let Some(ip) = remote_ip {
+            form.push(("remoteip", ip.to_string()));
+        }
+
+        let response: VerifyResponse = self
+            .client
+            .post(&self.config.verify_url)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !response.success {
+            tracing::debug!("Captcha rejected: {}", response.error_codes.join(", "));
+        }
+        Ok(response.success)
+    }
+}
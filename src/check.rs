@@ -0,0 +1,81 @@
+//! `dogbox --check-config`: validate a deployment without serving traffic
+//!
+//! Prints one line per check and fails if any check failed, so it can gate a
+//! CI/CD rollout.
+
+use crate::config::Config;
+use crate::database::Database;
+use std::path::{Path, PathBuf};
+
+struct Report {
+    failed: usize,
+}
+
+impl Report {
+    fn record(&mut self, check: &str, outcome: anyhow::Result<String>) {
+        match outcome {
+            Ok(detail) => println!("✓ {}: {}", check, detail),
+            Err(e) => {
+                self.failed += 1;
+                println!("✗ {}: {}", check, e);
+            }
+        }
+    }
+}
+
+/// Run every check and return an error if any failed
+pub async fn run(config_file: Option<&Path>) -> anyhow::Result<()> {
+    let mut report = Report { failed: 0 };
+
+    // Config validation covers the admin message, tokens, CSP, TLS pairing, etc.
+    let config = match Config::load(config_file) {
+        Ok(config) => config,
+        Err(e) => {
+            report.record("config", Err(e));
+            anyhow::bail!("Configuration check failed");
+        }
+    };
+    let source = match config_file {
+        Some(path) => format!("loaded (environment + {})", path.display()),
+        None => "loaded (environment)".to_string(),
+    };
+    report.record("config", Ok(source));
+
+    report.record(
+        "admin message",
+        Ok(match &config.admin_message {
+            Some(msg) => format!("valid ({} characters)", msg.len()),
+            None => "not set".to_string(),
+        }),
+    );
+
+    report.record("database", check_database(&config).await);
+    report.record("upload directory", check_upload_dir(&config.upload_dir).await);
+
+    if report.failed > 0 {
+        anyhow::bail!("{} configuration check(s) failed", report.failed);
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+async fn check_database(config: &Config) -> anyhow::Result<String> {
+    let db = Database::new(&config.database_url, &config.sqlite).await?;
+    db.ping().await?;
+    Ok(format!("connected to {}", config.database_url))
+}
+
+/// The upload directory must resolve to a real path and accept new files
+pub async fn check_upload_dir(upload_dir: &str) -> anyhow::Result<String> {
+    let canonical = PathBuf::from(upload_dir)
+        .canonicalize()
+        .map_err(|e| anyhow::anyhow!("{} can't be resolved: {}", upload_dir, e))?;
+
+    let probe = canonical.join(format!(".dogbox-check-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&probe, b"")
+        .await
+        .map_err(|e| anyhow::anyhow!("{} is not writable: {}", canonical.display(), e))?;
+    tokio::fs::remove_file(&probe).await?;
+
+    Ok(format!("{} is writable", canonical.display()))
+}
@@ -1,8 +1,8 @@
-use crate::config::Config;
-use crate::constants::CLEANUP_INTERVAL_SECS;
-use crate::database::Database;
-use crate::services::FileService;
+use crate::constants::STATS_SNAPSHOT_INTERVAL_SECS;
+use crate::models::{AuditActor, CleanupReport};
+use crate::state::AppState;
 use chrono::{DateTime, Utc};
+use rand::Rng;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -12,20 +12,43 @@ use tokio::time;
 pub static NEXT_TEST_DELETE: once_cell::sync::Lazy<Arc<RwLock<Option<DateTime<Utc>>>>> =
     once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(None)));
 
+/// When the last expired-file cleanup finished successfully (reported by /api/health)
+pub static LAST_CLEANUP: once_cell::sync::Lazy<Arc<RwLock<Option<DateTime<Utc>>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(None)));
+
+/// When the cleanup task started, so /api/health can tell "not run yet" from "stuck"
+pub static CLEANUP_STARTED: once_cell::sync::Lazy<Arc<RwLock<Option<DateTime<Utc>>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(None)));
+
+/// One-line summary of a cleanup run
+pub fn log_report(report: &CleanupReport) {
+    tracing::info!(
+        "🗑️  Cleanup: {} expired files and pastes, {} abandoned uploads, {} KB reclaimed",
+        report.expired,
+        report.upload_sessions,
+        report.bytes_reclaimed / 1024
+    );
+}
+
 /// Background task to cleanup expired files
-pub async fn start_cleanup_task(config: Config) -> anyhow::Result<()> {
-    let db = Database::new(&config.database_url).await?;
-    let service = FileService::new(config.clone(), db.clone());
+///
+/// Interval and jitter aren't reloadable, so changing them needs a restart.
+pub async fn start_cleanup_task(state: AppState) -> anyhow::Result<()> {
+    // Test mode settings aren't reloadable, so a snapshot is fine
+    let config = state.config.load_full();
+    let db = state.db.clone();
+    let service = state.file_service.clone();
 
-    // Run cleanup every hour
-    let mut interval = time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
+    // Instances sharing a database start at random offsets instead of all at once
+    let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..=config.cleanup_jitter.as_millis() as u64));
+    let mut interval = time::interval_at(time::Instant::now() + jitter, config.cleanup_interval);
+    *CLEANUP_STARTED.write().await = Some(Utc::now());
+    let mut stats_interval = time::interval(Duration::from_secs(STATS_SNAPSHOT_INTERVAL_SECS));
 
     // For test mode: track deletion cycles based on configured period
-    let mut test_mode_interval = if let Some(period_hours) = config.test_delete_period_hours {
-        Some(time::interval(Duration::from_secs(period_hours as u64 * 3600)))
-    } else {
-        None
-    };
+    let mut test_mode_interval = config
+        .test_delete_period_hours
+        .map(|period_hours| time::interval(Duration::from_secs(period_hours as u64 * 3600)));
 
     if let Some(period_hours) = config.test_delete_period_hours {
         // Calculate and store next deletion time
@@ -34,22 +57,45 @@ pub async fn start_cleanup_task(config: Config) -> anyhow::Result<()> {
         tracing::warn!("🧪 TEST MODE: All data will be deleted every {} hours (next: {})", period_hours, next_delete);
     }
 
-    tracing::info!("🧹 Starting cleanup task (runs every hour)");
+    tracing::info!(
+        "🧹 Starting cleanup task (every {} minutes, first run in {} seconds)",
+        config.cleanup_interval.as_secs() / 60,
+        jitter.as_secs()
+    );
 
     loop {
         tokio::select! {
+            _ = stats_interval.tick() => {
+                // Hourly totals for the stats history chart, whatever the cleanup interval
+                if let Err(e) = db.record_stats_snapshot().await {
+                    tracing::error!("❌ Recording stats snapshot failed: {}", e);
+                }
+            }
             _ = interval.tick() => {
-                // Regular hourly cleanup of expired files
+                // Warn uploaders before their files go
+                match service.send_expiry_warnings().await {
+                    Ok(count) if count > 0 => tracing::info!("⏰ Sent {} expiry warnings", count),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("❌ Sending expiry warnings failed: {}", e),
+                }
+
+                // Regular cleanup of expired files
                 match service.cleanup_expired().await {
-                    Ok(count) => {
-                        if count > 0 {
-                            tracing::info!("🗑️  Cleaned up {} expired files", count);
-                        }
+                    Ok(report) => {
+                        *LAST_CLEANUP.write().await = Some(Utc::now());
+                        log_report(&report);
                     }
                     Err(e) => {
                         tracing::error!("❌ Cleanup task failed: {}", e);
                     }
                 }
+
+                // Lapsed IP bans already stopped matching; drop their rows
+                match db.delete_expired_bans().await {
+                    Ok(count) if count > 0 => tracing::info!("🧹 Removed {} expired IP bans", count),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("❌ Removing expired IP bans failed: {}", e),
+                }
             }
             _ = async {
                 if let Some(ref mut interval) = test_mode_interval {
@@ -64,11 +110,8 @@ pub async fn start_cleanup_task(config: Config) -> anyhow::Result<()> {
                     match db.truncate_all_tables().await {
                         Ok(_) => {
                             // Also delete uploaded files
-                            if let Err(e) = tokio::fs::remove_dir_all(&config.upload_dir).await {
-                                tracing::error!("❌ Failed to delete upload directory: {}", e);
-                            }
-                            if let Err(e) = tokio::fs::create_dir_all(&config.upload_dir).await {
-                                tracing::error!("❌ Failed to recreate upload directory: {}", e);
+                            if let Err(e) = service.delete_all_blobs().await {
+                                tracing::error!("❌ Failed to delete stored blobs: {}", e);
                             }
 
                             // Update next deletion time
@@ -76,6 +119,7 @@ pub async fn start_cleanup_task(config: Config) -> anyhow::Result<()> {
                             *NEXT_TEST_DELETE.write().await = Some(next_delete);
 
                             tracing::warn!("🧪 TEST MODE: All data wiped successfully (next: {})", next_delete);
+                            db.audit(AuditActor::System, "test_mode.wipe", None, None).await;
                         }
                         Err(e) => {
                             tracing::error!("❌ Test mode truncation failed: {}", e);
@@ -0,0 +1,65 @@
+//! Command-line interface
+//!
+//! Running `dogbox` without a subcommand starts the server, as before.
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Config file picked up from the working directory when `--config` is not given
+pub const DEFAULT_CONFIG_FILE: &str = "dogbox.toml";
+
+#[derive(Debug, Parser)]
+#[command(name = "dogbox", version, about = "Zero-knowledge encrypted file sharing")]
+pub struct Cli {
+    /// TOML config file (environment variables take precedence over its values)
+    #[arg(long, global = true, env = "DOGBOX_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Validate config, database and upload directory, then exit without serving
+    #[arg(long)]
+    pub check_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (default)
+    Serve,
+    /// Apply pending database migrations and exit
+    Migrate,
+    /// Delete expired files without starting the HTTP server
+    Cleanup {
+        /// Run a single pass and exit instead of repeating every CLEANUP_INTERVAL_SECS
+        #[arg(long)]
+        once: bool,
+    },
+    /// Print upload and storage statistics as JSON
+    Stats,
+    /// Print the GraphQL schema (SDL) for client code generators
+    GraphqlSchema,
+    /// Write a consistent backup (database snapshot, blobs and manifest) to a tar file
+    ///
+    /// Safe to run while the server is live.
+    Backup {
+        /// Tar file to write (replaced atomically once the backup is complete)
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Restore a backup into an empty database and upload directory, verifying every blob
+    Restore {
+        /// Tar file written by `dogbox backup`
+        archive: PathBuf,
+    },
+}
+
+impl Cli {
+    /// The explicit `--config` path, or `dogbox.toml` if one exists
+    pub fn config_file(&self) -> Option<PathBuf> {
+        self.config.clone().or_else(|| {
+            let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+            default.exists().then_some(default)
+        })
+    }
+}
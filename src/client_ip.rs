@@ -0,0 +1,51 @@
+//! Proxy-aware client IP resolution
+//!
+//! Forwarding headers are only honored when the TCP peer is a configured trusted
+//! proxy; otherwise any client could spoof its address by setting them.
+
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, Request};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+fn is_trusted(ip: &IpAddr, trusted_proxies: &[IpNet]) -> bool {
+    trusted_proxies.iter().any(|net| net.contains(ip))
+}
+
+/// Resolve the real client IP for a request received from `peer`
+///
+/// `X-Forwarded-For` is walked right to left, skipping trusted proxies, so a client
+/// cannot inject a fake address in front of the ones our proxies appended.
+/// `X-Real-IP` is used as a fallback.
+pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    if !is_trusted(&peer, trusted_proxies) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+
+    if let Some(ip) = forwarded.iter().rev().find(|ip| !is_trusted(ip, trusted_proxies)) {
+        return *ip;
+    }
+    if let Some(ip) = forwarded.first() {
+        return *ip;
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// Resolve the client IP of an incoming request (requires `ConnectInfo<SocketAddr>`)
+pub fn from_request<T>(req: &Request<T>, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>()?.0.ip();
+    Some(resolve(peer, req.headers(), trusted_proxies))
+}
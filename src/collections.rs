@@ -0,0 +1,182 @@
+//! Collections (albums) grouping separately uploaded files under one link
+//!
+//! A collection only references files by ID; members keep their own expiry and
+//! drop out of the collection when they expire or are deleted. Decryption keys
+//! are the client's business (e.g. in the share link's fragment).
+
+use crate::constants::{MAX_COLLECTION_FILES, MAX_COLLECTION_FILES_PER_REQUEST};
+use crate::error::{AppError, Result};
+use crate::models::*;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+
+/// Load a collection and check its key (constant-time)
+async fn authorize(state: &AppState, collection_id: &str, collection_key: &str) -> Result<CollectionRecord> {
+    let collection = state
+        .db
+        .get_collection(collection_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if !bool::from(collection_key.as_bytes().ct_eq(collection.collection_key.as_bytes())) {
+        return Err(AppError::Forbidden("Invalid collection key".to_string()));
+    }
+
+    Ok(collection)
+}
+
+/// Create an empty collection
+#[utoipa::path(
+    post,
+    path = "/api/collections",
+    tag = "dogbox.moe",
+    request_body = CreateCollectionRequest,
+    responses(
+        (status = 200, description = "Collection created", body = CreateCollectionResponse)
+    )
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    Json(req): Json<CreateCollectionRequest>,
+) -> Result<Json<CreateCollectionResponse>> {
+    let collection = CollectionRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        collection_key: format!("DOGBOX_KEY_COLLECTION_{}", uuid::Uuid::new_v4()),
+        title_encrypted: req.title_encrypted,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    state.db.create_collection(&collection).await?;
+
+    tracing::info!("Created collection {}", collection.id);
+
+    Ok(Json(CreateCollectionResponse {
+        collection_id: collection.id,
+        collection_key: collection.collection_key,
+    }))
+}
+
+/// View a collection's member file metadata
+#[utoipa::path(
+    get,
+    path = "/api/collections/{id}",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Collection ID")
+    ),
+    responses(
+        (status = 200, description = "Collection with member metadata", body = CollectionResponse),
+        (status = 404, description = "Collection not found")
+    )
+)]
+pub async fn view(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CollectionResponse>> {
+    let collection = state.db.get_collection(&id).await?.ok_or(AppError::NotFound)?;
+    let mut files = state.db.list_collection_files(&id).await?;
+    if !state.config.load().analytics_enabled {
+        files.iter_mut().for_each(|file| file.view_count = None);
+    }
+
+    Ok(Json(CollectionResponse {
+        collection_id: collection.id,
+        title_encrypted: collection.title_encrypted,
+        created_at: collection.created_at,
+        files,
+    }))
+}
+
+/// Add files to a collection
+#[utoipa::path(
+    post,
+    path = "/api/collections/{id}/files",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Collection ID")
+    ),
+    request_body = AddCollectionFilesRequest,
+    responses(
+        (status = 200, description = "Files added", body = AddCollectionFilesResponse),
+        (status = 400, description = "Unknown file or too many files"),
+        (status = 403, description = "Invalid collection key"),
+        (status = 404, description = "Collection not found")
+    )
+)]
+pub async fn add_files(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<AddCollectionFilesRequest>,
+) -> Result<Json<AddCollectionFilesResponse>> {
+    authorize(&state, &id, &req.collection_key).await?;
+
+    if req.file_ids.is_empty() || req.file_ids.len() > MAX_COLLECTION_FILES_PER_REQUEST {
+        return Err(AppError::BadRequest(format!(
+            "file_ids must contain between 1 and {} IDs",
+            MAX_COLLECTION_FILES_PER_REQUEST
+        )));
+    }
+
+    let existing = state.db.count_collection_files(&id).await?;
+    if existing + req.file_ids.len() as i64 > MAX_COLLECTION_FILES {
+        return Err(AppError::BadRequest(format!(
+            "Maximum collection size reached ({} files)",
+            MAX_COLLECTION_FILES
+        )));
+    }
+
+    for file_id in &req.file_ids {
+        if state.db.get_file(file_id).await?.is_none() {
+            return Err(AppError::BadRequest(format!("File {} not found or expired", file_id)));
+        }
+    }
+
+    let added = state.db.add_collection_files(&id, &req.file_ids).await?;
+
+    Ok(Json(AddCollectionFilesResponse {
+        added,
+        total: existing + added as i64,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionKeyQuery {
+    collection_key: String,
+}
+
+/// Remove a file from a collection (the file itself is untouched)
+#[utoipa::path(
+    delete,
+    path = "/api/collections/{id}/files/{file_id}",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Collection ID"),
+        ("file_id" = String, Path, description = "Member file ID"),
+        ("collection_key" = String, Query, description = "Collection key")
+    ),
+    responses(
+        (status = 200, description = "File removed from the collection", body = DeleteResponse),
+        (status = 403, description = "Invalid collection key"),
+        (status = 404, description = "Collection or member not found")
+    )
+)]
+pub async fn remove_file(
+    State(state): State<AppState>,
+    Path((id, file_id)): Path<(String, String)>,
+    Query(query): Query<CollectionKeyQuery>,
+) -> Result<Json<DeleteResponse>> {
+    authorize(&state, &id, &query.collection_key).await?;
+
+    if !state.db.remove_collection_file(&id, &file_id).await? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(Json(DeleteResponse {
+        success: true,
+        message: "File removed from collection".to_string(),
+    }))
+}
@@ -1,23 +1,463 @@
+use arc_swap::ArcSwap;
+use crate::constants::{
+    DEFAULT_CLEANUP_INTERVAL_SECS, DEFAULT_CLEANUP_JITTER_SECS, DEFAULT_CSP_SCRIPT_HASHES, DEFAULT_MAX_DOGPASTE_BYTES, DEFAULT_MAX_POST_BYTES,
+    DEFAULT_MAX_POST_ENTRIES, DEFAULT_MAX_POST_ENTRY_BYTES, DEFAULT_MAX_POST_TOTAL_BYTES, DEFAULT_ROBOTS_DISALLOW, DEFAULT_SHORT_ID_LENGTH, MAX_SECURE_DELETE_PASSES, MAX_UPLOAD_SIZE,
+    DEFAULT_POW_DIFFICULTY, DEFAULT_TOR_EXIT_LIST_REFRESH_MINS, DEFAULT_TOR_EXIT_LIST_URL, MAX_POW_DIFFICULTY,
+    MIN_ADMIN_TOKEN_LENGTH, MIN_POW_DIFFICULTY,
+};
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where committed encrypted blobs are stored
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    /// Files in `upload_dir`
+    Local,
+    /// S3-compatible bucket (credentials from the standard AWS_* variables)
+    S3 {
+        bucket: String,
+        region: String,
+        endpoint: Option<String>,
+        allow_http: bool,
+    },
+}
+
+/// Scheduled incremental backups to an S3-compatible bucket
+#[derive(Debug, Clone)]
+pub struct RemoteBackupConfig {
+    pub bucket: String,
+    pub region: String,
+    pub endpoint: Option<String>,
+    pub allow_http: bool,
+    /// Key prefix everything is written under
+    pub prefix: String,
+    pub interval: Duration,
+    /// Snapshots older than this are pruned (the newest is always kept)
+    pub retention: Duration,
+}
+
+/// Country policy for uploads, looked up in a MaxMind GeoIP2 / GeoLite2 database
+#[derive(Debug, Clone)]
+pub struct GeoIpConfig {
+    /// Path to a Country or City `.mmdb` file
+    pub database: String,
+    /// ISO 3166-1 alpha-2 codes (uppercase) uploads are rejected from
+    pub blocked_countries: Vec<String>,
+    /// When non-empty, uploads are only accepted from these countries
+    pub allowed_countries: Vec<String>,
+}
+
+/// Declared MIME types and extensions accepted for files and post attachments
+///
+/// Uploads are encrypted, so this only sees what the client declares: it keeps
+/// honest clients (and the bundled frontend) from sharing content classes the
+/// operator can't host, but can't inspect the content itself.
+#[derive(Debug, Clone, Default)]
+pub struct ContentPolicy {
+    /// MIME types (or `type/*` patterns) accepted; empty accepts any
+    pub allowed_mime_types: Vec<String>,
+    pub blocked_mime_types: Vec<String>,
+    /// Extensions (lowercase, without the dot) accepted; empty accepts any
+    pub allowed_extensions: Vec<String>,
+    pub blocked_extensions: Vec<String>,
+}
+
+impl ContentPolicy {
+    /// Reason the declared type is refused, if it is
+    pub fn check(&self, mime_type: Option<&str>, file_extension: Option<&str>) -> Result<(), String> {
+        // Parameters (e.g. `; charset=utf-8`) don't change the type
+        let mime_type = mime_type
+            .map(|m| m.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+            .filter(|m| !m.is_empty());
+        let file_extension = file_extension
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|e| !e.is_empty());
+
+        let mime_matches = |pattern: &String, mime: &str| match pattern.strip_suffix("/*") {
+            Some(major) => mime.split('/').next() == Some(major),
+            None => pattern == mime,
+        };
+
+        match &mime_type {
+            Some(mime) if self.blocked_mime_types.iter().any(|p| mime_matches(p, mime)) => {
+                return Err(format!("Uploads of type {} are not accepted here", mime));
+            }
+            Some(mime)
+                if !self.allowed_mime_types.is_empty()
+                    && !self.allowed_mime_types.iter().any(|p| mime_matches(p, mime)) =>
+            {
+                return Err(format!("Uploads of type {} are not accepted here", mime));
+            }
+            None if !self.allowed_mime_types.is_empty() => {
+                return Err("A mime_type is required on this instance".to_string());
+            }
+            _ => {}
+        }
+
+        match &file_extension {
+            Some(ext) if self.blocked_extensions.contains(ext) => {
+                Err(format!("Uploads with extension .{} are not accepted here", ext))
+            }
+            Some(ext) if !self.allowed_extensions.is_empty() && !self.allowed_extensions.contains(ext) => {
+                Err(format!("Uploads with extension .{} are not accepted here", ext))
+            }
+            None if !self.allowed_extensions.is_empty() => {
+                Err("A file_extension is required on this instance".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where per-client rate limit state is kept
+#[derive(Debug, Clone)]
+pub enum RateLimitBackend {
+    /// Per-process state (each replica limits independently)
+    Memory,
+    /// Shared Redis state so limits apply across all replicas
+    Redis { url: String },
+}
+
+/// Rate limit for one class of routes; unset values fall back to the global limit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteRateLimit {
+    pub per_second: Option<f64>,
+    pub burst: Option<u32>,
+}
+
+/// Separate rate limit buckets, so e.g. polling a post can't lock a client out of downloads
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteRateLimits {
+    pub upload: RouteRateLimit,
+    pub download: RouteRateLimit,
+    pub post_view: RouteRateLimit,
+    /// Frontend pages, static assets and the health check
+    pub static_files: RouteRateLimit,
+}
+
+/// How new file and post IDs are generated
+#[derive(Debug, Clone)]
+pub enum IdScheme {
+    /// UUIDv4 (the original format)
+    Uuid,
+    /// Random characters from the dogpaste charset, for shorter URLs
+    Short { length: usize },
+}
+
+/// Connection settings applied to every SQLite connection in the pool
+#[derive(Debug, Clone)]
+pub struct SqliteTuning {
+    pub max_connections: u32,
+    /// WAL lets readers proceed while an upload is being written
+    pub journal_mode: SqliteJournalMode,
+    pub synchronous: SqliteSynchronous,
+    /// How long a writer waits for the lock before failing with `database is locked`
+    pub busy_timeout: Duration,
+    pub foreign_keys: bool,
+}
+
+/// Which requests need an instance access key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivateMode {
+    /// Public instance
+    Off,
+    /// Uploading needs a key; shared links work for anyone
+    Uploads,
+    /// Every API request needs a key
+    All,
+}
+
+/// How uploads from Tor exit nodes are treated (reads are never affected)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TorExitPolicy {
+    /// Tor exits are treated like everyone else (the exit list isn't fetched)
+    Allow,
+    /// Uploads from Tor exits get 403
+    BlockUploads,
+    /// Uploads from Tor exits need a solved proof-of-work challenge
+    Pow,
+}
+
+/// Captcha service checked on uploads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    /// Cloudflare Turnstile
+    Turnstile,
+    HCaptcha,
+}
+
+impl CaptchaProvider {
+    /// Server-side token verification endpoint
+    pub fn verify_url(self) -> &'static str {
+        match self {
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            Self::HCaptcha => "https://api.hcaptcha.com/siteverify",
+        }
+    }
+
+    /// Widget script loaded by the upload pages
+    pub fn script_url(self) -> &'static str {
+        match self {
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/api.js",
+            Self::HCaptcha => "https://js.hcaptcha.com/1/api.js",
+        }
+    }
+
+    /// Class of the element the widget script renders into
+    pub fn widget_class(self) -> &'static str {
+        match self {
+            Self::Turnstile => "cf-turnstile",
+            Self::HCaptcha => "h-captcha",
+        }
+    }
+
+    /// Origins the widget loads scripts, frames and styles from (added to the default CSP)
+    fn csp_sources(self) -> &'static str {
+        match self {
+            Self::Turnstile => "https://challenges.cloudflare.com",
+            Self::HCaptcha => "https://hcaptcha.com https://*.hcaptcha.com",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptchaConfig {
+    pub provider: CaptchaProvider,
+    /// Public key rendered into the widget
+    pub site_key: String,
+    pub secret: String,
+    /// Verification endpoint (the provider's unless overridden)
+    pub verify_url: String,
+}
+
+/// What to do with an upload that doesn't fit under MAX_TOTAL_STORAGE_BYTES
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageFullPolicy {
+    /// Reject the upload with 507
+    Reject,
+    /// Delete the least recently downloaded non-permanent files until it fits
+    Evict,
+}
+
+/// Certificate and key for serving HTTPS directly
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert_path: String,
+    /// PEM private key
+    pub key_path: String,
+}
+
+/// Live configuration shared by handlers, swapped in place on reload
+pub type SharedConfig = Arc<ArcSwap<Config>>;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub port: u16,
     pub database_url: String,
+    /// Apply embedded migrations at startup (disable when schema changes are managed externally)
+    pub run_migrations: bool,
+    pub sqlite: SqliteTuning,
+    /// How often to vacuum and analyze the database (None = never)
+    pub maintenance_interval: Option<Duration>,
+    /// How often expired files are cleaned up
+    pub cleanup_interval: Duration,
+    /// The first cleanup waits a random time up to this, so instances sharing a database drift apart
+    pub cleanup_jitter: Duration,
+    /// Ship snapshots and new blobs to a bucket on a schedule (disabled when unset)
+    pub remote_backup: Option<RemoteBackupConfig>,
     pub upload_dir: String,
     pub default_expiry_hours: i64,
     pub max_expiry_hours: i64,
+    /// Largest file upload (encrypted bytes)
+    pub max_file_bytes: u64,
+    /// Largest initial post content; posts live in SQLite rows, so this stays small
+    pub max_post_bytes: u64,
+    /// Largest entry (markdown or attachment) appended to a post
+    pub max_post_entry_bytes: u64,
+    /// Entries a post may hold, initial content included
+    pub max_post_entries: i64,
+    /// Budget for all of a post's entries together
+    pub max_post_total_bytes: u64,
+    /// Largest dogpaste
+    pub max_dogpaste_bytes: u64,
+    pub content_policy: ContentPolicy,
+    /// Cap on the total size of stored file blobs; uploads beyond it get 507
+    pub max_total_storage_bytes: Option<u64>,
+    pub storage_full_policy: StorageFullPolicy,
+    /// Free space to keep on the upload directory's filesystem; uploads that would dip below it get 507
+    pub min_free_disk_bytes: Option<u64>,
     pub test_delete_period_hours: Option<i64>,
     pub admin_message: Option<String>,
+    /// `admin_message` was set through the admin API and outlives config reloads
+    pub admin_message_from_db: bool,
+    pub storage: StorageConfig,
+    /// Overwrite local blobs this many times before unlinking them (0 = plain delete)
+    pub secure_delete_passes: u32,
+    /// Bearer token for /api/admin/* (admin API disabled when unset)
+    pub admin_token: Option<String>,
+    pub private_mode: PrivateMode,
+    /// Upload size limit for requests presenting a trusted API key
+    pub api_key_max_upload_bytes: u64,
+    /// Expiry limit for requests presenting a trusted API key
+    pub api_key_max_expiry_hours: i64,
+    /// Endpoint receiving JSON file events (webhooks disabled when unset)
+    pub webhook_url: Option<String>,
+    /// Key for the `X-Dogbox-Signature` HMAC-SHA256 header on webhook deliveries
+    pub webhook_secret: Option<String>,
+    /// Let uploaders register callback URLs for expiry warnings (the server POSTs to them)
+    pub expiry_callbacks_enabled: bool,
+    /// Count views, dedup hits and download recency (off = keep no per-file access data)
+    pub analytics_enabled: bool,
+    /// Serve the GraphQL API at /api/graphql
+    pub graphql_enabled: bool,
+    /// Serve WebDAV at /dav/ for trusted API keys
+    pub webdav_enabled: bool,
+    /// Serve the S3 API at /s3/ for trusted API keys
+    pub s3_api_enabled: bool,
+    /// Port for the gRPC API (disabled when unset)
+    pub grpc_port: Option<u16>,
+    /// OTLP/HTTP collector receiving traces (export disabled when unset)
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` reported with exported traces
+    pub otlp_service_name: String,
+    /// Sentry DSN receiving internal errors and panics (reporting disabled when unset)
+    pub sentry_dsn: Option<String>,
+    /// `environment` tag on Sentry events (e.g. production, staging)
+    pub sentry_environment: Option<String>,
+    /// Per-IP rate limiting (disable when a proxy in front already limits)
+    pub rate_limit_enabled: bool,
+    /// Sustained requests per second allowed per client
+    pub rate_limit_per_second: f64,
+    /// Requests a client may make in a burst before the sustained rate applies
+    pub rate_limit_burst: u32,
+    pub rate_limit_routes: RouteRateLimits,
+    pub rate_limit_backend: RateLimitBackend,
+    /// Time limit for handling a request (408 after it)
+    pub request_timeout: Duration,
+    /// Time limit for upload requests, which stream large bodies
+    pub upload_timeout: Duration,
+    /// How stale `/api/stats` may be; refreshed in the background this often (zero = compute per request)
+    pub stats_cache: Duration,
+    /// Requests handled at once; further requests get 503 (None = unlimited)
+    pub max_concurrent_requests: Option<usize>,
+    /// Reject requests early while response times are above target
+    pub load_shed_enabled: bool,
+    /// Average response time above which the server counts as overloaded
+    pub load_shed_target_latency: Duration,
+    /// Requests in flight beyond which everything is shed while overloaded
+    pub load_shed_queue_depth: usize,
+    /// Reverse proxies whose X-Forwarded-For / X-Real-IP headers are trusted
+    pub trusted_proxies: Vec<IpNet>,
+    /// Upload country policy (disabled when unset)
+    pub geoip: Option<GeoIpConfig>,
+    pub tor_exit_policy: TorExitPolicy,
+    /// Where the list of Tor exit addresses is downloaded from
+    pub tor_exit_list_url: String,
+    /// How often the Tor exit list is downloaded again
+    pub tor_exit_list_refresh: Duration,
+    /// Leading zero bits required of proof-of-work solutions
+    pub pow_difficulty: u32,
+    /// Captcha required for uploads (disabled when unset)
+    pub captcha: Option<CaptchaConfig>,
+    /// Name shown in page titles and the navbar
+    pub site_name: String,
+    /// Public base URL for absolute links (e.g. https://dogbox.moe); derived from Host when unset
+    pub public_url: Option<String>,
+    pub id_scheme: IdScheme,
+    /// Content-Security-Policy header sent with every response
+    pub content_security_policy: String,
+    /// Serve the frontend from this directory instead of the copy embedded in the binary
+    pub static_dir: Option<String>,
+    /// Body of /robots.txt
+    pub robots_txt: String,
+    /// Fields of /.well-known/security.txt (RFC 9116); not served without a contact
+    pub security_txt: Option<SecurityTxt>,
+    /// Directory served at /.well-known/ (besides the generated security.txt)
+    pub well_known_dir: Option<String>,
+    /// Serve HTTPS directly instead of plain HTTP behind a reverse proxy
+    pub tls: Option<TlsConfig>,
+    /// TOML file this config was loaded from, re-read on reload
+    pub config_file: Option<PathBuf>,
+}
+
+/// Vulnerability disclosure details for /.well-known/security.txt
+#[derive(Debug, Clone)]
+pub struct SecurityTxt {
+    /// `mailto:`, `https:` or `tel:` URIs, most preferred first
+    pub contacts: Vec<String>,
+    /// Disclosure policy URL
+    pub policy: Option<String>,
+}
+
+/// Default robots.txt: share pages and the API are never worth indexing
+fn default_robots_txt(disallow: &[String]) -> String {
+    let mut robots = String::from("User-agent: *\n");
+    if disallow.is_empty() {
+        robots.push_str("Disallow:\n");
+    }
+    for path in disallow {
+        robots.push_str(&format!("Disallow: {}\n", path));
+    }
+    robots
+}
+
+/// Admin messages may only contain alphanumerics, whitespace and `,.-'`, so they can't inject markup
+pub fn is_safe_admin_message(message: &str) -> bool {
+    message
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c.is_whitespace() || matches!(c, ',' | '.' | '-' | '\''))
+}
+
+/// Default CSP for the bundled frontend, allowing the given inline script hashes
+///
+/// 'wasm-unsafe-eval' is required for BLAKE3 WASM compilation.
+fn default_csp(script_hashes: &[String], captcha: Option<CaptchaProvider>) -> String {
+    let script_src = script_hashes
+        .iter()
+        .map(|hash| format!(" '{}'", hash))
+        .collect::<String>();
+    // The captcha widget runs in a frame served by the provider
+    let captcha_src = captcha
+        .map(|provider| format!(" {}", provider.csp_sources()))
+        .unwrap_or_default();
+
+    format!(
+        "default-src 'self'; \
+         script-src 'self' 'wasm-unsafe-eval'{script_src}{captcha_src}; \
+         style-src 'self' 'unsafe-inline'{captcha_src}; \
+         img-src 'self' data: blob:; \
+         media-src 'self' blob:; \
+         font-src 'self' data:; \
+         connect-src 'self'{captcha_src}; \
+         frame-src 'self'{captcha_src}; \
+         frame-ancestors 'none';"
+    )
 }
 
 impl Config {
-    pub fn from_env() -> anyhow::Result<Self> {
+    /// Load from the environment, falling back to an optional TOML file
+    ///
+    /// Environment variables always win over values from the file.
+    pub fn load(config_file: Option<&Path>) -> anyhow::Result<Self> {
+        let file_vars = match config_file {
+            Some(path) => FileConfig::read(path)?.into_vars(),
+            None => HashMap::new(),
+        };
+        let var = |key: &str| -> Result<String, env::VarError> {
+            env::var(key).or_else(|_| file_vars.get(key).cloned().ok_or(env::VarError::NotPresent))
+        };
+
         // Validate admin message if set (allow safe characters only)
-        let admin_message = if let Ok(msg) = env::var("ADMIN_MESSAGE") {
-            if !msg.chars().all(|c| {
-                c.is_ascii_alphanumeric() || c.is_whitespace() || matches!(c, ',' | '.' | '-' | '\'')
-            }) {
+        let admin_message = if let Ok(msg) = var("ADMIN_MESSAGE") {
+            if !is_safe_admin_message(&msg) {
                 anyhow::bail!(
                     "ADMIN_MESSAGE contains invalid characters. Only alphanumeric characters, spaces, commas, periods, hyphens, and apostrophes are allowed to prevent XSS."
                 );
@@ -27,24 +467,975 @@ impl Config {
             None
         };
 
-        Ok(Self {
-            port: env::var("PORT")
-                .unwrap_or_else(|_| "8080".to_string())
+        let storage = match var("STORAGE_BACKEND").as_deref() {
+            Ok("local") | Err(_) => StorageConfig::Local,
+            Ok("s3") => StorageConfig::S3 {
+                bucket: var("S3_BUCKET")
+                    .map_err(|_| anyhow::anyhow!("S3_BUCKET must be set when STORAGE_BACKEND=s3"))?,
+                region: var("S3_REGION")
+                    .unwrap_or_else(|_| "us-east-1".to_string()),
+                endpoint: var("S3_ENDPOINT").ok(),
+                allow_http: var("S3_ALLOW_HTTP")
+                    .map(|v| v == "true")
+                    .unwrap_or(false),
+            },
+            Ok(other) => anyhow::bail!("Unknown STORAGE_BACKEND '{}' (expected 'local' or 's3')", other),
+        };
+
+        // SECURITY: Refuse trivially guessable admin tokens
+        let admin_token = match var("ADMIN_TOKEN") {
+            Ok(token) if token.len() < MIN_ADMIN_TOKEN_LENGTH => anyhow::bail!(
+                "ADMIN_TOKEN must be at least {} characters long",
+                MIN_ADMIN_TOKEN_LENGTH
+            ),
+            Ok(token) => Some(token),
+            Err(_) => None,
+        };
+
+        let private_mode = match var("PRIVATE_MODE").as_deref() {
+            Ok("off") | Err(_) => PrivateMode::Off,
+            Ok("uploads") => PrivateMode::Uploads,
+            Ok("all") => PrivateMode::All,
+            Ok(other) => anyhow::bail!("Unknown PRIVATE_MODE '{}' (expected 'off', 'uploads' or 'all')", other),
+        };
+        // Keys can only be issued through the admin API
+        if private_mode != PrivateMode::Off && admin_token.is_none() {
+            anyhow::bail!("PRIVATE_MODE requires ADMIN_TOKEN so access keys can be issued");
+        }
+
+        let max_expiry_hours: i64 = var("MAX_EXPIRY_HOURS")
+            .unwrap_or_else(|_| "168".to_string())
+            .parse()?;
+        let size_limit = |key: &str, default: u64| -> anyhow::Result<u64> {
+            let bytes = var(key).map(|v| v.parse()).unwrap_or(Ok(default))?;
+            if bytes == 0 || bytes > MAX_UPLOAD_SIZE as u64 {
+                anyhow::bail!("{} must be between 1 and {} bytes", key, MAX_UPLOAD_SIZE);
+            }
+            Ok(bytes)
+        };
+        let max_file_bytes = size_limit("MAX_FILE_BYTES", MAX_UPLOAD_SIZE as u64)?;
+        let max_post_bytes = size_limit("MAX_POST_BYTES", DEFAULT_MAX_POST_BYTES)?;
+        let max_post_entry_bytes = size_limit("MAX_POST_ENTRY_BYTES", DEFAULT_MAX_POST_ENTRY_BYTES)?;
+        let max_dogpaste_bytes = size_limit("MAX_DOGPASTE_BYTES", DEFAULT_MAX_DOGPASTE_BYTES)?;
+        let max_post_total_bytes = var("MAX_POST_TOTAL_BYTES")
+            .map(|v| v.parse())
+            .unwrap_or(Ok(DEFAULT_MAX_POST_TOTAL_BYTES))?;
+        if max_post_total_bytes < max_post_bytes {
+            anyhow::bail!("MAX_POST_TOTAL_BYTES must be at least MAX_POST_BYTES");
+        }
+        let max_post_entries: i64 = var("MAX_POST_ENTRIES")
+            .map(|v| v.parse())
+            .unwrap_or(Ok(DEFAULT_MAX_POST_ENTRIES))?;
+        if max_post_entries < 1 {
+            anyhow::bail!("MAX_POST_ENTRIES must be at least 1");
+        }
+
+        // Trusted API keys never get less than everyone else
+        let api_key_max_upload_bytes = var("API_KEY_MAX_UPLOAD_BYTES")
+            .ok()
+            .map(|v| v.parse::<u64>())
+            .transpose()?
+            .unwrap_or(max_file_bytes)
+            .max(max_file_bytes);
+        let api_key_max_expiry_hours = var("API_KEY_MAX_EXPIRY_HOURS")
+            .ok()
+            .map(|v| v.parse::<i64>())
+            .transpose()?
+            .unwrap_or(max_expiry_hours)
+            .max(max_expiry_hours);
+
+        let webhook_url = var("WEBHOOK_URL").ok().filter(|url| !url.is_empty());
+        if let Some(url) = &webhook_url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                anyhow::bail!("WEBHOOK_URL must be an http:// or https:// URL");
+            }
+        }
+        let webhook_secret = var("WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty());
+
+        let otlp_endpoint = var("OTLP_ENDPOINT").ok().filter(|url| !url.is_empty());
+        if let Some(url) = &otlp_endpoint {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                anyhow::bail!("OTLP_ENDPOINT must be an http:// or https:// URL");
+            }
+        }
+
+        let sentry_dsn = var("SENTRY_DSN").ok().filter(|dsn| !dsn.is_empty());
+        if let Some(dsn) = &sentry_dsn {
+            sentry::types::Dsn::from_str(dsn).map_err(|e| anyhow::anyhow!("Invalid SENTRY_DSN: {}", e))?;
+        }
+
+        let rate_limit_per_second: f64 = var("RATE_LIMIT_PER_SECOND")
+            .unwrap_or_else(|_| "0.5".to_string())
+            .parse()?;
+        if !(rate_limit_per_second > 0.0 && rate_limit_per_second <= 1000.0) {
+            anyhow::bail!("RATE_LIMIT_PER_SECOND must be greater than 0 and at most 1000");
+        }
+
+        let rate_limit_burst: u32 = var("RATE_LIMIT_BURST")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()?;
+        if rate_limit_burst == 0 {
+            anyhow::bail!("RATE_LIMIT_BURST must be at least 1");
+        }
+
+        let route_rate_limit = |class: &str| -> anyhow::Result<RouteRateLimit> {
+            let per_second = var(&format!("RATE_LIMIT_{}_PER_SECOND", class))
+                .ok()
+                .map(|v| v.parse::<f64>())
+                .transpose()?;
+            if per_second.is_some_and(|v| !(v > 0.0 && v <= 1000.0)) {
+                anyhow::bail!("RATE_LIMIT_{}_PER_SECOND must be greater than 0 and at most 1000", class);
+            }
+            let burst = var(&format!("RATE_LIMIT_{}_BURST", class))
+                .ok()
+                .map(|v| v.parse::<u32>())
+                .transpose()?;
+            if burst == Some(0) {
+                anyhow::bail!("RATE_LIMIT_{}_BURST must be at least 1", class);
+            }
+            Ok(RouteRateLimit { per_second, burst })
+        };
+        let rate_limit_routes = RouteRateLimits {
+            upload: route_rate_limit("UPLOAD")?,
+            download: route_rate_limit("DOWNLOAD")?,
+            post_view: route_rate_limit("POST_VIEW")?,
+            static_files: route_rate_limit("STATIC")?,
+        };
+
+        let request_timeout_secs: u64 = var("REQUEST_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?;
+        let upload_timeout_secs: u64 = var("UPLOAD_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse()?;
+        if request_timeout_secs == 0 || upload_timeout_secs == 0 {
+            anyhow::bail!("REQUEST_TIMEOUT_SECS and UPLOAD_TIMEOUT_SECS must be at least 1");
+        }
+        let max_concurrent_requests = match var("MAX_CONCURRENT_REQUESTS")
+            .unwrap_or_else(|_| "512".to_string())
+            .parse::<usize>()?
+        {
+            0 => None,
+            n => Some(n),
+        };
+
+        let load_shed_target_latency_ms: u64 = var("LOAD_SHED_TARGET_LATENCY_MS")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse()?;
+        let load_shed_queue_depth: usize = var("LOAD_SHED_QUEUE_DEPTH")
+            .unwrap_or_else(|_| "64".to_string())
+            .parse()?;
+        if load_shed_target_latency_ms == 0 || load_shed_queue_depth == 0 {
+            anyhow::bail!("LOAD_SHED_TARGET_LATENCY_MS and LOAD_SHED_QUEUE_DEPTH must be at least 1");
+        }
+
+        let rate_limit_backend = match var("RATE_LIMIT_BACKEND").as_deref() {
+            Ok("memory") | Err(_) => RateLimitBackend::Memory,
+            Ok("redis") => RateLimitBackend::Redis {
+                url: var("REDIS_URL")
+                    .map_err(|_| anyhow::anyhow!("REDIS_URL must be set when RATE_LIMIT_BACKEND=redis"))?,
+            },
+            Ok(other) => anyhow::bail!("Unknown RATE_LIMIT_BACKEND '{}' (expected 'memory' or 'redis')", other),
+        };
+
+        // Comma-separated lists, normalised to lowercase (extensions without the dot)
+        let list = |key: &str| -> Vec<String> {
+            var(key)
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().trim_start_matches('.').to_ascii_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        let content_policy = ContentPolicy {
+            allowed_mime_types: list("ALLOWED_MIME_TYPES"),
+            blocked_mime_types: list("BLOCKED_MIME_TYPES"),
+            allowed_extensions: list("ALLOWED_EXTENSIONS"),
+            blocked_extensions: list("BLOCKED_EXTENSIONS"),
+        };
+
+        // Accept bare addresses as well as CIDR ranges
+        let trusted_proxies = var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<IpNet>()
+                    .or_else(|_| s.parse::<std::net::IpAddr>().map(IpNet::from))
+                    .map_err(|_| anyhow::anyhow!("Invalid TRUSTED_PROXIES entry '{}'", s))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let id_scheme = match var("ID_SCHEME").as_deref() {
+            Ok("uuid") | Err(_) => IdScheme::Uuid,
+            Ok("short") => {
+                let length: usize = var("SHORT_ID_LENGTH")
+                    .unwrap_or_else(|_| DEFAULT_SHORT_ID_LENGTH.to_string())
+                    .parse()?;
+                // Shorter IDs make guessing links practical
+                if !(8..=32).contains(&length) {
+                    anyhow::bail!("SHORT_ID_LENGTH must be between 8 and 32");
+                }
+                IdScheme::Short { length }
+            }
+            Ok(other) => anyhow::bail!("Unknown ID_SCHEME '{}' (expected 'uuid' or 'short')", other),
+        };
+
+        let captcha = match var("CAPTCHA_PROVIDER").as_deref() {
+            Err(_) => None,
+            Ok(name) => {
+                let provider = match name {
+                    "turnstile" => CaptchaProvider::Turnstile,
+                    "hcaptcha" => CaptchaProvider::HCaptcha,
+                    other => anyhow::bail!("Unknown CAPTCHA_PROVIDER '{}' (expected 'turnstile' or 'hcaptcha')", other),
+                };
+                let (Ok(site_key), Ok(secret)) = (var("CAPTCHA_SITE_KEY"), var("CAPTCHA_SECRET")) else {
+                    anyhow::bail!("CAPTCHA_PROVIDER requires CAPTCHA_SITE_KEY and CAPTCHA_SECRET");
+                };
+                // The site key ends up in an HTML attribute
+                if site_key.is_empty() || !site_key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                    anyhow::bail!("CAPTCHA_SITE_KEY may only contain letters, digits, '-' and '_'");
+                }
+                Some(CaptchaConfig {
+                    provider,
+                    site_key,
+                    secret,
+                    verify_url: var("CAPTCHA_VERIFY_URL").unwrap_or_else(|_| provider.verify_url().to_string()),
+                })
+            }
+        };
+
+        // A full CSP override wins; otherwise only the inline script hashes are swapped
+        let content_security_policy = match var("CONTENT_SECURITY_POLICY") {
+            Ok(csp) => csp,
+            Err(_) => {
+                let script_hashes = match var("CSP_SCRIPT_HASHES") {
+                    Ok(hashes) => hashes
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(|hash| {
+                            let is_hash = ["sha256-", "sha384-", "sha512-"]
+                                .iter()
+                                .any(|prefix| hash.starts_with(prefix));
+                            if !is_hash || hash.contains(['\'', ';', ' ']) {
+                                anyhow::bail!("Invalid CSP_SCRIPT_HASHES entry '{}' (expected sha256-<base64>)", hash);
+                            }
+                            Ok(hash.to_string())
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                    Err(_) => DEFAULT_CSP_SCRIPT_HASHES.iter().map(|h| h.to_string()).collect(),
+                };
+                default_csp(&script_hashes, captcha.as_ref().map(|captcha| captcha.provider))
+            }
+        };
+        if axum::http::HeaderValue::from_str(&content_security_policy).is_err() {
+            anyhow::bail!("CONTENT_SECURITY_POLICY contains characters not allowed in a header");
+        }
+
+        // ISO 3166-1 alpha-2 codes, uppercase as in the GeoIP database
+        let countries = |key: &str| -> anyhow::Result<Vec<String>> {
+            var(key)
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_ascii_uppercase())
+                .filter(|s| !s.is_empty())
+                .map(|code| {
+                    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+                        anyhow::bail!("{} entry '{}' is not a two-letter country code", key, code);
+                    }
+                    Ok(code)
+                })
+                .collect()
+        };
+        let blocked_countries = countries("GEOIP_BLOCKED_COUNTRIES")?;
+        let allowed_countries = countries("GEOIP_ALLOWED_COUNTRIES")?;
+        let geoip = match var("GEOIP_DATABASE").ok().filter(|path| !path.is_empty()) {
+            Some(database) => {
+                if !Path::new(&database).is_file() {
+                    anyhow::bail!("GEOIP_DATABASE {} is not a file", database);
+                }
+                Some(GeoIpConfig { database, blocked_countries, allowed_countries })
+            }
+            None if blocked_countries.is_empty() && allowed_countries.is_empty() => None,
+            None => anyhow::bail!("GEOIP_BLOCKED_COUNTRIES and GEOIP_ALLOWED_COUNTRIES require GEOIP_DATABASE"),
+        };
+
+        let tor_exit_policy = match var("TOR_EXIT_POLICY").as_deref() {
+            Ok("allow") | Err(_) => TorExitPolicy::Allow,
+            Ok("block_uploads") => TorExitPolicy::BlockUploads,
+            Ok("pow") => TorExitPolicy::Pow,
+            Ok(other) => anyhow::bail!(
+                "Unknown TOR_EXIT_POLICY '{}' (expected 'allow', 'block_uploads' or 'pow')",
+                other
+            ),
+        };
+        let tor_exit_list_refresh_mins: u64 = var("TOR_EXIT_LIST_REFRESH_MINS")
+            .unwrap_or_else(|_| DEFAULT_TOR_EXIT_LIST_REFRESH_MINS.to_string())
+            .parse()?;
+        if tor_exit_list_refresh_mins < 5 {
+            anyhow::bail!("TOR_EXIT_LIST_REFRESH_MINS must be at least 5");
+        }
+        let pow_difficulty: u32 = var("POW_DIFFICULTY")
+            .unwrap_or_else(|_| DEFAULT_POW_DIFFICULTY.to_string())
+            .parse()?;
+        if !(MIN_POW_DIFFICULTY..=MAX_POW_DIFFICULTY).contains(&pow_difficulty) {
+            anyhow::bail!(
+                "POW_DIFFICULTY must be between {} and {}",
+                MIN_POW_DIFFICULTY,
+                MAX_POW_DIFFICULTY
+            );
+        }
+
+        let well_known_dir = var("WELL_KNOWN_DIR").ok().filter(|dir| !dir.is_empty());
+        if let Some(dir) = &well_known_dir {
+            if !Path::new(dir).is_dir() {
+                anyhow::bail!("WELL_KNOWN_DIR {} is not a directory", dir);
+            }
+        }
+
+        // A full robots.txt wins; otherwise only the disallowed paths are swapped
+        let robots_txt = match var("ROBOTS_TXT") {
+            Ok(robots) => robots,
+            Err(_) => {
+                let disallow: Vec<String> = match var("ROBOTS_DISALLOW") {
+                    Ok(paths) => paths
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(|path| {
+                            if !path.starts_with('/') || path.contains(char::is_whitespace) {
+                                anyhow::bail!("Invalid ROBOTS_DISALLOW entry '{}' (expected a path such as /f/)", path);
+                            }
+                            Ok(path.to_string())
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                    Err(_) => DEFAULT_ROBOTS_DISALLOW.iter().map(|p| p.to_string()).collect(),
+                };
+                default_robots_txt(&disallow)
+            }
+        };
+
+        let security_txt = match var("SECURITY_CONTACT") {
+            Ok(contacts) => {
+                let contacts = contacts
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|contact| {
+                        let is_uri = ["mailto:", "https://", "tel:"].iter().any(|scheme| contact.starts_with(scheme));
+                        // Bare email addresses are the common case
+                        let is_email = !contact.contains(':') && contact.contains('@');
+                        if contact.contains(char::is_whitespace) || !(is_uri || is_email) {
+                            anyhow::bail!(
+                                "Invalid SECURITY_CONTACT entry '{}' (expected an email address or mailto:/https:/tel: URI)",
+                                contact
+                            );
+                        }
+                        Ok(if is_email { format!("mailto:{}", contact) } else { contact.to_string() })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                (!contacts.is_empty()).then(|| SecurityTxt {
+                    contacts,
+                    policy: var("SECURITY_POLICY").ok().filter(|url| !url.trim().is_empty()),
+                })
+            }
+            Err(_) => None,
+        };
+        if security_txt.is_none() && var("SECURITY_POLICY").is_ok_and(|url| !url.trim().is_empty()) {
+            anyhow::bail!("SECURITY_POLICY requires SECURITY_CONTACT");
+        }
+
+        let static_dir = var("STATIC_DIR").ok().filter(|dir| !dir.is_empty());
+        if let Some(dir) = &static_dir {
+            if !Path::new(dir).is_dir() {
+                anyhow::bail!("STATIC_DIR {} is not a directory", dir);
+            }
+        }
+
+        let secure_delete_passes: u32 = var("SECURE_DELETE_PASSES")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse()?;
+        if secure_delete_passes > MAX_SECURE_DELETE_PASSES {
+            anyhow::bail!("SECURE_DELETE_PASSES must be at most {}", MAX_SECURE_DELETE_PASSES);
+        }
+
+        let storage_full_policy = match var("STORAGE_FULL_POLICY").as_deref() {
+            Ok("reject") | Err(_) => StorageFullPolicy::Reject,
+            Ok("evict") => StorageFullPolicy::Evict,
+            Ok(other) => anyhow::bail!("Unknown STORAGE_FULL_POLICY '{}' (expected 'reject' or 'evict')", other),
+        };
+
+        let sqlite = SqliteTuning {
+            max_connections: var("DB_MAX_CONNECTIONS")
+                .unwrap_or_else(|_| "5".to_string())
                 .parse()?,
-            database_url: env::var("DATABASE_URL")
+            journal_mode: SqliteJournalMode::from_str(
+                &var("SQLITE_JOURNAL_MODE").unwrap_or_else(|_| "wal".to_string()),
+            )
+            .map_err(|_| anyhow::anyhow!("Invalid SQLITE_JOURNAL_MODE (expected wal, delete, truncate, persist, memory or off)"))?,
+            synchronous: SqliteSynchronous::from_str(
+                &var("SQLITE_SYNCHRONOUS").unwrap_or_else(|_| "normal".to_string()),
+            )
+            .map_err(|_| anyhow::anyhow!("Invalid SQLITE_SYNCHRONOUS (expected off, normal, full or extra)"))?,
+            busy_timeout: Duration::from_millis(
+                var("SQLITE_BUSY_TIMEOUT_MS")
+                    .unwrap_or_else(|_| "5000".to_string())
+                    .parse()?,
+            ),
+            foreign_keys: var("SQLITE_FOREIGN_KEYS")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+        };
+        if sqlite.max_connections == 0 {
+            anyhow::bail!("DB_MAX_CONNECTIONS must be at least 1");
+        }
+
+        let maintenance_interval = match var("DB_MAINTENANCE_INTERVAL_HOURS")
+            .unwrap_or_else(|_| "24".to_string())
+            .parse::<u64>()?
+        {
+            0 => None,
+            hours => Some(Duration::from_secs(hours * 3600)),
+        };
+
+        let cleanup_interval_secs: u64 = var("CLEANUP_INTERVAL_SECS")
+            .map(|v| v.parse())
+            .unwrap_or(Ok(DEFAULT_CLEANUP_INTERVAL_SECS))?;
+        if cleanup_interval_secs < 60 {
+            anyhow::bail!("CLEANUP_INTERVAL_SECS must be at least 60");
+        }
+        let cleanup_jitter_secs: u64 = var("CLEANUP_JITTER_SECS")
+            .map(|v| v.parse())
+            .unwrap_or(Ok(DEFAULT_CLEANUP_JITTER_SECS))?;
+        if cleanup_jitter_secs > cleanup_interval_secs {
+            anyhow::bail!("CLEANUP_JITTER_SECS can't be longer than CLEANUP_INTERVAL_SECS");
+        }
+
+        let remote_backup = match var("BACKUP_S3_BUCKET") {
+            Ok(bucket) => {
+                let interval_hours: u64 = var("BACKUP_INTERVAL_HOURS")
+                    .unwrap_or_else(|_| "24".to_string())
+                    .parse()?;
+                let retention_days: u64 = var("BACKUP_RETENTION_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()?;
+                if interval_hours == 0 || retention_days == 0 {
+                    anyhow::bail!("BACKUP_INTERVAL_HOURS and BACKUP_RETENTION_DAYS must be at least 1");
+                }
+                Some(RemoteBackupConfig {
+                    bucket,
+                    region: var("BACKUP_S3_REGION")
+                        .unwrap_or_else(|_| "us-east-1".to_string()),
+                    endpoint: var("BACKUP_S3_ENDPOINT").ok(),
+                    allow_http: var("BACKUP_S3_ALLOW_HTTP")
+                        .map(|v| v == "true")
+                        .unwrap_or(false),
+                    prefix: var("BACKUP_S3_PREFIX")
+                        .unwrap_or_else(|_| "dogbox-backups".to_string()),
+                    interval: Duration::from_secs(interval_hours * 3600),
+                    retention: Duration::from_secs(retention_days * 86400),
+                })
+            }
+            Err(_) => None,
+        };
+
+        let tls = match (var("TLS_CERT_PATH"), var("TLS_KEY_PATH")) {
+            (Ok(cert_path), Ok(key_path)) => Some(TlsConfig { cert_path, key_path }),
+            (Err(_), Err(_)) => None,
+            _ => anyhow::bail!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        };
+
+        let port: u16 = var("PORT").unwrap_or_else(|_| "8080".to_string()).parse()?;
+        let grpc_port = var("GRPC_PORT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u16>())
+            .transpose()?;
+        if grpc_port == Some(port) {
+            anyhow::bail!("GRPC_PORT must differ from PORT");
+        }
+
+        Ok(Self {
+            port,
+            database_url: var("DATABASE_URL")
                 .unwrap_or_else(|_| "sqlite:./dogbox.db".to_string()),
-            upload_dir: env::var("UPLOAD_DIR")
+            run_migrations: var("RUN_MIGRATIONS")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            sqlite,
+            maintenance_interval,
+            cleanup_interval: Duration::from_secs(cleanup_interval_secs),
+            cleanup_jitter: Duration::from_secs(cleanup_jitter_secs),
+            remote_backup,
+            upload_dir: var("UPLOAD_DIR")
                 .unwrap_or_else(|_| "./uploads".to_string()),
-            default_expiry_hours: env::var("DEFAULT_EXPIRY_HOURS")
+            default_expiry_hours: var("DEFAULT_EXPIRY_HOURS")
                 .unwrap_or_else(|_| "24".to_string())
                 .parse()?,
-            max_expiry_hours: env::var("MAX_EXPIRY_HOURS")
-                .unwrap_or_else(|_| "168".to_string())
-                .parse()?,
-            test_delete_period_hours: env::var("TEST_DELETE_PERIOD_HOURS")
+            max_expiry_hours,
+            max_file_bytes,
+            max_post_bytes,
+            max_post_entry_bytes,
+            max_post_entries,
+            max_post_total_bytes,
+            max_dogpaste_bytes,
+            content_policy,
+            max_total_storage_bytes: var("MAX_TOTAL_STORAGE_BYTES")
+                .ok()
+                .map(|v| v.parse())
+                .transpose()?,
+            storage_full_policy,
+            min_free_disk_bytes: var("MIN_FREE_DISK_BYTES")
+                .ok()
+                .map(|v| v.parse())
+                .transpose()?,
+            test_delete_period_hours: var("TEST_DELETE_PERIOD_HOURS")
                 .ok()
                 .and_then(|s| s.parse().ok()),
             admin_message,
+            admin_message_from_db: false,
+            storage,
+            secure_delete_passes,
+            admin_token,
+            private_mode,
+            api_key_max_upload_bytes,
+            api_key_max_expiry_hours,
+            webhook_url,
+            webhook_secret,
+            otlp_endpoint,
+            otlp_service_name: var("OTLP_SERVICE_NAME").unwrap_or_else(|_| "dogbox".to_string()),
+            sentry_dsn,
+            sentry_environment: var("SENTRY_ENVIRONMENT").ok().filter(|env| !env.is_empty()),
+            expiry_callbacks_enabled: var("EXPIRY_CALLBACKS_ENABLED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            analytics_enabled: var("ANALYTICS_ENABLED")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            graphql_enabled: var("GRAPHQL_ENABLED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            webdav_enabled: var("WEBDAV_ENABLED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            s3_api_enabled: var("S3_API_ENABLED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            grpc_port,
+            rate_limit_enabled: var("RATE_LIMIT_ENABLED")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            rate_limit_per_second,
+            rate_limit_burst,
+            rate_limit_routes,
+            request_timeout: Duration::from_secs(request_timeout_secs),
+            upload_timeout: Duration::from_secs(upload_timeout_secs),
+            stats_cache: Duration::from_secs(
+                var("STATS_CACHE_SECS").unwrap_or_else(|_| "60".to_string()).parse()?,
+            ),
+            max_concurrent_requests,
+            load_shed_enabled: var("LOAD_SHED_ENABLED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            load_shed_target_latency: Duration::from_millis(load_shed_target_latency_ms),
+            load_shed_queue_depth,
+            rate_limit_backend,
+            trusted_proxies,
+            geoip,
+            tor_exit_policy,
+            tor_exit_list_url: var("TOR_EXIT_LIST_URL")
+                .unwrap_or_else(|_| DEFAULT_TOR_EXIT_LIST_URL.to_string()),
+            tor_exit_list_refresh: Duration::from_secs(tor_exit_list_refresh_mins * 60),
+            pow_difficulty,
+            captcha,
+            site_name: var("SITE_NAME")
+                .ok()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| "dogbox.moe".to_string()),
+            public_url: var("PUBLIC_URL")
+                .ok()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            id_scheme,
+            content_security_policy,
+            static_dir,
+            robots_txt,
+            security_txt,
+            well_known_dir,
+            tls,
+            config_file: config_file.map(Path::to_path_buf),
+        })
+    }
+
+    /// Re-read the config file and environment, applying only the reloadable settings
+    ///
+    /// Admin message (unless set through the admin API), expiry defaults, storage limits and
+    /// rate limits take effect immediately; everything else (port, storage, database, TLS, ...)
+    /// still needs a restart.
+    pub fn reloaded(&self) -> anyhow::Result<Self> {
+        let fresh = Self::load(self.config_file.as_deref())?;
+
+        Ok(Self {
+            admin_message: if self.admin_message_from_db {
+                self.admin_message.clone()
+            } else {
+                fresh.admin_message
+            },
+            default_expiry_hours: fresh.default_expiry_hours,
+            max_expiry_hours: fresh.max_expiry_hours,
+            max_file_bytes: fresh.max_file_bytes,
+            max_post_bytes: fresh.max_post_bytes,
+            max_post_entry_bytes: fresh.max_post_entry_bytes,
+            max_post_entries: fresh.max_post_entries,
+            max_post_total_bytes: fresh.max_post_total_bytes,
+            max_dogpaste_bytes: fresh.max_dogpaste_bytes,
+            content_policy: fresh.content_policy,
+            max_total_storage_bytes: fresh.max_total_storage_bytes,
+            storage_full_policy: fresh.storage_full_policy,
+            min_free_disk_bytes: fresh.min_free_disk_bytes,
+            rate_limit_enabled: fresh.rate_limit_enabled,
+            rate_limit_per_second: fresh.rate_limit_per_second,
+            rate_limit_burst: fresh.rate_limit_burst,
+            rate_limit_routes: fresh.rate_limit_routes,
+            load_shed_enabled: fresh.load_shed_enabled,
+            load_shed_target_latency: fresh.load_shed_target_latency,
+            load_shed_queue_depth: fresh.load_shed_queue_depth,
+            webhook_url: fresh.webhook_url,
+            webhook_secret: fresh.webhook_secret,
+            expiry_callbacks_enabled: fresh.expiry_callbacks_enabled,
+            ..self.clone()
         })
     }
 }
+
+/// `dogbox.toml` layout
+///
+/// Every key maps onto the environment variable of the same meaning, so the
+/// file only supplies defaults and validation stays in [`Config::load`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: ServerSection,
+    database: DatabaseSection,
+    storage: StorageSection,
+    backup: BackupSection,
+    limits: LimitsSection,
+    rate_limit: RateLimitSection,
+    admin: AdminSection,
+    webhook: WebhookSection,
+    telemetry: TelemetrySection,
+    geoip: GeoIpSection,
+    tor: TorSection,
+    captcha: CaptchaSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerSection {
+    port: Option<u16>,
+    public_url: Option<String>,
+    site_name: Option<String>,
+    id_scheme: Option<String>,
+    short_id_length: Option<usize>,
+    tls_cert_path: Option<String>,
+    tls_key_path: Option<String>,
+    content_security_policy: Option<String>,
+    static_dir: Option<String>,
+    csp_script_hashes: Option<Vec<String>>,
+    robots_txt: Option<String>,
+    robots_disallow: Option<Vec<String>>,
+    security_contact: Option<Vec<String>>,
+    security_policy: Option<String>,
+    well_known_dir: Option<String>,
+    request_timeout_secs: Option<u64>,
+    upload_timeout_secs: Option<u64>,
+    stats_cache_secs: Option<u64>,
+    analytics_enabled: Option<bool>,
+    graphql_enabled: Option<bool>,
+    webdav_enabled: Option<bool>,
+    s3_api_enabled: Option<bool>,
+    grpc_port: Option<u16>,
+    max_concurrent_requests: Option<usize>,
+    load_shed_enabled: Option<bool>,
+    load_shed_target_latency_ms: Option<u64>,
+    load_shed_queue_depth: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DatabaseSection {
+    url: Option<String>,
+    run_migrations: Option<bool>,
+    max_connections: Option<u32>,
+    journal_mode: Option<String>,
+    synchronous: Option<String>,
+    busy_timeout_ms: Option<u64>,
+    foreign_keys: Option<bool>,
+    maintenance_interval_hours: Option<u64>,
+    cleanup_interval_secs: Option<u64>,
+    cleanup_jitter_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageSection {
+    backend: Option<String>,
+    upload_dir: Option<String>,
+    s3_bucket: Option<String>,
+    s3_region: Option<String>,
+    s3_endpoint: Option<String>,
+    s3_allow_http: Option<bool>,
+    secure_delete_passes: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BackupSection {
+    s3_bucket: Option<String>,
+    s3_region: Option<String>,
+    s3_endpoint: Option<String>,
+    s3_allow_http: Option<bool>,
+    s3_prefix: Option<String>,
+    interval_hours: Option<u64>,
+    retention_days: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LimitsSection {
+    default_expiry_hours: Option<i64>,
+    max_expiry_hours: Option<i64>,
+    max_file_bytes: Option<u64>,
+    max_post_bytes: Option<u64>,
+    max_post_entry_bytes: Option<u64>,
+    max_post_entries: Option<i64>,
+    max_post_total_bytes: Option<u64>,
+    max_dogpaste_bytes: Option<u64>,
+    allowed_mime_types: Option<Vec<String>>,
+    blocked_mime_types: Option<Vec<String>>,
+    allowed_extensions: Option<Vec<String>>,
+    blocked_extensions: Option<Vec<String>>,
+    max_total_storage_bytes: Option<u64>,
+    storage_full_policy: Option<String>,
+    min_free_disk_bytes: Option<u64>,
+    test_delete_period_hours: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitSection {
+    enabled: Option<bool>,
+    per_second: Option<f64>,
+    burst: Option<u32>,
+    backend: Option<String>,
+    redis_url: Option<String>,
+    trusted_proxies: Option<Vec<String>>,
+    upload: RouteRateLimitSection,
+    download: RouteRateLimitSection,
+    post_view: RouteRateLimitSection,
+    #[serde(rename = "static")]
+    static_files: RouteRateLimitSection,
+}
+
+/// `[rate_limit.upload]` etc.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RouteRateLimitSection {
+    per_second: Option<f64>,
+    burst: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AdminSection {
+    token: Option<String>,
+    message: Option<String>,
+    private_mode: Option<String>,
+    api_key_max_upload_bytes: Option<u64>,
+    api_key_max_expiry_hours: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WebhookSection {
+    url: Option<String>,
+    secret: Option<String>,
+    expiry_callbacks_enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GeoIpSection {
+    database: Option<String>,
+    blocked_countries: Option<Vec<String>>,
+    allowed_countries: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TorSection {
+    exit_policy: Option<String>,
+    exit_list_url: Option<String>,
+    exit_list_refresh_mins: Option<u64>,
+    pow_difficulty: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CaptchaSection {
+    provider: Option<String>,
+    site_key: Option<String>,
+    secret: Option<String>,
+    verify_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TelemetrySection {
+    otlp_endpoint: Option<String>,
+    otlp_service_name: Option<String>,
+    sentry_dsn: Option<String>,
+    sentry_environment: Option<String>,
+}
+
+impl FileConfig {
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Flatten into environment variable names
+    fn into_vars(self) -> HashMap<String, String> {
+        let mut vars = HashMap::new();
+        let mut set = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                vars.insert(key.to_string(), value);
+            }
+        };
+
+        let Self { server, database, storage, backup, limits, rate_limit, admin, webhook, telemetry, geoip, tor, captcha } = self;
+
+        set("PORT", server.port.map(|v| v.to_string()));
+        set("PUBLIC_URL", server.public_url);
+        set("SITE_NAME", server.site_name);
+        set("ID_SCHEME", server.id_scheme);
+        set("SHORT_ID_LENGTH", server.short_id_length.map(|v| v.to_string()));
+        set("TLS_CERT_PATH", server.tls_cert_path);
+        set("TLS_KEY_PATH", server.tls_key_path);
+        set("CONTENT_SECURITY_POLICY", server.content_security_policy);
+        set("STATIC_DIR", server.static_dir);
+        set("CSP_SCRIPT_HASHES", server.csp_script_hashes.map(|v| v.join(",")));
+        set("ROBOTS_TXT", server.robots_txt);
+        set("ROBOTS_DISALLOW", server.robots_disallow.map(|v| v.join(",")));
+        set("SECURITY_CONTACT", server.security_contact.map(|v| v.join(",")));
+        set("SECURITY_POLICY", server.security_policy);
+        set("WELL_KNOWN_DIR", server.well_known_dir);
+        set("REQUEST_TIMEOUT_SECS", server.request_timeout_secs.map(|v| v.to_string()));
+        set("UPLOAD_TIMEOUT_SECS", server.upload_timeout_secs.map(|v| v.to_string()));
+        set("STATS_CACHE_SECS", server.stats_cache_secs.map(|v| v.to_string()));
+        set("ANALYTICS_ENABLED", server.analytics_enabled.map(|v| v.to_string()));
+        set("GRAPHQL_ENABLED", server.graphql_enabled.map(|v| v.to_string()));
+        set("WEBDAV_ENABLED", server.webdav_enabled.map(|v| v.to_string()));
+        set("S3_API_ENABLED", server.s3_api_enabled.map(|v| v.to_string()));
+        set("GRPC_PORT", server.grpc_port.map(|v| v.to_string()));
+        set("MAX_CONCURRENT_REQUESTS", server.max_concurrent_requests.map(|v| v.to_string()));
+        set("LOAD_SHED_ENABLED", server.load_shed_enabled.map(|v| v.to_string()));
+        set("LOAD_SHED_TARGET_LATENCY_MS", server.load_shed_target_latency_ms.map(|v| v.to_string()));
+        set("LOAD_SHED_QUEUE_DEPTH", server.load_shed_queue_depth.map(|v| v.to_string()));
+
+        set("DATABASE_URL", database.url);
+        set("RUN_MIGRATIONS", database.run_migrations.map(|v| v.to_string()));
+        set("DB_MAX_CONNECTIONS", database.max_connections.map(|v| v.to_string()));
+        set("SQLITE_JOURNAL_MODE", database.journal_mode);
+        set("SQLITE_SYNCHRONOUS", database.synchronous);
+        set("SQLITE_BUSY_TIMEOUT_MS", database.busy_timeout_ms.map(|v| v.to_string()));
+        set("SQLITE_FOREIGN_KEYS", database.foreign_keys.map(|v| v.to_string()));
+        set("DB_MAINTENANCE_INTERVAL_HOURS", database.maintenance_interval_hours.map(|v| v.to_string()));
+        set("CLEANUP_INTERVAL_SECS", database.cleanup_interval_secs.map(|v| v.to_string()));
+        set("CLEANUP_JITTER_SECS", database.cleanup_jitter_secs.map(|v| v.to_string()));
+
+        set("STORAGE_BACKEND", storage.backend);
+        set("UPLOAD_DIR", storage.upload_dir);
+        set("S3_BUCKET", storage.s3_bucket);
+        set("S3_REGION", storage.s3_region);
+        set("S3_ENDPOINT", storage.s3_endpoint);
+        set("S3_ALLOW_HTTP", storage.s3_allow_http.map(|v| v.to_string()));
+        set("SECURE_DELETE_PASSES", storage.secure_delete_passes.map(|v| v.to_string()));
+
+        set("BACKUP_S3_BUCKET", backup.s3_bucket);
+        set("BACKUP_S3_REGION", backup.s3_region);
+        set("BACKUP_S3_ENDPOINT", backup.s3_endpoint);
+        set("BACKUP_S3_ALLOW_HTTP", backup.s3_allow_http.map(|v| v.to_string()));
+        set("BACKUP_S3_PREFIX", backup.s3_prefix);
+        set("BACKUP_INTERVAL_HOURS", backup.interval_hours.map(|v| v.to_string()));
+        set("BACKUP_RETENTION_DAYS", backup.retention_days.map(|v| v.to_string()));
+
+        set("DEFAULT_EXPIRY_HOURS", limits.default_expiry_hours.map(|v| v.to_string()));
+        set("MAX_EXPIRY_HOURS", limits.max_expiry_hours.map(|v| v.to_string()));
+        set("MAX_FILE_BYTES", limits.max_file_bytes.map(|v| v.to_string()));
+        set("MAX_POST_BYTES", limits.max_post_bytes.map(|v| v.to_string()));
+        set("MAX_POST_ENTRY_BYTES", limits.max_post_entry_bytes.map(|v| v.to_string()));
+        set("MAX_POST_ENTRIES", limits.max_post_entries.map(|v| v.to_string()));
+        set("MAX_POST_TOTAL_BYTES", limits.max_post_total_bytes.map(|v| v.to_string()));
+        set("MAX_DOGPASTE_BYTES", limits.max_dogpaste_bytes.map(|v| v.to_string()));
+        set("ALLOWED_MIME_TYPES", limits.allowed_mime_types.map(|v| v.join(",")));
+        set("BLOCKED_MIME_TYPES", limits.blocked_mime_types.map(|v| v.join(",")));
+        set("ALLOWED_EXTENSIONS", limits.allowed_extensions.map(|v| v.join(",")));
+        set("BLOCKED_EXTENSIONS", limits.blocked_extensions.map(|v| v.join(",")));
+        set("MAX_TOTAL_STORAGE_BYTES", limits.max_total_storage_bytes.map(|v| v.to_string()));
+        set("STORAGE_FULL_POLICY", limits.storage_full_policy);
+        set("MIN_FREE_DISK_BYTES", limits.min_free_disk_bytes.map(|v| v.to_string()));
+        set("TEST_DELETE_PERIOD_HOURS", limits.test_delete_period_hours.map(|v| v.to_string()));
+
+        set("RATE_LIMIT_ENABLED", rate_limit.enabled.map(|v| v.to_string()));
+        set("RATE_LIMIT_PER_SECOND", rate_limit.per_second.map(|v| v.to_string()));
+        set("RATE_LIMIT_BURST", rate_limit.burst.map(|v| v.to_string()));
+        set("RATE_LIMIT_BACKEND", rate_limit.backend);
+        set("REDIS_URL", rate_limit.redis_url);
+        set("TRUSTED_PROXIES", rate_limit.trusted_proxies.map(|v| v.join(",")));
+        for (class, route) in [
+            ("UPLOAD", rate_limit.upload),
+            ("DOWNLOAD", rate_limit.download),
+            ("POST_VIEW", rate_limit.post_view),
+            ("STATIC", rate_limit.static_files),
+        ] {
+            set(&format!("RATE_LIMIT_{}_PER_SECOND", class), route.per_second.map(|v| v.to_string()));
+            set(&format!("RATE_LIMIT_{}_BURST", class), route.burst.map(|v| v.to_string()));
+        }
+
+        set("ADMIN_TOKEN", admin.token);
+        set("ADMIN_MESSAGE", admin.message);
+        set("PRIVATE_MODE", admin.private_mode);
+        set("API_KEY_MAX_UPLOAD_BYTES", admin.api_key_max_upload_bytes.map(|v| v.to_string()));
+        set("API_KEY_MAX_EXPIRY_HOURS", admin.api_key_max_expiry_hours.map(|v| v.to_string()));
+
+        set("WEBHOOK_URL", webhook.url);
+        set("WEBHOOK_SECRET", webhook.secret);
+        set("EXPIRY_CALLBACKS_ENABLED", webhook.expiry_callbacks_enabled.map(|v| v.to_string()));
+
+        set("OTLP_ENDPOINT", telemetry.otlp_endpoint);
+        set("OTLP_SERVICE_NAME", telemetry.otlp_service_name);
+        set("SENTRY_DSN", telemetry.sentry_dsn);
+        set("SENTRY_ENVIRONMENT", telemetry.sentry_environment);
+
+        set("GEOIP_DATABASE", geoip.database);
+        set("GEOIP_BLOCKED_COUNTRIES", geoip.blocked_countries.map(|v| v.join(",")));
+        set("GEOIP_ALLOWED_COUNTRIES", geoip.allowed_countries.map(|v| v.join(",")));
+
+        set("TOR_EXIT_POLICY", tor.exit_policy);
+        set("TOR_EXIT_LIST_URL", tor.exit_list_url);
+        set("TOR_EXIT_LIST_REFRESH_MINS", tor.exit_list_refresh_mins.map(|v| v.to_string()));
+        set("POW_DIFFICULTY", tor.pow_difficulty.map(|v| v.to_string()));
+
+        set("CAPTCHA_PROVIDER", captcha.provider);
+        set("CAPTCHA_SITE_KEY", captcha.site_key);
+        set("CAPTCHA_SECRET", captcha.secret);
+        set("CAPTCHA_VERIFY_URL", captcha.verify_url);
+
+        vars
+    }
+}
@@ -1,19 +1,193 @@
-/// Application-wide constants
-/// All magic numbers and constant values should be defined here
+//! Application-wide constants
+//! All magic numbers and constant values should be defined here
 
 /// Maximum upload size in bytes (5 GB)
 pub const MAX_UPLOAD_SIZE: usize = 5 * 1024 * 1024 * 1024;
 
+/// Default cap on initial post content (10 MB); posts are stored in the database
+pub const DEFAULT_MAX_POST_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Default cap on one entry appended to a post (10 MB)
+pub const DEFAULT_MAX_POST_ENTRY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Default cap on a dogpaste (1 MB)
+pub const DEFAULT_MAX_DOGPASTE_BYTES: u64 = 1024 * 1024;
+
 /// Dogbox emoji sequence used in logs and UI
 pub const DOGBOX_EMOJI: &str = "🐕🐾🦴💨";
 
-/// Cleanup task interval in seconds (1 hour)
-pub const CLEANUP_INTERVAL_SECS: u64 = 3600;
+/// Default cleanup task interval in seconds (1 hour)
+pub const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 3600;
+
+/// Default upper bound of the random delay before the first cleanup run
+pub const DEFAULT_CLEANUP_JITTER_SECS: u64 = 60;
+
+/// How often totals are recorded for the stats history chart (1 hour)
+pub const STATS_SNAPSHOT_INTERVAL_SECS: u64 = 3600;
+
+/// Default maximum number of content entries per post (prevents memory exhaustion)
+pub const DEFAULT_MAX_POST_ENTRIES: i64 = 1000;
 
-/// Maximum number of content entries per post (prevents memory exhaustion)
-pub const MAX_POST_CONTENT_ENTRIES: i64 = 1000;
+/// Default cap on all of a post's entries together (100 MB)
+pub const DEFAULT_MAX_POST_TOTAL_BYTES: u64 = 100 * 1024 * 1024;
 
 /// Dogpaste character set for IDs and encryption keys
 /// Human-friendly: excludes ambiguous characters (0, O, 1, l, I)
 /// This ensures codes are easy to type and read
 pub const DOGPASTE_CHARSET: &str = "23456789abcdefghjkmnpqrstuvwxyz";
+
+/// tus protocol version implemented by the resumable upload endpoints
+pub const TUS_VERSION: &str = "1.0.0";
+
+/// How long an unfinished resumable upload session is kept (24 hours)
+pub const TUS_SESSION_EXPIRY_HOURS: i64 = 24;
+
+/// Minimum length of the ADMIN_TOKEN secret
+pub const MIN_ADMIN_TOKEN_LENGTH: usize = 32;
+
+/// Default and maximum page sizes for admin listings
+pub const ADMIN_DEFAULT_PAGE_SIZE: i64 = 50;
+pub const ADMIN_MAX_PAGE_SIZE: i64 = 500;
+
+/// How often each instance reloads IP bans from the database (bans made through
+/// this instance apply immediately)
+pub const BAN_REFRESH_SECS: u64 = 60;
+
+/// How often the in-memory rate limiter forgets clients whose buckets have refilled
+pub const RATE_LIMIT_PRUNE_SECS: u64 = 60;
+
+/// Tor Project's list of exit addresses (one per line)
+pub const DEFAULT_TOR_EXIT_LIST_URL: &str = "https://check.torproject.org/torbulkexitlist";
+pub const DEFAULT_TOR_EXIT_LIST_REFRESH_MINS: u64 = 60;
+pub const TOR_EXIT_LIST_TIMEOUT_SECS: u64 = 30;
+
+/// Proof-of-work difficulty in leading zero bits of SHA-256 (each bit doubles the work;
+/// 18 takes a browser a few seconds)
+pub const DEFAULT_POW_DIFFICULTY: u32 = 18;
+pub const MIN_POW_DIFFICULTY: u32 = 8;
+pub const MAX_POW_DIFFICULTY: u32 = 32;
+
+/// How long a proof-of-work challenge can be solved and redeemed
+pub const POW_CHALLENGE_TTL_SECS: i64 = 300;
+
+/// Settings key of the secret proof-of-work challenges are signed with
+pub const POW_SECRET_SETTING: &str = "pow_secret";
+
+/// Time limit for asking the captcha provider about a token
+pub const CAPTCHA_VERIFY_TIMEOUT_SECS: u64 = 10;
+
+/// Nesting depth and complexity (roughly, fields resolved) allowed per GraphQL query
+pub const GRAPHQL_MAX_DEPTH: usize = 8;
+pub const GRAPHQL_MAX_COMPLEXITY: usize = 500;
+
+/// Default and maximum length of the top-N lists in admin stats
+pub const ADMIN_STATS_DEFAULT_TOP: i64 = 10;
+pub const ADMIN_STATS_MAX_TOP: i64 = 100;
+
+/// Default and maximum number of days of uploads in admin stats
+pub const ADMIN_STATS_DEFAULT_DAYS: i64 = 30;
+pub const ADMIN_STATS_MAX_DAYS: i64 = 365;
+
+/// Maximum number of collaborator append keys per post
+pub const MAX_POST_KEYS: i64 = 50;
+
+/// Settings key of the admin message set through PUT /api/admin/motd
+pub const ADMIN_MESSAGE_SETTING: &str = "admin_message";
+
+/// Maximum length of an admin message set through the admin API
+pub const MAX_ADMIN_MESSAGE_LENGTH: usize = 500;
+
+/// Maximum length of a collaborator key label
+pub const MAX_POST_KEY_LABEL_LENGTH: usize = 100;
+
+/// Maximum number of files in one collection
+pub const MAX_COLLECTION_FILES: i64 = 1000;
+
+/// Maximum number of file IDs accepted per add-to-collection request
+pub const MAX_COLLECTION_FILES_PER_REQUEST: usize = 100;
+
+/// Maximum length of a path below /dav/ (bytes, after percent-decoding)
+pub const MAX_DAV_PATH_LENGTH: usize = 1024;
+
+/// Empty collections are removed by the cleanup task after this many hours
+pub const EMPTY_COLLECTION_RETENTION_HOURS: i64 = 24;
+
+/// Maximum number of file IDs per bulk metadata lookup
+pub const MAX_BULK_INFO_IDS: usize = 100;
+
+/// Default length of short file IDs (ID_SCHEME=short)
+pub const DEFAULT_SHORT_ID_LENGTH: usize = 10;
+
+/// Attempts at finding an unused short ID before giving up
+pub const MAX_ID_ATTEMPTS: usize = 5;
+
+/// Maximum length of a file extension, without the dot
+pub const MAX_FILE_EXTENSION_LENGTH: usize = 16;
+
+/// Maximum length of a `?name=` download filename, in characters
+pub const MAX_DOWNLOAD_NAME_CHARS: usize = 255;
+
+/// Declared MIME types a download may be served inline for (`?inline=1`)
+///
+/// Media only: no SVG, HTML, XML or PDF, which can carry script.
+pub const INLINE_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "image/bmp",
+    "audio/*",
+    "video/*",
+];
+
+/// Content-Security-Policy for inline downloads: the browser's own media viewer, nothing else
+pub const INLINE_CONTENT_SECURITY_POLICY: &str =
+    "default-src 'none'; img-src 'self'; media-src 'self'; style-src 'unsafe-inline'; sandbox";
+
+/// Minimum length of a custom post slug
+pub const MIN_SLUG_LENGTH: usize = 3;
+
+/// Maximum length of a custom post slug
+pub const MAX_SLUG_LENGTH: usize = 64;
+
+/// Days of stats history returned when `?days=` is not given
+pub const STATS_HISTORY_DEFAULT_DAYS: i64 = 30;
+
+/// Stats snapshots older than this are deleted
+pub const STATS_HISTORY_MAX_DAYS: i64 = 365;
+
+/// Counter of files evicted under STORAGE_FULL_POLICY=evict
+pub const EVICTED_FILES_COUNTER: &str = "evicted_files";
+
+/// Upper bound on SECURE_DELETE_PASSES (Gutmann's 35 passes)
+pub const MAX_SECURE_DELETE_PASSES: u32 = 35;
+
+/// Buffer size for secure delete overwrite passes (1 MB)
+pub const SHRED_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Paths the default robots.txt asks crawlers to skip (share pages and the API)
+pub const DEFAULT_ROBOTS_DISALLOW: &[&str] = &["/f/", "/p/", "/api/"];
+
+/// How far ahead security.txt's Expires field is set (RFC 9116 recommends under a year)
+pub const SECURITY_TXT_EXPIRY_DAYS: i64 = 180;
+
+/// Hashes of the bundled frontend's inline scripts, allowed by the default CSP
+/// Override with CSP_SCRIPT_HASHES when serving a modified frontend
+/// To regenerate: just hash-scripts
+pub const DEFAULT_CSP_SCRIPT_HASHES: &[&str] = &[
+    "sha256-HUvE11OLFz7AoCbhbk01ZWOcwLvkI+CahEfFg54mSTU=",
+    "sha256-SGWGvJu8HcqulHmTV7/WfP/TjcWCtNH40zGJbhmurLQ=",
+    "sha256-UwIxe9p9b2FNZcGBE29ru4ohO+xC1LiOPTC/1s6DRDI=",
+    "sha256-dOFOu+c3tOHIxiHjp4NQ7kBAJNPVqIV2C0nsVeEtLZU=",
+    "sha256-gXFFdg/UCt0MfJH9IbdwGFsCxpsE4aa9D0vNkYyzRcA=",
+];
+
+/// Per-attempt timeout for webhook deliveries
+pub const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// Deliveries attempted per webhook event before it is dropped
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 3;
+
+/// Maximum length of an expiry warning callback URL
+pub const MAX_CALLBACK_URL_LENGTH: usize = 2048;
@@ -1,7 +1,10 @@
+use crate::config::SqliteTuning;
 use crate::error::Result;
-use crate::models::{FileRecord, PostContent};
+use crate::models::{DeletedFile, FileRecord, PostContent, StoredBlob};
 use chrono::{DateTime, Utc};
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use subtle::ConstantTimeEq;
 use rand::Rng;
 
@@ -11,11 +14,35 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn new(database_url: &str) -> anyhow::Result<Self> {
-        // Create database if it doesn't exist
+    pub async fn new(database_url: &str, tuning: &SqliteTuning) -> anyhow::Result<Self> {
+        // Create database if it doesn't exist; pragmas are applied on every new connection
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .journal_mode(tuning.journal_mode)
+            .synchronous(tuning.synchronous)
+            .busy_timeout(tuning.busy_timeout)
+            .foreign_keys(tuning.foreign_keys);
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(database_url)
+            .max_connections(tuning.max_connections)
+            .connect_with(options)
+            .await?;
+
+        Ok(Self { pool })
+    }
+
+    /// File a `sqlite:` database URL points at
+    pub fn file_path(database_url: &str) -> anyhow::Result<PathBuf> {
+        Ok(SqliteConnectOptions::from_str(database_url)?.get_filename().into_owned())
+    }
+
+    /// Open an existing database file without writing to it (backup snapshots)
+    pub async fn open_read_only(path: &Path) -> anyhow::Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
             .await?;
 
         Ok(Self { pool })
@@ -23,21 +50,455 @@ impl Database {
 
     pub async fn migrate(&self) -> anyhow::Result<()> {
         tracing::info!("Running database migrations...");
-        sqlx::migrate!("./migrations")
-            .run(&self.pool)
-            .await?;
+        // Rebuilding a table drops it, which would cascade into every table referencing
+        // it. Foreign keys can't be switched inside the transaction each migration runs
+        // in, so the migrations share one connection that has them off.
+        let mut conn = self.pool.acquire().await?;
+        let foreign_keys: bool = sqlx::query_scalar("PRAGMA foreign_keys").fetch_one(&mut *conn).await?;
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+        let migrated = sqlx::migrate!("./migrations").run(&mut *conn).await;
+        if foreign_keys {
+            sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+        }
+        migrated?;
         tracing::info!("✓ Migrations complete");
         Ok(())
     }
 
+    /// Versions of embedded migrations that haven't been applied successfully
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>> {
+        let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(sqlx::migrate!("./migrations")
+            .iter()
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect())
+    }
+
+    /// Write a transactionally consistent copy of the database to `path`
+    ///
+    /// `VACUUM INTO` reads inside a single transaction, so the copy is safe to take
+    /// while the server keeps writing (WAL readers don't block writers).
+    pub async fn snapshot_into(&self, path: &Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Files whose blobs are still in storage (posts have none, exhausted files lost theirs)
+    pub async fn list_stored_blobs(&self) -> Result<Vec<StoredBlob>> {
+        let blobs = sqlx::query_as::<_, StoredBlob>(
+            r#"
+            SELECT id, storage_path, size_bytes, blake3_hash FROM files
+            WHERE post_type = 'file' AND (downloads_remaining IS NULL OR downloads_remaining > 0)
+            ORDER BY uploaded_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(blobs)
+    }
+
+    /// Storage keys of every file row, live or not (orphan collection keeps these)
+    pub async fn list_storage_paths(&self) -> Result<Vec<String>> {
+        let paths = sqlx::query_scalar("SELECT storage_path FROM files WHERE post_type = 'file'")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(paths)
+    }
+
+    /// Bytes of file blobs currently held in storage (expired but not yet cleaned up included)
+    pub async fn get_blob_bytes(&self) -> Result<i64> {
+        let bytes: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(size_bytes), 0) FROM files
+            WHERE post_type = 'file' AND (downloads_remaining IS NULL OR downloads_remaining > 0)
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(bytes)
+    }
+
+    /// Delete the non-permanent file whose blob was least recently downloaded (or uploaded)
+    pub async fn evict_least_recently_used(&self) -> Result<Option<DeletedFile>> {
+        let evicted = sqlx::query_as::<_, DeletedFile>(
+            r#"
+            DELETE FROM files WHERE id = (
+                SELECT id FROM files
+                WHERE is_permanent = 0 AND post_type = 'file' AND quarantined_at IS NULL
+                  AND (downloads_remaining IS NULL OR downloads_remaining > 0)
+                ORDER BY datetime(COALESCE(last_downloaded_at, uploaded_at)) ASC
+                LIMIT 1
+            )
+            RETURNING id, storage_path, post_type, size_bytes, downloads_remaining
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(evicted)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %id))]
+    pub async fn mark_downloaded(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE files SET last_downloaded_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn increment_counter(&self, name: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO counters (name, value) VALUES (?, 1) ON CONFLICT(name) DO UPDATE SET value = value + 1"
+        )
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_counter(&self, name: &str) -> Result<i64> {
+        let value: Option<i64> = sqlx::query_scalar("SELECT value FROM counters WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(value.unwrap_or(0))
+    }
+
+    /// Runtime setting stored through the admin API (None = not overridden)
+    pub async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(value)
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at"
+        )
+        .bind(key)
+        .bind(value)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Store `value` unless the setting exists, returning whichever value is stored
+    pub async fn get_or_init_setting(&self, key: &str, value: &str) -> Result<String> {
+        sqlx::query("INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?) ON CONFLICT(key) DO NOTHING")
+            .bind(key)
+            .bind(value)
+            .bind(Utc::now().timestamp())
+            .execute(&self.pool)
+            .await?;
+        let stored = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(stored)
+    }
+
+    pub async fn delete_setting(&self, key: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM settings WHERE key = ?")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Access key methods (keys are stored as BLAKE3 hashes)
+    pub async fn create_access_key(&self, key: &crate::models::AccessKey, key_hash: &str) -> Result<()> {
+        sqlx::query("INSERT INTO access_keys (id, key_hash, label, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&key.id)
+            .bind(key_hash)
+            .bind(&key.label)
+            .bind(key.created_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_access_keys(&self) -> Result<Vec<crate::models::AccessKey>> {
+        let keys = sqlx::query_as::<_, crate::models::AccessKey>(
+            "SELECT id, label, created_at FROM access_keys ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(keys)
+    }
+
+    pub async fn revoke_access_key(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM access_keys WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn access_key_exists(&self, key_hash: &str) -> Result<bool> {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM access_keys WHERE key_hash = ?")
+            .bind(key_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(exists.is_some())
+    }
+
+    // API key methods (keys are stored as BLAKE3 hashes)
+    pub async fn create_api_key(&self, key: &crate::models::ApiKey, key_hash: &str, s3_secret_key: &str) -> Result<()> {
+        sqlx::query("INSERT INTO api_keys (id, key_hash, label, created_at, s3_secret_key) VALUES (?, ?, ?, ?, ?)")
+            .bind(&key.id)
+            .bind(key_hash)
+            .bind(&key.label)
+            .bind(key.created_at)
+            .bind(s3_secret_key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_api_keys(&self) -> Result<Vec<crate::models::ApiKey>> {
+        let keys = sqlx::query_as::<_, crate::models::ApiKey>(
+            "SELECT id, label, created_at, last_used_at FROM api_keys ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(keys)
+    }
+
+    pub async fn revoke_api_key(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM api_keys WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Look up an API key by hash; returns the key ID
+    pub async fn find_api_key(&self, key_hash: &str) -> Result<Option<String>> {
+        let id: Option<String> = sqlx::query_scalar("SELECT id FROM api_keys WHERE key_hash = ?")
+            .bind(key_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(id)
+    }
+
+    /// Record that an API key was just used successfully
+    pub async fn record_api_key_use(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE api_keys SET last_used_at = ? WHERE id = ?")
+            .bind(chrono::Utc::now().timestamp())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// S3 secret of an API key by ID (its access key ID)
+    pub async fn find_api_key_s3_secret(&self, id: &str) -> Result<Option<String>> {
+        let secret: Option<String> =
+            sqlx::query_scalar("SELECT s3_secret_key FROM api_keys WHERE id = ? AND s3_secret_key IS NOT NULL")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(secret)
+    }
+
+    // Upload token methods (tokens are stored as BLAKE3 hashes)
+    pub async fn create_upload_token(&self, token: &crate::models::UploadToken, token_hash: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO upload_tokens (id, token_hash, label, max_size_bytes, max_expiry_hours, uses_remaining, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&token.id)
+        .bind(token_hash)
+        .bind(&token.label)
+        .bind(token.max_size_bytes)
+        .bind(token.max_expiry_hours)
+        .bind(token.uses_remaining)
+        .bind(token.expires_at)
+        .bind(token.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_upload_tokens(&self) -> Result<Vec<crate::models::UploadToken>> {
+        let tokens = sqlx::query_as::<_, crate::models::UploadToken>(
+            "SELECT id, label, max_size_bytes, max_expiry_hours, uses_remaining, expires_at, created_at
+             FROM upload_tokens ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tokens)
+    }
+
+    pub async fn revoke_upload_token(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM upload_tokens WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Use up one upload of a token that is still valid (atomic, so a single-use
+    /// token can't be redeemed twice by concurrent requests)
+    pub async fn consume_upload_token(&self, token_hash: &str) -> Result<Option<crate::models::UploadToken>> {
+        let token = sqlx::query_as::<_, crate::models::UploadToken>(
+            "UPDATE upload_tokens SET uses_remaining = uses_remaining - 1
+             WHERE token_hash = ?
+               AND (uses_remaining IS NULL OR uses_remaining > 0)
+               AND (expires_at IS NULL OR expires_at > ?)
+             RETURNING id, label, max_size_bytes, max_expiry_hours, uses_remaining, expires_at, created_at"
+        )
+        .bind(token_hash)
+        .bind(chrono::Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(token)
+    }
+
+    /// Give back a use taken by `consume_upload_token` when the upload failed
+    pub async fn refund_upload_token(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE upload_tokens SET uses_remaining = uses_remaining + 1 WHERE id = ? AND uses_remaining IS NOT NULL")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Audit log methods
+    /// Append to the audit log
+    ///
+    /// Failures are logged rather than returned so they never undo the audited action.
+    pub async fn audit(
+        &self,
+        actor: crate::models::AuditActor,
+        action: &str,
+        target: Option<&str>,
+        details: Option<&str>,
+    ) {
+        let result = sqlx::query(
+            "INSERT INTO audit_log (created_at, actor, action, target, details) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(chrono::Utc::now().timestamp())
+        .bind(actor.as_str())
+        .bind(action)
+        .bind(target)
+        .bind(details)
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to write audit log entry {}: {}", action, e);
+        }
+    }
+
+    /// Page of audit log entries, newest first, optionally filtered by action and actor
+    pub async fn list_audit_log(
+        &self,
+        action: Option<&str>,
+        actor: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<crate::models::AuditEntry>, i64)> {
+        let entries = sqlx::query_as::<_, crate::models::AuditEntry>(
+            "SELECT id, created_at, actor, action, target, details FROM audit_log
+             WHERE (?1 IS NULL OR action = ?1) AND (?2 IS NULL OR actor = ?2)
+             ORDER BY id DESC LIMIT ?3 OFFSET ?4"
+        )
+        .bind(action)
+        .bind(actor)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM audit_log WHERE (?1 IS NULL OR action = ?1) AND (?2 IS NULL OR actor = ?2)"
+        )
+        .bind(action)
+        .bind(actor)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((entries, total))
+    }
+
+    // Maintenance methods
+    /// Return free pages to the filesystem and refresh query planner statistics
+    ///
+    /// The first run switches the database to incremental auto-vacuum, which takes
+    /// a full VACUUM (rewriting the whole file).
+    pub async fn run_maintenance(&self) -> Result<crate::models::MaintenanceReport> {
+        let started = std::time::Instant::now();
+        // Pragmas below report on and act through a single connection
+        let mut conn = self.pool.acquire().await?;
+
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(&mut *conn).await?;
+        let free_before: i64 = sqlx::query_scalar("PRAGMA freelist_count").fetch_one(&mut *conn).await?;
+
+        // auto_vacuum: 0 = none, 1 = full, 2 = incremental
+        let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum").fetch_one(&mut *conn).await?;
+        let full_vacuum = auto_vacuum != 2;
+        if full_vacuum {
+            sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&mut *conn).await?;
+            sqlx::query("VACUUM").execute(&mut *conn).await?;
+        } else {
+            sqlx::query("PRAGMA incremental_vacuum").execute(&mut *conn).await?;
+        }
+        sqlx::query("ANALYZE").execute(&mut *conn).await?;
+
+        let free_after: i64 = sqlx::query_scalar("PRAGMA freelist_count").fetch_one(&mut *conn).await?;
+        Ok(crate::models::MaintenanceReport {
+            full_vacuum,
+            bytes_reclaimed: (free_before - free_after).max(0) * page_size,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Round-trip a trivial query to confirm the database is reachable
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %file.id))]
     pub async fn create_file(&self, file: &FileRecord) -> Result<()> {
+        Self::insert_file(&self.pool, file).await
+    }
+
+    /// Create a file written through the WebDAV/S3 namespace, recording it as such
+    /// in the same transaction
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %file.id))]
+    pub async fn create_namespace_file(&self, file: &FileRecord) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert_file(&mut *tx, file).await?;
+        sqlx::query("INSERT INTO dav_files (file_id) VALUES (?)")
+            .bind(&file.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_file<'e>(executor: impl sqlx::SqliteExecutor<'e>, file: &FileRecord) -> Result<()> {
         sqlx::query!(
             r#"
             INSERT INTO files (
                 id, filename_encrypted, size_bytes, mime_type,
                 uploaded_at, expires_at, deletion_token, storage_path,
-                blake3_hash, post_type, post_append_key, is_permanent, view_count, file_extension
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                blake3_hash, post_type, post_append_key, is_permanent, view_count, file_extension,
+                downloads_remaining, slug
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             file.id,
             file.filename_encrypted,
@@ -53,13 +514,16 @@ impl Database {
             file.is_permanent,
             file.view_count,
             file.file_extension,
+            file.downloads_remaining,
+            file.slug,
         )
-        .execute(&self.pool)
+        .execute(executor)
         .await?;
 
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %id))]
     pub async fn get_file(&self, id: &str) -> Result<Option<FileRecord>> {
         let file = sqlx::query_as!(
             FileRecord,
@@ -72,9 +536,11 @@ impl Database {
                    post_type, post_append_key,
                    is_permanent as "is_permanent: bool",
                    view_count,
-                   file_extension
+                   file_extension,
+                   downloads_remaining,
+                   slug
             FROM files
-            WHERE id = ? AND (is_permanent = 1 OR expires_at > datetime('now'))
+            WHERE id = ? AND (is_permanent = 1 OR datetime(expires_at) > datetime('now'))
             "#,
             id
         )
@@ -84,6 +550,7 @@ impl Database {
         Ok(file)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %id))]
     pub async fn delete_file(&self, id: &str, deletion_token: &str) -> Result<bool> {
         // Fetch the file record to get the stored deletion token
         let file = sqlx::query!(
@@ -127,16 +594,31 @@ impl Database {
         }
     }
 
-    pub async fn cleanup_expired(&self) -> Result<u64> {
+    /// Delete expired rows, returning how many were removed and the deleted files
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn cleanup_expired(&self) -> Result<(u64, Vec<DeletedFile>)> {
         // Clean up expired files
-        let files_result = sqlx::query!(
+        let expired_files: Vec<DeletedFile> = sqlx::query_as(
             r#"
             DELETE FROM files
-            WHERE is_permanent = 0 AND expires_at <= datetime('now')
+            WHERE is_permanent = 0 AND quarantined_at IS NULL AND datetime(expires_at) <= datetime('now')
+            RETURNING id, storage_path, post_type, size_bytes, downloads_remaining
             "#
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
+        let files_deleted = expired_files.len() as u64;
+
+        // Warnings and WebDAV paths of deleted files (in case foreign keys are disabled)
+        sqlx::query("DELETE FROM expiry_warnings WHERE file_id NOT IN (SELECT id FROM files)")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM dav_entries WHERE file_id NOT IN (SELECT id FROM files)")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM dav_files WHERE file_id NOT IN (SELECT id FROM files)")
+            .execute(&self.pool)
+            .await?;
 
         // Clean up expired dogpastes
         let now = chrono::Utc::now().timestamp();
@@ -147,39 +629,75 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
-        let total = files_result.rows_affected() + pastes_result.rows_affected();
+        let total = files_deleted + pastes_result.rows_affected();
 
         if pastes_result.rows_affected() > 0 {
             tracing::debug!("🗑️  Cleaned up {} expired dogpastes", pastes_result.rows_affected());
         }
 
-        Ok(total)
+        // Collections whose files have all expired (or that were never filled)
+        let collections_cutoff = now - crate::constants::EMPTY_COLLECTION_RETENTION_HOURS * 3600;
+        let collections_result = sqlx::query(
+            r#"
+            DELETE FROM collections
+            WHERE created_at <= ?
+              AND NOT EXISTS (SELECT 1 FROM collection_files WHERE collection_id = collections.id)
+            "#
+        )
+        .bind(collections_cutoff)
+        .execute(&self.pool)
+        .await?;
+
+        if collections_result.rows_affected() > 0 {
+            tracing::debug!("🗑️  Cleaned up {} empty collections", collections_result.rows_affected());
+        }
+
+        Ok((total, expired_files))
     }
 
-    pub async fn find_by_hash(&self, blake3_hash: &str) -> Result<Option<FileRecord>> {
-        let file = sqlx::query_as!(
-            FileRecord,
+    /// A live file that an upload of the same blob can be answered with
+    ///
+    /// Only plain files qualify: posts can be appended to, a download limit belongs to
+    /// the upload that set it, quarantined files aren't served, and namespace files go
+    /// away with their path. Of several copies, the one expiring last is returned.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn find_dedup_target(&self, blake3_hash: &str, is_permanent: bool) -> Result<Option<FileRecord>> {
+        let file = sqlx::query_as::<_, FileRecord>(
             r#"
-            SELECT id, filename_encrypted, size_bytes, mime_type,
-                   uploaded_at as "uploaded_at: DateTime<Utc>",
-                   expires_at as "expires_at: DateTime<Utc>",
-                   deletion_token, storage_path, blake3_hash,
-                   created_at as "created_at: DateTime<Utc>",
-                   post_type, post_append_key,
-                   is_permanent as "is_permanent: bool",
-                   view_count,
-                   file_extension
+            SELECT id, filename_encrypted, size_bytes, mime_type, uploaded_at, expires_at,
+                   deletion_token, storage_path, blake3_hash, created_at, post_type,
+                   post_append_key, is_permanent, view_count, file_extension,
+                   downloads_remaining, slug
             FROM files
-            WHERE blake3_hash = ? AND (is_permanent = 1 OR expires_at > datetime('now'))
+            WHERE blake3_hash = ?
+              AND post_type = 'file'
+              AND downloads_remaining IS NULL
+              AND quarantined_at IS NULL
+              AND id NOT IN (SELECT file_id FROM dav_files)
+              AND is_permanent = ?
+              AND (is_permanent = 1 OR datetime(expires_at) > datetime('now'))
+            ORDER BY datetime(expires_at) DESC
+            LIMIT 1
             "#,
-            blake3_hash
         )
+        .bind(blake3_hash)
+        .bind(is_permanent)
         .fetch_optional(&self.pool)
         .await?;
 
         Ok(file)
     }
 
+    /// Count an upload that was answered with this file instead of a new copy
+    pub async fn record_dedup_hit(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE files SET dedup_hits = dedup_hits + 1 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %id))]
     pub async fn increment_view_count(&self, id: &str) -> Result<()> {
         sqlx::query!(
             r#"
@@ -196,6 +714,8 @@ impl Database {
     }
 
     // Post-specific methods
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
     pub async fn add_post_content(
         &self,
         file_id: &str,
@@ -228,6 +748,7 @@ impl Database {
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
     pub async fn get_post_content(&self, file_id: &str) -> Result<Vec<PostContent>> {
         let content = sqlx::query_as!(
             PostContent,
@@ -264,6 +785,17 @@ impl Database {
         Ok(result.next_order as i64)
     }
 
+    /// Bytes of encrypted content stored for a post (entries are base64, 4 characters per 3 bytes)
+    pub async fn get_post_content_bytes(&self, file_id: &str) -> Result<i64> {
+        let chars: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(LENGTH(content_encrypted)), 0) FROM posts_content WHERE file_id = ?"
+        )
+        .bind(file_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(chars / 4 * 3)
+    }
+
     pub async fn verify_append_key(&self, file_id: &str, append_key: &str) -> Result<bool> {
         // SECURITY: Use constant-time comparison to prevent timing attacks
         // Fetch the post record to get the stored append key
@@ -309,12 +841,74 @@ impl Database {
         Ok(())
     }
 
+    // Stats history methods
+    /// Record the current totals, dropping snapshots older than the history window
+    pub async fn record_stats_snapshot(&self) -> Result<()> {
+        let (total, posts, files, _permanent, _temporary, views, bytes) = self.get_stats().await?;
+        let (dogpastes, _dogpaste_views, _dogpaste_bytes) = self.get_dogpaste_stats().await?;
+        let now = Utc::now().timestamp();
+
+        sqlx::query(
+            "INSERT INTO stats_snapshots
+             (created_at, total_uploads, total_posts, total_files, total_dogpastes, total_views, storage_bytes)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(total)
+        .bind(posts)
+        .bind(files)
+        .bind(dogpastes)
+        .bind(views)
+        .bind(bytes)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("DELETE FROM stats_snapshots WHERE created_at < ?")
+            .bind(now - crate::constants::STATS_HISTORY_MAX_DAYS * 86400)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Reset view counts, dedup hits and download recency (ANALYTICS_ENABLED=false)
+    ///
+    /// Dogpaste views are only kept where a view limit needs them.
+    pub async fn forget_analytics(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE files SET view_count = 0, dedup_hits = 0, last_downloaded_at = NULL
+             WHERE view_count != 0 OR dedup_hits != 0 OR last_downloaded_at IS NOT NULL"
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE dogpaste SET views = 0 WHERE max_views IS NULL AND views != 0")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE stats_snapshots SET total_views = 0 WHERE total_views != 0")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Snapshots taken since `since` (Unix timestamp), oldest first
+    pub async fn list_stats_snapshots(&self, since: i64) -> Result<Vec<crate::models::StatsSnapshot>> {
+        let snapshots = sqlx::query_as::<_, crate::models::StatsSnapshot>(
+            "SELECT created_at, total_uploads, total_posts, total_files, total_dogpastes, total_views, storage_bytes
+             FROM stats_snapshots WHERE created_at >= ? ORDER BY created_at"
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(snapshots)
+    }
+
     pub async fn get_stats(&self) -> Result<(i64, i64, i64, i64, i64, i64, i64)> {
         let total_result = sqlx::query!(
             r#"
             SELECT COUNT(*) as "count!"
             FROM files
-            WHERE is_permanent = 1 OR expires_at > datetime('now')
+            WHERE is_permanent = 1 OR datetime(expires_at) > datetime('now')
             "#
         )
         .fetch_one(&self.pool)
@@ -324,7 +918,7 @@ impl Database {
             r#"
             SELECT COUNT(*) as "count!"
             FROM files
-            WHERE post_type = 'post' AND (is_permanent = 1 OR expires_at > datetime('now'))
+            WHERE post_type = 'post' AND (is_permanent = 1 OR datetime(expires_at) > datetime('now'))
             "#
         )
         .fetch_one(&self.pool)
@@ -334,7 +928,7 @@ impl Database {
             r#"
             SELECT COUNT(*) as "count!"
             FROM files
-            WHERE post_type = 'file' AND (is_permanent = 1 OR expires_at > datetime('now'))
+            WHERE post_type = 'file' AND (is_permanent = 1 OR datetime(expires_at) > datetime('now'))
             "#
         )
         .fetch_one(&self.pool)
@@ -354,7 +948,7 @@ impl Database {
             r#"
             SELECT COUNT(*) as "count!"
             FROM files
-            WHERE is_permanent = 0 AND expires_at > datetime('now')
+            WHERE is_permanent = 0 AND datetime(expires_at) > datetime('now')
             "#
         )
         .fetch_one(&self.pool)
@@ -364,7 +958,7 @@ impl Database {
             r#"
             SELECT COALESCE(SUM(view_count), 0) as "total_views!"
             FROM files
-            WHERE is_permanent = 1 OR expires_at > datetime('now')
+            WHERE is_permanent = 1 OR datetime(expires_at) > datetime('now')
             "#
         )
         .fetch_one(&self.pool)
@@ -374,7 +968,7 @@ impl Database {
             r#"
             SELECT COALESCE(SUM(size_bytes), 0) as "total_bytes!"
             FROM files
-            WHERE is_permanent = 1 OR expires_at > datetime('now')
+            WHERE is_permanent = 1 OR datetime(expires_at) > datetime('now')
             "#
         )
         .fetch_one(&self.pool)
@@ -391,19 +985,35 @@ impl Database {
         ))
     }
 
-    /// Get file extension statistics (count by extension)
-    pub async fn get_file_extension_stats(&self) -> Result<std::collections::HashMap<String, i64>> {
-        #[derive(sqlx::FromRow)]
-        struct ExtensionCount {
-            file_extension: Option<String>,
-            count: i64,
+    /// Entries and encrypted content bytes across unexpired posts
+    pub async fn get_post_stats(&self) -> Result<(i64, i64)> {
+        let (entries, chars): (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COALESCE(SUM(LENGTH(pc.content_encrypted)), 0)
+            FROM posts_content pc
+            JOIN files f ON f.id = pc.file_id
+            WHERE f.is_permanent = 1 OR datetime(f.expires_at) > datetime('now')
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        // Entries are base64, 4 characters per 3 bytes
+        Ok((entries, chars / 4 * 3))
+    }
+
+    /// Get file extension statistics (count by extension)
+    pub async fn get_file_extension_stats(&self) -> Result<std::collections::HashMap<String, i64>> {
+        #[derive(sqlx::FromRow)]
+        struct ExtensionCount {
+            file_extension: Option<String>,
+            count: i64,
         }
 
         let results = sqlx::query_as::<_, ExtensionCount>(
             r#"
             SELECT file_extension, COUNT(*) as count
             FROM files
-            WHERE (is_permanent = 1 OR expires_at > datetime('now'))
+            WHERE (is_permanent = 1 OR datetime(expires_at) > datetime('now'))
               AND post_type = 'file'
             GROUP BY file_extension
             ORDER BY count DESC
@@ -423,15 +1033,22 @@ impl Database {
     }
 
     // Dogpaste methods
-    pub async fn create_dogpaste(&self, id: &str, encrypted_data: &[u8], expires_at: i64) -> Result<()> {
+    pub async fn create_dogpaste(
+        &self,
+        id: &str,
+        encrypted_data: &[u8],
+        expires_at: i64,
+        max_views: Option<i64>,
+    ) -> Result<()> {
         let now = chrono::Utc::now().timestamp();
         sqlx::query(
-            "INSERT INTO dogpaste (id, encrypted_data, created_at, expires_at, views) VALUES (?, ?, ?, ?, 0)"
+            "INSERT INTO dogpaste (id, encrypted_data, created_at, expires_at, views, max_views) VALUES (?, ?, ?, ?, 0, ?)"
         )
         .bind(id)
         .bind(encrypted_data)
         .bind(now)
         .bind(expires_at)
+        .bind(max_views)
         .execute(&self.pool)
         .await?;
         Ok(())
@@ -439,7 +1056,7 @@ impl Database {
 
     pub async fn get_dogpaste(&self, id: &str) -> Result<Option<crate::models::DogpasteRecord>> {
         let record = sqlx::query_as::<_, crate::models::DogpasteRecord>(
-            "SELECT id, encrypted_data, created_at, expires_at, views FROM dogpaste WHERE id = ?"
+            "SELECT id, encrypted_data, created_at, expires_at, views, max_views FROM dogpaste WHERE id = ?"
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -455,31 +1072,870 @@ impl Database {
         Ok(())
     }
 
-    pub async fn increment_dogpaste_views(&self, id: &str) -> Result<()> {
-        sqlx::query("UPDATE dogpaste SET views = views + 1 WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    /// Count a view, deleting the paste if it just reached its view limit
+    ///
+    /// Runs in a transaction so concurrent viewers can never exceed the limit.
+    /// Returns None if the paste is gone (or its last view was already taken).
+    pub async fn record_dogpaste_view(&self, id: &str) -> Result<Option<crate::models::DogpasteRecord>> {
+        let mut tx = self.pool.begin().await?;
+
+        let record = sqlx::query_as::<_, crate::models::DogpasteRecord>(
+            r#"
+            UPDATE dogpaste SET views = views + 1
+            WHERE id = ? AND (max_views IS NULL OR views < max_views)
+            RETURNING id, encrypted_data, created_at, expires_at, views, max_views
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if record.as_ref().is_some_and(|r| r.max_views == Some(r.views)) {
+            sqlx::query("DELETE FROM dogpaste WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(record)
     }
 
-    pub async fn get_dogpaste_stats(&self) -> Result<(i64, i64)> {
-        // Get count of active (non-expired) dogpastes
+    /// Count, total views and stored bytes of active (non-expired) dogpastes
+    pub async fn get_dogpaste_stats(&self) -> Result<(i64, i64, i64)> {
         let now = chrono::Utc::now().timestamp();
 
         #[derive(sqlx::FromRow)]
         struct DogpasteStats {
             count: i64,
             total_views: i64,
+            total_bytes: i64,
         }
 
         let stats = sqlx::query_as::<_, DogpasteStats>(
-            "SELECT COUNT(*) as count, COALESCE(SUM(views), 0) as total_views FROM dogpaste WHERE expires_at > ?"
+            r#"
+            SELECT COUNT(*) as count, COALESCE(SUM(views), 0) as total_views,
+                   COALESCE(SUM(LENGTH(encrypted_data)), 0) as total_bytes
+            FROM dogpaste WHERE expires_at > ?
+            "#
+        )
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((stats.count, stats.total_views, stats.total_bytes))
+    }
+
+    // Resumable upload session methods
+    pub async fn create_upload_session(&self, session: &crate::models::UploadSession) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO upload_sessions (
+                id, upload_length, upload_offset, filename_encrypted, mime_type,
+                file_extension, expiry_hours, is_permanent, max_downloads, created_at, expires_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&session.id)
+        .bind(session.upload_length)
+        .bind(session.upload_offset)
+        .bind(&session.filename_encrypted)
+        .bind(&session.mime_type)
+        .bind(&session.file_extension)
+        .bind(session.expiry_hours)
+        .bind(session.is_permanent)
+        .bind(session.max_downloads)
+        .bind(session.created_at)
+        .bind(session.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_upload_session(&self, id: &str) -> Result<Option<crate::models::UploadSession>> {
+        let now = chrono::Utc::now().timestamp();
+        let session = sqlx::query_as::<_, crate::models::UploadSession>(
+            r#"
+            SELECT id, upload_length, upload_offset, filename_encrypted, mime_type,
+                   file_extension, expiry_hours, is_permanent, max_downloads, created_at, expires_at,
+                   file_id, deletion_token
+            FROM upload_sessions
+            WHERE id = ? AND expires_at > ?
+            "#
+        )
+        .bind(id)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    pub async fn update_upload_offset(&self, id: &str, upload_offset: i64) -> Result<()> {
+        sqlx::query("UPDATE upload_sessions SET upload_offset = ? WHERE id = ?")
+            .bind(upload_offset)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn complete_upload_session(&self, id: &str, file_id: &str, deletion_token: &str) -> Result<()> {
+        sqlx::query("UPDATE upload_sessions SET file_id = ?, deletion_token = ? WHERE id = ?")
+            .bind(file_id)
+            .bind(deletion_token)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete expired upload sessions, returning their IDs so partial blobs can be removed
+    pub async fn cleanup_expired_upload_sessions(&self) -> Result<Vec<String>> {
+        let now = chrono::Utc::now().timestamp();
+        let ids: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM upload_sessions WHERE expires_at <= ? RETURNING id"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ids)
+    }
+
+    // Admin methods
+    /// A page of file rows matching the filters (unset filters match everything), with the total
+    pub async fn list_files(
+        &self,
+        post_type: Option<&str>,
+        min_size: Option<i64>,
+        expired: Option<bool>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<crate::models::AdminFileSummary>, i64)> {
+        const FILTER: &str = r#"
+            WHERE (?1 IS NULL OR post_type = ?1)
+              AND (?2 IS NULL OR size_bytes >= ?2)
+              AND (?3 IS NULL OR (is_permanent = 0 AND datetime(expires_at) <= datetime('now')) = ?3)
+        "#;
+
+        let files = sqlx::query_as::<_, crate::models::AdminFileSummary>(&format!(
+            r#"
+            SELECT id, post_type, size_bytes, mime_type, file_extension, is_permanent,
+                   view_count, uploaded_at, expires_at, blake3_hash, quarantined_at
+            FROM files
+            {}
+            ORDER BY uploaded_at DESC
+            LIMIT ?4 OFFSET ?5
+            "#,
+            FILTER
+        ))
+        .bind(post_type)
+        .bind(min_size)
+        .bind(expired)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM files {}", FILTER))
+            .bind(post_type)
+            .bind(min_size)
+            .bind(expired)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((files, total))
+    }
+
+    /// Delete a file row regardless of token or expiry, returning it if it existed
+    pub async fn force_delete_file(&self, id: &str) -> Result<Option<DeletedFile>> {
+        let deleted = sqlx::query_as::<_, DeletedFile>(
+            "DELETE FROM files WHERE id = ? RETURNING id, storage_path, post_type, size_bytes, downloads_remaining"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(deleted)
+    }
+
+    pub async fn get_storage_totals(&self) -> Result<crate::models::AdminStorageResponse> {
+        let now = chrono::Utc::now().timestamp();
+        let totals = sqlx::query_as::<_, crate::models::AdminStorageResponse>(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM files WHERE post_type = 'file') as file_count,
+                (SELECT COALESCE(SUM(size_bytes), 0) FROM files WHERE post_type = 'file') as file_bytes,
+                (SELECT COUNT(*) FROM files WHERE post_type = 'post') as post_count,
+                (SELECT COALESCE(SUM(LENGTH(content_encrypted)), 0) FROM posts_content) as post_bytes,
+                (SELECT COUNT(*) FROM files WHERE is_permanent = 0 AND datetime(expires_at) <= datetime('now')) as expired_count,
+                (SELECT COALESCE(SUM(size_bytes), 0) FROM files WHERE is_permanent = 0 AND datetime(expires_at) <= datetime('now')) as expired_bytes,
+                (SELECT COUNT(*) FROM dogpaste WHERE expires_at > ?) as dogpaste_count,
+                (SELECT COALESCE(SUM(LENGTH(encrypted_data)), 0) FROM dogpaste WHERE expires_at > ?) as dogpaste_bytes
+            "#
+        )
+        .bind(now)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(totals)
+    }
+
+    /// Largest (`order_by = "size_bytes"`) or most viewed (`"view_count"`) rows
+    async fn top_files(&self, order_by: &str, limit: i64) -> Result<Vec<crate::models::AdminFileSummary>> {
+        let files = sqlx::query_as::<_, crate::models::AdminFileSummary>(&format!(
+            r#"
+            SELECT id, post_type, size_bytes, mime_type, file_extension, is_permanent,
+                   view_count, uploaded_at, expires_at, blake3_hash, quarantined_at
+            FROM files
+            ORDER BY {} DESC, uploaded_at DESC
+            LIMIT ?
+            "#,
+            order_by
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(files)
+    }
+
+    /// Capacity planning figures for the admin API (see `AdminStatsResponse`)
+    pub async fn get_admin_stats(&self, top: i64, days: i64) -> Result<crate::models::AdminStatsResponse> {
+        let now = chrono::Utc::now().timestamp();
+
+        let uploads_per_day = sqlx::query_as::<_, crate::models::DailyUploads>(
+            r#"
+            SELECT date(uploaded_at) as day, COUNT(*) as uploads, COALESCE(SUM(size_bytes), 0) as bytes
+            FROM files
+            WHERE datetime(uploaded_at) >= datetime('now', ?)
+            GROUP BY day
+            ORDER BY day
+            "#
+        )
+        .bind(format!("-{} days", days))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let storage_by_post_type = sqlx::query_as::<_, crate::models::PostTypeStorage>(
+            r#"
+            SELECT 'file' as post_type, COUNT(*) as count, COALESCE(SUM(size_bytes), 0) as bytes
+            FROM files WHERE post_type = 'file'
+            UNION ALL
+            SELECT 'post',
+                   (SELECT COUNT(*) FROM files WHERE post_type = 'post'),
+                   (SELECT COALESCE(SUM(LENGTH(content_encrypted)), 0) FROM posts_content)
+            UNION ALL
+            SELECT 'dogpaste', COUNT(*), COALESCE(SUM(LENGTH(encrypted_data)), 0)
+            FROM dogpaste WHERE expires_at > ?
+            "#
         )
         .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let dedup = sqlx::query_as::<_, crate::models::DedupSavings>(
+            r#"
+            SELECT COALESCE(SUM(dedup_hits), 0) as deduplicated_uploads,
+                   COALESCE(SUM(dedup_hits * size_bytes), 0) as bytes_saved
+            FROM files
+            "#
+        )
         .fetch_one(&self.pool)
         .await?;
 
-        Ok((stats.count, stats.total_views))
+        Ok(crate::models::AdminStatsResponse {
+            top_by_size: self.top_files("size_bytes", top).await?,
+            top_by_views: Some(self.top_files("view_count", top).await?),
+            uploads_per_day,
+            storage_by_post_type,
+            dedup: Some(dedup),
+        })
+    }
+
+    // Hash denylist methods
+    // Quarantine (legal hold) methods
+    pub async fn is_quarantined(&self, id: &str) -> Result<bool> {
+        let quarantined: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM files WHERE id = ? AND quarantined_at IS NOT NULL"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(quarantined.is_some())
+    }
+
+    /// Place a hold on a file (expired rows not yet cleaned up can be held too)
+    pub async fn quarantine_file(&self, id: &str, reason: Option<&str>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE files SET quarantined_at = COALESCE(quarantined_at, ?), quarantine_reason = ? WHERE id = ?"
+        )
+        .bind(chrono::Utc::now().timestamp())
+        .bind(reason)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn release_quarantine(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE files SET quarantined_at = NULL, quarantine_reason = NULL WHERE id = ? AND quarantined_at IS NOT NULL"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn is_hash_blocked(&self, blake3_hash: &str) -> Result<bool> {
+        let blocked: Option<i64> = sqlx::query_scalar("SELECT 1 FROM blocked_hashes WHERE blake3_hash = ?")
+            .bind(blake3_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(blocked.is_some())
+    }
+
+    pub async fn block_hash(&self, blake3_hash: &str, reason: Option<&str>) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query(
+            "INSERT INTO blocked_hashes (blake3_hash, reason, created_at) VALUES (?, ?, ?) \
+             ON CONFLICT(blake3_hash) DO UPDATE SET reason = excluded.reason"
+        )
+        .bind(blake3_hash)
+        .bind(reason)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn unblock_hash(&self, blake3_hash: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM blocked_hashes WHERE blake3_hash = ?")
+            .bind(blake3_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_blocked_hashes(&self) -> Result<Vec<crate::models::BlockedHash>> {
+        let hashes = sqlx::query_as::<_, crate::models::BlockedHash>(
+            "SELECT blake3_hash, reason, created_at FROM blocked_hashes ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(hashes)
+    }
+
+    // IP ban methods
+    /// Ban a network, or update the existing ban on it
+    pub async fn ban_network(&self, ban: &crate::models::BannedNetwork) -> Result<crate::models::BannedNetwork> {
+        let ban = sqlx::query_as::<_, crate::models::BannedNetwork>(
+            "INSERT INTO banned_networks (id, network, scope, reason, expires_at, created_at) VALUES (?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(network) DO UPDATE SET scope = excluded.scope, reason = excluded.reason, expires_at = excluded.expires_at \
+             RETURNING id, network, scope, reason, expires_at, created_at"
+        )
+        .bind(&ban.id)
+        .bind(&ban.network)
+        .bind(&ban.scope)
+        .bind(&ban.reason)
+        .bind(ban.expires_at)
+        .bind(ban.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(ban)
+    }
+
+    pub async fn unban_network(&self, id: &str) -> Result<Option<String>> {
+        let network: Option<String> = sqlx::query_scalar("DELETE FROM banned_networks WHERE id = ? RETURNING network")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(network)
+    }
+
+    /// Bans, newest first (lapsed ones not yet cleaned up only if `include_expired`)
+    pub async fn list_bans(&self, include_expired: bool) -> Result<Vec<crate::models::BannedNetwork>> {
+        let bans = sqlx::query_as::<_, crate::models::BannedNetwork>(
+            "SELECT id, network, scope, reason, expires_at, created_at FROM banned_networks
+             WHERE ?1 OR expires_at IS NULL OR expires_at > ?2
+             ORDER BY created_at DESC"
+        )
+        .bind(include_expired)
+        .bind(chrono::Utc::now().timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(bans)
+    }
+
+    /// Delete lapsed bans, returning how many there were
+    pub async fn delete_expired_bans(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM banned_networks WHERE expires_at <= ?")
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    // Download limit methods
+    /// Use up one download of a limited file
+    ///
+    /// Single atomic UPDATE, so concurrent downloads can never exceed the limit.
+    /// Returns the downloads left afterwards, or None if none were left to use.
+    pub async fn consume_download(&self, id: &str) -> Result<Option<i64>> {
+        let remaining: Option<i64> = sqlx::query_scalar(
+            r#"
+            UPDATE files SET downloads_remaining = downloads_remaining - 1
+            WHERE id = ? AND downloads_remaining > 0
+            RETURNING downloads_remaining
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(remaining)
+    }
+
+
+    // Expiry methods
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %id))]
+    pub async fn verify_deletion_token(&self, id: &str, deletion_token: &str) -> Result<bool> {
+        let stored: Option<String> = sqlx::query_scalar("SELECT deletion_token FROM files WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        // Use a dummy token if file doesn't exist to prevent timing leak
+        let stored_token = stored.as_deref().unwrap_or("00000000000000000000000000000000");
+        let tokens_match = deletion_token.as_bytes().ct_eq(stored_token.as_bytes());
+
+        // Add random delay (0-10ms) to prevent timing analysis
+        let delay_ms = rand::thread_rng().gen_range(0..10);
+        tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+
+        Ok(tokens_match.into() && stored.is_some())
+    }
+
+    /// Set a new expiry time (an explicit expiry makes permanent uploads temporary)
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %id))]
+    pub async fn update_expiry(&self, id: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE files SET expires_at = ?, is_permanent = 0 WHERE id = ?")
+            .bind(expires_at)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        // A new expiry re-arms the warning
+        sqlx::query("UPDATE expiry_warnings SET notified_at = NULL WHERE file_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Expiry warning methods
+    pub async fn set_expiry_warning(&self, warning: &crate::models::ExpiryWarning) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO expiry_warnings (file_id, callback_url, hours_before, notified_at) VALUES (?, ?, ?, NULL)
+             ON CONFLICT(file_id) DO UPDATE SET callback_url = excluded.callback_url,
+                 hours_before = excluded.hours_before, notified_at = NULL"
+        )
+        .bind(&warning.file_id)
+        .bind(&warning.callback_url)
+        .bind(warning.hours_before)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_expiry_warning(&self, file_id: &str) -> Result<Option<crate::models::ExpiryWarning>> {
+        let warning = sqlx::query_as::<_, crate::models::ExpiryWarning>(
+            "SELECT file_id, callback_url, hours_before, notified_at FROM expiry_warnings WHERE file_id = ?"
+        )
+        .bind(file_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(warning)
+    }
+
+    pub async fn delete_expiry_warning(&self, file_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM expiry_warnings WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Callback warnings whose window has opened and that haven't been sent yet
+    pub async fn due_expiry_warnings(&self) -> Result<Vec<crate::models::DueExpiryWarning>> {
+        let due = sqlx::query_as::<_, crate::models::DueExpiryWarning>(
+            r#"
+            SELECT w.file_id, w.callback_url, f.post_type, f.size_bytes,
+                   CAST(strftime('%s', f.expires_at) AS INTEGER) AS expires_at, f.deletion_token
+            FROM expiry_warnings w
+            JOIN files f ON f.id = w.file_id
+            WHERE w.notified_at IS NULL
+              AND w.callback_url IS NOT NULL
+              AND f.is_permanent = 0
+              AND datetime(f.expires_at) > datetime('now')
+              AND datetime(f.expires_at, '-' || w.hours_before || ' hours') <= datetime('now')
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(due)
+    }
+
+    pub async fn mark_expiry_warning_sent(&self, file_id: &str) -> Result<()> {
+        sqlx::query("UPDATE expiry_warnings SET notified_at = ? WHERE file_id = ?")
+            .bind(chrono::Utc::now().timestamp())
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Post locking methods
+    /// Clear a post's append key so it can never be appended to again
+    /// (collaborator keys are revoked along with it)
+    pub async fn lock_post(&self, id: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE files SET post_append_key = NULL WHERE id = ? AND post_type = 'post' AND post_append_key IS NOT NULL"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM post_keys WHERE post_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace a post's append key (locked posts stay locked)
+    pub async fn rotate_append_key(&self, id: &str, new_key: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE files SET post_append_key = ? WHERE id = ? AND post_type = 'post' AND post_append_key IS NOT NULL"
+        )
+        .bind(new_key)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Collaborator append key methods
+    pub async fn create_post_key(&self, key: &crate::models::PostKey, append_key: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO post_keys (id, post_id, append_key, label, created_at) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(&key.id)
+        .bind(&key.post_id)
+        .bind(append_key)
+        .bind(&key.label)
+        .bind(key.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_post_keys(&self, post_id: &str) -> Result<Vec<crate::models::PostKey>> {
+        let keys = sqlx::query_as::<_, crate::models::PostKey>(
+            "SELECT id, post_id, label, created_at FROM post_keys WHERE post_id = ? ORDER BY created_at"
+        )
+        .bind(post_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(keys)
+    }
+
+    pub async fn count_post_keys(&self, post_id: &str) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM post_keys WHERE post_id = ?")
+            .bind(post_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    pub async fn revoke_post_key(&self, post_id: &str, key_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM post_keys WHERE post_id = ? AND id = ?")
+            .bind(post_id)
+            .bind(key_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Check a collaborator append key
+    pub async fn verify_collaborator_key(&self, post_id: &str, append_key: &str) -> Result<bool> {
+        // SECURITY: Compare every key in constant time rather than looking the key up
+        let keys: Vec<String> = sqlx::query_scalar("SELECT append_key FROM post_keys WHERE post_id = ?")
+            .bind(post_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let matched = keys
+            .iter()
+            .fold(subtle::Choice::from(0), |acc, key| acc | append_key.as_bytes().ct_eq(key.as_bytes()));
+
+        Ok(matched.into())
+    }
+
+    // Collection methods
+    pub async fn create_collection(&self, collection: &crate::models::CollectionRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO collections (id, collection_key, title_encrypted, created_at) VALUES (?, ?, ?, ?)"
+        )
+        .bind(&collection.id)
+        .bind(&collection.collection_key)
+        .bind(&collection.title_encrypted)
+        .bind(collection.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_collection(&self, id: &str) -> Result<Option<crate::models::CollectionRecord>> {
+        let collection = sqlx::query_as::<_, crate::models::CollectionRecord>(
+            "SELECT id, collection_key, title_encrypted, created_at FROM collections WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(collection)
+    }
+
+    pub async fn count_collection_files(&self, collection_id: &str) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM collection_files WHERE collection_id = ?")
+            .bind(collection_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// Append files to a collection in order (files already in it are skipped)
+    pub async fn add_collection_files(&self, collection_id: &str, file_ids: &[String]) -> Result<u64> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+
+        let mut position: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM collection_files WHERE collection_id = ?"
+        )
+        .bind(collection_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut added = 0;
+        for file_id in file_ids {
+            let result = sqlx::query(
+                "INSERT OR IGNORE INTO collection_files (collection_id, file_id, position, added_at) VALUES (?, ?, ?, ?)"
+            )
+            .bind(collection_id)
+            .bind(file_id)
+            .bind(position)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            if result.rows_affected() > 0 {
+                added += 1;
+                position += 1;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(added)
+    }
+
+    pub async fn remove_collection_file(&self, collection_id: &str, file_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM collection_files WHERE collection_id = ? AND file_id = ?")
+            .bind(collection_id)
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Metadata of a collection's live members, in collection order
+    pub async fn list_collection_files(&self, collection_id: &str) -> Result<Vec<crate::models::CollectionFile>> {
+        let files = sqlx::query_as::<_, crate::models::CollectionFile>(
+            r#"
+            SELECT f.id as file_id, f.post_type, f.size_bytes, f.mime_type, f.file_extension,
+                   f.is_permanent, f.expires_at, f.view_count, cf.added_at
+            FROM collection_files cf
+            JOIN files f ON f.id = cf.file_id
+            WHERE cf.collection_id = ?
+              AND (f.is_permanent = 1 OR datetime(f.expires_at) > datetime('now'))
+              AND f.blake3_hash NOT IN (SELECT blake3_hash FROM blocked_hashes)
+              AND f.quarantined_at IS NULL
+            ORDER BY cf.position
+            "#
+        )
+        .bind(collection_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(files)
+    }
+
+    // WebDAV namespace methods
+    /// Live files in an API key's namespace whose path starts with `prefix`, in path order
+    pub async fn list_dav_entries(&self, api_key_id: &str, prefix: &str) -> Result<Vec<crate::models::DavEntry>> {
+        let entries = sqlx::query_as::<_, crate::models::DavEntry>(
+            r#"
+            SELECT d.path, d.file_id, f.size_bytes, f.mime_type, f.blake3_hash, d.created_at
+            FROM dav_entries d
+            JOIN files f ON f.id = d.file_id
+            WHERE d.api_key_id = ?
+              AND substr(d.path, 1, length(?)) = ?
+              AND (f.is_permanent = 1 OR datetime(f.expires_at) > datetime('now'))
+              AND f.blake3_hash NOT IN (SELECT blake3_hash FROM blocked_hashes)
+              AND f.quarantined_at IS NULL
+            ORDER BY d.path
+            "#
+        )
+        .bind(api_key_id)
+        .bind(prefix)
+        .bind(prefix)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entries)
+    }
+
+    /// The live file at `path` in an API key's namespace
+    pub async fn get_dav_entry(&self, api_key_id: &str, path: &str) -> Result<Option<crate::models::DavEntry>> {
+        let entry = sqlx::query_as::<_, crate::models::DavEntry>(
+            r#"
+            SELECT d.path, d.file_id, f.size_bytes, f.mime_type, f.blake3_hash, d.created_at
+            FROM dav_entries d
+            JOIN files f ON f.id = d.file_id
+            WHERE d.api_key_id = ? AND d.path = ?
+              AND (f.is_permanent = 1 OR datetime(f.expires_at) > datetime('now'))
+              AND f.blake3_hash NOT IN (SELECT blake3_hash FROM blocked_hashes)
+              AND f.quarantined_at IS NULL
+            "#
+        )
+        .bind(api_key_id)
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(entry)
+    }
+
+    /// Point `path` at `file_id`; returns the file it pointed at before, if any
+    pub async fn put_dav_entry(&self, api_key_id: &str, path: &str, file_id: &str) -> Result<Option<String>> {
+        let mut tx = self.pool.begin().await?;
+
+        let previous: Option<String> =
+            sqlx::query_scalar("SELECT file_id FROM dav_entries WHERE api_key_id = ? AND path = ?")
+                .bind(api_key_id)
+                .bind(path)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO dav_entries (api_key_id, path, file_id, created_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT (api_key_id, path) DO UPDATE SET file_id = excluded.file_id, created_at = excluded.created_at
+            "#
+        )
+        .bind(api_key_id)
+        .bind(path)
+        .bind(file_id)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(previous)
+    }
+
+    /// Remove `path` from an API key's namespace; returns the file it pointed at
+    pub async fn remove_dav_entry(&self, api_key_id: &str, path: &str) -> Result<Option<String>> {
+        let file_id: Option<String> =
+            sqlx::query_scalar("DELETE FROM dav_entries WHERE api_key_id = ? AND path = ? RETURNING file_id")
+                .bind(api_key_id)
+                .bind(path)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(file_id)
+    }
+
+    /// Whether any WebDAV path (of any key) still points at a file
+    pub async fn dav_entry_exists(&self, file_id: &str) -> Result<bool> {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM dav_entries WHERE file_id = ? LIMIT 1")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(exists.is_some())
+    }
+
+    /// Whether a file was created through the WebDAV/S3 namespace
+    pub async fn is_dav_file(&self, file_id: &str) -> Result<bool> {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM dav_files WHERE file_id = ?")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(exists.is_some())
+    }
+
+    // File info methods
+    /// Metadata for the live files among `ids` (unknown, expired, blocked and quarantined IDs are omitted)
+    pub async fn get_files_info(&self, ids: &[String]) -> Result<Vec<crate::models::FileInfo>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            r#"
+            SELECT id as file_id, post_type, size_bytes, mime_type, file_extension, is_permanent,
+                   CASE WHEN is_permanent = 1 THEN NULL ELSE expires_at END as expires_at,
+                   view_count, blake3_hash, downloads_remaining
+            FROM files
+            WHERE (is_permanent = 1 OR datetime(expires_at) > datetime('now'))
+              AND blake3_hash NOT IN (SELECT blake3_hash FROM blocked_hashes)
+              AND quarantined_at IS NULL
+              AND id IN (
+            "#
+        );
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+
+        let files = query
+            .build_query_as::<crate::models::FileInfo>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(files)
+    }
+
+    // Slug methods
+    /// Whether a post already uses `slug`
+    pub async fn slug_exists(&self, slug: &str) -> Result<bool> {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM files WHERE slug = ?")
+            .bind(slug)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(exists.is_some())
+    }
+
+    /// Resolve a post ID or custom slug to the post's ID (IDs take precedence)
+    #[tracing::instrument(level = "debug", skip_all, fields(id_or_slug = %id_or_slug))]
+    pub async fn resolve_id(&self, id_or_slug: &str) -> Result<Option<String>> {
+        let id = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT id FROM files
+            WHERE id = ?1 OR slug = ?1
+            ORDER BY id = ?1 DESC
+            LIMIT 1
+            "#
+        )
+        .bind(id_or_slug)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(id)
     }
 }
@@ -28,15 +28,53 @@ pub enum AppError {
     #[error("Invalid request: {0}")]
     BadRequest(String),
 
+    /// 400 naming the offending request field, so clients can point at it
+    #[error("Invalid {field}: {message}")]
+    InvalidField { field: &'static str, message: String },
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Gone: {0}")]
+    Gone(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// 428: the request needs a solved proof-of-work challenge
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    #[error("Unavailable for legal reasons: {0}")]
+    UnavailableForLegalReasons(String),
+
+    #[error("Request timed out")]
+    RequestTimeout,
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    #[error("Insufficient storage: {0}")]
+    InsufficientStorage(String),
+
     #[error("Internal server error")]
     Internal(#[from] anyhow::Error),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
+impl AppError {
+    /// Status, client-facing message and offending field (internal details are only logged)
+    pub fn into_parts(self) -> (StatusCode, String, Option<&'static str>) {
+        let mut invalid_field = None;
         let (status, error_message) = match self {
             AppError::Database(e) => {
                 tracing::error!("Database error: {}", e);
+                crate::telemetry::report_error(&e);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
             }
             AppError::Io(e) => {
@@ -52,17 +90,44 @@ impl IntoResponse for AppError {
             }
             AppError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::InvalidField { field, message } => {
+                invalid_field = Some(field);
+                (StatusCode::BAD_REQUEST, message)
+            }
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::Gone(msg) => (StatusCode::GONE, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::PreconditionRequired(msg) => (StatusCode::PRECONDITION_REQUIRED, msg),
+            AppError::UnsupportedMediaType(msg) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg),
+            AppError::UnavailableForLegalReasons(msg) => (StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, msg),
+            AppError::RequestTimeout => (StatusCode::REQUEST_TIMEOUT, "Request timed out".to_string()),
+            AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            AppError::InsufficientStorage(msg) => (StatusCode::INSUFFICIENT_STORAGE, msg),
             AppError::Internal(e) => {
                 tracing::error!("Internal error: {}", e);
+                crate::telemetry::report_error(e.as_ref());
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
             }
         };
+        (status, error_message, invalid_field)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_message, invalid_field) = self.into_parts();
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+        let mut body = json!({ "error": error_message });
+        if let Some(field) = invalid_field {
+            body["field"] = json!(field);
+        }
+        // Quote the request ID so users can reference it when reporting a failure
+        if let Some(request) = crate::middleware::current_request() {
+            body["request_id"] = json!(request.id);
+        }
 
-        (status, body).into_response()
+        (status, Json(body)).into_response()
     }
 }
 
@@ -0,0 +1,70 @@
+//! In-process fan-out of post updates to live viewers
+//!
+//! Each post with at least one live viewer gets a broadcast channel; channels are
+//! dropped again once their last viewer disconnects. Events carry the appended
+//! entry exactly as stored (still encrypted), never decrypted content.
+
+use crate::models::PostContentView;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Events buffered per post before slow viewers start missing updates
+const POST_EVENT_BUFFER: usize = 64;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostEvent {
+    /// New content was appended (SSE viewers fetch the post to get it)
+    Appended {
+        order: i64,
+        content_type: String,
+        appended_at: DateTime<Utc>,
+        /// The encrypted entry itself (only pushed over WebSocket)
+        #[serde(skip)]
+        entry: Arc<PostContentView>,
+    },
+    /// The post became read-only; no further events will follow
+    Locked,
+}
+
+impl PostEvent {
+    /// Event name used for the SSE `event:` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEvent::Appended { .. } => "appended",
+            PostEvent::Locked => "locked",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PostEvents {
+    channels: Mutex<HashMap<String, broadcast::Sender<PostEvent>>>,
+}
+
+impl PostEvents {
+    /// Receive events for a post from now on
+    pub fn subscribe(&self, post_id: &str) -> broadcast::Receiver<PostEvent> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Forget posts whose viewers have all gone away
+        channels.retain(|_, tx| tx.receiver_count() > 0);
+
+        channels
+            .entry(post_id.to_string())
+            .or_insert_with(|| broadcast::channel(POST_EVENT_BUFFER).0)
+            .subscribe()
+    }
+
+    /// Notify a post's live viewers (no-op if nobody is watching)
+    pub fn publish(&self, post_id: &str, event: PostEvent) {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(tx) = channels.get(post_id) {
+            let _ = tx.send(event);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Upload country policy backed by a MaxMind GeoIP2 / GeoLite2 database
+//!
+//! Only uploads are checked; downloads work from anywhere. Addresses the database
+//! doesn't know (private ranges, new allocations) pass a denylist but not an allowlist.
+
+use crate::config::GeoIpConfig;
+use maxminddb::{geoip2, Reader};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+pub struct GeoIp {
+    reader: Reader<Vec<u8>>,
+    blocked: HashSet<String>,
+    allowed: HashSet<String>,
+}
+
+impl GeoIp {
+    pub fn open(config: &GeoIpConfig) -> anyhow::Result<Self> {
+        let reader = Reader::open_readfile(&config.database)
+            .map_err(|e| anyhow::anyhow!("Failed to open GeoIP database {}: {}", config.database, e))?;
+        tracing::info!(
+            "🌍 GeoIP upload policy ({}): {} blocked, {} allowed countries",
+            reader.metadata.database_type,
+            config.blocked_countries.len(),
+            config.allowed_countries.len()
+        );
+        Ok(Self {
+            reader,
+            blocked: config.blocked_countries.iter().cloned().collect(),
+            allowed: config.allowed_countries.iter().cloned().collect(),
+        })
+    }
+
+    /// ISO country code of `ip`, if the database has one
+    pub fn country(&self, ip: IpAddr) -> Option<String> {
+        let record: geoip2::Country = self.reader.lookup(ip.to_canonical()).ok()?;
+        record.country?.iso_code.map(str::to_string)
+    }
+
+    pub fn allows_uploads(&self, ip: IpAddr) -> bool {
+        match self.country(ip) {
+            Some(country) if self.blocked.contains(&country) => false,
+            Some(country) => self.allowed.is_empty() || self.allowed.contains(&country),
+            None => self.allowed.is_empty(),
+        }
+    }
+}
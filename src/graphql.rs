@@ -0,0 +1,194 @@
+//! Optional GraphQL API (`POST /api/graphql`, GRAPHQL_ENABLED)
+//!
+//! Queries cover file metadata, posts and stats; mutations cover what can be
+//! done to an existing upload (appending, locking, deleting). Uploads themselves
+//! stay on the REST endpoints. Appending is subject to the same upload policies
+//! (bans, private mode, captcha, Tor, GeoIP) as `POST /api/posts/:id/append`.
+//! Resolvers go through the same services as the REST handlers, so they enforce
+//! the same checks and return the same messages.
+
+use crate::constants::{GRAPHQL_MAX_COMPLEXITY, GRAPHQL_MAX_DEPTH, MAX_BULK_INFO_IDS};
+use crate::error::AppError;
+use crate::middleware::{TrustedClient, Uploader};
+use crate::models::{AppendRequest, FileInfo, PostViewResponse, StatsResponse};
+use crate::state::AppState;
+use async_graphql::{Context, EmptySubscription, ErrorExtensions, Object, Schema};
+use axum::{
+    extract::{ConnectInfo, State},
+    Extension,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Json,
+};
+use once_cell::sync::Lazy;
+use std::net::{IpAddr, SocketAddr};
+
+pub type DogboxSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Built once; the app state is passed along with each request
+static SCHEMA: Lazy<DogboxSchema> = Lazy::new(|| {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(GRAPHQL_MAX_DEPTH)
+        .limit_complexity(GRAPHQL_MAX_COMPLEXITY)
+        .finish()
+});
+
+/// Client address of the request, for counting unique post views
+struct Viewer(Option<IpAddr>);
+
+/// Headers of the request and whether it carried a trusted API key, for the upload policies
+struct Caller {
+    headers: HeaderMap,
+    trusted: bool,
+}
+
+/// Execute a GraphQL request (404 unless GRAPHQL_ENABLED)
+pub async fn handler(
+    State(state): State<AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    trusted: Option<Extension<TrustedClient>>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Response {
+    let config = state.config.load_full();
+    if !config.graphql_enabled {
+        return AppError::NotFound.into_response();
+    }
+    let viewer = peer.map(|ConnectInfo(addr)| crate::client_ip::resolve(addr.ip(), &headers, &config.trusted_proxies));
+
+    let caller = Caller {
+        headers,
+        trusted: trusted.is_some(),
+    };
+
+    let response = SCHEMA.execute(request.data(state).data(Viewer(viewer)).data(caller)).await;
+    Json(response).into_response()
+}
+
+/// The schema in SDL, for `dogbox graphql-schema` and client code generators
+pub fn sdl() -> String {
+    SCHEMA.sdl()
+}
+
+/// Same message as the REST API, with the HTTP status (and field) as extensions
+fn error(e: AppError) -> async_graphql::Error {
+    let (status, message, field) = e.into_parts();
+    async_graphql::Error::new(message).extend_with(|_, extensions| {
+        extensions.set("status", status.as_u16());
+        if let Some(field) = field {
+            extensions.set("field", field);
+        }
+    })
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Metadata of a stored file or post
+    async fn file(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<FileInfo> {
+        let state = ctx.data::<AppState>()?;
+        let file = state.file_service.file_metadata(&id).await.map_err(error)?;
+        let mut info = FileInfo::from(file);
+        if !state.config.load().analytics_enabled {
+            info.view_count = None;
+        }
+        Ok(info)
+    }
+
+    /// Metadata of up to 100 files at once; unknown or expired IDs are left out
+    async fn files(&self, ctx: &Context<'_>, ids: Vec<String>) -> async_graphql::Result<Vec<FileInfo>> {
+        let state = ctx.data::<AppState>()?;
+        if ids.len() > MAX_BULK_INFO_IDS {
+            return Err(error(AppError::BadRequest(format!(
+                "At most {} file IDs can be looked up at once",
+                MAX_BULK_INFO_IDS
+            ))));
+        }
+        let mut files = state.db.get_files_info(&ids).await.map_err(error)?;
+        if !state.config.load().analytics_enabled {
+            files.iter_mut().for_each(|info| info.view_count = None);
+        }
+        Ok(files)
+    }
+
+    /// A post with all of its (encrypted) entries; counts as a view
+    async fn post(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<PostViewResponse> {
+        let state = ctx.data::<AppState>()?;
+        let Viewer(viewer) = ctx.data::<Viewer>()?;
+        let id = state.file_service.resolve_post_id(&id).await.map_err(error)?;
+        state.file_service.view_post(&id, *viewer).await.map_err(error)
+    }
+
+    /// Public statistics (up to STATS_CACHE_SECS old, like `/api/stats`)
+    async fn stats(&self, ctx: &Context<'_>) -> async_graphql::Result<StatsResponse> {
+        let state = ctx.data::<AppState>()?;
+        let (max_age, analytics) = {
+            let config = state.config.load();
+            (config.stats_cache, config.analytics_enabled)
+        };
+        if max_age.is_zero() {
+            return crate::handlers::gather_stats(&state.db, analytics).await.map_err(error);
+        }
+        let cached = match state.stats.get(max_age) {
+            Some(cached) => cached,
+            None => state.stats.refresh(&state.db, analytics).await.map_err(error)?,
+        };
+        Ok(cached.stats.clone())
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Append an entry to a post; returns the entry's position
+    async fn append_to_post(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        input: AppendRequest,
+    ) -> async_graphql::Result<i64> {
+        let state = ctx.data::<AppState>()?;
+        let Viewer(viewer) = ctx.data::<Viewer>()?;
+        let caller = ctx.data::<Caller>()?;
+        let uploader = Uploader {
+            ip: *viewer,
+            headers: &caller.headers,
+            trusted: caller.trusted,
+            // Upload tokens are only accepted by the REST upload endpoints
+            upload_token: false,
+        };
+        crate::middleware::check_upload_policies(state, &uploader).await.map_err(error)?;
+        let id = state.file_service.resolve_post_id(&id).await.map_err(error)?;
+        let order = state
+            .file_service
+            .append_to_post(
+                &id,
+                &input.append_key,
+                input.content,
+                input.content_type,
+                input.mime_type,
+                input.file_extension,
+                input.file_size,
+            )
+            .await
+            .map_err(error)?;
+        Ok(order)
+    }
+
+    /// Make a post permanently read-only
+    async fn lock_post(&self, ctx: &Context<'_>, id: String, append_key: String) -> async_graphql::Result<bool> {
+        let state = ctx.data::<AppState>()?;
+        let id = state.file_service.resolve_post_id(&id).await.map_err(error)?;
+        state.file_service.lock_post(&id, &append_key).await.map_err(error)?;
+        Ok(true)
+    }
+
+    /// Delete a file or post with the deletion token returned by the upload
+    async fn delete_file(&self, ctx: &Context<'_>, id: String, deletion_token: String) -> async_graphql::Result<bool> {
+        let state = ctx.data::<AppState>()?;
+        state.file_service.delete_file(&id, &deletion_token).await.map_err(error)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,238 @@
+//! Optional gRPC API (GRPC_PORT), defined in proto/dogbox.proto
+//!
+//! Mirrors upload, download, append and delete for backend-to-backend
+//! integrations, with uploads and downloads streamed in chunks instead of
+//! multipart bodies. It listens on its own port, outside the HTTP middleware
+//! stack (rate limiting, bans, captcha), so every call has to present a trusted
+//! API key in the `x-api-key` metadata and gets that key's upload limits.
+//! Calls go through `FileService`, so the same checks and policies apply.
+
+use crate::error::AppError;
+use crate::models::PostType;
+use crate::state::AppState;
+use axum::http::StatusCode;
+use futures_util::{Stream, StreamExt};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tonic::{metadata::MetadataMap, Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("dogbox.v1");
+}
+
+use proto::dogbox_server::{Dogbox, DogboxServer};
+use proto::{download_response, upload_request};
+
+pub struct GrpcService {
+    state: AppState,
+}
+
+/// Serve the gRPC API on `port` until the process exits
+pub async fn serve(state: AppState, port: u16) -> anyhow::Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    tracing::info!("📡 gRPC API listening on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(DogboxServer::new(GrpcService { state }))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+/// The REST status of an error as the closest gRPC code, with the same message
+fn status(e: AppError) -> Status {
+    let (status, message, _) = e.into_parts();
+    let code = match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNSUPPORTED_MEDIA_TYPE => tonic::Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+        StatusCode::FORBIDDEN | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => tonic::Code::PermissionDenied,
+        StatusCode::NOT_FOUND | StatusCode::GONE => tonic::Code::NotFound,
+        StatusCode::CONFLICT => tonic::Code::AlreadyExists,
+        StatusCode::PAYLOAD_TOO_LARGE | StatusCode::INSUFFICIENT_STORAGE => tonic::Code::ResourceExhausted,
+        StatusCode::PRECONDITION_REQUIRED => tonic::Code::FailedPrecondition,
+        StatusCode::REQUEST_TIMEOUT => tonic::Code::DeadlineExceeded,
+        StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+        _ => tonic::Code::Internal,
+    };
+    Status::new(code, message)
+}
+
+impl From<proto::PostType> for PostType {
+    fn from(post_type: proto::PostType) -> Self {
+        match post_type {
+            proto::PostType::File => PostType::File,
+            proto::PostType::Post => PostType::Post,
+        }
+    }
+}
+
+impl From<PostType> for proto::PostType {
+    fn from(post_type: PostType) -> Self {
+        match post_type {
+            PostType::File => proto::PostType::File,
+            PostType::Post => proto::PostType::Post,
+        }
+    }
+}
+
+impl GrpcService {
+    /// Require a trusted API key, like `X-Api-Key` on the HTTP API
+    async fn authenticate(&self, metadata: &MetadataMap) -> Result<(), Status> {
+        let Some(presented) = metadata.get("x-api-key") else {
+            return Err(Status::unauthenticated("API key required"));
+        };
+        let key_hash = blake3::hash(presented.as_bytes()).to_hex().to_string();
+        match self.state.db.find_api_key(&key_hash).await.map_err(status)? {
+            Some(key_id) => {
+                tracing::debug!("gRPC call authenticated with API key {}", key_id);
+                self.state.db.record_api_key_use(&key_id).await.map_err(status)
+            }
+            None => {
+                tracing::warn!("Rejected gRPC call with invalid API key");
+                Err(Status::unauthenticated("Invalid API key"))
+            }
+        }
+    }
+}
+
+type DownloadStream = Pin<Box<dyn Stream<Item = Result<proto::DownloadResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Dogbox for GrpcService {
+    async fn upload(
+        &self,
+        request: Request<Streaming<proto::UploadRequest>>,
+    ) -> Result<Response<proto::UploadResponse>, Status> {
+        self.authenticate(request.metadata()).await?;
+        let mut messages = request.into_inner();
+
+        let Some(upload_request::Payload::Metadata(metadata)) = messages.message().await?.and_then(|m| m.payload)
+        else {
+            return Err(Status::invalid_argument("The first message must be the upload metadata"));
+        };
+        let limits = self.state.file_service.upload_limits(true);
+        if let Some(size) = metadata.size_bytes.filter(|size| *size > limits.max_upload_bytes) {
+            return Err(status(AppError::PayloadTooLarge(format!(
+                "Upload of {} bytes exceeds maximum upload size of {} bytes",
+                size, limits.max_upload_bytes
+            ))));
+        }
+
+        // Staged on disk as chunks arrive (never buffered in memory)
+        let mut writer = self
+            .state
+            .file_service
+            .begin_upload(metadata.size_bytes.unwrap_or(0), limits)
+            .await
+            .map_err(status)?;
+        while let Some(message) = messages.message().await? {
+            match message.payload {
+                Some(upload_request::Payload::Chunk(chunk)) => writer.write_chunk(&chunk).await.map_err(status)?,
+                _ => return Err(Status::invalid_argument("Only data chunks may follow the upload metadata")),
+            }
+        }
+        let upload = writer.finish().await.map_err(status)?;
+
+        let post_type = PostType::from(metadata.post_type());
+        let file = self
+            .state
+            .file_service
+            .store_file(
+                upload,
+                metadata.filename_encrypted,
+                metadata.mime_type,
+                metadata.expiry_hours,
+                post_type,
+                metadata.is_permanent,
+                metadata.file_extension,
+                metadata.max_downloads,
+                metadata.slug,
+                limits,
+            )
+            .await
+            .map_err(status)?;
+
+        let post_type = file.get_post_type();
+        let url = match post_type {
+            PostType::Post => format!("/p/{}", file.slug.as_deref().unwrap_or(&file.id)),
+            PostType::File => format!("/f/{}", file.id),
+        };
+        Ok(Response::new(proto::UploadResponse {
+            expires_at: (!file.is_permanent).then(|| file.expires_at.timestamp()),
+            url,
+            post_type: proto::PostType::from(post_type).into(),
+            is_permanent: file.is_permanent,
+            downloads_remaining: file.downloads_remaining,
+            file_id: file.id,
+            deletion_token: file.deletion_token,
+            post_append_key: file.post_append_key,
+        }))
+    }
+
+    type DownloadStream = DownloadStream;
+
+    // The stream's item type is fixed by tonic, large `Status` and all
+    #[allow(clippy::result_large_err)]
+    async fn download(
+        &self,
+        request: Request<proto::DownloadRequest>,
+    ) -> Result<Response<Self::DownloadStream>, Status> {
+        self.authenticate(request.metadata()).await?;
+        let file_id = request.into_inner().file_id;
+        let (file, size, blob) = self.state.file_service.retrieve_file(&file_id).await.map_err(status)?;
+
+        let metadata = proto::DownloadResponse {
+            payload: Some(download_response::Payload::Metadata(proto::FileMetadata {
+                size_bytes: size,
+                filename_encrypted: file.filename_encrypted,
+                mime_type: file.mime_type,
+                file_extension: file.file_extension,
+                blake3_hash: file.blake3_hash,
+            })),
+        };
+        let chunks = blob.map(|chunk| match chunk {
+            Ok(bytes) => Ok(proto::DownloadResponse {
+                payload: Some(download_response::Payload::Chunk(bytes.to_vec())),
+            }),
+            Err(e) => Err(status(AppError::Io(e))),
+        });
+        Ok(Response::new(Box::pin(futures_util::stream::once(async { Ok(metadata) }).chain(chunks))))
+    }
+
+    async fn append(
+        &self,
+        request: Request<proto::AppendRequest>,
+    ) -> Result<Response<proto::AppendResponse>, Status> {
+        self.authenticate(request.metadata()).await?;
+        let request = request.into_inner();
+        let post_id = self.state.file_service.resolve_post_id(&request.post_id).await.map_err(status)?;
+        let content_order = self
+            .state
+            .file_service
+            .append_to_post(
+                &post_id,
+                &request.append_key,
+                request.content,
+                request.content_type,
+                request.mime_type,
+                request.file_extension,
+                request.file_size,
+            )
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::AppendResponse { content_order }))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<proto::DeleteRequest>,
+    ) -> Result<Response<proto::DeleteResponse>, Status> {
+        self.authenticate(request.metadata()).await?;
+        let request = request.into_inner();
+        self.state
+            .file_service
+            .delete_file(&request.file_id, &request.deletion_token)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::DeleteResponse {}))
+    }
+}
@@ -1,40 +1,134 @@
-use crate::config::Config;
-use crate::database::Database;
 use crate::error::{AppError, Result};
+use crate::middleware::TrustedClient;
 use crate::models::*;
-use crate::services::FileService;
+use crate::state::AppState;
 use axum::{
-    body::Bytes,
-    extract::{Multipart, Path, Query, State},
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Multipart, Path, Query, State,
+    },
     http::{header, HeaderMap},
-    response::IntoResponse,
-    Json,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
 };
-use serde::Deserialize;
-use std::sync::Arc;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::str::FromStr;
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(health, admin_motd, upload, download, delete_file, view_post, append_to_post, stats, dogpaste_create, dogpaste_view),
+    paths(
+        health, livez, readyz, admin_motd, upload, raw_upload, download, download_head, preview, file_info, file_qr, verify_file, files_info, delete_file, update_expiry,
+        set_expiry_warning, expiry_warning_status, remove_expiry_warning, view_post, append_to_post, lock_post, post_events, post_ws, post_archive, rotate_append_key,
+        create_post_key, list_post_keys, revoke_post_key, stats, stats_history, dogpaste_create, dogpaste_view,
+        crate::collections::create, crate::collections::view, crate::collections::add_files,
+        crate::collections::remove_file,
+        crate::tus::options, crate::tus::create, crate::tus::upload_offset, crate::tus::upload_chunk,
+        crate::pow::challenge,
+        crate::admin::list_files, crate::admin::delete_file, crate::admin::quarantine_file,
+        crate::admin::release_quarantine, crate::admin::storage, crate::admin::stats,
+        crate::admin::list_blocked_hashes, crate::admin::block_hash, crate::admin::unblock_hash,
+        crate::admin::list_bans, crate::admin::ban_network, crate::admin::unban_network,
+        crate::admin::reload_config, crate::admin::run_maintenance, crate::admin::run_cleanup,
+        crate::admin::set_admin_message, crate::admin::reset_admin_message, crate::admin::list_access_keys, crate::admin::create_access_key,
+        crate::admin::revoke_access_key, crate::admin::list_api_keys, crate::admin::create_api_key,
+        crate::admin::revoke_api_key, crate::admin::list_upload_tokens, crate::admin::create_upload_token,
+        crate::admin::revoke_upload_token, crate::admin::audit_log
+    ),
     components(schemas(
         HealthResponse,
+        HealthChecks,
+        DatabaseHealth,
+        DiskHealth,
+        CleanupHealth,
+        StorageHealth,
+        ReadinessResponse,
+        ReadinessCheck,
         UploadRequest,
         UploadResponse,
         DeleteResponse,
+        FileInfoRequest,
+        FileInfo,
+        FileInfoResponse,
+        VerifyResponse,
+        UpdateExpiryRequest,
+        UpdateExpiryResponse,
+        ExpiryWarningRequest,
+        ExpiryWarningStatus,
         PostType,
         PostViewResponse,
         PostContentView,
         AppendRequest,
         AppendResponse,
+        LockPostRequest,
+        LockPostResponse,
+        crate::events::PostEvent,
+        crate::archive::ArchiveManifest,
+        crate::archive::ArchiveManifestEntry,
+        RotateAppendKeyRequest,
+        RotateAppendKeyResponse,
+        PostKey,
+        CreatePostKeyRequest,
+        CreatePostKeyResponse,
         StatsResponse,
+        UploadStats,
+        PostStats,
+        DogpasteStats,
+        DiskStats,
+        StatsSnapshot,
+        StatsHistoryResponse,
         DogpasteCreateRequest,
         DogpasteCreateResponse,
-        DogpasteViewResponse
+        DogpasteViewResponse,
+        CreateCollectionRequest,
+        CreateCollectionResponse,
+        AddCollectionFilesRequest,
+        AddCollectionFilesResponse,
+        CollectionFile,
+        CollectionResponse,
+        AdminFileSummary,
+        AdminFileListResponse,
+        QuarantineRequest,
+        AdminStorageResponse,
+        AdminStatsResponse,
+        DailyUploads,
+        PostTypeStorage,
+        DedupSavings,
+        MaintenanceReport,
+        CleanupReport,
+        OrphanReport,
+        AdminCleanupResponse,
+        PowChallenge,
+        SetAdminMessageRequest,
+        AdminMessageResponse,
+        BlockedHash,
+        BlockHashRequest,
+        BanScope,
+        BannedNetwork,
+        BanNetworkRequest,
+        AccessKey,
+        CreateAccessKeyRequest,
+        CreateAccessKeyResponse,
+        ApiKey,
+        CreateApiKeyRequest,
+        CreateApiKeyResponse,
+        UploadToken,
+        CreateUploadTokenRequest,
+        CreateUploadTokenResponse,
+        AuditEntry,
+        AuditLogResponse
     )),
+    modifiers(&AdminSecurity),
     tags(
-        (name = "dogbox.moe", description = "Privacy-focused file hosting with E2EE")
+        (name = "dogbox.moe", description = "Privacy-focused file hosting with E2EE"),
+        (name = "admin", description = "Operator endpoints (requires ADMIN_TOKEN bearer auth)")
     ),
     info(
         title = "dogbox.moe API",
@@ -58,32 +152,94 @@ use utoipa::OpenApi;
 )]
 pub struct ApiDoc;
 
+/// Registers the bearer token scheme used by the admin endpoints
+struct AdminSecurity;
+
+impl utoipa::Modify for AdminSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
 /// Health check endpoint
 #[utoipa::path(
     get,
     path = "/api/health",
     tag = "dogbox.moe",
     responses(
-        (status = 200, description = "Service is healthy", body = HealthResponse)
+        (status = 200, description = "Service status, including per-subsystem checks", body = HealthResponse)
     )
 )]
-pub async fn health(State(config): State<Arc<Config>>) -> Json<HealthResponse> {
-    let next_test_delete = if config.test_delete_period_hours.is_some() {
+pub async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
+    let checks = crate::health::subsystems(&state).await;
+
+    let next_test_delete = if state.config.load().test_delete_period_hours.is_some() {
         *crate::cleanup::NEXT_TEST_DELETE.read().await
     } else {
         None
     };
 
     Json(HealthResponse {
-        status: "ok".to_string(),
+        status: if checks.all_ok() { "ok" } else { "degraded" }.to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
-        test_mode: config.test_delete_period_hours.is_some(),
+        test_mode: state.config.load().test_delete_period_hours.is_some(),
         next_test_delete,
-        admin_message: config.admin_message.clone(),
-        max_upload_size: crate::constants::MAX_UPLOAD_SIZE,
+        admin_message: state.config.load().admin_message.clone(),
+        max_upload_size: state.config.load().max_file_bytes as usize,
+        max_post_size: state.config.load().max_post_bytes,
+        max_post_entry_size: state.config.load().max_post_entry_bytes,
+        max_dogpaste_size: state.config.load().max_dogpaste_bytes,
+        checks,
     })
 }
 
+/// Liveness probe
+///
+/// Answers as long as the process is serving requests; checks nothing else, so an
+/// orchestrator only restarts instances that are actually stuck.
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "dogbox.moe",
+    responses(
+        (status = 200, description = "Process is alive", body = String)
+    )
+)]
+pub async fn livez() -> &'static str {
+    "ok"
+}
+
+/// Readiness probe
+///
+/// 503 unless the database answers, all migrations are applied and the upload
+/// directory is writable.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "dogbox.moe",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ReadinessResponse),
+        (status = 503, description = "A check failed", body = ReadinessResponse)
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let checks = crate::health::readiness(&state).await;
+    let ready = checks.iter().all(|check| check.ok);
+    let status = if ready {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(ReadinessResponse { ready, checks })).into_response()
+}
+
 /// Get admin message of the day (MOTD)
 ///
 /// SECURITY: Message is validated at startup to only contain safe characters:
@@ -97,13 +253,31 @@ pub async fn health(State(config): State<Arc<Config>>) -> Json<HealthResponse> {
         (status = 204, description = "No admin message set")
     )
 )]
-pub async fn admin_motd(State(config): State<Arc<Config>>) -> impl IntoResponse {
-    match &config.admin_message {
+pub async fn admin_motd(State(state): State<AppState>) -> impl IntoResponse {
+    match &state.config.load().admin_message {
         Some(msg) => (axum::http::StatusCode::OK, msg.clone()).into_response(),
         None => axum::http::StatusCode::NO_CONTENT.into_response(),
     }
 }
 
+/// Limits for an upload: a redeemed upload token's caps win over the client's usual ones
+fn upload_limits(
+    state: &AppState,
+    trusted: Option<Extension<TrustedClient>>,
+    upload_token: Option<Extension<UploadToken>>,
+) -> crate::services::UploadLimits {
+    let mut limits = state.file_service.upload_limits(trusted.is_some());
+    if let Some(Extension(token)) = upload_token {
+        if let Some(max_size_bytes) = token.max_size_bytes {
+            limits.max_upload_bytes = max_size_bytes as u64;
+        }
+        if let Some(max_expiry_hours) = token.max_expiry_hours {
+            limits.max_expiry_hours = max_expiry_hours;
+        }
+    }
+    limits
+}
+
 /// Upload encrypted file blob
 ///
 /// Client should:
@@ -115,56 +289,69 @@ pub async fn admin_motd(State(config): State<Arc<Config>>) -> impl IntoResponse
     post,
     path = "/api/upload",
     tag = "dogbox.moe",
+    params(
+        ("X-Upload-Token" = Option<String>, Header, description = "Pre-authorized upload token issued by the admin")
+    ),
     request_body(content = inline(Vec<u8>), description = "Encrypted file blob", content_type = "application/octet-stream"),
     responses(
         (status = 200, description = "File uploaded successfully", body = UploadResponse),
+        (status = 401, description = "Invalid, expired or used up upload token"),
         (status = 413, description = "File too large"),
-        (status = 500, description = "Upload failed")
+        (status = 415, description = "Declared MIME type or extension not accepted"),
+        (status = 500, description = "Upload failed"),
+        (status = 507, description = "Storage quota reached or disk nearly full")
     )
 )]
 pub async fn upload(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+    upload_token: Option<Extension<UploadToken>>,
     headers: HeaderMap,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>> {
+    let limits = upload_limits(&state, trusted, upload_token);
+
     // SECURITY: Validate Content-Length before loading any data into memory
-    if let Some(content_length) = headers.get(header::CONTENT_LENGTH) {
-        if let Ok(length_str) = content_length.to_str() {
-            if let Ok(length) = length_str.parse::<usize>() {
-                if length > crate::constants::MAX_UPLOAD_SIZE {
-                    return Err(AppError::PayloadTooLarge(format!(
-                        "Content-Length {} exceeds maximum upload size of {} bytes",
-                        length,
-                        crate::constants::MAX_UPLOAD_SIZE
-                    )));
-                }
-            }
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if let Some(length) = content_length {
+        if length as u64 > limits.max_upload_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Content-Length {} exceeds maximum upload size of {} bytes",
+                length,
+                limits.max_upload_bytes
+            )));
         }
     }
 
-    let db = Database::new(&config.database_url).await?;
-    let service = FileService::new((*config).clone(), db);
-
-    let mut file_data: Option<Vec<u8>> = None;
+    let mut staged_upload: Option<crate::services::StagedUpload> = None;
     let mut filename_encrypted: Option<String> = None;
     let mut mime_type: Option<String> = None;
     let mut expiry_hours: Option<i64> = None;
     let mut post_type: Option<PostType> = None;
     let mut is_permanent: Option<bool> = None;
     let mut file_extension: Option<String> = None;
+    let mut max_downloads: Option<i64> = None;
+    let mut slug: Option<String> = None;
 
     // Parse multipart form data
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| {
         AppError::BadRequest(format!("Failed to parse multipart: {}", e))
     })? {
         let name = field.name().unwrap_or("").to_string();
 
         match name.as_str() {
             "file" => {
-                let data = field.bytes().await.map_err(|e| {
+                // Stream the encrypted blob straight to disk (never buffered in memory)
+                let mut writer = state.file_service.begin_upload(content_length.unwrap_or(0) as u64, limits).await?;
+                while let Some(chunk) = field.chunk().await.map_err(|e| {
                     AppError::BadRequest(format!("Failed to read file data: {}", e))
-                })?;
-                file_data = Some(data.to_vec());
+                })? {
+                    writer.write_chunk(&chunk).await?;
+                }
+                staged_upload = Some(writer.finish().await?);
             }
             "filename" => {
                 filename_encrypted = Some(field.text().await.map_err(|e| {
@@ -205,22 +392,35 @@ pub async fn upload(
                     AppError::BadRequest(format!("Failed to read file_extension: {}", e))
                 })?);
             }
+            "max_downloads" => {
+                let text = field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read max_downloads: {}", e))
+                })?;
+                max_downloads = Some(text.parse().map_err(|_| {
+                    AppError::BadRequest("Invalid max_downloads value".to_string())
+                })?);
+            }
+            "slug" => {
+                slug = Some(field.text().await.map_err(|e| {
+                    AppError::BadRequest(format!("Failed to read slug: {}", e))
+                })?);
+            }
             _ => {}
         }
     }
 
-    let data = file_data.ok_or_else(|| AppError::BadRequest("No file data provided".to_string()))?;
+    let upload = staged_upload.ok_or_else(|| AppError::BadRequest("No file data provided".to_string()))?;
     let final_post_type = post_type.unwrap_or(PostType::File);
     let final_is_permanent = is_permanent.unwrap_or(false);
 
     // Store encrypted file
-    let file = service
-        .store_file(data, filename_encrypted, mime_type, expiry_hours, final_post_type, final_is_permanent, file_extension)
+    let file = state.file_service
+        .store_file(upload, filename_encrypted, mime_type, expiry_hours, final_post_type, final_is_permanent, file_extension, max_downloads, slug, limits)
         .await?;
 
     let post_type = file.get_post_type();
     let url = match post_type {
-        PostType::Post => format!("/p/{}", file.id),
+        PostType::Post => format!("/p/{}", file.slug.as_deref().unwrap_or(&file.id)),
         PostType::File => format!("/f/{}", file.id),
     };
 
@@ -232,35 +432,265 @@ pub async fn upload(
         post_type,
         post_append_key: file.post_append_key.clone(),
         is_permanent: file.is_permanent,
+        downloads_remaining: file.downloads_remaining,
     }))
 }
 
+#[derive(Deserialize)]
+pub struct DownloadQuery {
+    name: Option<String>,
+    inline: Option<String>,
+}
+
+impl DownloadQuery {
+    fn inline(&self) -> bool {
+        matches!(self.inline.as_deref(), Some("1" | "true"))
+    }
+}
+
 /// Download encrypted file blob
 ///
-/// Returns the encrypted blob. Client must decrypt using key from URL fragment.
+/// Streams the encrypted blob. Client must decrypt using key from URL fragment.
+/// `name` only affects the suggested filename; it is sent to the server in the
+/// clear, so clients should only pass it when the user is fine with that.
+/// `inline=1` asks for an inline disposition, honoured only for image, audio and
+/// video types (anything else is still sent as an attachment).
 #[utoipa::path(
     get,
     path = "/api/files/{id}",
     tag = "dogbox.moe",
     params(
-        ("id" = String, Path, description = "File ID")
+        ("id" = String, Path, description = "File ID"),
+        ("name" = Option<String>, Query, description = "Filename for Content-Disposition (sent unencrypted)"),
+        ("inline" = Option<String>, Query, description = "1 to render image/audio/video types in the browser")
     ),
     responses(
         (status = 200, description = "Encrypted file blob", body = Vec<u8>, content_type = "application/octet-stream"),
-        (status = 404, description = "File not found or expired")
+        (status = 304, description = "If-None-Match matched the file's ETag"),
+        (status = 404, description = "File not found or expired"),
+        (status = 410, description = "Download limit reached or content removed"),
+        (status = 451, description = "Content quarantined by the operator")
     )
 )]
 pub async fn download(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse> {
-    let db = Database::new(&config.database_url).await?;
-    let service = FileService::new((*config).clone(), db);
+    Query(query): Query<DownloadQuery>,
+    request_headers: HeaderMap,
+) -> Result<Response> {
+    // Revalidation: answer from metadata before opening the blob or using up a download
+    if request_headers.contains_key(header::IF_NONE_MATCH) {
+        let file = state.file_service.file_metadata(&id).await?;
+        if file.get_post_type() == PostType::File && etag_matches(&request_headers, &file) {
+            return Ok(not_modified(&file));
+        }
+    }
+
+    let (file, blob_len, blob) = state.file_service.retrieve_file(&id).await?;
+
+    // Stream from storage so memory use stays constant regardless of blob size
+    Ok((download_headers(&file, blob_len, query.name.as_deref(), query.inline()), Body::from_stream(blob)).into_response())
+}
+
+/// Download headers without the blob
+///
+/// Doesn't count as a view or use up a download, so clients can check the size
+/// before committing to a large download.
+#[utoipa::path(
+    head,
+    path = "/api/files/{id}",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File ID"),
+        ("name" = Option<String>, Query, description = "Filename for Content-Disposition (sent unencrypted)"),
+        ("inline" = Option<String>, Query, description = "1 to render image/audio/video types in the browser")
+    ),
+    responses(
+        (status = 200, description = "File exists; headers describe the blob"),
+        (status = 304, description = "If-None-Match matched the file's ETag"),
+        (status = 404, description = "File not found or expired"),
+        (status = 410, description = "Download limit reached or content removed"),
+        (status = 451, description = "Content quarantined by the operator")
+    )
+)]
+pub async fn download_head(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+    request_headers: HeaderMap,
+) -> Result<Response> {
+    let file = state.file_service.file_metadata(&id).await?;
+
+    if file.get_post_type() == PostType::Post {
+        return Err(AppError::BadRequest(
+            "This is a post, not a file. Use /api/posts/{id} endpoint instead.".to_string()
+        ));
+    }
+
+    if etag_matches(&request_headers, &file) {
+        return Ok(not_modified(&file));
+    }
+
+    Ok(download_headers(&file, file.size_bytes as u64, query.name.as_deref(), query.inline()).into_response())
+}
+
+/// Stream a file blob for the in-browser media player
+///
+/// Unlike `/api/files/{id}` this honours single `Range` requests, serves media types
+/// inline and is cacheable, and it never uses up a download. Files with a download
+/// limit can't be previewed.
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}/preview",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 200, description = "Encrypted file blob", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 206, description = "Requested byte range of the blob", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 304, description = "If-None-Match matched the file's ETag"),
+        (status = 403, description = "File has a download limit"),
+        (status = 404, description = "File not found or expired"),
+        (status = 410, description = "Download limit reached or content removed"),
+        (status = 416, description = "Range outside the blob"),
+        (status = 451, description = "Content quarantined by the operator")
+    )
+)]
+pub async fn preview(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request_headers: HeaderMap,
+) -> Result<Response> {
+    let file = state.file_service.file_metadata(&id).await?;
+    if file.get_post_type() == PostType::File && etag_matches(&request_headers, &file) {
+        return Ok(not_modified(&file));
+    }
+
+    let size = file.size_bytes as u64;
+    let (status, range) = match requested_range(&request_headers, &file, size) {
+        ByteRange::Full => (axum::http::StatusCode::OK, 0..size),
+        ByteRange::Partial(range) => (axum::http::StatusCode::PARTIAL_CONTENT, range),
+        ByteRange::Unsatisfiable => {
+            let mut headers = HeaderMap::new();
+            if let Ok(header_value) = format!("bytes */{}", size).parse() {
+                headers.insert(header::CONTENT_RANGE, header_value);
+            }
+            return Ok((axum::http::StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response());
+        }
+    };
+
+    let blob = state.file_service.preview_file(&file, range.clone()).await?;
+
+    let mut headers = download_headers(&file, range.end - range.start, None, true);
+    headers.insert(header::ACCEPT_RANGES, header::HeaderValue::from_static("bytes"));
+    if status == axum::http::StatusCode::PARTIAL_CONTENT {
+        if let Ok(header_value) = format!("bytes {}-{}/{}", range.start, range.end - 1, size).parse() {
+            headers.insert(header::CONTENT_RANGE, header_value);
+        }
+    }
+    // The blob never changes, but it must not outlive the file in caches
+    let max_age = if file.is_permanent {
+        PREVIEW_MAX_AGE_SECS
+    } else {
+        (file.expires_at - chrono::Utc::now()).num_seconds().clamp(0, PREVIEW_MAX_AGE_SECS)
+    };
+    if let Ok(header_value) = format!("private, max-age={}", max_age).parse() {
+        headers.insert(header::CACHE_CONTROL, header_value);
+    }
+
+    Ok((status, headers, Body::from_stream(blob)).into_response())
+}
+
+/// Longest a browser may cache a preview, in seconds
+const PREVIEW_MAX_AGE_SECS: i64 = 86400;
+
+/// What a request's `Range` header asks for out of a blob of `size` bytes
+enum ByteRange {
+    Full,
+    Partial(std::ops::Range<u64>),
+    Unsatisfiable,
+}
+
+/// Parse a single `bytes=` range; multiple ranges, other units and a stale
+/// `If-Range` fall back to the whole blob, as RFC 9110 allows
+fn requested_range(request_headers: &HeaderMap, file: &FileRecord, size: u64) -> ByteRange {
+    let Some(spec) = request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+    else {
+        return ByteRange::Full;
+    };
+    if let Some(if_range) = request_headers.get(header::IF_RANGE) {
+        if if_range.to_str().ok() != Some(file_etag(file).as_str()) {
+            return ByteRange::Full;
+        }
+    }
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // bytes=a-b (end inclusive, clamped to the blob)
+        (Ok(start), Ok(end)) if start <= end => start..(end + 1).min(size),
+        // bytes=a-
+        (Ok(start), Err(_)) if end.is_empty() => start..size,
+        // bytes=-n: the last n bytes
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => size.saturating_sub(suffix)..size,
+        _ => return ByteRange::Full,
+    };
+
+    if range.start >= size {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(range)
+    }
+}
+
+/// Strong ETag for a file: blobs are immutable, so the content hash identifies them
+fn file_etag(file: &FileRecord) -> String {
+    format!("\"{}\"", file.blake3_hash)
+}
+
+/// Whether the request's If-None-Match covers the file's current ETag
+fn etag_matches(request_headers: &HeaderMap, file: &FileRecord) -> bool {
+    let etag = file_etag(file);
+    request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        // If-None-Match uses weak comparison
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
 
-    let (file, data) = service.retrieve_file(&id).await?;
+fn not_modified(file: &FileRecord) -> Response {
+    let mut headers = HeaderMap::new();
+    if let Ok(header_value) = file_etag(file).parse() {
+        headers.insert(header::ETAG, header_value);
+    }
+    (axum::http::StatusCode::NOT_MODIFIED, headers).into_response()
+}
 
-    // Create headers with MIME type and filename
+/// Content-Length, Content-Type, Content-Disposition, ETag and hash headers for a file download
+///
+/// `inline` is ignored unless the declared MIME type is in `INLINE_MIME_TYPES`; inline
+/// responses also get a locked-down CSP so nothing but the media itself can load.
+fn download_headers(file: &FileRecord, blob_len: u64, name: Option<&str>, inline: bool) -> HeaderMap {
     let mut headers = HeaderMap::new();
+    if let Ok(header_value) = file_etag(file).parse() {
+        headers.insert(header::ETAG, header_value);
+    }
+    // Lets clients check the ciphertext before attempting decryption
+    if let Ok(header_value) = file.blake3_hash.parse() {
+        headers.insert(header::HeaderName::from_static("x-content-blake3"), header_value);
+    }
+    headers.insert(header::CONTENT_LENGTH, blob_len.into());
     if let Some(mime_type) = &file.mime_type {
         if let Ok(header_value) = mime_type.parse() {
             headers.insert(header::CONTENT_TYPE, header_value);
@@ -268,17 +698,332 @@ pub async fn download(
     }
 
     // Set Content-Disposition with file extension for better download experience
-    let filename = if let Some(ext) = &file.file_extension {
-        format!("file{}", if ext.starts_with('.') { ext.clone() } else { format!(".{}", ext) })
-    } else {
-        "file".to_string()
+    // (records from before extensions were validated are re-checked, not echoed)
+    let filename = match crate::services::normalize_file_extension(file.file_extension.clone()) {
+        Ok(Some(ext)) => format!("file{}", ext),
+        _ => "file".to_string(),
     };
 
-    if let Ok(header_value) = format!("attachment; filename=\"{}\"", filename).parse() {
+    let inline = inline && file.mime_type.as_deref().is_some_and(is_inline_mime_type);
+    let disposition = if inline { "inline" } else { "attachment" };
+    if let Ok(header_value) = content_disposition(disposition, &filename, name).parse() {
         headers.insert(header::CONTENT_DISPOSITION, header_value);
     }
+    if inline {
+        headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            header::HeaderValue::from_static(crate::constants::INLINE_CONTENT_SECURITY_POLICY),
+        );
+    }
+
+    headers
+}
+
+/// Whether a declared MIME type (parameters ignored) may be rendered inline
+fn is_inline_mime_type(mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    crate::constants::INLINE_MIME_TYPES.iter().any(|allowed| match allowed.strip_suffix("/*") {
+        Some(class) => essence.strip_prefix(class).is_some_and(|rest| rest.starts_with('/')),
+        None => essence == *allowed,
+    })
+}
+
+/// Disposition header naming the download after the client-supplied name when usable
+///
+/// The ASCII `filename` is for clients that don't understand RFC 6266's `filename*`,
+/// which carries the full UTF-8 name percent-encoded as in RFC 5987.
+fn content_disposition(disposition: &str, fallback: &str, name: Option<&str>) -> String {
+    let Some(name) = name.map(sanitize_download_name).filter(|name| !name.is_empty()) else {
+        return format!("{}; filename=\"{}\"", disposition, fallback);
+    };
+
+    let ascii: String = name.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect();
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        // RFC 5987 attr-char
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    format!("{}; filename=\"{}\"; filename*=UTF-8''{}", disposition, ascii, encoded)
+}
+
+/// Drop anything that could escape the header value or name a path, and cap the length
+fn sanitize_download_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '"' | ';'))
+        .take(crate::constants::MAX_DOWNLOAD_NAME_CHARS)
+        .collect();
+    // Leading dots would make the download a hidden file (or "..")
+    name.trim().trim_start_matches('.').to_string()
+}
+
+/// Get metadata for a single file
+///
+/// Returns size, MIME type, extension, expiry and BLAKE3 hash without
+/// transferring the blob.
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}/info",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 200, description = "File metadata", body = FileInfo),
+        (status = 404, description = "File not found or expired"),
+        (status = 410, description = "Download limit reached or content removed"),
+        (status = 451, description = "Content quarantined by the operator")
+    )
+)]
+pub async fn file_info(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<FileInfo>> {
+    let file = state.file_service.file_metadata(&id).await?;
+    let mut info = FileInfo::from(file);
+    if !state.config.load().analytics_enabled {
+        info.view_count = None;
+    }
+    Ok(Json(info))
+}
+
+/// QR code for a file or post's share URL
+///
+/// Encodes the share page URL without the key fragment; clients that want a
+/// one-scan link should render their own code including the fragment.
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}/qr.png",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File or post ID")
+    ),
+    responses(
+        (status = 200, description = "QR code image", content_type = "image/png"),
+        (status = 404, description = "File not found or expired")
+    )
+)]
+pub async fn file_qr(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    let file = state.file_service.file_metadata(&id).await?;
+
+    let path = match file.get_post_type() {
+        PostType::Post => format!("/p/{}", file.slug.as_deref().unwrap_or(&file.id)),
+        PostType::File => format!("/f/{}", file.id),
+    };
+    let png = crate::qr::png(&absolute_url(&state, &headers, &path))?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
+/// Check a stored blob for corruption
+///
+/// Re-hashes the blob server-side and compares it with the BLAKE3 hash recorded
+/// at upload. Doesn't count as a view or use up a download.
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}/verify",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File ID")
+    ),
+    responses(
+        (status = 200, description = "Verification result", body = VerifyResponse),
+        (status = 400, description = "Posts can't be verified"),
+        (status = 404, description = "File not found or expired"),
+        (status = 410, description = "Download limit reached or content removed"),
+        (status = 451, description = "Content quarantined by the operator")
+    )
+)]
+pub async fn verify_file(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<VerifyResponse>> {
+    let (file, actual_hash, size_bytes) = state.file_service.verify_file(&id).await?;
+
+    Ok(Json(VerifyResponse {
+        file_id: file.id,
+        valid: actual_hash == file.blake3_hash,
+        expected_hash: file.blake3_hash,
+        actual_hash,
+        size_bytes,
+    }))
+}
+
+/// Look up metadata for several files at once
+///
+/// Returns size, MIME type, expiry and view count for each ID (no blob data), so
+/// clients can render file lists without one request per file.
+#[utoipa::path(
+    post,
+    path = "/api/files/info",
+    tag = "dogbox.moe",
+    request_body = FileInfoRequest,
+    responses(
+        (status = 200, description = "Metadata for the requested files", body = FileInfoResponse),
+        (status = 400, description = "Too many IDs")
+    )
+)]
+pub async fn files_info(
+    State(state): State<AppState>,
+    Json(req): Json<FileInfoRequest>,
+) -> Result<Json<FileInfoResponse>> {
+    use crate::constants::MAX_BULK_INFO_IDS;
+
+    let mut ids = req.file_ids;
+    if ids.len() > MAX_BULK_INFO_IDS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} file IDs can be looked up at once",
+            MAX_BULK_INFO_IDS
+        )));
+    }
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+
+    let analytics = state.config.load().analytics_enabled;
+    let mut found: std::collections::HashMap<String, FileInfo> = state
+        .db
+        .get_files_info(&ids)
+        .await?
+        .into_iter()
+        .map(|mut info| {
+            if !analytics {
+                info.view_count = None;
+            }
+            (info.file_id.clone(), info)
+        })
+        .collect();
+
+    let mut files = Vec::with_capacity(found.len());
+    let mut missing = Vec::new();
+    for id in ids {
+        match found.remove(&id) {
+            Some(info) => files.push(info),
+            None => missing.push(id),
+        }
+    }
+
+    Ok(Json(FileInfoResponse { files, missing }))
+}
+
+/// Minimal upload for shell users (`curl -T file https://dogbox.moe/api/raw`)
+///
+/// The request body is stored as-is and the download URL is returned as plain
+/// text; the deletion token is sent in the `X-Deletion-Token` header. The server
+/// doesn't encrypt anything, so encrypt before uploading (e.g. with `age`).
+#[utoipa::path(
+    put,
+    path = "/api/raw",
+    tag = "dogbox.moe",
+    params(
+        ("expiry_hours" = Option<i64>, Query, description = "Hours until the file expires"),
+        ("is_permanent" = Option<bool>, Query, description = "Never expire"),
+        ("max_downloads" = Option<i64>, Query, description = "Delete after this many downloads"),
+        ("file_extension" = Option<String>, Query, description = "Extension used for the download filename"),
+        ("X-Upload-Token" = Option<String>, Header, description = "Pre-authorized upload token issued by the admin")
+    ),
+    request_body(content = inline(Vec<u8>), description = "File contents", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Download URL", body = String, content_type = "text/plain"),
+        (status = 401, description = "Invalid, expired or used up upload token"),
+        (status = 413, description = "File too large"),
+        (status = 415, description = "Declared MIME type or extension not accepted"),
+        (status = 507, description = "Storage quota reached or disk nearly full")
+    )
+)]
+pub async fn raw_upload(
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+    upload_token: Option<Extension<UploadToken>>,
+    Query(query): Query<RawUploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response> {
+    let limits = upload_limits(&state, trusted, upload_token);
+
+    // SECURITY: Validate Content-Length before reading the body
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if let Some(length) = content_length {
+        if length as u64 > limits.max_upload_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Content-Length {} exceeds maximum upload size of {} bytes",
+                length,
+                limits.max_upload_bytes
+            )));
+        }
+    }
+
+    let mut writer = state.file_service.begin_upload(content_length.unwrap_or(0) as u64, limits).await?;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AppError::BadRequest(format!("Failed to read file data: {}", e)))?;
+        writer.write_chunk(&chunk).await?;
+    }
+    let upload = writer.finish().await?;
+
+    let mime_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let file = state.file_service
+        .store_file(
+            upload,
+            None,
+            mime_type,
+            query.expiry_hours,
+            PostType::File,
+            query.is_permanent,
+            query.file_extension,
+            query.max_downloads,
+            None,
+            limits,
+        )
+        .await?;
+
+    // The blob isn't client-encrypted, so link straight to it rather than the decrypting download page
+    let url = absolute_url(&state, &headers, &format!("/api/files/{}", file.id));
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("text/plain; charset=utf-8"));
+    if let Ok(header_value) = file.deletion_token.parse() {
+        response_headers.insert(header::HeaderName::from_static("x-deletion-token"), header_value);
+    }
+    if !file.is_permanent {
+        if let Ok(header_value) = file.expires_at.to_rfc3339().parse() {
+            response_headers.insert(header::HeaderName::from_static("x-expires"), header_value);
+        }
+    }
+
+    Ok((response_headers, format!("{}\n", url)).into_response())
+}
+
+/// Absolute URL for `path`, based on PUBLIC_URL or else the request's Host header
+fn absolute_url(state: &AppState, headers: &HeaderMap, path: &str) -> String {
+    if let Some(public_url) = &state.config.load().public_url {
+        return format!("{}{}", public_url, path);
+    }
 
-    Ok((headers, Bytes::from(data)))
+    let header_str = |name| headers.get(name).and_then(|v: &header::HeaderValue| v.to_str().ok());
+    match header_str(header::HOST) {
+        Some(host) => {
+            let scheme = header_str(header::HeaderName::from_static("x-forwarded-proto")).unwrap_or("http");
+            format!("{}://{}{}", scheme, host, path)
+        }
+        None => path.to_string(),
+    }
 }
 
 #[derive(Deserialize)]
@@ -304,14 +1049,11 @@ pub struct DeleteQuery {
     )
 )]
 pub async fn delete_file(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
     Query(query): Query<DeleteQuery>,
 ) -> Result<Json<DeleteResponse>> {
-    let db = Database::new(&config.database_url).await?;
-    let service = FileService::new((*config).clone(), db);
-
-    service.delete_file(&id, &query.token).await?;
+    state.file_service.delete_file(&id, &query.token).await?;
 
     Ok(Json(DeleteResponse {
         success: true,
@@ -319,13 +1061,132 @@ pub async fn delete_file(
     }))
 }
 
+/// Extend or shorten a file's expiry
+///
+/// Requires the deletion token returned during upload. The new expiry is counted
+/// from now and clamped to the caller's maximum, which a trusted API key or an
+/// `X-Upload-Token` raises like on uploads; 0 expires the file immediately.
+#[utoipa::path(
+    patch,
+    path = "/api/files/{id}/expiry",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File or post ID")
+    ),
+    request_body = UpdateExpiryRequest,
+    responses(
+        (status = 200, description = "Expiry updated", body = UpdateExpiryResponse),
+        (status = 400, description = "Invalid expiry_hours"),
+        (status = 403, description = "Invalid deletion token"),
+        (status = 404, description = "File not found or expired")
+    )
+)]
+pub async fn update_expiry(
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+    upload_token: Option<Extension<UploadToken>>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateExpiryRequest>,
+) -> Result<Json<UpdateExpiryResponse>> {
+    let limits = upload_limits(&state, trusted, upload_token);
+    let expires_at = state
+        .file_service
+        .update_expiry(&id, &req.deletion_token, req.expiry_hours, limits)
+        .await?;
+
+    Ok(Json(UpdateExpiryResponse {
+        file_id: id,
+        expires_at,
+    }))
+}
+
+/// Register an expiry warning
+///
+/// Requires the deletion token. With a `callback_url` the server POSTs a signed
+/// `expiring` event there once the file is within `hours_before` of expiring
+/// (`X-Dogbox-Signature: sha256=<HMAC-SHA256 of the body keyed with the deletion token>`);
+/// without one, poll `GET /api/files/{id}/expiry-warning`. Changing the expiry re-arms it.
+#[utoipa::path(
+    put,
+    path = "/api/files/{id}/expiry-warning",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File or post ID")
+    ),
+    request_body = ExpiryWarningRequest,
+    responses(
+        (status = 200, description = "Warning registered", body = ExpiryWarningStatus),
+        (status = 400, description = "Invalid hours_before or callback_url, or callbacks disabled"),
+        (status = 403, description = "Invalid deletion token"),
+        (status = 404, description = "File not found or expired")
+    )
+)]
+pub async fn set_expiry_warning(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ExpiryWarningRequest>,
+) -> Result<Json<ExpiryWarningStatus>> {
+    let status = state
+        .file_service
+        .set_expiry_warning(&id, &req.deletion_token, req.callback_url, req.hours_before)
+        .await?;
+    Ok(Json(status))
+}
+
+/// Check a file's expiry and whether its warning is due
+#[utoipa::path(
+    get,
+    path = "/api/files/{id}/expiry-warning",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File or post ID"),
+        ("token" = String, Query, description = "Deletion token")
+    ),
+    responses(
+        (status = 200, description = "Expiry and warning state", body = ExpiryWarningStatus),
+        (status = 403, description = "Invalid deletion token"),
+        (status = 404, description = "File not found or expired")
+    )
+)]
+pub async fn expiry_warning_status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<Json<ExpiryWarningStatus>> {
+    Ok(Json(state.file_service.expiry_warning_status(&id, &query.token).await?))
+}
+
+/// Remove an expiry warning
+#[utoipa::path(
+    delete,
+    path = "/api/files/{id}/expiry-warning",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "File or post ID"),
+        ("token" = String, Query, description = "Deletion token")
+    ),
+    responses(
+        (status = 204, description = "Warning removed"),
+        (status = 403, description = "Invalid deletion token"),
+        (status = 404, description = "File or warning not found")
+    )
+)]
+pub async fn remove_expiry_warning(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<axum::http::StatusCode> {
+    state.file_service.remove_expiry_warning(&id, &query.token).await?;
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
 /// View a post with all appended content
 #[utoipa::path(
     get,
     path = "/api/posts/{id}",
     tag = "dogbox.moe",
     params(
-        ("id" = String, Path, description = "Post ID")
+        ("id" = String, Path, description = "Post ID or slug")
     ),
     responses(
         (status = 200, description = "Post content", body = PostViewResponse),
@@ -333,13 +1194,15 @@ pub async fn delete_file(
     )
 )]
 pub async fn view_post(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
 ) -> Result<Json<PostViewResponse>> {
-    let db = Database::new(&config.database_url).await?;
-    let service = FileService::new((*config).clone(), db);
-
-    let post = service.view_post(&id).await?;
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let trusted_proxies = state.config.load().trusted_proxies.clone();
+    let viewer = peer.map(|ConnectInfo(addr)| crate::client_ip::resolve(addr.ip(), &headers, &trusted_proxies));
+    let post = state.file_service.view_post(&id, viewer).await?;
 
     Ok(Json(post))
 }
@@ -350,24 +1213,26 @@ pub async fn view_post(
     path = "/api/posts/{id}/append",
     tag = "dogbox.moe",
     params(
-        ("id" = String, Path, description = "Post ID")
+        ("id" = String, Path, description = "Post ID or slug")
     ),
     request_body = AppendRequest,
     responses(
         (status = 200, description = "Content appended successfully", body = AppendResponse),
+        (status = 400, description = "Malformed entry (`field` names the offending request field)"),
         (status = 403, description = "Invalid append key"),
-        (status = 404, description = "Post not found")
+        (status = 404, description = "Post not found"),
+        (status = 409, description = "Post is locked"),
+        (status = 413, description = "Entry too large"),
+        (status = 415, description = "Declared MIME type or extension not accepted")
     )
 )]
 pub async fn append_to_post(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
     Json(req): Json<AppendRequest>,
 ) -> Result<Json<AppendResponse>> {
-    let db = Database::new(&config.database_url).await?;
-    let service = FileService::new((*config).clone(), db);
-
-    let order = service.append_to_post(
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let order = state.file_service.append_to_post(
         &id,
         &req.append_key,
         req.content,
@@ -384,56 +1249,425 @@ pub async fn append_to_post(
     }))
 }
 
+/// Lock a post, making it permanently read-only
+///
+/// Discards the append key, so no further content can be appended by anyone.
+#[utoipa::path(
+    post,
+    path = "/api/posts/{id}/lock",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug")
+    ),
+    request_body = LockPostRequest,
+    responses(
+        (status = 200, description = "Post locked", body = LockPostResponse),
+        (status = 403, description = "Invalid append key"),
+        (status = 404, description = "Post not found"),
+        (status = 409, description = "Post is already locked")
+    )
+)]
+pub async fn lock_post(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<LockPostRequest>,
+) -> Result<Json<LockPostResponse>> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    state.file_service.lock_post(&id, &req.append_key).await?;
+
+    Ok(Json(LockPostResponse {
+        success: true,
+        message: "Post locked".to_string(),
+    }))
+}
+
+/// Download a whole post as a tar archive
+///
+/// Contains `manifest.json` (an `ArchiveManifest`) and every entry's encrypted
+/// bytes as `entries/NNNN.enc`, so a post can be backed up in one request.
+#[utoipa::path(
+    get,
+    path = "/api/posts/{id}/archive",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug")
+    ),
+    responses(
+        (status = 200, description = "Tar archive of the post", body = Vec<u8>, content_type = "application/x-tar"),
+        (status = 400, description = "Not a post"),
+        (status = 404, description = "Post not found or expired")
+    )
+)]
+pub async fn post_archive(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let (post, entries) = state.file_service.post_with_content(&id).await?;
+    let archive = crate::archive::post_archive(&post, entries)
+        .map_err(|e| AppError::Internal(e.into()))?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/x-tar"));
+    if let Ok(header_value) = format!("attachment; filename=\"post-{}.tar\"", post.id).parse() {
+        headers.insert(header::CONTENT_DISPOSITION, header_value);
+    }
+
+    Ok((headers, Body::from_stream(archive)))
+}
+
+/// Live post updates (Server-Sent Events)
+///
+/// Emits an `appended` event (JSON `PostEvent`) whenever content is appended, so
+/// viewers can refetch the post instead of polling. A `lagged` event means some
+/// updates were missed; the stream ends after a `locked` event.
+#[utoipa::path(
+    get,
+    path = "/api/posts/{id}/events",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug")
+    ),
+    responses(
+        (status = 200, description = "Event stream", body = PostEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Not a post"),
+        (status = 404, description = "Post not found")
+    )
+)]
+pub async fn post_events(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let receiver = state.file_service.subscribe_post_events(&id).await?;
+
+    let stream = futures_util::stream::unfold(Some(receiver), |receiver| async move {
+        let mut receiver = receiver?;
+        match receiver.recv().await {
+            Ok(event) => {
+                let done = matches!(event, crate::events::PostEvent::Locked);
+                let sse_event = Event::default().event(event.name()).json_data(&event);
+                Some((sse_event, (!done).then_some(receiver)))
+            }
+            Err(RecvError::Lagged(missed)) => {
+                let sse_event = Event::default().event("lagged").data(missed.to_string());
+                Some((Ok(sse_event), Some(receiver)))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Live post updates (WebSocket)
+///
+/// Pushes each newly appended entry, still encrypted, as JSON text messages:
+/// `{"type":"appended","entry":PostContentView}`, `{"type":"lagged","missed":n}`
+/// (some entries were missed; refetch the post) and a final `{"type":"locked"}`.
+#[utoipa::path(
+    get,
+    path = "/api/posts/{id}/ws",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug")
+    ),
+    responses(
+        (status = 101, description = "WebSocket upgrade"),
+        (status = 400, description = "Not a post"),
+        (status = 404, description = "Post not found")
+    )
+)]
+pub async fn post_ws(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Result<Response> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let receiver = state.file_service.subscribe_post_events(&id).await?;
+
+    Ok(ws.on_upgrade(move |socket| push_post_updates(socket, receiver)))
+}
+
+/// Message pushed to WebSocket viewers
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PostWsMessage<'a> {
+    Appended { entry: &'a PostContentView },
+    Lagged { missed: u64 },
+    Locked,
+}
+
+async fn push_post_updates(mut socket: WebSocket, mut receiver: broadcast::Receiver<crate::events::PostEvent>) {
+    use crate::events::PostEvent;
+
+    loop {
+        tokio::select! {
+            event = receiver.recv() => {
+                let (message, done) = match &event {
+                    Ok(PostEvent::Appended { entry, .. }) => (PostWsMessage::Appended { entry }, false),
+                    Ok(PostEvent::Locked) => (PostWsMessage::Locked, true),
+                    Err(RecvError::Lagged(missed)) => (PostWsMessage::Lagged { missed: *missed }, false),
+                    Err(RecvError::Closed) => break,
+                };
+                let Ok(text) = serde_json::to_string(&message) else { break };
+                if socket.send(Message::Text(text)).await.is_err() || done {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                // Viewers only listen; stop once they close or drop the connection
+                if matches!(incoming, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = socket.close().await;
+}
+
+/// Rotate a post's append key
+///
+/// Invalidates a leaked append key without deleting the post. Authorize with
+/// either the current append key or the post's deletion token.
+#[utoipa::path(
+    post,
+    path = "/api/posts/{id}/rotate-key",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug")
+    ),
+    request_body = RotateAppendKeyRequest,
+    responses(
+        (status = 200, description = "New append key", body = RotateAppendKeyResponse),
+        (status = 400, description = "Neither or both credentials given, or not a post"),
+        (status = 403, description = "Invalid append key or deletion token"),
+        (status = 409, description = "Post is locked")
+    )
+)]
+pub async fn rotate_append_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<RotateAppendKeyRequest>,
+) -> Result<Json<RotateAppendKeyResponse>> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let post_append_key = state
+        .file_service
+        .rotate_append_key(&id, req.append_key.as_deref(), req.deletion_token.as_deref())
+        .await?;
+
+    Ok(Json(RotateAppendKeyResponse { post_append_key }))
+}
+
+#[derive(Deserialize)]
+pub struct PostKeyQuery {
+    append_key: String,
+}
+
+/// Issue a collaborator append key
+///
+/// Lets someone else append to the post without sharing the master append key.
+/// Collaborator keys can append but can't lock the post or manage keys.
+#[utoipa::path(
+    post,
+    path = "/api/posts/{id}/keys",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug")
+    ),
+    request_body = CreatePostKeyRequest,
+    responses(
+        (status = 200, description = "Collaborator key issued", body = CreatePostKeyResponse),
+        (status = 400, description = "Label too long or key limit reached"),
+        (status = 403, description = "Invalid master append key"),
+        (status = 409, description = "Post is locked")
+    )
+)]
+pub async fn create_post_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<CreatePostKeyRequest>,
+) -> Result<Json<CreatePostKeyResponse>> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    let (key, append_key) = state
+        .file_service
+        .create_post_key(&id, &req.append_key, req.label)
+        .await?;
+
+    Ok(Json(CreatePostKeyResponse {
+        key_id: key.id,
+        append_key,
+        label: key.label,
+    }))
+}
+
+/// List collaborator append keys
+#[utoipa::path(
+    get,
+    path = "/api/posts/{id}/keys",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug"),
+        ("append_key" = String, Query, description = "Master append key")
+    ),
+    responses(
+        (status = 200, description = "Collaborator keys (IDs and labels only)", body = Vec<PostKey>),
+        (status = 403, description = "Invalid master append key")
+    )
+)]
+pub async fn list_post_keys(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<PostKeyQuery>,
+) -> Result<Json<Vec<PostKey>>> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    Ok(Json(state.file_service.list_post_keys(&id, &query.append_key).await?))
+}
+
+/// Revoke a collaborator append key
+#[utoipa::path(
+    delete,
+    path = "/api/posts/{id}/keys/{key_id}",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Post ID or slug"),
+        ("key_id" = String, Path, description = "Collaborator key ID"),
+        ("append_key" = String, Query, description = "Master append key")
+    ),
+    responses(
+        (status = 200, description = "Key revoked", body = DeleteResponse),
+        (status = 403, description = "Invalid master append key"),
+        (status = 404, description = "Key not found")
+    )
+)]
+pub async fn revoke_post_key(
+    State(state): State<AppState>,
+    Path((id, key_id)): Path<(String, String)>,
+    Query(query): Query<PostKeyQuery>,
+) -> Result<Json<DeleteResponse>> {
+    let id = state.file_service.resolve_post_id(&id).await?;
+    state
+        .file_service
+        .revoke_post_key(&id, &query.append_key, &key_id)
+        .await?;
+
+    Ok(Json(DeleteResponse {
+        success: true,
+        message: "Key revoked".to_string(),
+    }))
+}
+
 /// Get public statistics
 #[utoipa::path(
     get,
     path = "/api/stats",
     tag = "dogbox.moe",
     responses(
-        (status = 200, description = "System statistics", body = StatsResponse)
+        (status = 200, description = "System statistics (up to STATS_CACHE_SECS old)", body = StatsResponse)
+    )
+)]
+pub async fn stats(State(state): State<AppState>) -> Result<Response> {
+    let (max_age, analytics) = {
+        let config = state.config.load();
+        (config.stats_cache, config.analytics_enabled)
+    };
+    if max_age.is_zero() {
+        return Ok(Json(gather_stats(&state.db, analytics).await?).into_response());
+    }
+
+    // Normally warm; only computed here before the refresh task's first run
+    let cached = match state.stats.get(max_age) {
+        Some(cached) => cached,
+        None => state.stats.refresh(&state.db, analytics).await?,
+    };
+    let remaining = max_age.saturating_sub(cached.computed_at.elapsed());
+    Ok((
+        [(header::CACHE_CONTROL, format!("public, max-age={}", remaining.as_secs()))],
+        Json(&cached.stats),
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsHistoryQuery {
+    days: Option<i64>,
+}
+
+/// Get hourly stats snapshots for charting growth over time
+#[utoipa::path(
+    get,
+    path = "/api/stats/history",
+    tag = "dogbox.moe",
+    params(
+        ("days" = Option<i64>, Query, description = "How far back to go (default 30, max 365)")
+    ),
+    responses(
+        (status = 200, description = "Snapshots, oldest first", body = StatsHistoryResponse)
     )
 )]
-pub async fn stats(
-    State(config): State<Arc<Config>>,
-) -> Result<Json<StatsResponse>> {
-    let db = Database::new(&config.database_url).await?;
+pub async fn stats_history(
+    State(state): State<AppState>,
+    Query(query): Query<StatsHistoryQuery>,
+) -> Result<Json<StatsHistoryResponse>> {
+    let days = query
+        .days
+        .unwrap_or(crate::constants::STATS_HISTORY_DEFAULT_DAYS)
+        .clamp(1, crate::constants::STATS_HISTORY_MAX_DAYS);
+    let since = chrono::Utc::now().timestamp() - days * 86400;
 
+    let mut snapshots = state.db.list_stats_snapshots(since).await?;
+    if !state.config.load().analytics_enabled {
+        snapshots.iter_mut().for_each(|snapshot| snapshot.total_views = None);
+    }
+    Ok(Json(StatsHistoryResponse { days, snapshots }))
+}
+
+/// Upload, storage and disk statistics (shared with `dogbox stats`)
+///
+/// View totals are left out unless `analytics` is on.
+pub async fn gather_stats(db: &crate::database::Database, analytics: bool) -> Result<StatsResponse> {
     let (total, posts, files, permanent, temporary, views, bytes) = db.get_stats().await?;
     let file_extensions = db.get_file_extension_stats().await?;
-    let (dogpastes, dogpaste_views) = db.get_dogpaste_stats().await?;
+    let (post_entries, post_bytes) = db.get_post_stats().await?;
+    let (dogpastes, dogpaste_views, dogpaste_bytes) = db.get_dogpaste_stats().await?;
+    let evicted_files = db.get_counter(crate::constants::EVICTED_FILES_COUNTER).await?;
 
     // Get disk space information for root filesystem
-    let (disk_total_gb, disk_used_gb, disk_free_gb) = match nix::sys::statvfs::statvfs("/") {
-        Ok(stats) => {
-            let block_size = stats.block_size() as f64;
-            let total_blocks = stats.blocks() as f64;
-            let free_blocks = stats.blocks_free() as f64;
-
-            let total_bytes = total_blocks * block_size;
-            let free_bytes = free_blocks * block_size;
-            let used_bytes = total_bytes - free_bytes;
-
-            let gb = 1024.0 * 1024.0 * 1024.0;
-            (total_bytes / gb, used_bytes / gb, free_bytes / gb)
+    let gb = 1024.0 * 1024.0 * 1024.0;
+    let disk = match crate::storage::disk_space("/") {
+        Ok(space) => DiskStats {
+            total_gb: space.total as f64 / gb,
+            used_gb: (space.total - space.free) as f64 / gb,
+            free_gb: space.free as f64 / gb,
         },
-        Err(_) => (0.0, 0.0, 0.0),
+        Err(_) => DiskStats { total_gb: 0.0, used_gb: 0.0, free_gb: 0.0 },
     };
+    let mb = |bytes: i64| bytes as f64 / (1024.0 * 1024.0);
 
-    Ok(Json(StatsResponse {
-        total_uploads: total,
-        total_posts: posts,
-        total_files: files,
-        total_dogpastes: dogpastes,
-        permanent_count: permanent,
-        temporary_count: temporary,
-        total_views: views,
-        dogpaste_views,
-        storage_mb: (bytes as f64) / (1024.0 * 1024.0),
-        disk_total_gb,
-        disk_used_gb,
-        disk_free_gb,
-        file_extensions,
-    }))
+    Ok(StatsResponse {
+        uploads: UploadStats {
+            total,
+            files,
+            permanent,
+            temporary,
+            views: analytics.then_some(views),
+            storage_mb: mb(bytes),
+            file_extensions,
+            evicted_files,
+        },
+        posts: PostStats {
+            count: posts,
+            entries: post_entries,
+            storage_mb: mb(post_bytes),
+        },
+        dogpastes: DogpasteStats {
+            count: dogpastes,
+            views: analytics.then_some(dogpaste_views),
+            storage_mb: mb(dogpaste_bytes),
+        },
+        disk,
+    })
 }
 
 /// Create a dogpaste (short encrypted paste)
@@ -445,11 +1679,13 @@ pub async fn stats(
     responses(
         (status = 200, description = "Paste created successfully", body = DogpasteCreateResponse),
         (status = 400, description = "Invalid request"),
-        (status = 409, description = "ID already exists (collision)")
+        (status = 409, description = "ID already exists (collision)"),
+        (status = 413, description = "Paste too large")
     )
 )]
 pub async fn dogpaste_create(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
     Json(req): Json<crate::models::DogpasteCreateRequest>,
 ) -> Result<Json<crate::models::DogpasteCreateResponse>> {
     use base64::{Engine as _, engine::general_purpose};
@@ -467,16 +1703,32 @@ pub async fn dogpaste_create(
         .decode(&req.encrypted_data)
         .map_err(|e| AppError::BadRequest(format!("Invalid base64 data: {}", e)))?;
 
+    // Same expiry rules as file uploads
+    let config = state.config.load_full();
+    if encrypted_data.len() as u64 > config.max_dogpaste_bytes {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Paste exceeds the maximum of {} bytes",
+            config.max_dogpaste_bytes
+        )));
+    }
+    let expiry_hours = req.expiry_hours.unwrap_or(config.default_expiry_hours);
+    if expiry_hours < 1 {
+        return Err(AppError::BadRequest("expiry_hours must be at least 1".to_string()));
+    }
+    let expiry_hours = expiry_hours.min(state.file_service.upload_limits(trusted.is_some()).max_expiry_hours);
+
+    if req.max_views.is_some_and(|n| n < 1) {
+        return Err(AppError::BadRequest("max_views must be at least 1".to_string()));
+    }
+
     // Create record
     let now = chrono::Utc::now().timestamp();
-    let expires_at = now + (24 * 60 * 60); // 24 hours
-
-    let db = Database::new(&config.database_url).await?;
+    let expires_at = now + expiry_hours * 60 * 60;
 
     // Try to insert with collision handling (max 3 retries)
     // Note: Client generates the ID, so collision means the client should
     // regenerate. We return an error to have them try again with a new ID.
-    db.create_dogpaste(&req.id, &encrypted_data, expires_at)
+    state.db.create_dogpaste(&req.id, &encrypted_data, expires_at, req.max_views)
         .await
         .map_err(|e| {
             if e.to_string().contains("UNIQUE constraint failed") {
@@ -491,6 +1743,7 @@ pub async fn dogpaste_create(
     Ok(Json(crate::models::DogpasteCreateResponse {
         success: true,
         id: req.id,
+        expires_at,
     }))
 }
 
@@ -508,7 +1761,7 @@ pub async fn dogpaste_create(
     )
 )]
 pub async fn dogpaste_view(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> Result<Json<crate::models::DogpasteViewResponse>> {
     use base64::{Engine as _, engine::general_purpose};
@@ -518,10 +1771,8 @@ pub async fn dogpaste_view(
         return Err(AppError::NotFound);
     }
 
-    let db = Database::new(&config.database_url).await?;
-
     // Get paste from database
-    let record = db.get_dogpaste(&id)
+    let record = state.db.get_dogpaste(&id)
         .await?
         .ok_or(AppError::NotFound)?;
 
@@ -529,12 +1780,19 @@ pub async fn dogpaste_view(
     let now = chrono::Utc::now().timestamp();
     if record.expires_at < now {
         // Delete expired paste
-        db.delete_dogpaste(&id).await.ok();
+        state.db.delete_dogpaste(&id).await.ok();
         return Err(AppError::NotFound);
     }
 
-    // Increment view counter
-    db.increment_dogpaste_views(&id).await.ok();
+    // Count the view (burns the paste if this was its last allowed view); without
+    // analytics only view-limited pastes are counted
+    let record = if record.max_views.is_some() || state.config.load().analytics_enabled {
+        state.db.record_dogpaste_view(&record.id)
+            .await?
+            .ok_or(AppError::NotFound)?
+    } else {
+        record
+    };
 
     // Encode data as base64
     let encrypted_data_b64 = general_purpose::URL_SAFE_NO_PAD.encode(&record.encrypted_data);
@@ -542,5 +1800,6 @@ pub async fn dogpaste_view(
     Ok(Json(crate::models::DogpasteViewResponse {
         encrypted_data: encrypted_data_b64,
         created_at: record.created_at,
+        remaining_views: record.max_views.map(|max| max - record.views),
     }))
 }
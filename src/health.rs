@@ -0,0 +1,82 @@
+//! Readiness checks for `/readyz` and subsystem status for `/api/health`
+//!
+//! Liveness only means the process answers; readiness also needs the database,
+//! its schema and the upload directory, so a load balancer or orchestrator
+//! doesn't route traffic to an instance that would fail every request. The
+//! health endpoint reports softer signals (latency, disk space, background
+//! tasks) that monitoring should alert on without taking the instance out.
+
+use crate::cleanup::{CLEANUP_STARTED, LAST_CLEANUP};
+use crate::config::StorageConfig;
+use crate::models::{CleanupHealth, DatabaseHealth, DiskHealth, HealthChecks, ReadinessCheck, StorageHealth};
+use crate::state::AppState;
+use chrono::Utc;
+use std::time::Instant;
+
+/// Run every readiness check (each one is independent, so all are reported)
+pub async fn readiness(state: &AppState) -> Vec<ReadinessCheck> {
+    let upload_dir = state.config.load().upload_dir.clone();
+    let database = state.db.ping().await.map_err(|e| e.to_string());
+    let migrations = match state.db.pending_migrations().await {
+        Ok(pending) if pending.is_empty() => Ok(()),
+        Ok(pending) => Err(format!("{} migrations not applied (first: {})", pending.len(), pending[0])),
+        Err(e) => Err(e.to_string()),
+    };
+    // Uploads are staged in the upload directory whatever the storage backend
+    let upload_dir = crate::check::check_upload_dir(&upload_dir).await.map(|_| ()).map_err(|e| e.to_string());
+
+    vec![
+        ReadinessCheck::new("database", database),
+        ReadinessCheck::new("migrations", migrations),
+        ReadinessCheck::new("upload_dir", upload_dir),
+    ]
+}
+
+/// Subsystem checks for `/api/health`
+pub async fn subsystems(state: &AppState) -> HealthChecks {
+    let config = state.config.load_full();
+
+    let started = Instant::now();
+    let database = match state.db.ping().await {
+        Ok(()) => DatabaseHealth {
+            ok: true,
+            latency_ms: Some(started.elapsed().as_secs_f64() * 1000.0),
+        },
+        Err(_) => DatabaseHealth { ok: false, latency_ms: None },
+    };
+
+    let disk = match crate::storage::disk_space(&config.upload_dir) {
+        Ok(space) => DiskHealth {
+            ok: config.min_free_disk_bytes.is_none_or(|min| space.available >= min),
+            available_bytes: Some(space.available),
+            total_bytes: Some(space.total),
+        },
+        Err(_) => DiskHealth { ok: false, available_bytes: None, total_bytes: None },
+    };
+
+    // Healthy while the last run (or the task's start, before the first run) is recent
+    let last_run = *LAST_CLEANUP.read().await;
+    let since = last_run.or(*CLEANUP_STARTED.read().await);
+    let allowed = 2 * config.cleanup_interval + config.cleanup_jitter;
+    let cleanup = CleanupHealth {
+        ok: since.is_some_and(|at| (Utc::now() - at).to_std().unwrap_or_default() < allowed),
+        last_run,
+    };
+
+    let storage = StorageHealth {
+        ok: state.file_service.check_storage().await.is_ok(),
+        backend: match config.storage {
+            StorageConfig::Local => "local",
+            StorageConfig::S3 { .. } => "s3",
+        }
+        .to_string(),
+    };
+
+    HealthChecks { database, disk, cleanup, storage }
+}
+
+impl HealthChecks {
+    pub fn all_ok(&self) -> bool {
+        self.database.ok && self.disk.ok && self.cleanup.ok && self.storage.ok
+    }
+}
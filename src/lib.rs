@@ -0,0 +1,403 @@
+//! dogbox.moe server library
+//!
+//! Everything the `dogbox` binary serves is built here, so other Rust apps can
+//! embed an instance or write their own binaries:
+//!
+//! ```ignore
+//! let config = dogbox_core::config::Config::load(None)?;
+//! let app = dogbox_core::build_router(config).await?;
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
+//! ```
+//!
+//! The router needs the peer address (rate limiting, bans and view counting
+//! resolve the client IP from it), so serve it with
+//! `into_make_service_with_connect_info::<SocketAddr>()` as above.
+
+use axum::{
+    routing::{any, get, post, put, delete, patch},
+    Router,
+    response::Response,
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, State},
+    middleware as axum_middleware,
+};
+use std::net::SocketAddr;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::ServiceBuilder;
+use tower_http::trace::TraceLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+pub mod admin;
+pub mod archive;
+pub mod assets;
+pub mod backup;
+pub mod bans;
+pub mod captcha;
+pub mod check;
+pub mod cleanup;
+pub mod client_ip;
+pub mod collections;
+pub mod config;
+pub mod constants;
+pub mod database;
+pub mod error;
+pub mod events;
+pub mod geoip;
+pub mod graphql;
+pub mod grpc;
+pub mod handlers;
+pub mod health;
+pub mod load_shed;
+pub mod maintenance;
+pub mod middleware;
+pub mod models;
+pub mod pages;
+pub mod pow;
+pub mod qr;
+pub mod rate_limit;
+pub mod remote_backup;
+pub mod s3;
+pub mod services;
+pub mod state;
+pub mod stats;
+pub mod storage;
+pub mod telemetry;
+pub mod tor;
+pub mod tus;
+pub mod views;
+pub mod webdav;
+pub mod webhooks;
+pub mod well_known;
+
+pub use config::Config;
+pub use database::Database;
+pub use error::AppError;
+pub use services::FileService;
+pub use state::AppState;
+
+use config::TorExitPolicy;
+use constants::{MAX_UPLOAD_SIZE, DOGBOX_EMOJI};
+
+async fn serve_favicon(State(state): State<AppState>) -> Response {
+    assets::favicon(&state).await
+}
+
+/// Open the database, run migrations and build the shared application state
+pub async fn build_state(config: Config) -> anyhow::Result<AppState> {
+    // Initialize database and run embedded migrations
+    let db = Database::new(&config.database_url, &config.sqlite).await?;
+    if config.run_migrations {
+        db.migrate().await?;
+    } else {
+        tracing::info!("Skipping migrations (RUN_MIGRATIONS=false)");
+    }
+
+    // Create upload directory
+    tokio::fs::create_dir_all(&config.upload_dir).await?;
+
+    if !config.analytics_enabled {
+        db.forget_analytics().await?;
+        tracing::info!("🙈 Analytics disabled: not counting views or downloads");
+    }
+
+    // Build application state (single shared DB pool for all handlers)
+    let app_state = AppState::new(config, db)?;
+    app_state.file_service.load_storage_usage().await?;
+    app_state.load_stored_admin_message().await?;
+    app_state.bans.reload(&app_state.db).await?;
+    app_state.pow.load_secret(&app_state.db).await?;
+    Ok(app_state)
+}
+
+/// Start cleanup, maintenance, backups, the gRPC API and the other background
+/// tasks an instance needs besides its router
+pub fn spawn_background_tasks(app_state: &AppState) -> anyhow::Result<()> {
+    // gRPC API on its own port
+    if let Some(grpc_port) = app_state.config.load().grpc_port {
+        let grpc_state = app_state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = grpc::serve(grpc_state, grpc_port).await {
+                tracing::error!("❌ gRPC server failed: {}", e);
+            }
+        });
+    }
+
+    // Start background cleanup task
+    let cleanup_state = app_state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = cleanup::start_cleanup_task(cleanup_state).await {
+            tracing::error!("Cleanup task failed: {}", e);
+        }
+    });
+
+    // Periodic VACUUM/ANALYZE
+    if let Some(every) = app_state.config.load().maintenance_interval {
+        tokio::spawn(maintenance::start_maintenance_task(app_state.db.clone(), every));
+    }
+
+    // Incremental backups to a bucket
+    if let Some(backup_config) = app_state.config.load().remote_backup.clone() {
+        let backup = remote_backup::RemoteBackup::new(&backup_config)?;
+        let config = app_state.config.load();
+        tokio::spawn(remote_backup::start_remote_backup_task(
+            backup,
+            app_state.db.clone(),
+            storage::from_config(&config)?,
+            config.upload_dir.clone(),
+            backup_config.interval,
+        ));
+    }
+
+    // Pick up IP bans made through other instances
+    tokio::spawn(bans::start_refresh_task(app_state.clone()));
+
+    // Tor exit list for TOR_EXIT_POLICY
+    if app_state.config.load().tor_exit_policy != TorExitPolicy::Allow {
+        tokio::spawn(tor::start_refresh_task(app_state.clone()));
+    }
+
+    // Keep the /api/stats snapshot fresh
+    let stats_cache = app_state.config.load().stats_cache;
+    if !stats_cache.is_zero() {
+        tokio::spawn(stats::start_refresh_task(app_state.clone(), stats_cache));
+    }
+
+    Ok(())
+}
+
+/// The complete HTTP application (pages, API, admin, static files and docs)
+/// with the whole middleware stack, for an already built state
+pub async fn router(app_state: AppState) -> anyhow::Result<Router> {
+    // Bodies may be as large as the biggest upload allowed (trusted API keys can exceed MAX_UPLOAD_SIZE)
+    let body_limit = MAX_UPLOAD_SIZE.max(app_state.config.load().api_key_max_upload_bytes as usize);
+    let static_dir = app_state.config.load().static_dir.clone();
+    let (request_timeout, upload_timeout) = {
+        let config = app_state.config.load();
+        (config.request_timeout, config.upload_timeout)
+    };
+    // Uploads (and WebDAV and S3, which carry them too) stream bodies of up to several GB, so they get their own (longer) timeout
+    let upload_routes = Router::new()
+        .route("/api/upload", post(handlers::upload))
+        .route("/api/raw", put(handlers::raw_upload))
+        .route("/api/tus", post(tus::create).options(tus::options))
+        .route("/api/tus/:id", patch(tus::upload_chunk).head(tus::upload_offset))
+        .route("/dav", any(webdav::handler))
+        .route("/dav/", any(webdav::handler))
+        .route("/dav/*path", any(webdav::handler))
+        .route("/s3", any(s3::handler))
+        .route("/s3/", any(s3::handler))
+        .route("/s3/*path", any(s3::handler))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(middleware::handle_overload))
+                .timeout(upload_timeout),
+        );
+    let mut app = Router::new()
+        // Frontend routes
+        .route("/", get(pages::index))
+        .route("/favicon.ico", get(serve_favicon))
+        .route("/robots.txt", get(well_known::robots_txt))
+        .route("/.well-known/*path", get(well_known::serve))
+        .route("/navbar", get(pages::navbar))
+        .route("/f/:id", get(pages::download))
+        .route("/p/:id", get(pages::download))
+        .route("/dogpaste", get(pages::dogpaste))
+        .route("/faq", get(pages::faq))
+        .route("/post-types", get(pages::post_types))
+        .route("/prohibited-uploads", get(pages::prohibited_uploads))
+        .route("/stats", get(pages::stats))
+        // API routes
+        .route("/api/health", get(handlers::health))
+        .route("/livez", get(handlers::livez))
+        .route("/readyz", get(handlers::readyz))
+        .route("/api/admin-motd", get(handlers::admin_motd))
+        .route("/api/pow/challenge", get(pow::challenge))
+        .route("/api/stats", get(handlers::stats))
+        .route("/api/stats/history", get(handlers::stats_history))
+        .route("/api/graphql", post(graphql::handler))
+        .route("/api/files/info", post(handlers::files_info))
+        .route("/api/files/:id", get(handlers::download).head(handlers::download_head))
+        .route("/api/files/:id/info", get(handlers::file_info))
+        .route("/api/files/:id/preview", get(handlers::preview))
+        .route("/api/files/:id/verify", get(handlers::verify_file))
+        .route("/api/files/:id/qr.png", get(handlers::file_qr))
+        .route("/api/files/:id", delete(handlers::delete_file))
+        .route("/api/files/:id/expiry", patch(handlers::update_expiry))
+        .route(
+            "/api/files/:id/expiry-warning",
+            get(handlers::expiry_warning_status)
+                .put(handlers::set_expiry_warning)
+                .delete(handlers::remove_expiry_warning),
+        )
+        .route("/api/posts/:id", get(handlers::view_post))
+        .route("/api/posts/:id/append", post(handlers::append_to_post))
+        .route("/api/posts/:id/lock", post(handlers::lock_post))
+        .route("/api/posts/:id/events", get(handlers::post_events))
+        .route("/api/posts/:id/archive", get(handlers::post_archive))
+        .route("/api/posts/:id/ws", get(handlers::post_ws))
+        .route("/api/posts/:id/rotate-key", post(handlers::rotate_append_key))
+        .route("/api/posts/:id/keys", get(handlers::list_post_keys).post(handlers::create_post_key))
+        .route("/api/posts/:id/keys/:key_id", delete(handlers::revoke_post_key))
+        .route("/api/dogpaste", post(handlers::dogpaste_create))
+        .route("/api/dogpaste/:id", get(handlers::dogpaste_view))
+        .route("/api/collections", post(collections::create))
+        .route("/api/collections/:id", get(collections::view))
+        .route("/api/collections/:id/files", post(collections::add_files))
+        .route("/api/collections/:id/files/:file_id", delete(collections::remove_file))
+        .nest("/api/admin", admin::router(app_state.clone()))
+        // Static files (embedded in the binary unless STATIC_DIR is set)
+        .nest("/static", assets::router(static_dir.as_deref()))
+        // API docs
+        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", handlers::ApiDoc::openapi()))
+        // SECURITY: Stalled handlers and slow clients get 408 instead of holding a slot forever
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(middleware::handle_overload))
+                .timeout(request_timeout),
+        )
+        .merge(upload_routes)
+        // SECURITY: Middleware layers (order matters - applied bottom to top)
+        .layer(DefaultBodyLimit::max(body_limit))
+        .layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::captcha))
+        .layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::private_access))
+        .layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::upload_token))
+        .layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::security_headers))
+        .layer(axum_middleware::from_fn(middleware::csrf_protection));
+
+    // Invalid API keys are only turned away once the rate limiter has counted them
+    app = app.layer(axum_middleware::from_fn(middleware::reject_credentials));
+    // SECURITY: Per-IP rate limiting, permissive by default so page loads with many
+    // JS modules fit in the burst. Always installed so it can be switched on by a
+    // config reload.
+    let limiter = std::sync::Arc::new(rate_limit::RateLimiter::new(app_state.config.clone()).await?);
+    tokio::spawn(rate_limit::start_prune_task(limiter.clone()));
+    app = app.layer(axum_middleware::from_fn_with_state(limiter, rate_limit::rate_limit));
+    // SECURITY: Banned networks are turned away before they use up rate limit tokens
+    app = app.layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::banned_networks));
+    if app_state.geoip.is_some() {
+        app = app.layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::country_policy));
+    }
+    if app_state.config.load().tor_exit_policy != TorExitPolicy::Allow {
+        app = app.layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::tor_exit_policy));
+    }
+    // Trusted API keys are checked first so valid ones can skip the rate limiter
+    app = app.layer(axum_middleware::from_fn_with_state(app_state.clone(), middleware::api_key));
+
+    // Adaptive load shedding (switchable by a config reload, so always installed)
+    app = app.layer(axum_middleware::from_fn_with_state(
+        std::sync::Arc::new(load_shed::LoadShedder::new(app_state.config.clone())),
+        load_shed::load_shed,
+    ));
+
+    // SECURITY: Cap requests in flight; the excess gets 503 right away instead of
+    // queueing up on the SQLite pool and disk
+    if let Some(max_concurrent) = app_state.config.load().max_concurrent_requests {
+        app = app.layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(middleware::handle_overload))
+                .load_shed()
+                .layer(GlobalConcurrencyLimitLayer::new(max_concurrent)),
+        );
+        tracing::info!("🚧 Handling at most {} requests at once", max_concurrent);
+    }
+
+    // Outermost: tag each request with an ID and open its span, so rejections from
+    // the layers above are logged and answered with the ID as well
+    app = app
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::make_request_span)
+                .on_response(telemetry::log_response),
+        )
+        .layer(axum_middleware::from_fn(middleware::request_id));
+
+    if app_state.config.load().rate_limit_enabled {
+        tracing::info!(
+            "🚦 Rate limiting: {} req/s sustained, burst {}",
+            app_state.config.load().rate_limit_per_second,
+            app_state.config.load().rate_limit_burst
+        );
+    } else {
+        tracing::warn!("⚠️  Rate limiting disabled (RATE_LIMIT_ENABLED=false)");
+    }
+
+    Ok(app.with_state(app_state))
+}
+
+/// A ready-to-serve instance: state, background tasks and router in one call
+pub async fn build_router(config: Config) -> anyhow::Result<Router> {
+    let app_state = build_state(config).await?;
+    spawn_background_tasks(&app_state)?;
+    router(app_state).await
+}
+
+/// Run the HTTP server (what `dogbox serve` does)
+pub async fn serve(config: Config) -> anyhow::Result<()> {
+    // Store port and TLS settings before moving config
+    let port = config.port;
+    let tls = config.tls.clone();
+
+    let app_state = build_state(config).await?;
+    spawn_background_tasks(&app_state)?;
+
+    // Reload the reloadable settings on SIGHUP (also available as POST /api/admin/reload-config)
+    let reload_state = app_state.clone();
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("Received SIGHUP, reloading configuration");
+            match reload_state.reload_config() {
+                Ok(()) => {
+                    reload_state
+                        .db
+                        .audit(models::AuditActor::System, "config.reload", None, Some("SIGHUP"))
+                        .await
+                }
+                Err(e) => tracing::error!("❌ Config reload failed, keeping the current config: {}", e),
+            }
+        }
+    });
+
+    let app = router(app_state).await?;
+
+    // Start server, on the socket systemd passed in if socket-activated (see dogbox.socket)
+    let listener = match listenfd::ListenFd::from_env().take_tcp_listener(0)? {
+        Some(listener) => {
+            tracing::info!("🔌 Using listener from systemd socket activation");
+            listener
+        }
+        None => std::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))?,
+    };
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    tracing::info!("{} dogbox.moe listening on {}", DOGBOX_EMOJI, addr);
+    tracing::info!("📖 API docs available at {}://{}/docs", scheme, addr);
+
+    // IMPORTANT: Use into_make_service_with_connect_info to provide SocketAddr
+    // for rate limiting middleware (client IP resolution needs the peer IP)
+    match tls {
+        Some(tls) => {
+            // Several crypto providers may be compiled in; pick ring explicitly
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let rustls_config = axum_server::tls_rustls::RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to load TLS certificate/key: {}", e))?;
+
+            tracing::info!("🔒 TLS enabled ({})", tls.cert_path);
+            axum_server::from_tcp_rustls(listener, rustls_config)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+        None => {
+            axum::serve(
+                tokio::net::TcpListener::from_std(listener)?,
+                app.into_make_service_with_connect_info::<SocketAddr>()
+            ).await?;
+        }
+    }
+
+    Ok(())
+}
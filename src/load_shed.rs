@@ -0,0 +1,126 @@
+//! Adaptive load shedding
+//!
+//! Keeps a moving average of how long requests take to get a response. Once it
+//! rises above LOAD_SHED_TARGET_LATENCY_MS the server is considered under
+//! pressure: requests beyond LOAD_SHED_QUEUE_DEPTH in flight are rejected, and
+//! the rest are rejected with a probability that grows with the overshoot. At
+//! least one in ten requests still gets through so the average can recover.
+//!
+//! Uploads stream their whole body inside the handler, so they're neither timed
+//! nor shed here (the concurrency limit and storage checks cover them). The
+//! health check and admin API are never shed.
+
+use crate::config::SharedConfig;
+use crate::error::AppError;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderValue, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use rand::Rng;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Upper bound on the chance of shedding a request, so samples keep coming in
+const MAX_SHED_PROBABILITY: f64 = 0.9;
+
+/// Weight of a new latency sample in the moving average (1/8)
+const EWMA_SHIFT: u32 = 3;
+
+pub struct LoadShedder {
+    config: SharedConfig,
+    in_flight: AtomicUsize,
+    /// Moving average of request latency in microseconds
+    latency_us: AtomicU64,
+}
+
+impl LoadShedder {
+    pub fn new(config: SharedConfig) -> Self {
+        Self {
+            config,
+            in_flight: AtomicUsize::new(0),
+            latency_us: AtomicU64::new(0),
+        }
+    }
+
+    fn record_latency(&self, sample_us: u64) {
+        // Lost updates under contention only make the average slightly noisier
+        let average = self.latency_us.load(Ordering::Relaxed);
+        let updated = average - (average >> EWMA_SHIFT) + (sample_us >> EWMA_SHIFT);
+        self.latency_us.store(updated, Ordering::Relaxed);
+    }
+
+    /// Whether to reject a request arriving with `in_flight` requests already running
+    fn should_shed(&self, in_flight: usize, target_us: u64, queue_depth: usize) -> bool {
+        let average = self.latency_us.load(Ordering::Relaxed);
+        if average <= target_us {
+            return false;
+        }
+        if in_flight >= queue_depth {
+            return true;
+        }
+        let overshoot = (average - target_us) as f64 / target_us as f64;
+        rand::thread_rng().gen_bool(overshoot.min(MAX_SHED_PROBABILITY))
+    }
+}
+
+/// Decrements the in-flight count when the request finishes (or is cancelled)
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn is_exempt(path: &str) -> bool {
+    matches!(path, "/api/health" | "/livez" | "/readyz") || path.starts_with("/api/admin/")
+}
+
+fn is_upload(path: &str) -> bool {
+    path == "/api/upload" || path == "/api/raw" || path.starts_with("/api/tus")
+}
+
+/// Load shedding middleware (no-op unless LOAD_SHED_ENABLED)
+pub async fn load_shed(
+    State(shedder): State<Arc<LoadShedder>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let (enabled, target_us, queue_depth) = {
+        let config = shedder.config.load();
+        (
+            config.load_shed_enabled,
+            config.load_shed_target_latency.as_micros() as u64,
+            config.load_shed_queue_depth,
+        )
+    };
+    let path = request.uri().path();
+    if !enabled || is_exempt(path) || is_upload(path) {
+        return next.run(request).await;
+    }
+
+    let in_flight = shedder.in_flight.fetch_add(1, Ordering::Relaxed);
+    let _guard = InFlight(&shedder.in_flight);
+
+    if shedder.should_shed(in_flight, target_us, queue_depth) {
+        tracing::debug!(
+            "Shedding request: average latency {}ms, {} in flight",
+            shedder.latency_us.load(Ordering::Relaxed) / 1000,
+            in_flight
+        );
+        let mut response =
+            AppError::ServiceUnavailable("Server is under heavy load, try again shortly".to_string())
+                .into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+        return response;
+    }
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    shedder.record_latency(started.elapsed().as_micros() as u64);
+    response
+}
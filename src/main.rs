@@ -1,194 +1,92 @@
-use axum::{
-    routing::{get, post, delete},
-    Router,
-    response::{Html, IntoResponse},
-    http::StatusCode,
-    extract::DefaultBodyLimit,
-    middleware as axum_middleware,
-};
-use std::net::SocketAddr;
-use tower_http::trace::TraceLayer;
-use tower_http::services::ServeDir;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
-use tower_governor::{
-    governor::GovernorConfigBuilder,
-    GovernorLayer,
-};
+//! The `dogbox` server binary: command-line handling around `dogbox_core`
 
-mod cleanup;
-mod config;
-mod constants;
-mod database;
-mod error;
-mod handlers;
-mod middleware;
-mod models;
-mod services;
+use clap::Parser;
+use dogbox_core::{backup, check, cleanup, graphql, handlers, telemetry};
+use dogbox_core::{AppState, Config, Database};
 
-use config::Config;
-use constants::{MAX_UPLOAD_SIZE, DOGBOX_EMOJI};
-use database::Database;
+mod cli;
 
-async fn serve_index() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/index.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
-    }
-}
-
-async fn serve_download() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/download.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
-    }
-}
+use cli::{Cli, Command};
 
-async fn serve_faq() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/faq.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
-    }
-}
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    dotenvy::dotenv().ok();
 
-async fn serve_post_types() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/post-types.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
-    }
-}
+    // Initialize tracing (the OTLP layer is filled in once the config is loaded)
+    let otel_handle = telemetry::init_logging(telemetry::LogFormat::from_env()?);
 
-async fn serve_stats() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/stats.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
+    // Load configuration
+    let config_file = cli.config_file();
+    if cli.check_config {
+        return check::run(config_file.as_deref()).await;
     }
-}
-
-async fn serve_prohibited_uploads() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/prohibited-uploads.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
+    if let Some(path) = &config_file {
+        tracing::info!("⚙️  Loading config from {}", path.display());
     }
-}
+    let config = Config::load(config_file.as_deref())?;
+    let tracer_provider = telemetry::init(&config, &otel_handle)?;
+    let _sentry = telemetry::init_sentry(&config);
 
-async fn serve_dogpaste() -> impl IntoResponse {
-    match tokio::fs::read_to_string("static/dogpaste.html").await {
-        Ok(content) => Html(content).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response(),
-    }
-}
+    let result = run(cli.command.unwrap_or(Command::Serve), config).await;
 
-async fn serve_favicon() -> impl IntoResponse {
-    match tokio::fs::read("static/favicon.ico").await {
-        Ok(content) => (
-            StatusCode::OK,
-            [(axum::http::header::CONTENT_TYPE, "image/x-icon")],
-            content
-        ).into_response(),
-        Err(_) => (StatusCode::NOT_FOUND, "Favicon not found").into_response(),
+    // Flush spans still waiting in the batch exporter
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Failed to flush traces: {}", e);
+        }
     }
+    result
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "dogbox=debug,tower_http=debug,axum=trace".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    // Load configuration
-    dotenvy::dotenv().ok();
-    let config = Config::from_env()?;
-
-    // Initialize database and run migrations
-    let db = Database::new(&config.database_url).await?;
-    db.migrate().await?;
-
-    // Create upload directory
-    tokio::fs::create_dir_all(&config.upload_dir).await?;
-
-    // Store port before moving config
-    let port = config.port;
-
-    // Clone config for cleanup task
-    let cleanup_config = config.clone();
-
-    // Build application state
-    let app_state = std::sync::Arc::new(config);
-
-    // Start background cleanup task
-    tokio::spawn(async move {
-        if let Err(e) = cleanup::start_cleanup_task(cleanup_config).await {
-            tracing::error!("Cleanup task failed: {}", e);
+/// Run the selected subcommand
+async fn run(command: Command, config: Config) -> anyhow::Result<()> {
+    match command {
+        Command::Serve => dogbox_core::serve(config).await,
+        Command::Migrate => {
+            let db = Database::new(&config.database_url, &config.sqlite).await?;
+            db.migrate().await
         }
-    });
-
-    // SECURITY: Rate limiting - Very permissive to allow normal usage
-    // 100 req/min = ~1.67 req/sec, with burst of 100 for page loads with many assets
-    let governor_conf = GovernorConfigBuilder::default()
-        .per_second(2) // ~2 requests per second (120 per minute, slightly over target)
-        .burst_size(100) // Allow burst of 100 requests for initial page load with many JS modules
-        .finish()
-        .ok_or_else(|| anyhow::anyhow!("Failed to build rate limit config"))?;
-
-    let rate_limit_layer = GovernorLayer {
-        config: std::sync::Arc::new(governor_conf),
-    };
-
-    // Build router
-    let app = Router::new()
-        // Frontend routes
-        .route("/", get(serve_index))
-        .route("/favicon.ico", get(serve_favicon))
-        .route("/f/:id", get(serve_download))
-        .route("/p/:id", get(serve_download))
-        .route("/dogpaste", get(serve_dogpaste))
-        .route("/faq", get(serve_faq))
-        .route("/post-types", get(serve_post_types))
-        .route("/prohibited-uploads", get(serve_prohibited_uploads))
-        .route("/stats", get(serve_stats))
-        // API routes
-        .route("/api/health", get(handlers::health))
-        .route("/api/admin-motd", get(handlers::admin_motd))
-        .route("/api/stats", get(handlers::stats))
-        .route("/api/upload", post(handlers::upload))
-        .route("/api/files/:id", get(handlers::download))
-        .route("/api/files/:id", delete(handlers::delete_file))
-        .route("/api/posts/:id", get(handlers::view_post))
-        .route("/api/posts/:id/append", post(handlers::append_to_post))
-        .route("/api/dogpaste", post(handlers::dogpaste_create))
-        .route("/api/dogpaste/:id", get(handlers::dogpaste_view))
-        // Static files
-        .nest_service("/static", ServeDir::new("static"))
-        // API docs
-        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", handlers::ApiDoc::openapi()))
-        // SECURITY: Middleware layers (order matters - applied bottom to top)
-        .layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE))
-        .layer(TraceLayer::new_for_http())
-        .layer(axum_middleware::from_fn(middleware::security_headers))
-        .layer(axum_middleware::from_fn(middleware::csrf_protection))
-        .layer(rate_limit_layer)
-        .with_state(app_state);
-
-    // Start server
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    tracing::info!("{} dogbox.moe listening on {}", DOGBOX_EMOJI, addr);
-    tracing::info!("📖 API docs available at http://{}/docs", addr);
-
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-
-    // IMPORTANT: Use into_make_service_with_connect_info to provide SocketAddr
-    // for rate limiting middleware (GovernorLayer needs peer IP)
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>()
-    ).await?;
-
-    Ok(())
+        Command::Cleanup { once } => {
+            let db = Database::new(&config.database_url, &config.sqlite).await?;
+            let app_state = AppState::new(config, db)?;
+            if once {
+                let report = app_state.file_service.cleanup_expired().await?;
+                cleanup::log_report(&report);
+                Ok(())
+            } else {
+                cleanup::start_cleanup_task(app_state).await
+            }
+        }
+        Command::Stats => {
+            let db = Database::new(&config.database_url, &config.sqlite).await?;
+            let stats = handlers::gather_stats(&db, config.analytics_enabled).await?;
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            Ok(())
+        }
+        Command::GraphqlSchema => {
+            print!("{}", graphql::sdl());
+            Ok(())
+        }
+        Command::Backup { out } => {
+            let manifest = backup::create(&config, &out).await?;
+            tracing::info!(
+                "💾 Backed up database and {} blobs to {} ({} missing)",
+                manifest.blobs.len(),
+                out.display(),
+                manifest.missing_blobs.len()
+            );
+            Ok(())
+        }
+        Command::Restore { archive } => {
+            let summary = backup::restore(&config, &archive).await?;
+            tracing::info!(
+                "💾 Restored backup from {} with {} blobs ({} files without a blob dropped)",
+                summary.backup_created_at,
+                summary.blobs,
+                summary.dropped_files
+            );
+            Ok(())
+        }
+    }
 }
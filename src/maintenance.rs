@@ -0,0 +1,37 @@
+//! Periodic SQLite maintenance
+//!
+//! SQLite never gives pages back to the filesystem on its own, so after cleanup
+//! passes or test-mode wipes the file stays at its high-water mark. This job
+//! reclaims free pages and refreshes the query planner's statistics.
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::{AuditActor, MaintenanceReport};
+use std::time::Duration;
+use tokio::time;
+
+/// Run one maintenance pass and audit it
+pub async fn run(db: &Database, actor: AuditActor) -> Result<MaintenanceReport> {
+    let report = db.run_maintenance().await?;
+    tracing::info!(
+        "🧽 Database maintenance: {} KB reclaimed in {} ms{}",
+        report.bytes_reclaimed / 1024,
+        report.duration_ms,
+        if report.full_vacuum { " (full VACUUM)" } else { "" }
+    );
+    let details = format!("{} bytes reclaimed", report.bytes_reclaimed);
+    db.audit(actor, "db.maintenance", None, Some(&details)).await;
+    Ok(report)
+}
+
+/// Background task running maintenance every `every` (first run one period after startup)
+pub async fn start_maintenance_task(db: Database, every: Duration) {
+    let mut interval = time::interval_at(time::Instant::now() + every, every);
+    tracing::info!("🧽 Database maintenance every {} hours", every.as_secs() / 3600);
+    loop {
+        interval.tick().await;
+        if let Err(e) = run(&db, AuditActor::System).await {
+            tracing::error!("❌ Database maintenance failed: {}", e);
+        }
+    }
+}
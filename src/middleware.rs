@@ -1,12 +1,20 @@
+use crate::config::{PrivateMode, TorExitPolicy};
+use crate::error::AppError;
+use crate::models::BanScope;
+use crate::state::AppState;
 use axum::{
     body::Body,
-    http::{Request, Response, StatusCode, header},
+    extract::{MatchedPath, State},
+    http::{HeaderMap, Request, Response, StatusCode, header},
     middleware::Next,
+    response::IntoResponse,
 };
+use subtle::ConstantTimeEq;
 
 /// Security headers middleware
 /// Adds essential security headers to all responses
 pub async fn security_headers(
+    State(state): State<AppState>,
     request: Request<Body>,
     next: Next,
 ) -> Result<Response<Body>, StatusCode> {
@@ -39,27 +47,13 @@ pub async fn security_headers(
     );
 
     // Content Security Policy - restrict resource loading
-    // Script hashes allow specific inline scripts (import maps and initialization)
-    // 'wasm-unsafe-eval' required for BLAKE3 WASM compilation
-    // To regenerate hashes: just hash-scripts
-    headers.insert(
-        header::HeaderName::from_static("content-security-policy"),
-        header::HeaderValue::from_static(
-            "default-src 'self'; \
-             script-src 'self' 'wasm-unsafe-eval' \
-               'sha256-HUvE11OLFz7AoCbhbk01ZWOcwLvkI+CahEfFg54mSTU=' \
-               'sha256-SGWGvJu8HcqulHmTV7/WfP/TjcWCtNH40zGJbhmurLQ=' \
-               'sha256-UwIxe9p9b2FNZcGBE29ru4ohO+xC1LiOPTC/1s6DRDI=' \
-               'sha256-dOFOu+c3tOHIxiHjp4NQ7kBAJNPVqIV2C0nsVeEtLZU=' \
-               'sha256-gXFFdg/UCt0MfJH9IbdwGFsCxpsE4aa9D0vNkYyzRcA='; \
-             style-src 'self' 'unsafe-inline'; \
-             img-src 'self' data: blob:; \
-             media-src 'self' blob:; \
-             font-src 'self' data:; \
-             connect-src 'self'; \
-             frame-ancestors 'none';"
-        ),
-    );
+    // Built from config at startup (see Config::content_security_policy); handlers that
+    // set a stricter policy themselves (inline downloads) keep theirs
+    if !headers.contains_key(header::CONTENT_SECURITY_POLICY) {
+        if let Ok(csp) = header::HeaderValue::from_str(&state.config.load().content_security_policy) {
+            headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+        }
+    }
 
     // Referrer policy
     headers.insert(
@@ -114,7 +108,10 @@ pub async fn csrf_protection(
 
         // For API requests, also check for X-Requested-With or Content-Type: application/json
         // This prevents simple form submissions from browsers
-        let has_custom_header = headers.get("x-requested-with").is_some();
+        // (tus clients always send Tus-Resumable, which likewise forces a CORS preflight)
+        let has_custom_header = headers.get("x-requested-with").is_some()
+            || headers.get("tus-resumable").is_some()
+            || headers.get(header::AUTHORIZATION).is_some();
         let content_type = headers.get(header::CONTENT_TYPE)
             .and_then(|v| v.to_str().ok())
             .unwrap_or("");
@@ -124,9 +121,13 @@ pub async fn csrf_protection(
         // Allow if: custom header present, JSON content type, or multipart (for file uploads)
         if !has_custom_header && !is_json && !is_multipart {
             // For upload endpoint, we allow multipart without custom headers
+            // Raw PUT uploads are for curl; browsers can't send a cross-origin PUT without a preflight
             // For other endpoints, require JSON or custom header
             let path = request.uri().path();
-            if !path.starts_with("/api/upload") {
+            let is_raw_put = method == "PUT" && path == "/api/raw";
+            // WebDAV clients send neither; cross-origin PUT and DELETE need a preflight anyway
+            let is_dav = (method == "PUT" || method == "DELETE") && crate::webdav::is_dav_path(path);
+            if !path.starts_with("/api/upload") && !is_raw_put && !is_dav {
                 tracing::warn!("CSRF: Blocked request without custom header or JSON content type");
                 return Err(StatusCode::FORBIDDEN);
             }
@@ -135,3 +136,520 @@ pub async fn csrf_protection(
 
     Ok(next.run(request).await)
 }
+
+/// Admin authentication middleware
+/// Requires `Authorization: Bearer <ADMIN_TOKEN>`; the admin API is hidden (404) when no token is configured
+pub async fn admin_auth(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response<Body>, StatusCode> {
+    let config = state.config.load_full();
+    let Some(admin_token) = config.admin_token.as_deref() else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    // Constant-time comparison to prevent timing attacks
+    if !bool::from(presented.as_bytes().ct_eq(admin_token.as_bytes())) {
+        tracing::warn!("Rejected admin API request with invalid token");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Whether a request creates new content (what PRIVATE_MODE=uploads protects)
+fn is_upload_request(method: &axum::http::Method, path: &str) -> bool {
+    use axum::http::Method;
+
+    path.starts_with("/api/tus")
+        || (method == Method::PUT && (path == "/api/raw" || crate::webdav::is_dav_path(path) || crate::s3::is_s3_path(path)))
+        || (method == Method::POST
+            && (matches!(path, "/api/upload" | "/api/dogpaste" | "/api/collections")
+                || (path.starts_with("/api/posts/") && path.ends_with("/append"))))
+}
+
+/// Who is uploading, for the upload policies
+pub struct Uploader<'a> {
+    pub ip: Option<std::net::IpAddr>,
+    pub headers: &'a HeaderMap,
+    /// Presented a trusted API key
+    pub trusted: bool,
+    /// Presented an upload token
+    pub upload_token: bool,
+}
+
+impl<'a> Uploader<'a> {
+    fn of(request: &'a Request<Body>, state: &AppState) -> Self {
+        let trusted_proxies = state.config.load().trusted_proxies.clone();
+        Self {
+            ip: crate::client_ip::from_request(request, &trusted_proxies),
+            headers: request.headers(),
+            trusted: request.extensions().get::<TrustedClient>().is_some(),
+            upload_token: request.extensions().get::<crate::models::UploadToken>().is_some(),
+        }
+    }
+}
+
+/// Every upload policy (bans, GeoIP, Tor exits, captcha, private mode) at once
+///
+/// The middlewares below apply them to upload routes; uploads that don't arrive at
+/// one, such as GraphQL's `appendToPost`, are checked with this instead.
+pub async fn check_upload_policies(state: &AppState, uploader: &Uploader<'_>) -> Result<(), AppError> {
+    if let Some(ip) = uploader.ip {
+        if state.bans.scope_for(ip).is_some() {
+            tracing::warn!("Rejected upload from banned address {}", ip);
+            return Err(network_banned());
+        }
+        check_country(state, ip)?;
+    }
+    check_tor_exit(state, uploader, false)?;
+    check_captcha(state, uploader).await?;
+    check_access_key(state, uploader).await
+}
+
+fn network_banned() -> AppError {
+    AppError::Forbidden("Your network has been banned from this instance".to_string())
+}
+
+/// IP / CIDR bans: reject uploads, or every request, from banned networks with 403
+///
+/// The admin API stays reachable so an operator can lift a ban that caught them.
+pub async fn banned_networks(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let trusted_proxies = state.config.load().trusted_proxies.clone();
+    let Some(ip) = crate::client_ip::from_request(&request, &trusted_proxies) else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    let banned = match state.bans.scope_for(ip) {
+        None => false,
+        Some(BanScope::Uploads) => is_upload_request(request.method(), path),
+        Some(BanScope::All) => !path.starts_with("/api/admin/"),
+    };
+    if banned {
+        tracing::warn!("Rejected {} {} from banned address {}", request.method(), path, ip);
+        return network_banned().into_response();
+    }
+    next.run(request).await
+}
+
+/// GeoIP country policy: reject uploads from blocked (or not allowed) countries with 403
+pub async fn country_policy(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    if state.geoip.is_none() || !is_upload_request(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let trusted_proxies = state.config.load().trusted_proxies.clone();
+    if let Some(ip) = crate::client_ip::from_request(&request, &trusted_proxies) {
+        if let Err(e) = check_country(&state, ip) {
+            return e.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+fn check_country(state: &AppState, ip: std::net::IpAddr) -> Result<(), AppError> {
+    let Some(geoip) = &state.geoip else {
+        return Ok(());
+    };
+    if !geoip.allows_uploads(ip) {
+        tracing::warn!("Rejected upload from {} ({})", ip, geoip.country(ip).as_deref().unwrap_or("unknown country"));
+        return Err(AppError::Forbidden("Uploads are not available in your region".to_string()));
+    }
+    Ok(())
+}
+
+/// TOR_EXIT_POLICY: reject uploads from Tor exits, or make them solve a proof-of-work
+///
+/// Reads over Tor are never affected. Resumable upload chunks continue an upload whose
+/// creation was already checked, so only the creation needs a solution.
+pub async fn tor_exit_policy(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let method = request.method();
+    let path = request.uri().path();
+    if !is_upload_request(method, path) {
+        return next.run(request).await;
+    }
+    let continues_upload = path.starts_with("/api/tus/") || *method == axum::http::Method::OPTIONS;
+    let checked = check_tor_exit(&state, &Uploader::of(&request, &state), continues_upload);
+    match checked {
+        Ok(()) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+fn check_tor_exit(state: &AppState, uploader: &Uploader<'_>, continues_upload: bool) -> Result<(), AppError> {
+    let Some(ip) = uploader.ip.filter(|_| !uploader.trusted) else {
+        return Ok(());
+    };
+    if !state.tor_exits.contains(ip) {
+        return Ok(());
+    }
+
+    let config = state.config.load();
+    match config.tor_exit_policy {
+        TorExitPolicy::Allow => Ok(()),
+        TorExitPolicy::BlockUploads => {
+            tracing::warn!("Rejected upload from Tor exit {}", ip);
+            Err(AppError::Forbidden("Uploads over Tor are not accepted on this instance".to_string()))
+        }
+        TorExitPolicy::Pow => {
+            let solved = uploader
+                .headers
+                .get("x-pow-solution")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|solution| state.pow.verify(solution, config.pow_difficulty));
+            if continues_upload || solved {
+                Ok(())
+            } else {
+                Err(AppError::PreconditionRequired(
+                    "Uploads over Tor need a solved challenge from /api/pow/challenge in X-Pow-Solution".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// CAPTCHA_PROVIDER: uploads need an `X-Captcha-Token` the provider accepts
+///
+/// Clients with an API key or upload token are vouched for already, and resumable
+/// upload chunks continue an upload whose creation was checked.
+pub async fn captcha(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    if state.captcha.is_none() {
+        return next.run(request).await;
+    }
+    let path = request.uri().path();
+    let exempt = !is_upload_request(request.method(), path)
+        || path.starts_with("/api/tus/")
+        || request.method() == axum::http::Method::OPTIONS;
+    if exempt {
+        return next.run(request).await;
+    }
+
+    let checked = check_captcha(&state, &Uploader::of(&request, &state)).await;
+    match checked {
+        Ok(()) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn check_captcha(state: &AppState, uploader: &Uploader<'_>) -> Result<(), AppError> {
+    let Some(captcha) = &state.captcha else {
+        return Ok(());
+    };
+    if uploader.trusted || uploader.upload_token {
+        return Ok(());
+    }
+
+    let token = uploader
+        .headers
+        .get("x-captcha-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if token.is_empty() {
+        return Err(AppError::Forbidden("Captcha required".to_string()));
+    }
+    match captcha.verify(token, uploader.ip).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(AppError::Forbidden("Captcha verification failed".to_string())),
+        Err(e) => {
+            tracing::error!("❌ Captcha verification request failed: {}", e);
+            Err(AppError::ServiceUnavailable("Captcha verification is unavailable, try again later".to_string()))
+        }
+    }
+}
+
+/// Private instance mode: require `X-Access-Key` for uploads, or for the whole API
+///
+/// The frontend pages, health check, MOTD and admin API (which has its own token)
+/// stay reachable so the UI can load and ask for a key.
+pub async fn private_access(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let private_mode = state.config.load().private_mode;
+    let path = request.uri().path();
+    let needs_key = match private_mode {
+        PrivateMode::Off => false,
+        PrivateMode::Uploads => is_upload_request(request.method(), path),
+        PrivateMode::All => {
+            path.starts_with("/api/")
+                && !matches!(path, "/api/health" | "/api/admin-motd")
+                && !path.starts_with("/api/admin/")
+        }
+    };
+    if !needs_key {
+        return next.run(request).await;
+    }
+
+    let checked = require_access_key(&state, &Uploader::of(&request, &state)).await;
+    match checked {
+        Ok(()) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// PRIVATE_MODE's access key, for an upload that didn't come through `private_access`
+async fn check_access_key(state: &AppState, uploader: &Uploader<'_>) -> Result<(), AppError> {
+    if state.config.load().private_mode == PrivateMode::Off {
+        return Ok(());
+    }
+    require_access_key(state, uploader).await
+}
+
+async fn require_access_key(state: &AppState, uploader: &Uploader<'_>) -> Result<(), AppError> {
+    // API keys and upload tokens are issued by the admin too, so they open private instances as well
+    if uploader.trusted || uploader.upload_token {
+        return Ok(());
+    }
+
+    let presented = uploader
+        .headers
+        .get("x-access-key")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if presented.is_empty() {
+        return Err(AppError::Unauthorized("Access key required".to_string()));
+    }
+
+    // Keys are looked up by hash, so the comparison leaks nothing about stored keys
+    let key_hash = blake3::hash(presented.as_bytes()).to_hex().to_string();
+    if !state.db.access_key_exists(&key_hash).await? {
+        tracing::warn!("Rejected request with invalid access key");
+        return Err(AppError::Unauthorized("Invalid access key".to_string()));
+    }
+    Ok(())
+}
+
+/// Request extension marking a client that presented a valid trusted API key
+#[derive(Debug, Clone)]
+pub struct TrustedClient {
+    /// ID of the key (WebDAV paths are namespaced by it)
+    pub key_id: String,
+}
+
+/// Request extension marking credentials that failed verification
+///
+/// Such requests still pass through the rate limiter, so failed attempts count
+/// against the client's bucket, before `reject_credentials` answers them.
+#[derive(Debug, Clone)]
+pub enum RejectedCredentials {
+    ApiKey,
+    S3(crate::s3::S3Error),
+}
+
+/// Trusted API keys: verify `X-Api-Key` and mark the request as trusted
+///
+/// Runs outside the rate limiter so trusted clients can skip it. A key that is
+/// presented but unknown is rejected rather than silently downgraded, though only
+/// once the limiter has counted the attempt. WebDAV clients can only do HTTP
+/// Basic, so under /dav/ the key may also be given as the Basic password (the
+/// username is ignored). S3 requests are signed with the key's S3 credentials
+/// instead.
+pub async fn api_key(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let is_s3 = crate::s3::is_s3_path(request.uri().path());
+    if is_s3 && state.config.load().s3_api_enabled && !request.headers().contains_key("x-api-key") {
+        match crate::s3::authenticate(&state, request.method(), request.uri(), request.headers()).await {
+            Ok(Some(auth)) => {
+                tracing::debug!("S3 request signed by API key {}", auth.key_id);
+                request.extensions_mut().insert(TrustedClient { key_id: auth.key_id.clone() });
+                request.extensions_mut().insert(auth);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                request.extensions_mut().insert(RejectedCredentials::S3(e));
+            }
+        }
+        return next.run(request).await;
+    }
+
+    let presented = match request.headers().get("x-api-key") {
+        Some(presented) => presented.as_bytes().to_vec(),
+        None => match basic_password(&request).filter(|_| crate::webdav::is_dav_path(request.uri().path())) {
+            Some(password) => password,
+            None => return next.run(request).await,
+        },
+    };
+    let key_hash = blake3::hash(&presented).to_hex().to_string();
+
+    match state.db.find_api_key(&key_hash).await {
+        Ok(Some(key_id)) => {
+            tracing::debug!("Request authenticated with API key {}", key_id);
+            if let Err(e) = state.db.record_api_key_use(&key_id).await {
+                return e.into_response();
+            }
+            request.extensions_mut().insert(TrustedClient { key_id });
+        }
+        Ok(None) => {
+            tracing::warn!("Rejected request with invalid API key");
+            request.extensions_mut().insert(RejectedCredentials::ApiKey);
+        }
+        Err(e) => return e.into_response(),
+    }
+    next.run(request).await
+}
+
+/// Answer requests whose credentials `api_key` rejected (inside the rate limiter)
+pub async fn reject_credentials(request: Request<Body>, next: Next) -> Response<Body> {
+    match request.extensions().get::<RejectedCredentials>().cloned() {
+        Some(RejectedCredentials::ApiKey) => AppError::Unauthorized("Invalid API key".to_string()).into_response(),
+        Some(RejectedCredentials::S3(e)) => e.into_response(),
+        None => next.run(request).await,
+    }
+}
+
+/// Password of an `Authorization: Basic` header
+fn basic_password(request: &Request<Body>) -> Option<Vec<u8>> {
+    use base64::Engine as _;
+
+    let encoded = request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let colon = decoded.iter().position(|&b| b == b':')?;
+    Some(decoded[colon + 1..].to_vec())
+}
+
+/// Pre-authorized upload tokens: redeem `X-Upload-Token` on single-request uploads
+/// and expiry updates
+///
+/// The redeemed token is added to the request extensions so the handler can apply
+/// its limits. A use is only spent if the request succeeds.
+pub async fn upload_token(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    use axum::http::Method;
+
+    let path = request.uri().path();
+    let redeemable = match *request.method() {
+        Method::POST => path == "/api/upload",
+        Method::PUT => path == "/api/raw",
+        Method::PATCH => path.starts_with("/api/files/") && path.ends_with("/expiry"),
+        _ => false,
+    };
+    let Some(presented) = request.headers().get("x-upload-token").filter(|_| redeemable) else {
+        return next.run(request).await;
+    };
+    let token_hash = blake3::hash(presented.as_bytes()).to_hex().to_string();
+
+    let token = match state.db.consume_upload_token(&token_hash).await {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            tracing::warn!("Rejected upload with invalid, expired or used up upload token");
+            return AppError::Unauthorized("Upload token is invalid, expired or used up".to_string())
+                .into_response();
+        }
+        Err(e) => return e.into_response(),
+    };
+
+    let token_id = token.id.clone();
+    request.extensions_mut().insert(token);
+    let response = next.run(request).await;
+
+    if response.status().is_success() {
+        tracing::info!("Upload made with upload token {}", token_id);
+    } else if let Err(e) = state.db.refund_upload_token(&token_id).await {
+        tracing::error!("Failed to refund upload token {}: {}", token_id, e);
+    }
+    response
+}
+
+/// The request being handled, for error bodies and error reports
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub id: String,
+    pub method: String,
+    /// Matched route pattern (or the raw path if no route matched)
+    pub route: String,
+}
+
+tokio::task_local! {
+    /// Set by [`request_id`] for the lifetime of each request
+    static REQUEST: RequestContext;
+}
+
+/// Longest client-supplied `X-Request-Id` we pass through
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The current request, if called while one is being handled
+pub fn current_request() -> Option<RequestContext> {
+    REQUEST.try_with(Clone::clone).ok()
+}
+
+/// Request IDs: reuse a sane incoming `X-Request-Id` or generate one
+///
+/// The ID is written back onto the request (the trace span picks it up from
+/// there), returned in the response headers and quoted in error bodies.
+pub async fn request_id(mut request: Request<Body>, next: Next) -> Response<Body> {
+    let id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    // Only [A-Za-z0-9._-] gets this far, so the header value is always valid
+    let value = header::HeaderValue::from_str(&id).expect("request ID is a valid header value");
+
+    let context = RequestContext {
+        id,
+        method: request.method().to_string(),
+        route: request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| request.uri().path().to_string()),
+    };
+    request.headers_mut().insert("x-request-id", value.clone());
+    let mut response = REQUEST.scope(context, next.run(request)).await;
+    response.headers_mut().insert("x-request-id", value);
+    response
+}
+
+/// Turn errors from the timeout and concurrency limit layers into JSON responses
+pub async fn handle_overload(err: tower::BoxError) -> AppError {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        AppError::RequestTimeout
+    } else if err.is::<tower::load_shed::error::Overloaded>() {
+        tracing::warn!("Shedding request: concurrency limit reached");
+        AppError::ServiceUnavailable("Server is busy, try again shortly".to_string())
+    } else {
+        AppError::Internal(anyhow::anyhow!(err))
+    }
+}
@@ -1,23 +1,20 @@
+use crate::config::IdScheme;
 use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema, async_graphql::Enum)]
 #[serde(rename_all = "lowercase")]
 pub enum PostType {
+    #[default]
     #[serde(rename = "file")]
     File,  // Classic one-off file upload
     #[serde(rename = "post")]
     Post,  // Appendable post with markdown content
 }
 
-impl Default for PostType {
-    fn default() -> Self {
-        PostType::File
-    }
-}
-
 impl std::fmt::Display for PostType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -38,21 +35,16 @@ impl std::str::FromStr for PostType {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema, async_graphql::Enum)]
 #[serde(rename_all = "lowercase")]
 pub enum PostContentType {
+    #[default]
     #[serde(rename = "markdown")]
     Markdown,  // Text/markdown content
     #[serde(rename = "file")]
     File,      // Encrypted file attachment
 }
 
-impl Default for PostContentType {
-    fn default() -> Self {
-        PostContentType::Markdown
-    }
-}
-
 impl std::fmt::Display for PostContentType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -90,6 +82,35 @@ pub struct FileRecord {
     pub is_permanent: bool,
     pub view_count: i64,
     pub file_extension: Option<String>,
+    pub downloads_remaining: Option<i64>,  // None = unlimited
+    pub slug: Option<String>,  // Custom post slug, resolves alongside the ID
+}
+
+/// Row removed from `files`, with what's needed to release its blob
+#[derive(Debug, sqlx::FromRow)]
+pub struct DeletedFile {
+    pub id: String,
+    pub storage_path: String,
+    pub post_type: String,
+    pub size_bytes: i64,
+    pub downloads_remaining: Option<i64>,
+}
+
+impl DeletedFile {
+    /// Size of the blob still in storage, if any (posts have none, exhausted files lost theirs)
+    pub fn blob_bytes(&self) -> Option<i64> {
+        (self.post_type == PostType::File.to_string() && self.downloads_remaining != Some(0))
+            .then_some(self.size_bytes)
+    }
+}
+
+/// File row whose encrypted blob is still in storage (backup listing)
+#[derive(Debug, sqlx::FromRow)]
+pub struct StoredBlob {
+    pub id: String,
+    pub storage_path: String,
+    pub size_bytes: i64,
+    pub blake3_hash: String,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -121,10 +142,14 @@ pub struct UploadResponse {
 
     /// Whether this upload is permanent
     pub is_permanent: bool,
+
+    /// Downloads left before the file is deleted (null if unlimited)
+    pub downloads_remaining: Option<i64>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct HealthResponse {
+    /// "ok", or "degraded" when one of the checks failed
     pub status: String,
     pub version: String,
     pub test_mode: bool,
@@ -132,8 +157,109 @@ pub struct HealthResponse {
     pub next_test_delete: Option<DateTime<Utc>>,
     /// Optional admin message to display
     pub admin_message: Option<String>,
-    /// Maximum upload size in bytes
+    /// Maximum file upload size in bytes
     pub max_upload_size: usize,
+    /// Maximum initial post content size in bytes
+    pub max_post_size: u64,
+    /// Maximum size of one entry appended to a post, in bytes
+    pub max_post_entry_size: u64,
+    /// Maximum dogpaste size in bytes
+    pub max_dogpaste_size: u64,
+    pub checks: HealthChecks,
+}
+
+/// Subsystem status, so monitoring can alert on degradation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthChecks {
+    pub database: DatabaseHealth,
+    pub disk: DiskHealth,
+    pub cleanup: CleanupHealth,
+    pub storage: StorageHealth,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DatabaseHealth {
+    pub ok: bool,
+    /// Round trip of a trivial query (null when it failed)
+    pub latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DiskHealth {
+    /// False when available space is below MIN_FREE_DISK_BYTES or can't be read
+    pub ok: bool,
+    /// Space left for uploads on the upload directory's filesystem
+    pub available_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CleanupHealth {
+    /// False when expired files haven't been cleaned up for two intervals (plus jitter)
+    pub ok: bool,
+    /// When the last cleanup run finished successfully
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StorageHealth {
+    pub ok: bool,
+    /// "local" or "s3"
+    pub backend: String,
+}
+
+/// What one expired-content cleanup run removed
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct CleanupReport {
+    /// Expired files, posts and dogpastes deleted
+    pub expired: u64,
+    /// Abandoned resumable uploads discarded
+    pub upload_sessions: u64,
+    /// Blob and partial upload bytes freed in storage
+    pub bytes_reclaimed: u64,
+}
+
+/// What one orphan collection run removed
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct OrphanReport {
+    /// Blobs in storage that no file refers to
+    pub blobs: u64,
+    /// Staged uploads left behind by interrupted requests
+    pub stale_uploads: u64,
+    pub bytes_reclaimed: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminCleanupResponse {
+    pub expired: CleanupReport,
+    pub orphans: OrphanReport,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// Whether every check passed
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessCheck {
+    /// database, migrations or upload_dir
+    pub name: String,
+    pub ok: bool,
+    /// Why the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReadinessCheck {
+    pub fn new(name: &str, result: std::result::Result<(), String>) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -142,21 +268,145 @@ pub struct DeleteResponse {
     pub message: String,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateExpiryRequest {
+    /// Token returned at upload time
+    pub deletion_token: String,
+
+    /// Hours from now until expiry (clamped to the server maximum; 0 expires immediately)
+    #[schema(example = 48)]
+    pub expiry_hours: i64,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
+pub struct UpdateExpiryResponse {
+    pub file_id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExpiryWarningRequest {
+    /// Token returned at upload time
+    pub deletion_token: String,
+
+    /// HTTPS URL to POST to when the warning is due (omit to only poll for it;
+    /// needs EXPIRY_CALLBACKS_ENABLED on the server)
+    pub callback_url: Option<String>,
+
+    /// Hours before expiry that the warning becomes due
+    #[schema(example = 24)]
+    pub hours_before: i64,
+}
+
+/// Registered expiry warning (stored row)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ExpiryWarning {
+    pub file_id: String,
+    pub callback_url: Option<String>,
+    pub hours_before: i64,
+    pub notified_at: Option<i64>,
+}
+
+/// Expiry warning whose callback is due, joined with its file
+#[derive(Debug, sqlx::FromRow)]
+pub struct DueExpiryWarning {
+    pub file_id: String,
+    pub callback_url: String,
+    pub post_type: String,
+    pub size_bytes: i64,
+    /// Unix timestamp
+    pub expires_at: i64,
+    pub deletion_token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpiryWarningStatus {
+    pub file_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub is_permanent: bool,
+    /// Registered warning lead time (null when no warning is registered)
+    pub hours_before: Option<i64>,
+    pub callback_url: Option<String>,
+    /// The file is within its warning window
+    pub warning_due: bool,
+    /// Unix timestamp the callback was sent
+    pub notified_at: Option<i64>,
+}
+
+/// Public statistics (`GET /api/stats`, `dogbox stats`)
+#[derive(Debug, Clone, Serialize, ToSchema, async_graphql::SimpleObject)]
 pub struct StatsResponse {
+    pub uploads: UploadStats,
+    pub posts: PostStats,
+    pub dogpastes: DogpasteStats,
+    pub disk: DiskStats,
+}
+
+/// Stored files and posts together (unexpired)
+#[derive(Debug, Clone, Serialize, ToSchema, async_graphql::SimpleObject)]
+pub struct UploadStats {
+    /// Files and posts
+    pub total: i64,
+    pub files: i64,
+    pub permanent: i64,
+    pub temporary: i64,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub views: Option<i64>,
+    pub storage_mb: f64,
+    /// Files by declared extension (top 20)
+    pub file_extensions: std::collections::HashMap<String, i64>,
+    /// Files evicted to make room under the storage quota (STORAGE_FULL_POLICY=evict)
+    pub evicted_files: i64,
+}
+
+/// Unexpired posts
+#[derive(Debug, Clone, Serialize, ToSchema, async_graphql::SimpleObject)]
+pub struct PostStats {
+    pub count: i64,
+    /// Entries across all posts, initial content included
+    pub entries: i64,
+    pub storage_mb: f64,
+}
+
+/// Unexpired dogpastes
+#[derive(Debug, Clone, Serialize, ToSchema, async_graphql::SimpleObject)]
+pub struct DogpasteStats {
+    pub count: i64,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub views: Option<i64>,
+    pub storage_mb: f64,
+}
+
+/// Filesystem holding the server's root directory
+#[derive(Debug, Clone, Serialize, ToSchema, async_graphql::SimpleObject)]
+pub struct DiskStats {
+    pub total_gb: f64,
+    pub used_gb: f64,
+    pub free_gb: f64,
+}
+
+/// Totals at one point in time (recorded hourly)
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct StatsSnapshot {
+    /// Unix timestamp
+    pub created_at: i64,
     pub total_uploads: i64,
     pub total_posts: i64,
     pub total_files: i64,
     pub total_dogpastes: i64,
-    pub permanent_count: i64,
-    pub temporary_count: i64,
-    pub total_views: i64,
-    pub dogpaste_views: i64,
-    pub storage_mb: f64,
-    pub disk_total_gb: f64,
-    pub disk_used_gb: f64,
-    pub disk_free_gb: f64,
-    pub file_extensions: std::collections::HashMap<String, i64>,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_views: Option<i64>,
+    pub storage_bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatsHistoryResponse {
+    pub days: i64,
+    /// Oldest first
+    pub snapshots: Vec<StatsSnapshot>,
 }
 
 
@@ -180,7 +430,8 @@ impl PostContent {
     }
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Deserialize, ToSchema, async_graphql::InputObject)]
+#[graphql(name = "AppendInput")]
 pub struct AppendRequest {
     /// Key that allows appending to this post
     pub append_key: String,
@@ -209,19 +460,78 @@ pub struct AppendResponse {
     pub content_order: i64,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LockPostRequest {
+    /// Current append key (discarded once the post is locked)
+    pub append_key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LockPostResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Authorize with either the current append key or the deletion token
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RotateAppendKeyRequest {
+    pub append_key: Option<String>,
+    pub deletion_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RotateAppendKeyResponse {
+    /// New append key (the previous one stops working immediately)
+    pub post_append_key: String,
+}
+
+/// Collaborator append key (the key itself is only revealed when issued)
+#[derive(Debug, Clone, Serialize, ToSchema, sqlx::FromRow)]
+pub struct PostKey {
+    /// Key ID (used to revoke the key)
+    pub id: String,
+    #[serde(skip)]
+    pub post_id: String,
+    pub label: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePostKeyRequest {
+    /// The post's master append key
+    pub append_key: String,
+    /// Optional note for telling keys apart (stored in plaintext)
+    pub label: Option<String>,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
+pub struct CreatePostKeyResponse {
+    pub key_id: String,
+    /// Append key to hand to the collaborator
+    pub append_key: String,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema, async_graphql::SimpleObject)]
 pub struct PostViewResponse {
     pub post_id: String,
     pub post_type: PostType,
     pub is_permanent: bool,
     pub expires_at: Option<DateTime<Utc>>,
     pub uploaded_at: DateTime<Utc>,
-    pub view_count: i64,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_count: Option<i64>,
+    /// Whether the post is read-only (no further appends possible)
+    pub is_locked: bool,
+    /// Custom slug the post can also be reached by
+    pub slug: Option<String>,
     /// Encrypted content chunks in order (for posts)
     pub content: Vec<PostContentView>,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema, async_graphql::SimpleObject)]
 pub struct PostContentView {
     pub content_encrypted: String,
     pub appended_at: DateTime<Utc>,
@@ -233,6 +543,7 @@ pub struct PostContentView {
 }
 
 impl FileRecord {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         filename_encrypted: Option<String>,
         size_bytes: i64,
@@ -245,7 +556,7 @@ impl FileRecord {
         file_extension: Option<String>,
     ) -> Self {
         let post_append_key = if post_type == PostType::Post {
-            Some(format!("DOGBOX_KEY_APPEND_{}", Uuid::new_v4()))
+            Some(Self::generate_append_key())
         } else {
             None
         };
@@ -266,9 +577,50 @@ impl FileRecord {
             is_permanent,
             view_count: 0,
             file_extension,
+            downloads_remaining: None,
+            slug: None,
         }
     }
 
+    /// New file ID in the configured format
+    pub fn generate_id(scheme: &IdScheme) -> String {
+        match scheme {
+            IdScheme::Uuid => Uuid::new_v4().to_string(),
+            IdScheme::Short { length } => {
+                let charset = crate::constants::DOGPASTE_CHARSET.as_bytes();
+                let mut rng = rand::thread_rng();
+                (0..*length)
+                    .map(|_| charset[rng.gen_range(0..charset.len())] as char)
+                    .collect()
+            }
+        }
+    }
+
+    pub fn generate_append_key() -> String {
+        format!("DOGBOX_KEY_APPEND_{}", Uuid::new_v4())
+    }
+
+    pub fn generate_access_key() -> String {
+        format!("DOGBOX_KEY_ACCESS_{}", Uuid::new_v4())
+    }
+
+    pub fn generate_api_key() -> String {
+        format!("DOGBOX_KEY_API_{}", Uuid::new_v4())
+    }
+
+    /// Secret access key for the S3 API, in the usual 40 character form
+    pub fn generate_s3_secret_key() -> String {
+        rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(40)
+            .map(char::from)
+            .collect()
+    }
+
+    pub fn generate_upload_token() -> String {
+        format!("DOGBOX_KEY_UPLOAD_{}", Uuid::new_v4())
+    }
+
     pub fn get_post_type(&self) -> PostType {
         self.post_type.parse().unwrap_or(PostType::File)
     }
@@ -279,18 +631,26 @@ impl FileRecord {
 pub struct DogpasteCreateRequest {
     pub id: String,
     pub encrypted_data: String,  // Base64-encoded encrypted data
+    /// Hours until the paste expires (defaults to the server default, clamped to the maximum)
+    #[serde(default)]
+    pub expiry_hours: Option<i64>,
+    /// Delete the paste after this many views (unlimited if omitted)
+    #[serde(default)]
+    pub max_views: Option<i64>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct DogpasteCreateResponse {
     pub success: bool,
     pub id: String,
+    pub expires_at: i64,         // Unix timestamp
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct DogpasteViewResponse {
     pub encrypted_data: String,  // Base64-encoded encrypted data
     pub created_at: i64,         // Unix timestamp
+    pub remaining_views: Option<i64>,  // None = unlimited; 0 = this was the last view
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -300,4 +660,509 @@ pub struct DogpasteRecord {
     pub created_at: i64,
     pub expires_at: i64,
     pub views: i64,
+    pub max_views: Option<i64>,
+}
+
+// Resumable (tus) upload session
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UploadSession {
+    pub id: String,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub filename_encrypted: Option<String>,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub expiry_hours: Option<i64>,
+    pub is_permanent: bool,
+    pub max_downloads: Option<i64>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub file_id: Option<String>,
+    pub deletion_token: Option<String>,
+}
+
+impl UploadSession {
+    pub fn is_complete(&self) -> bool {
+        self.file_id.is_some()
+    }
+}
+
+// Admin API models
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct AdminFileSummary {
+    pub id: String,
+    pub post_type: String,
+    pub size_bytes: i64,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub is_permanent: bool,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_count: Option<i64>,
+    pub uploaded_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub blake3_hash: String,
+    /// Unix timestamp a legal hold was placed (null = not quarantined)
+    pub quarantined_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct QuarantineRequest {
+    /// Operator's note, e.g. a case reference (kept until the hold is released)
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminFileListResponse {
+    pub files: Vec<AdminFileSummary>,
+    /// Total number of rows matching the filters (including expired rows not yet cleaned up)
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct AdminStorageResponse {
+    pub file_count: i64,
+    pub file_bytes: i64,
+    pub post_count: i64,
+    pub post_bytes: i64,
+    /// Rows past their expiry that the cleanup task has not removed yet
+    pub expired_count: i64,
+    pub expired_bytes: i64,
+    pub dogpaste_count: i64,
+    pub dogpaste_bytes: i64,
+}
+
+/// Operator-only statistics for capacity planning (`GET /api/admin/stats`)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminStatsResponse {
+    pub top_by_size: Vec<AdminFileSummary>,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_by_views: Option<Vec<AdminFileSummary>>,
+    /// Uploads still stored, by UTC day (deleted and cleaned up rows are not counted)
+    pub uploads_per_day: Vec<DailyUploads>,
+    /// `file`, `post` (all entries) and `dogpaste` (unexpired)
+    pub storage_by_post_type: Vec<PostTypeStorage>,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupSavings>,
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct DailyUploads {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub uploads: i64,
+    pub bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct PostTypeStorage {
+    pub post_type: String,
+    pub count: i64,
+    pub bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct DedupSavings {
+    /// Uploads answered with an already stored file, among files still stored
+    pub deduplicated_uploads: i64,
+    /// Storage those uploads would have taken up
+    pub bytes_saved: i64,
+}
+
+/// Outcome of a database maintenance pass
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MaintenanceReport {
+    /// The database was switched to incremental auto-vacuum with a full VACUUM
+    pub full_vacuum: bool,
+    /// Free pages returned to the filesystem, in bytes
+    pub bytes_reclaimed: i64,
+    pub duration_ms: u64,
+}
+
+// Hash denylist
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct BlockedHash {
+    pub blake3_hash: String,
+    pub reason: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BlockHashRequest {
+    /// BLAKE3 hash of the encrypted blob (64 hex characters)
+    pub blake3_hash: String,
+    /// Optional operator note
+    pub reason: Option<String>,
+}
+
+// Proof-of-work
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PowChallenge {
+    /// Opaque challenge string, solved and sent back as `X-Pow-Solution: <challenge>:<counter>`
+    pub challenge: String,
+    /// Leading zero bits required of SHA-256(`<challenge>:<counter>`)
+    pub difficulty: u32,
+    /// Unix timestamp after which the challenge is no longer accepted
+    pub expires_at: i64,
+}
+
+// IP bans
+/// What a banned network is kept from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BanScope {
+    /// Uploads, dogpastes and new collections
+    #[default]
+    Uploads,
+    /// Every request except the admin API
+    All,
+}
+
+impl std::fmt::Display for BanScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BanScope::Uploads => write!(f, "uploads"),
+            BanScope::All => write!(f, "all"),
+        }
+    }
+}
+
+impl std::str::FromStr for BanScope {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uploads" => Ok(BanScope::Uploads),
+            "all" => Ok(BanScope::All),
+            _ => Err(format!("Invalid ban scope: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct BannedNetwork {
+    pub id: String,
+    /// CIDR, e.g. 203.0.113.0/24
+    pub network: String,
+    /// `uploads` or `all`
+    pub scope: String,
+    pub reason: Option<String>,
+    /// Unix timestamp the ban lapses (null = never)
+    pub expires_at: Option<i64>,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BanNetworkRequest {
+    /// IP address or CIDR range, e.g. 203.0.113.7 or 2001:db8::/32
+    pub network: String,
+    /// Default: uploads
+    pub scope: Option<BanScope>,
+    /// Optional operator note
+    pub reason: Option<String>,
+    /// Hours until the ban lapses (default: never)
+    pub valid_hours: Option<i64>,
+}
+
+// Collection models
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CollectionRecord {
+    pub id: String,
+    pub collection_key: String,
+    pub title_encrypted: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateCollectionRequest {
+    /// Optional client-encrypted title
+    pub title_encrypted: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateCollectionResponse {
+    pub collection_id: String,
+    /// Secret required to add or remove files (keep it private)
+    pub collection_key: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddCollectionFilesRequest {
+    pub collection_key: String,
+    /// Files to append, in display order
+    pub file_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddCollectionFilesResponse {
+    /// Files newly added (IDs already in the collection are skipped)
+    pub added: u64,
+    pub total: i64,
+}
+
+/// Member file metadata (never blob data or keys)
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct CollectionFile {
+    pub file_id: String,
+    pub post_type: String,
+    pub size_bytes: i64,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub is_permanent: bool,
+    pub expires_at: DateTime<Utc>,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_count: Option<i64>,
+    /// Unix timestamp
+    pub added_at: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionResponse {
+    pub collection_id: String,
+    pub title_encrypted: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+    /// Live members in collection order (expired or deleted files drop out)
+    pub files: Vec<CollectionFile>,
+}
+
+// File info models
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FileInfoRequest {
+    /// File or post IDs to look up
+    pub file_ids: Vec<String>,
+}
+
+/// Metadata about a stored file (never blob data)
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow, async_graphql::SimpleObject)]
+pub struct FileInfo {
+    pub file_id: String,
+    pub post_type: String,
+    /// Size of the encrypted blob in bytes
+    pub size_bytes: i64,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub is_permanent: bool,
+    /// When the file will be automatically deleted (null if permanent)
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Omitted when ANALYTICS_ENABLED=false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_count: Option<i64>,
+    /// BLAKE3 hash of the encrypted blob (hex)
+    pub blake3_hash: String,
+    /// Downloads left before the file is deleted (null if unlimited)
+    pub downloads_remaining: Option<i64>,
+}
+
+impl From<FileRecord> for FileInfo {
+    fn from(file: FileRecord) -> Self {
+        Self {
+            expires_at: if file.is_permanent { None } else { Some(file.expires_at) },
+            file_id: file.id,
+            post_type: file.post_type,
+            size_bytes: file.size_bytes,
+            mime_type: file.mime_type,
+            file_extension: file.file_extension,
+            is_permanent: file.is_permanent,
+            view_count: Some(file.view_count),
+            blake3_hash: file.blake3_hash,
+            downloads_remaining: file.downloads_remaining,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FileInfoResponse {
+    /// Found files, in request order
+    pub files: Vec<FileInfo>,
+    /// Requested IDs that don't exist or have expired
+    pub missing: Vec<String>,
+}
+
+/// Result of re-hashing a stored blob
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VerifyResponse {
+    pub file_id: String,
+    /// Whether the stored blob still matches the hash recorded at upload
+    pub valid: bool,
+    pub expected_hash: String,
+    pub actual_hash: String,
+    /// Size of the blob as stored now
+    pub size_bytes: u64,
+}
+
+/// Query-string options for `PUT /api/raw`
+#[derive(Debug, Deserialize)]
+pub struct RawUploadQuery {
+    pub expiry_hours: Option<i64>,
+    #[serde(default)]
+    pub is_permanent: bool,
+    pub max_downloads: Option<i64>,
+    pub file_extension: Option<String>,
+}
+
+// Instance access keys (private mode)
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct AccessKey {
+    /// Key ID (used to revoke the key)
+    pub id: String,
+    pub label: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetAdminMessageRequest {
+    /// New message (alphanumerics, spaces and `,.-'` only); null or empty clears it
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminMessageResponse {
+    pub message: Option<String>,
+    /// Set through the admin API rather than ADMIN_MESSAGE
+    pub from_database: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateAccessKeyRequest {
+    /// Optional note for telling keys apart (stored in plaintext)
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateAccessKeyResponse {
+    pub key_id: String,
+    /// Key to hand out; sent as the X-Access-Key header (only shown once)
+    pub access_key: String,
+    pub label: Option<String>,
+}
+
+/// A file in an API key's WebDAV namespace, with what PROPFIND reports about it
+#[derive(Debug, sqlx::FromRow)]
+pub struct DavEntry {
+    /// Path below /dav/, without a leading slash
+    pub path: String,
+    pub file_id: String,
+    pub size_bytes: i64,
+    pub mime_type: Option<String>,
+    pub blake3_hash: String,
+    /// Unix timestamp of the PUT
+    pub created_at: i64,
+}
+
+// Trusted API keys (operator automation)
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct ApiKey {
+    /// Key ID (used to revoke the key)
+    pub id: String,
+    pub label: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+    /// Unix timestamp of the last request presenting the key
+    pub last_used_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    /// Optional note for telling keys apart (stored in plaintext)
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateApiKeyResponse {
+    pub key_id: String,
+    /// Key for the client; sent as the X-Api-Key header (only shown once)
+    pub api_key: String,
+    /// Secret access key for the S3 API, whose access key ID is `key_id` (only shown once)
+    pub s3_secret_key: String,
+    pub label: Option<String>,
+}
+
+// Pre-authorized upload tokens
+#[derive(Debug, Clone, Serialize, ToSchema, sqlx::FromRow)]
+pub struct UploadToken {
+    /// Token ID (used to revoke the token)
+    pub id: String,
+    pub label: Option<String>,
+    /// Size cap for uploads with this token (null = instance default)
+    pub max_size_bytes: Option<i64>,
+    /// Expiry cap for uploads with this token (null = instance default)
+    pub max_expiry_hours: Option<i64>,
+    /// Uploads left (null = unlimited until expires_at)
+    pub uses_remaining: Option<i64>,
+    /// Unix timestamp after which the token stops working
+    pub expires_at: Option<i64>,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateUploadTokenRequest {
+    /// Optional note for telling tokens apart (stored in plaintext)
+    pub label: Option<String>,
+    /// Number of uploads allowed (default: 1, or unlimited within valid_hours if that is set)
+    pub max_uses: Option<i64>,
+    /// Hours until the token stops working (default: never)
+    pub valid_hours: Option<i64>,
+    /// Size cap for uploads with this token
+    pub max_size_bytes: Option<i64>,
+    /// Expiry cap for uploads with this token
+    pub max_expiry_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateUploadTokenResponse {
+    pub token_id: String,
+    /// Token for the invitee; sent as the X-Upload-Token header (only shown once)
+    pub upload_token: String,
+    pub uses_remaining: Option<i64>,
+    pub expires_at: Option<i64>,
+}
+
+// Audit log
+/// Who performed an audited action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditActor {
+    /// Request authenticated with ADMIN_TOKEN
+    Admin,
+    /// The server itself (cleanup task, eviction, signals)
+    System,
+}
+
+impl AuditActor {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::System => "system",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Unix timestamp
+    pub created_at: i64,
+    /// "admin" or "system"
+    pub actor: String,
+    #[schema(example = "file.delete")]
+    pub action: String,
+    pub target: Option<String>,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditLogResponse {
+    pub entries: Vec<AuditEntry>,
+    /// Entries matching the filters
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
 }
@@ -0,0 +1,214 @@
+//! Server-rendered HTML pages
+//!
+//! Pages are askama templates under `templates/`, compiled into the binary, so
+//! instance-specific values (site name, limits, MOTD) are filled in server-side
+//! instead of being hard-coded in the HTML. Each page is rendered once per
+//! config and served from memory with an ETag after that.
+
+use crate::config::Config;
+use crate::state::AppState;
+use askama::Template;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Values shared by every page
+pub struct Instance {
+    pub site_name: String,
+    /// Human-readable file upload limit, e.g. `5GB`
+    pub max_upload_size: String,
+    /// Human-readable initial post content limit
+    pub max_post_size: String,
+    pub default_expiry_hours: i64,
+    pub max_expiry_hours: i64,
+    pub admin_message: Option<String>,
+    /// Captcha widget on the upload pages (CAPTCHA_PROVIDER)
+    pub captcha: Option<CaptchaWidget>,
+}
+
+pub struct CaptchaWidget {
+    pub script_url: &'static str,
+    pub class: &'static str,
+    pub site_key: String,
+}
+
+impl Instance {
+    fn new(config: &Config) -> Self {
+        Self {
+            site_name: config.site_name.clone(),
+            max_upload_size: format_size(config.max_file_bytes),
+            max_post_size: format_size(config.max_post_bytes),
+            default_expiry_hours: config.default_expiry_hours,
+            max_expiry_hours: config.max_expiry_hours,
+            admin_message: config.admin_message.clone(),
+            captcha: config.captcha.as_ref().map(|captcha| CaptchaWidget {
+                script_url: captcha.provider.script_url(),
+                class: captcha.provider.widget_class(),
+                site_key: captcha.site_key.clone(),
+            }),
+        }
+    }
+}
+
+/// Same rounding as the frontend's `.max-upload-size` labels
+fn format_size(bytes: u64) -> String {
+    const MB: u64 = 1024 * 1024;
+    const GB: u64 = 1024 * MB;
+    if bytes >= GB {
+        format!("{}GB", bytes / GB)
+    } else {
+        format!("{}MB", bytes / MB)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "index.html")]
+pub struct IndexPage {
+    instance: Instance,
+}
+
+#[derive(Template)]
+#[template(path = "download.html")]
+pub struct DownloadPage {
+    instance: Instance,
+}
+
+#[derive(Template)]
+#[template(path = "faq.html")]
+pub struct FaqPage {
+    instance: Instance,
+}
+
+#[derive(Template)]
+#[template(path = "post-types.html")]
+pub struct PostTypesPage {
+    instance: Instance,
+}
+
+#[derive(Template)]
+#[template(path = "stats.html")]
+pub struct StatsPage {
+    instance: Instance,
+}
+
+#[derive(Template)]
+#[template(path = "prohibited-uploads.html")]
+pub struct ProhibitedUploadsPage {
+    instance: Instance,
+}
+
+#[derive(Template)]
+#[template(path = "dogpaste.html")]
+pub struct DogpastePage {
+    instance: Instance,
+}
+
+/// Fragment loaded into every page by `init.js`
+#[derive(Template)]
+#[template(path = "navbar.html")]
+pub struct Navbar {
+    instance: Instance,
+}
+
+/// A rendered page, valid for as long as the config it was rendered from is live
+#[derive(Clone)]
+struct CachedPage {
+    config: Arc<Config>,
+    html: Bytes,
+    etag: HeaderValue,
+}
+
+/// Rendered pages by template name, filled in on first request
+#[derive(Default)]
+pub struct PageCache {
+    pages: Mutex<HashMap<&'static str, CachedPage>>,
+}
+
+impl PageCache {
+    /// The cached page, or a fresh render if the config has been reloaded since
+    fn get_or_render<T: Template>(
+        &self,
+        name: &'static str,
+        config: Arc<Config>,
+        page: impl FnOnce(Instance) -> T,
+    ) -> askama::Result<CachedPage> {
+        if let Some(cached) = self.pages.lock().unwrap().get(name) {
+            if Arc::ptr_eq(&cached.config, &config) {
+                return Ok(cached.clone());
+            }
+        }
+
+        let html = page(Instance::new(&config)).render()?;
+        let etag = format!("\"{}\"", &blake3::hash(html.as_bytes()).to_hex()[..16]);
+        let cached = CachedPage {
+            config,
+            html: Bytes::from(html),
+            etag: HeaderValue::from_str(&etag).expect("hex ETag is a valid header value"),
+        };
+        self.pages.lock().unwrap().insert(name, cached.clone());
+        Ok(cached)
+    }
+}
+
+/// Serve a page from the cache, answering 304 when the client's copy is current
+fn serve<T: Template>(
+    state: &AppState,
+    headers: &HeaderMap,
+    name: &'static str,
+    page: impl FnOnce(Instance) -> T,
+) -> Response {
+    let cached = match state.pages.get_or_render(name, state.config.load_full(), page) {
+        Ok(cached) => cached,
+        Err(e) => {
+            tracing::error!("Failed to render {}: {}", name, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load page").into_response();
+        }
+    };
+
+    // Pages change on config reload, so clients revalidate every time (cheap with the ETag)
+    let cache_headers = [
+        (header::ETAG, cached.etag.clone()),
+        (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+    ];
+    if headers.get(header::IF_NONE_MATCH) == Some(&cached.etag) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (cache_headers, Html(cached.html)).into_response()
+}
+
+pub async fn index(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "index", |instance| IndexPage { instance })
+}
+
+pub async fn download(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "download", |instance| DownloadPage { instance })
+}
+
+pub async fn faq(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "faq", |instance| FaqPage { instance })
+}
+
+pub async fn post_types(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "post-types", |instance| PostTypesPage { instance })
+}
+
+pub async fn stats(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "stats", |instance| StatsPage { instance })
+}
+
+pub async fn prohibited_uploads(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "prohibited-uploads", |instance| ProhibitedUploadsPage { instance })
+}
+
+pub async fn dogpaste(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "dogpaste", |instance| DogpastePage { instance })
+}
+
+pub async fn navbar(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    serve(&state, &headers, "navbar", |instance| Navbar { instance })
+}
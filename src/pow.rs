@@ -0,0 +1,137 @@
+//! Hashcash-style proof-of-work for uploads the operator wants to slow down
+//!
+//! `GET /api/pow/challenge` hands out `<expires>.<random>.<signature>`, signed with a
+//! secret kept in the settings table so every instance sharing the database accepts
+//! it. The client finds a counter such that SHA-256 of `<challenge>:<counter>` starts
+//! with `difficulty` zero bits and sends `X-Pow-Solution: <challenge>:<counter>`.
+//! Each challenge can be redeemed once (per instance) before it expires.
+
+use crate::constants::{POW_CHALLENGE_TTL_SECS, POW_SECRET_SETTING};
+use crate::database::Database;
+use crate::error::{AppError, Result};
+use crate::models::PowChallenge;
+use crate::state::AppState;
+use arc_swap::ArcSwap;
+use axum::{extract::State, Json};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Longest counter accepted in a solution
+const MAX_COUNTER_LEN: usize = 32;
+
+pub struct ProofOfWork {
+    secret: ArcSwap<Vec<u8>>,
+    /// Redeemed challenges and when they expire
+    redeemed: Mutex<HashMap<String, i64>>,
+}
+
+impl Default for ProofOfWork {
+    /// A random per-process secret, until `load_secret` replaces it with the shared one
+    fn default() -> Self {
+        Self {
+            secret: ArcSwap::from_pointee(random_hex(32).into_bytes()),
+            redeemed: Mutex::default(),
+        }
+    }
+}
+
+impl ProofOfWork {
+    /// Use the secret in the settings table, creating it on first start
+    pub async fn load_secret(&self, db: &Database) -> anyhow::Result<()> {
+        let secret = db.get_or_init_setting(POW_SECRET_SETTING, &random_hex(32)).await?;
+        self.secret.store(Arc::new(secret.into_bytes()));
+        Ok(())
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret.load()).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    pub fn challenge(&self) -> (String, i64) {
+        let expires_at = chrono::Utc::now().timestamp() + POW_CHALLENGE_TTL_SECS;
+        let payload = format!("{}.{}", expires_at, random_hex(16));
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+        (format!("{}.{}", payload, signature), expires_at)
+    }
+
+    /// Check and redeem an `X-Pow-Solution` value
+    pub fn verify(&self, solution: &str, difficulty: u32) -> bool {
+        let Some((challenge, counter)) = solution.rsplit_once(':') else {
+            return false;
+        };
+        if counter.is_empty() || counter.len() > MAX_COUNTER_LEN {
+            return false;
+        }
+        let Some((payload, signature)) = challenge.rsplit_once('.') else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        if self.mac(payload).verify_slice(&signature).is_err() {
+            return false;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let Some(expires_at) = payload.split('.').next().and_then(|t| t.parse::<i64>().ok()) else {
+            return false;
+        };
+        if expires_at <= now {
+            return false;
+        }
+
+        let hash = Sha256::digest(solution.as_bytes());
+        if leading_zero_bits(&hash) < difficulty {
+            return false;
+        }
+
+        let mut redeemed = self.redeemed.lock().expect("redeemed challenges lock");
+        redeemed.retain(|_, expires| *expires > now);
+        redeemed.insert(challenge.to_string(), expires_at).is_none()
+    }
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Get a proof-of-work challenge
+///
+/// Needed for uploads from Tor exits when TOR_EXIT_POLICY=pow. Solve it by finding a
+/// counter such that SHA-256(`<challenge>:<counter>`) starts with `difficulty` zero
+/// bits, then send `X-Pow-Solution: <challenge>:<counter>` with the upload.
+#[utoipa::path(
+    get,
+    path = "/api/pow/challenge",
+    tag = "dogbox.moe",
+    responses(
+        (status = 200, description = "Challenge to solve", body = PowChallenge),
+        (status = 404, description = "Proof-of-work is not in use on this instance")
+    )
+)]
+pub async fn challenge(State(state): State<AppState>) -> Result<Json<PowChallenge>> {
+    let config = state.config.load();
+    if config.tor_exit_policy != crate::config::TorExitPolicy::Pow {
+        return Err(AppError::NotFound);
+    }
+    let (challenge, expires_at) = state.pow.challenge();
+    Ok(Json(PowChallenge { challenge, difficulty: config.pow_difficulty, expires_at }))
+}
@@ -0,0 +1,42 @@
+//! QR codes for share links (`GET /api/files/{id}/qr.png`)
+//!
+//! Only the share URL path is encoded; the decryption key lives in the URL
+//! fragment, which never reaches the server, so the client appends it itself.
+
+use qrcode::{Color, QrCode};
+
+/// Pixels per QR module
+const MODULE_PX: usize = 8;
+
+/// Blank modules around the code, as required by the QR spec
+const QUIET_ZONE: usize = 4;
+
+/// Render `data` as a black-on-white grayscale PNG
+pub fn png(data: &str) -> anyhow::Result<Vec<u8>> {
+    let code = QrCode::new(data.as_bytes())?;
+    let modules = code.width();
+    let colors = code.to_colors();
+
+    let side = (modules + QUIET_ZONE * 2) * MODULE_PX;
+    let mut pixels = vec![0xFFu8; side * side];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x0 = (i % modules + QUIET_ZONE) * MODULE_PX;
+        let y0 = (i / modules + QUIET_ZONE) * MODULE_PX;
+        for y in y0..y0 + MODULE_PX {
+            pixels[y * side + x0..y * side + x0 + MODULE_PX].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+
+    Ok(out)
+}
@@ -0,0 +1,325 @@
+//! Per-client rate limiting
+//!
+//! Both backends use GCRA. The in-memory backend keeps state per process via
+//! governor; the Redis backend keeps the theoretical arrival time for each client in
+//! Redis so every replica enforces one shared limit. The Redis check runs as a Lua
+//! script using Redis' clock, so replicas with skewed clocks still agree.
+//!
+//! Limits are read from the live config on every request, so they follow reloads.
+//! Uploads, downloads, post views and static routes each have their own bucket
+//! per client; everything else shares the global one.
+
+use crate::client_ip;
+use crate::config::{Config, RateLimitBackend, RouteRateLimit, SharedConfig};
+use crate::constants::RATE_LIMIT_PRUNE_SECS;
+use crate::middleware::TrustedClient;
+use arc_swap::ArcSwap;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use governor::{clock::{Clock, DefaultClock}, DefaultKeyedRateLimiter, Quota};
+use redis::{
+    aio::{ConnectionManager, ConnectionManagerConfig},
+    Script,
+};
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Key prefix for limiter state in Redis
+const KEY_PREFIX: &str = "dogbox:ratelimit:";
+
+/// GCRA check; returns {allowed, wait_ms}
+const GCRA_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local period = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+
+local tat = tonumber(redis.call('GET', KEYS[1]) or now)
+if tat < now then tat = now end
+local new_tat = tat + period
+
+if new_tat - now > period * burst then
+    return {0, new_tat - now - period * burst}
+end
+
+redis.call('SET', KEYS[1], new_tat, 'PX', new_tat - now)
+return {1, 0}
+"#;
+
+/// Which bucket a request is counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteClass {
+    Upload,
+    Download,
+    PostView,
+    Static,
+    Other,
+}
+
+impl RouteClass {
+    const ALL: [RouteClass; 5] = [Self::Upload, Self::Download, Self::PostView, Self::Static, Self::Other];
+
+    fn of(method: &Method, path: &str) -> Self {
+        let is_read = method == Method::GET || method == Method::HEAD;
+
+        let is_namespace = crate::webdav::is_dav_path(path) || crate::s3::is_s3_path(path);
+
+        if (!path.starts_with("/api/") && !is_namespace) || path == "/api/health" || path == "/api/admin-motd" {
+            Self::Static
+        } else if path.starts_with("/api/tus")
+            || path == "/api/upload"
+            || path == "/api/raw"
+            || (method == Method::PUT && is_namespace)
+            || (method == Method::POST
+                && (path == "/api/dogpaste" || path == "/api/collections" || path.ends_with("/append")))
+        {
+            Self::Upload
+        } else if is_read && path.starts_with("/api/posts/") && !path.ends_with("/archive") {
+            Self::PostView
+        } else if is_read
+            && (is_namespace
+                || ["/api/files/", "/api/posts/", "/api/dogpaste/", "/api/collections/"]
+                    .iter()
+                    .any(|prefix| path.starts_with(prefix)))
+        {
+            Self::Download
+        } else {
+            Self::Other
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Download => "download",
+            Self::PostView => "post_view",
+            Self::Static => "static",
+            Self::Other => "other",
+        }
+    }
+
+    /// Period (ms) and burst for this class, falling back to the global limit
+    fn quota(self, config: &Config) -> (u64, u32) {
+        let route = match self {
+            Self::Upload => config.rate_limit_routes.upload,
+            Self::Download => config.rate_limit_routes.download,
+            Self::PostView => config.rate_limit_routes.post_view,
+            Self::Static => config.rate_limit_routes.static_files,
+            Self::Other => RouteRateLimit::default(),
+        };
+        (
+            period_ms(route.per_second.unwrap_or(config.rate_limit_per_second)),
+            route.burst.unwrap_or(config.rate_limit_burst),
+        )
+    }
+}
+
+/// Milliseconds between replenished requests for a sustained rate
+fn period_ms(per_second: f64) -> u64 {
+    ((1000.0 / per_second).ceil() as u64).max(1)
+}
+
+/// In-process GCRA state, rebuilt when the configured quota changes
+struct MemoryRateLimiter {
+    state: ArcSwap<MemoryState>,
+}
+
+struct MemoryState {
+    period_ms: u64,
+    burst: u32,
+    limiter: DefaultKeyedRateLimiter<IpAddr>,
+}
+
+impl MemoryState {
+    fn new(period_ms: u64, burst: u32) -> Self {
+        let quota = Quota::with_period(Duration::from_millis(period_ms))
+            .expect("period is at least 1ms")
+            .allow_burst(NonZeroU32::new(burst).unwrap_or(NonZeroU32::MIN));
+
+        Self {
+            period_ms,
+            burst,
+            limiter: DefaultKeyedRateLimiter::keyed(quota),
+        }
+    }
+}
+
+impl MemoryRateLimiter {
+    fn new(period_ms: u64, burst: u32) -> Self {
+        Self {
+            state: ArcSwap::from_pointee(MemoryState::new(period_ms, burst)),
+        }
+    }
+
+    /// Record a request from `ip`; returns None if allowed, or the seconds to wait
+    fn check(&self, ip: IpAddr, period_ms: u64, burst: u32) -> Option<u64> {
+        let mut state = self.state.load_full();
+        if state.period_ms != period_ms || state.burst != burst {
+            // Quota changed by a config reload; start every client with a fresh bucket
+            state = Arc::new(MemoryState::new(period_ms, burst));
+            self.state.store(state.clone());
+        }
+
+        state.limiter.check_key(&ip).err().map(|not_until| {
+            let wait = not_until.wait_time_from(DefaultClock::default().now());
+            wait.as_secs().max(1)
+        })
+    }
+
+    /// Drop the state of clients that are back to a full bucket
+    fn prune(&self) {
+        let state = self.state.load();
+        state.limiter.retain_recent();
+        state.limiter.shrink_to_fit();
+    }
+}
+
+pub struct RedisRateLimiter {
+    conn: ConnectionManager,
+    script: Script,
+}
+
+impl RedisRateLimiter {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        // Short timeouts: a slow Redis should fail open rather than stall every request
+        let manager_config = ConnectionManagerConfig::new()
+            .set_number_of_retries(3)
+            .set_factor(1)
+            .set_max_delay(1000)
+            .set_connection_timeout(Duration::from_secs(2))
+            .set_response_timeout(Duration::from_millis(500));
+        let conn = ConnectionManager::new_with_config(client, manager_config).await?;
+
+        Ok(Self {
+            conn,
+            script: Script::new(GCRA_SCRIPT),
+        })
+    }
+
+    /// Record a request from `ip`; returns None if allowed, or the seconds to wait
+    async fn check(
+        &self,
+        class: RouteClass,
+        ip: IpAddr,
+        period_ms: u64,
+        burst: u32,
+    ) -> redis::RedisResult<Option<u64>> {
+        let mut conn = self.conn.clone();
+        let (allowed, wait_ms): (i64, u64) = self
+            .script
+            .key(format!("{}{}:{}", KEY_PREFIX, class.as_str(), ip))
+            .arg(period_ms)
+            .arg(burst)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok((allowed == 0).then(|| wait_ms.div_ceil(1000).max(1)))
+    }
+}
+
+enum Backend {
+    /// One limiter per route class (indexed by `RouteClass as usize`)
+    Memory([MemoryRateLimiter; RouteClass::ALL.len()]),
+    Redis(Box<RedisRateLimiter>),
+}
+
+/// Rate limiter state for the `rate_limit` middleware
+pub struct RateLimiter {
+    backend: Backend,
+    config: SharedConfig,
+}
+
+impl RateLimiter {
+    /// Set up the backend chosen in config (the backend itself can't be reloaded)
+    pub async fn new(config: SharedConfig) -> anyhow::Result<Self> {
+        let current = config.load_full();
+        let backend = match &current.rate_limit_backend {
+            RateLimitBackend::Memory => Backend::Memory(RouteClass::ALL.map(|class| {
+                let (period_ms, burst) = class.quota(&current);
+                MemoryRateLimiter::new(period_ms, burst)
+            })),
+            RateLimitBackend::Redis { url } => {
+                tracing::info!("🧮 Rate limit state shared via Redis");
+                Backend::Redis(Box::new(RedisRateLimiter::connect(url).await?))
+            }
+        };
+
+        Ok(Self { backend, config })
+    }
+}
+
+/// Background task forgetting clients that went quiet
+///
+/// The in-memory limiters hold an entry per client IP, and clients can cheaply
+/// rotate through IPv6 addresses, so without pruning they only shrink when the
+/// quota changes. Redis keys expire by themselves.
+pub async fn start_prune_task(limiter: Arc<RateLimiter>) {
+    let Backend::Memory(memory) = &limiter.backend else {
+        return;
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(RATE_LIMIT_PRUNE_SECS));
+    loop {
+        interval.tick().await;
+        for class in memory {
+            class.prune();
+        }
+    }
+}
+
+fn too_many_requests(wait_secs: u64) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [
+            (header::RETRY_AFTER, wait_secs.to_string()),
+            (header::HeaderName::from_static("x-ratelimit-after"), wait_secs.to_string()),
+        ],
+        format!("Too Many Requests! Wait for {}s", wait_secs),
+    )
+        .into_response()
+}
+
+/// Per-client rate limiting middleware
+///
+/// The Redis backend fails open if Redis is unreachable, so an outage of the
+/// limiter doesn't take down uploads and downloads.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let config = limiter.config.load_full();
+    // Trusted API keys (verified by the outer `middleware::api_key` layer) aren't limited
+    if !config.rate_limit_enabled || request.extensions().get::<TrustedClient>().is_some() {
+        return next.run(request).await;
+    }
+
+    let Some(ip) = client_ip::from_request(&request, &config.trusted_proxies) else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Couldn't find the client IP").into_response();
+    };
+    let class = RouteClass::of(request.method(), request.uri().path());
+    let (period_ms, burst) = class.quota(&config);
+
+    let wait_secs = match &limiter.backend {
+        Backend::Memory(memory) => memory[class as usize].check(ip, period_ms, burst),
+        Backend::Redis(redis) => match redis.check(class, ip, period_ms, burst).await {
+            Ok(wait_secs) => wait_secs,
+            Err(e) => {
+                tracing::warn!("Redis rate limit check failed, allowing request: {}", e);
+                None
+            }
+        },
+    };
+
+    match wait_secs {
+        None => next.run(request).await,
+        Some(wait_secs) => too_many_requests(wait_secs),
+    }
+}
@@ -0,0 +1,263 @@
+//! Scheduled incremental backups to an S3-compatible bucket
+//!
+//! Layout under the configured prefix:
+//! - `blobs/<blake3 hash>`: encrypted blobs, uploaded once and shared by every snapshot
+//! - `snapshots/<timestamp>/dogbox.db`: consistent database snapshot
+//! - `snapshots/<timestamp>/manifest.json`: a `BackupManifest` whose blob paths are
+//!   keys relative to the prefix (written last, so a snapshot without one is incomplete)
+//!
+//! Each run only uploads blobs the bucket doesn't have yet. Snapshots past the
+//! retention period are then pruned (the newest is always kept), followed by blobs
+//! no remaining snapshot refers to.
+
+use crate::backup::{self, BackupBlob, BackupManifest};
+use crate::config::RemoteBackupConfig;
+use crate::database::Database;
+use crate::storage::{self, StorageBackend};
+use chrono::{NaiveDateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt};
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::time;
+use tokio_util::io::StreamReader;
+
+/// Snapshot directory names (UTC), which sort chronologically
+const SNAPSHOT_NAME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// What one backup run did
+#[derive(Debug)]
+pub struct RemoteBackupReport {
+    pub snapshot: String,
+    pub blobs_uploaded: usize,
+    pub bytes_uploaded: u64,
+    /// Blobs already in the bucket from earlier runs
+    pub blobs_skipped: usize,
+    pub snapshots_pruned: usize,
+    pub blobs_pruned: usize,
+}
+
+pub struct RemoteBackup {
+    store: Arc<dyn ObjectStore>,
+    prefix: String,
+    retention: Duration,
+}
+
+impl RemoteBackup {
+    pub fn new(config: &RemoteBackupConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            store: storage::s3_store(&config.bucket, &config.region, config.endpoint.as_deref(), config.allow_http)?,
+            prefix: config.prefix.trim_matches('/').to_string(),
+            retention: config.retention,
+        })
+    }
+
+    /// Bucket key for a path relative to the prefix
+    fn key(&self, relative: &str) -> ObjectPath {
+        if self.prefix.is_empty() {
+            ObjectPath::from(relative)
+        } else {
+            ObjectPath::from(format!("{}/{}", self.prefix, relative))
+        }
+    }
+
+    /// Snapshot the database, upload it with any new blobs, then prune old snapshots
+    ///
+    /// `staging_dir` holds the database snapshot while it is uploaded.
+    pub async fn run(
+        &self,
+        db: &Database,
+        storage: &dyn StorageBackend,
+        staging_dir: &str,
+    ) -> anyhow::Result<RemoteBackupReport> {
+        let snapshot_path = Path::new(staging_dir).join(format!(".backup-{}.db", uuid::Uuid::new_v4()));
+        let result = self.upload_snapshot(db, storage, &snapshot_path).await;
+        let _ = fs::remove_file(&snapshot_path).await;
+
+        let mut report = result?;
+        (report.snapshots_pruned, report.blobs_pruned) = self.prune(&report.snapshot).await?;
+        Ok(report)
+    }
+
+    async fn upload_snapshot(
+        &self,
+        db: &Database,
+        storage: &dyn StorageBackend,
+        snapshot_path: &Path,
+    ) -> anyhow::Result<RemoteBackupReport> {
+        db.snapshot_into(snapshot_path).await?;
+        let blobs = Database::open_read_only(snapshot_path).await?.list_stored_blobs().await?;
+
+        let created_at = Utc::now();
+        let snapshot = created_at.format(SNAPSHOT_NAME_FORMAT).to_string();
+        let mut manifest = BackupManifest {
+            format_version: backup::FORMAT_VERSION,
+            dogbox_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at,
+            database: format!("snapshots/{}/{}", snapshot, backup::DATABASE_MEMBER),
+            blobs: Vec::with_capacity(blobs.len()),
+            missing_blobs: Vec::new(),
+        };
+        let mut report = RemoteBackupReport {
+            snapshot: snapshot.clone(),
+            blobs_uploaded: 0,
+            bytes_uploaded: 0,
+            blobs_skipped: 0,
+            snapshots_pruned: 0,
+            blobs_pruned: 0,
+        };
+
+        let existing = self.list_blobs().await?;
+        for blob in blobs {
+            let path = format!("blobs/{}", blob.blake3_hash);
+            let size_bytes = if existing.contains(&blob.blake3_hash) {
+                report.blobs_skipped += 1;
+                blob.size_bytes as u64
+            } else {
+                let (size, stream) = match storage.stream(&blob.storage_path).await {
+                    Ok(opened) => opened,
+                    Err(e) if backup::is_missing(&e) => {
+                        manifest.missing_blobs.push(blob.id);
+                        continue;
+                    }
+                    Err(e) => anyhow::bail!("Failed to read blob {}: {}", blob.storage_path, e),
+                };
+                self.upload(&path, StreamReader::new(stream.map_err(std::io::Error::other))).await?;
+                report.blobs_uploaded += 1;
+                report.bytes_uploaded += size;
+                size
+            };
+
+            manifest.blobs.push(BackupBlob {
+                file_id: blob.id,
+                storage_path: blob.storage_path,
+                path,
+                size_bytes,
+                blake3_hash: blob.blake3_hash,
+            });
+        }
+
+        self.upload(&manifest.database, fs::File::open(snapshot_path).await?).await?;
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        self.store
+            .put(&self.key(&format!("snapshots/{}/{}", snapshot, backup::MANIFEST_MEMBER)), manifest_json.into())
+            .await?;
+
+        Ok(report)
+    }
+
+    /// Stream `data` to a key relative to the prefix (multipart, never buffering the whole object)
+    async fn upload(&self, relative: &str, mut data: impl AsyncRead + Unpin) -> anyhow::Result<()> {
+        let mut writer = object_store::buffered::BufWriter::new(self.store.clone(), self.key(relative));
+        tokio::io::copy(&mut data, &mut writer).await?;
+        writer.shutdown().await?;
+        Ok(())
+    }
+
+    /// Hashes of the blobs already in the bucket
+    async fn list_blobs(&self) -> anyhow::Result<HashSet<String>> {
+        let blobs = self
+            .store
+            .list(Some(&self.key("blobs")))
+            .map_ok(|meta| meta.location.filename().unwrap_or_default().to_string())
+            .try_collect()
+            .await?;
+        Ok(blobs)
+    }
+
+    /// Delete snapshots past retention (never `latest`), then blobs nothing references
+    async fn prune(&self, latest: &str) -> anyhow::Result<(usize, usize)> {
+        let cutoff = (Utc::now() - chrono::Duration::from_std(self.retention)?).naive_utc();
+        let snapshots_prefix = self.key("snapshots");
+
+        let objects: Vec<ObjectPath> = self
+            .store
+            .list(Some(&snapshots_prefix))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+
+        // Snapshot name is the first path segment below `snapshots/`
+        let snapshot_name = |location: &ObjectPath| {
+            location
+                .prefix_match(&snapshots_prefix)
+                .and_then(|mut parts| parts.next())
+                .map(|part| part.as_ref().to_string())
+        };
+        let expired: BTreeSet<String> = objects
+            .iter()
+            .filter_map(snapshot_name)
+            .filter(|name| name != latest)
+            .filter(|name| {
+                NaiveDateTime::parse_from_str(name, SNAPSHOT_NAME_FORMAT).is_ok_and(|taken| taken < cutoff)
+            })
+            .collect();
+
+        let doomed = objects
+            .iter()
+            .filter(|location| snapshot_name(location).is_some_and(|name| expired.contains(&name)))
+            .cloned()
+            .collect();
+        self.delete_all(doomed).await?;
+
+        // Blobs referenced by any snapshot that is left
+        let mut referenced = HashSet::new();
+        for location in &objects {
+            let kept = snapshot_name(location).is_some_and(|name| !expired.contains(&name));
+            if kept && location.filename() == Some(backup::MANIFEST_MEMBER) {
+                let bytes = self.store.get(location).await?.bytes().await?;
+                let manifest: BackupManifest = serde_json::from_slice(&bytes)?;
+                referenced.extend(manifest.blobs.into_iter().map(|blob| blob.blake3_hash));
+            }
+        }
+
+        let orphans: Vec<ObjectPath> = self
+            .list_blobs()
+            .await?
+            .into_iter()
+            .filter(|hash| !referenced.contains(hash))
+            .map(|hash| self.key(&format!("blobs/{}", hash)))
+            .collect();
+        let blobs_pruned = orphans.len();
+        self.delete_all(orphans).await?;
+
+        Ok((expired.len(), blobs_pruned))
+    }
+
+    async fn delete_all(&self, locations: Vec<ObjectPath>) -> anyhow::Result<()> {
+        let locations = futures_util::stream::iter(locations.into_iter().map(Ok)).boxed();
+        self.store.delete_stream(locations).try_collect::<Vec<_>>().await?;
+        Ok(())
+    }
+}
+
+/// Background task backing up every `every` (first run one period after startup)
+pub async fn start_remote_backup_task(
+    backup: RemoteBackup,
+    db: Database,
+    storage: Arc<dyn StorageBackend>,
+    staging_dir: String,
+    every: Duration,
+) {
+    let mut interval = time::interval_at(time::Instant::now() + every, every);
+    tracing::info!("☁️  Remote backups every {} hours", every.as_secs() / 3600);
+    loop {
+        interval.tick().await;
+        match backup.run(&db, storage.as_ref(), &staging_dir).await {
+            Ok(report) => tracing::info!(
+                "☁️  Remote backup {}: {} new blobs ({} KB), {} unchanged; pruned {} snapshots and {} blobs",
+                report.snapshot,
+                report.blobs_uploaded,
+                report.bytes_uploaded / 1024,
+                report.blobs_skipped,
+                report.snapshots_pruned,
+                report.blobs_pruned
+            ),
+            Err(e) => tracing::error!("❌ Remote backup failed: {}", e),
+        }
+    }
+}
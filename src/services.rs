@@ -1,92 +1,474 @@
-use crate::config::Config;
-use crate::constants::{MAX_UPLOAD_SIZE, MAX_POST_CONTENT_ENTRIES};
+use crate::config::{SharedConfig, StorageFullPolicy};
+use crate::constants::{
+    EVICTED_FILES_COUNTER, MAX_CALLBACK_URL_LENGTH, MAX_FILE_EXTENSION_LENGTH, MAX_ID_ATTEMPTS, MAX_POST_KEYS, MAX_SLUG_LENGTH, MIN_SLUG_LENGTH, MAX_POST_KEY_LABEL_LENGTH,
+    TUS_SESSION_EXPIRY_HOURS,
+};
 use crate::database::Database;
 use crate::error::{AppError, Result};
-use crate::models::{FileRecord, PostType, PostContentView, PostViewResponse};
+use crate::events::{PostEvent, PostEvents};
+use crate::models::{
+    AuditActor, CleanupReport, DeletedFile, OrphanReport, ExpiryWarning, ExpiryWarningStatus, FileRecord, PostContent, PostContentType, PostKey, PostType, PostContentView, PostViewResponse, UploadSession,
+};
+use crate::storage::{BlobStream, StorageBackend};
+use crate::views::UniqueViews;
+use crate::webhooks::{WebhookEvent, WebhookEventKind, Webhooks};
+use axum::body::Bytes;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use blake3;
 use chrono::{Duration, Utc};
+use futures_util::{Stream, StreamExt};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
+/// Encrypted upload being streamed to a temporary file in the upload directory
+///
+/// Chunks are hashed as they are written so the blob never has to be held in memory.
+pub struct UploadWriter {
+    file: fs::File,
+    path: PathBuf,
+    hasher: blake3::Hasher,
+    size_bytes: u64,
+    max_bytes: u64,
+    finished: bool,
+}
+
+impl UploadWriter {
+    /// Append a chunk of the encrypted blob
+    pub async fn write_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        self.size_bytes += chunk.len() as u64;
+
+        // SECURITY: Enforce size limit while streaming, before the disk fills up
+        if self.size_bytes > self.max_bytes {
+            return Err(AppError::FileTooLarge {
+                max_mb: self.max_bytes / (1024 * 1024),
+            });
+        }
+
+        self.hasher.update(chunk);
+        self.file.write_all(chunk).await?;
+        Ok(())
+    }
+
+    /// Flush the blob to disk and return the staged upload
+    pub async fn finish(mut self) -> Result<StagedUpload> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+        self.finished = true;
+
+        Ok(StagedUpload {
+            path: self.path.clone(),
+            size_bytes: self.size_bytes as i64,
+            blake3_hash: self.hasher.finalize().to_hex().to_string(),
+        })
+    }
+}
+
+impl Drop for UploadWriter {
+    fn drop(&mut self) {
+        // Abandoned (failed/oversized) uploads leave no partial file behind
+        if !self.finished {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Fully written upload waiting to be committed by `FileService::store_file`
+///
+/// The temporary file is removed on drop unless it was moved into place.
+pub struct StagedUpload {
+    path: PathBuf,
+    pub size_bytes: i64,
+    pub blake3_hash: String,
+}
+
+impl Drop for StagedUpload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Size and expiry caps applying to one upload
+#[derive(Debug, Clone, Copy)]
+pub struct UploadLimits {
+    pub max_upload_bytes: u64,
+    pub max_expiry_hours: i64,
+}
+
+/// Options supplied when a resumable upload session is created
+#[derive(Debug, Default)]
+pub struct UploadSessionOptions {
+    pub filename_encrypted: Option<String>,
+    pub mime_type: Option<String>,
+    pub file_extension: Option<String>,
+    pub expiry_hours: Option<i64>,
+    pub is_permanent: bool,
+    pub max_downloads: Option<i64>,
+}
+
+/// Marks a resumable upload session as busy until dropped
+struct SessionLock<'a> {
+    sessions: &'a Mutex<HashSet<String>>,
+    id: String,
+}
+
+impl Drop for SessionLock<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(&self.id);
+        }
+    }
+}
+
 pub struct FileService {
-    config: Config,
+    config: SharedConfig,
     db: Database,
+    storage: Arc<dyn StorageBackend>,
+    /// Resumable upload sessions currently receiving a chunk
+    active_sessions: Mutex<HashSet<String>>,
+    /// Live update channels for posts being watched
+    post_events: PostEvents,
+    /// Bytes of file blobs in storage, kept up to date as blobs come and go
+    storage_used: AtomicI64,
+    webhooks: Webhooks,
+    /// Who already viewed which post today (salted hashes)
+    unique_views: UniqueViews,
 }
 
 impl FileService {
-    pub fn new(config: Config, db: Database) -> Self {
-        Self { config, db }
+    pub fn new(config: SharedConfig, db: Database, storage: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            webhooks: Webhooks::new(config.clone()),
+            config,
+            db,
+            storage,
+            active_sessions: Mutex::new(HashSet::new()),
+            post_events: PostEvents::default(),
+            storage_used: AtomicI64::new(0),
+            unique_views: UniqueViews::default(),
+        }
+    }
+
+    /// Limits for an upload, elevated for clients presenting a trusted API key
+    pub fn upload_limits(&self, trusted: bool) -> UploadLimits {
+        let config = self.config.load();
+        if trusted {
+            UploadLimits {
+                max_upload_bytes: config.api_key_max_upload_bytes,
+                max_expiry_hours: config.api_key_max_expiry_hours,
+            }
+        } else {
+            UploadLimits {
+                max_upload_bytes: config.max_file_bytes,
+                max_expiry_hours: config.max_expiry_hours,
+            }
+        }
+    }
+
+    /// Seed the storage usage counter from the database (once, at startup)
+    pub async fn load_storage_usage(&self) -> Result<()> {
+        let used = self.db.get_blob_bytes().await?;
+        self.storage_used.store(used, Ordering::Relaxed);
+        tracing::info!("💾 {} bytes of file blobs in storage", used);
+        Ok(())
+    }
+
+    /// Reserve room for a new blob under MAX_TOTAL_STORAGE_BYTES
+    ///
+    /// The reservation is counted immediately so concurrent uploads can't overshoot
+    /// the quota together; release it with `release_storage` if the upload fails.
+    /// With STORAGE_FULL_POLICY=evict, old files are deleted until the blob fits.
+    async fn reserve_storage(&self, size_bytes: i64) -> Result<()> {
+        loop {
+            let used = self.storage_used.fetch_add(size_bytes, Ordering::Relaxed) + size_bytes;
+            let config = self.config.load_full();
+            let Some(max) = config.max_total_storage_bytes else {
+                return Ok(());
+            };
+            if used <= max as i64 {
+                return Ok(());
+            }
+            self.release_storage(size_bytes);
+
+            let evicted = match config.storage_full_policy {
+                StorageFullPolicy::Evict if size_bytes <= max as i64 => {
+                    self.db.evict_least_recently_used().await?
+                }
+                _ => None,
+            };
+            let Some(evicted) = evicted else {
+                tracing::warn!("Rejected upload of {} bytes: storage quota of {} bytes reached", size_bytes, max);
+                return Err(AppError::InsufficientStorage(
+                    "The server's storage quota is full, try again later".to_string(),
+                ));
+            };
+
+            if let Err(e) = self.delete_blob_of(&evicted).await {
+                tracing::error!("Failed to delete evicted blob {}: {}", evicted.storage_path, e);
+            }
+            self.db.increment_counter(EVICTED_FILES_COUNTER).await?;
+            self.db
+                .audit(AuditActor::System, "file.evict", Some(&evicted.id), Some(&format!("{} bytes", evicted.size_bytes)))
+                .await;
+            self.notify(WebhookEventKind::Evicted, &evicted);
+            tracing::warn!(
+                "♻️  Evicted file {} ({} bytes) to make room for a {} byte upload",
+                evicted.id, evicted.size_bytes, size_bytes
+            );
+        }
+    }
+
+    fn release_storage(&self, size_bytes: i64) {
+        self.storage_used.fetch_sub(size_bytes, Ordering::Relaxed);
+    }
+
+    /// Remove a blob from storage and stop counting it toward the quota
+    async fn delete_blob(&self, storage_path: &str, size_bytes: i64) -> Result<()> {
+        self.release_storage(size_bytes);
+        self.storage.delete(storage_path).await
+    }
+
+    /// Release the blob (if any) of a row that was just deleted
+    async fn delete_blob_of(&self, deleted: &DeletedFile) -> Result<()> {
+        match deleted.blob_bytes() {
+            Some(size_bytes) => self.delete_blob(&deleted.storage_path, size_bytes).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Send a webhook about a row that was just deleted
+    fn notify(&self, kind: WebhookEventKind, deleted: &DeletedFile) {
+        self.webhooks.send(WebhookEvent::new(kind, &deleted.id, &deleted.post_type, deleted.size_bytes));
+    }
+
+    /// Refuse an upload of `incoming` bytes if it would leave less than MIN_FREE_DISK_BYTES free
+    ///
+    /// Uploads are always staged in the upload directory, so that's the filesystem checked.
+    fn ensure_disk_space(&self, incoming: u64) -> Result<()> {
+        let config = self.config.load();
+        let Some(min_free) = config.min_free_disk_bytes else {
+            return Ok(());
+        };
+
+        let available = crate::storage::disk_space(&config.upload_dir)?.available;
+        if available < incoming.saturating_add(min_free) {
+            tracing::warn!(
+                "Rejected upload of {} bytes: {} bytes free, keeping at least {} free",
+                incoming, available, min_free
+            );
+            return Err(AppError::InsufficientStorage(
+                "The server is low on disk space, try again later".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Start streaming an encrypted upload to a temporary file in the upload directory
+    ///
+    /// `declared_length` is the client's announced size (0 if unknown), used for
+    /// the free disk space check.
+    pub async fn begin_upload(&self, declared_length: u64, limits: UploadLimits) -> Result<UploadWriter> {
+        self.ensure_disk_space(declared_length)?;
+
+        let upload_dir_canonical = PathBuf::from(&self.config.load().upload_dir).canonicalize()?;
+        let path = upload_dir_canonical.join(format!(".upload-{}.part", uuid::Uuid::new_v4()));
+        let file = fs::File::create(&path).await?;
+
+        Ok(UploadWriter {
+            file,
+            path,
+            hasher: blake3::Hasher::new(),
+            size_bytes: 0,
+            max_bytes: limits.max_upload_bytes,
+            finished: false,
+        })
     }
 
     /// Store encrypted file blob and return metadata
     /// Important: This function has no knowledge of the encryption key
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn store_file(
         &self,
-        data: Vec<u8>,
+        upload: StagedUpload,
         filename_encrypted: Option<String>,
         mime_type: Option<String>,
         expiry_hours: Option<i64>,
         post_type: PostType,
         is_permanent: bool,
         file_extension: Option<String>,
+        max_downloads: Option<i64>,
+        slug: Option<String>,
+        limits: UploadLimits,
     ) -> Result<FileRecord> {
-        // Validate size against constant (1 GB)
-        if data.len() > MAX_UPLOAD_SIZE {
-            return Err(AppError::FileTooLarge {
-                max_mb: (MAX_UPLOAD_SIZE / (1024 * 1024)) as u64,
-            });
+        self.commit_upload(
+            upload,
+            filename_encrypted,
+            mime_type,
+            expiry_hours,
+            post_type,
+            is_permanent,
+            file_extension,
+            max_downloads,
+            slug,
+            limits,
+            false,
+        )
+        .await
+    }
+
+    /// Store a file written through the WebDAV/S3 namespace
+    ///
+    /// It is never deduplicated into another upload, nor another upload into it, so
+    /// unlinking its path can delete it without touching anyone else's link.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn store_namespace_file(
+        &self,
+        upload: StagedUpload,
+        mime_type: Option<String>,
+        file_extension: Option<String>,
+        limits: UploadLimits,
+    ) -> Result<FileRecord> {
+        // Mounted files live as long as the key's uploads may
+        let expiry_hours = Some(limits.max_expiry_hours);
+        self.commit_upload(
+            upload,
+            None,
+            mime_type,
+            expiry_hours,
+            PostType::File,
+            false,
+            file_extension,
+            None,
+            None,
+            limits,
+            true,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn commit_upload(
+        &self,
+        upload: StagedUpload,
+        filename_encrypted: Option<String>,
+        mime_type: Option<String>,
+        expiry_hours: Option<i64>,
+        post_type: PostType,
+        is_permanent: bool,
+        file_extension: Option<String>,
+        max_downloads: Option<i64>,
+        slug: Option<String>,
+        limits: UploadLimits,
+        namespace: bool,
+    ) -> Result<FileRecord> {
+        // BLAKE3 hash was computed while streaming (used for deduplication)
+        let blake3_hash = upload.blake3_hash.clone();
+
+        let file_extension = normalize_file_extension(file_extension)?;
+
+        // Operator's restrictions on declared content types (posts hold markdown)
+        if post_type == PostType::File {
+            self.config
+                .load()
+                .content_policy
+                .check(mime_type.as_deref(), file_extension.as_deref())
+                .map_err(AppError::UnsupportedMediaType)?;
+        }
+
+        // Post content ends up base64-encoded in a database row, so it has its own smaller cap
+        let max_post_bytes = self.config.load().max_post_bytes;
+        if post_type == PostType::Post && upload.size_bytes as u64 > max_post_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Post content exceeds the maximum of {} bytes",
+                max_post_bytes
+            )));
+        }
+
+        if let Some(max_downloads) = max_downloads {
+            if post_type != PostType::File {
+                return Err(AppError::BadRequest("max_downloads is only supported for files".to_string()));
+            }
+            if max_downloads < 1 {
+                return Err(AppError::BadRequest("max_downloads must be at least 1".to_string()));
+            }
         }
 
-        // Calculate BLAKE3 hash for deduplication
-        let hash = blake3::hash(&data);
-        let blake3_hash = hash.to_hex().to_string();
+        if let Some(slug) = &slug {
+            if post_type != PostType::Post {
+                return Err(AppError::BadRequest("slug is only supported for posts".to_string()));
+            }
+            self.validate_slug(slug).await?;
+        }
 
-        // Check for existing file with same hash (deduplication)
-        if let Some(existing) = self.db.find_by_hash(&blake3_hash).await? {
-            tracing::info!("Deduplicated upload: using existing file {}", existing.id);
-            return Ok(existing);
+        // Reject content on the operator's denylist before it is committed
+        if self.db.is_hash_blocked(&blake3_hash).await? {
+            tracing::warn!("Rejected upload of blocked hash {}", blake3_hash);
+            return Err(AppError::Forbidden("This content has been blocked by the operator".to_string()));
         }
 
         // Calculate expiration (or set far future if permanent)
         let expires_at = if is_permanent {
             Utc::now() + Duration::days(36500) // ~100 years
         } else {
+            let config = self.config.load();
             let expiry_hours = expiry_hours
-                .unwrap_or(self.config.default_expiry_hours)
-                .min(self.config.max_expiry_hours);
+                .unwrap_or(config.default_expiry_hours)
+                .min(limits.max_expiry_hours);
             Utc::now() + Duration::hours(expiry_hours)
         };
 
-        // Generate storage path (UUID-based to avoid collisions)
+        // Answer with an existing copy of the same blob (deduplication), as long as it
+        // keeps everything this upload asked for. A download limit is counted per upload,
+        // so those always get their own file, as do namespace files. A copy expiring
+        // sooner is kept until this upload's expiry. The staged temporary file is
+        // discarded when `upload` is dropped.
+        if post_type == PostType::File && max_downloads.is_none() && !namespace {
+            if let Some(mut existing) = self.db.find_dedup_target(&blake3_hash, is_permanent).await? {
+                tracing::info!("Deduplicated upload: using existing file {}", existing.id);
+                if !is_permanent && existing.expires_at < expires_at {
+                    self.db.update_expiry(&existing.id, expires_at).await?;
+                    existing.expires_at = expires_at;
+                }
+                if self.config.load().analytics_enabled {
+                    self.db.record_dedup_hit(&existing.id).await?;
+                }
+                return Ok(existing);
+            }
+        }
+
+        let id = self.generate_file_id().await?;
+
+        // Generate storage key (UUID-based to avoid collisions)
         let file_id = uuid::Uuid::new_v4().to_string();
         let storage_path = if post_type == PostType::Post {
-            // Posts store content in database, not on disk
+            // Posts store content in database, not in blob storage
             format!("post:{}", file_id)
         } else {
-            let upload_dir_canonical = PathBuf::from(&self.config.upload_dir).canonicalize()?;
-            let file_path = upload_dir_canonical.join(&file_id);
+            file_id
+        };
 
-            // SECURITY: Validate path doesn't escape upload directory
-            if !file_path.starts_with(&upload_dir_canonical) {
-                return Err(AppError::BadRequest("Invalid file path".to_string()));
+        // Commit encrypted blob to the storage backend (for files only)
+        // Posts read the staged blob back so it can be stored in the database
+        let post_data = if post_type == PostType::File {
+            self.reserve_storage(upload.size_bytes).await?;
+            if let Err(e) = self.storage.put(&storage_path, &upload.path).await {
+                self.release_storage(upload.size_bytes);
+                return Err(e);
             }
-
-            file_path.to_string_lossy().to_string()
+            None
+        } else {
+            Some(fs::read(&upload.path).await?)
         };
 
-        // Write encrypted blob to disk (for files only)
-        if post_type == PostType::File {
-            let mut file = fs::File::create(&storage_path).await?;
-            file.write_all(&data).await?;
-            file.sync_all().await?;
-        }
-
         // Create database record
-        let file_record = FileRecord::new(
+        let mut file_record = FileRecord::new(
             filename_encrypted,
-            data.len() as i64,
+            upload.size_bytes,
             mime_type,
             expires_at,
             storage_path,
@@ -95,12 +477,50 @@ impl FileService {
             is_permanent,
             file_extension,
         );
+        file_record.id = id;
+        file_record.downloads_remaining = max_downloads;
+        file_record.slug = slug;
 
-        self.db.create_file(&file_record).await?;
+        // Short IDs can collide with existing ones; retry with a fresh ID
+        let mut attempts = 1;
+        let created = loop {
+            let created = if namespace {
+                self.db.create_namespace_file(&file_record).await
+            } else {
+                self.db.create_file(&file_record).await
+            };
+            match created {
+                Err(AppError::Database(sqlx::Error::Database(e)))
+                    if e.is_unique_violation()
+                        && e.message().contains("files.id")
+                        && attempts < MAX_ID_ATTEMPTS =>
+                {
+                    tracing::debug!("File ID {} already taken, retrying", file_record.id);
+                    attempts += 1;
+                    match self.generate_file_id().await {
+                        Ok(id) => file_record.id = id,
+                        Err(e) => break Err(e),
+                    }
+                }
+                result => break result,
+            }
+        };
+        if let Err(e) = created {
+            if post_type == PostType::File {
+                let _ = self.delete_blob(&file_record.storage_path, file_record.size_bytes).await;
+            }
+            // Lost a race for the slug since it was validated
+            if let AppError::Database(sqlx::Error::Database(db_err)) = &e {
+                if db_err.is_unique_violation() && db_err.message().contains("files.slug") {
+                    return Err(AppError::Conflict("This slug is already taken".to_string()));
+                }
+            }
+            return Err(e);
+        }
 
         // For posts, store initial content if provided
         // Base64 encode the encrypted binary data so it can be stored as text in the database
-        if post_type == PostType::Post && !data.is_empty() {
+        if let Some(data) = post_data.filter(|data| !data.is_empty()) {
             let content_encrypted = BASE64.encode(&data);
             // Default to markdown type for initial content
             self.db.add_post_content(
@@ -121,13 +541,20 @@ impl FileService {
             file_record.size_bytes,
             if is_permanent { "never expires".to_string() } else { format!("expires {}", file_record.expires_at) }
         );
+        self.webhooks.send(WebhookEvent::new(
+            WebhookEventKind::Uploaded,
+            &file_record.id,
+            &file_record.post_type,
+            file_record.size_bytes,
+        ));
 
         Ok(file_record)
     }
 
     /// Retrieve encrypted file blob
-    /// Important: Returns encrypted data; server cannot decrypt
-    pub async fn retrieve_file(&self, file_id: &str) -> Result<(FileRecord, Vec<u8>)> {
+    /// Important: Returns a stream of encrypted data (and its size); server cannot decrypt
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
+    pub async fn retrieve_file(&self, file_id: &str) -> Result<(FileRecord, u64, BlobStream)> {
         let file = self
             .db
             .get_file(file_id)
@@ -141,12 +568,201 @@ impl FileService {
             ));
         }
 
-        let data = fs::read(&file.storage_path).await?;
+        self.ensure_not_blocked(&file).await?;
+
+        if file.downloads_remaining == Some(0) {
+            return Err(AppError::Gone("This file has reached its download limit".to_string()));
+        }
+
+        // Open the blob before using up a download, so storage errors don't cost one
+        let (size, blob) = self.storage.stream(&file.storage_path).await?;
+        // Recency for STORAGE_FULL_POLICY=evict (oldest uploads go first without analytics)
+        if self.config.load().analytics_enabled {
+            self.db.mark_downloaded(file_id).await?;
+        }
+
+        if file.downloads_remaining.is_some() {
+            match self.db.consume_download(file_id).await? {
+                // Lost the race for the last download
+                None => return Err(AppError::Gone("This file has reached its download limit".to_string())),
+                // Last download: the blob is removed once it has been sent
+                Some(0) => {
+                    self.release_storage(file.size_bytes);
+                    let blob = crate::storage::delete_after(self.storage.clone(), file.storage_path.clone(), blob);
+                    tracing::info!("File {} reached its download limit", file_id);
+                    return Ok((file, size, blob));
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok((file, size, blob))
+    }
+
+    /// Stream part of a file blob for in-browser playback
+    ///
+    /// Media players fetch many overlapping ranges, so previews don't use up downloads;
+    /// files with a download limit have to go through `retrieve_file` instead.
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file.id))]
+    pub async fn preview_file(&self, file: &FileRecord, range: Range<u64>) -> Result<BlobStream> {
+        if file.get_post_type() == PostType::Post {
+            return Err(AppError::BadRequest(
+                "This is a post, not a file. Use /api/posts/{id} endpoint instead.".to_string()
+            ));
+        }
+        if file.downloads_remaining.is_some() {
+            return Err(AppError::Forbidden(
+                "Files with a download limit can't be previewed; download them instead".to_string()
+            ));
+        }
+
+        let blob = self.storage.stream_range(&file.storage_path, range.clone()).await?;
+        // Recency for STORAGE_FULL_POLICY=evict, once per playback rather than per range
+        if range.start == 0 && self.config.load().analytics_enabled {
+            self.db.mark_downloaded(&file.id).await?;
+        }
+        Ok(blob)
+    }
+
+    /// Resolve a post ID or custom slug to the post's ID
+    pub async fn resolve_post_id(&self, id_or_slug: &str) -> Result<String> {
+        self.db.resolve_id(id_or_slug).await?.ok_or(AppError::NotFound)
+    }
 
-        Ok((file, data))
+    /// New file ID in the configured format that isn't some post's slug
+    ///
+    /// Short IDs use the slug alphabet, and IDs take precedence when resolving, so an
+    /// ID equal to an existing slug would hide that post.
+    async fn generate_file_id(&self) -> Result<String> {
+        for _ in 0..MAX_ID_ATTEMPTS {
+            let id = FileRecord::generate_id(&self.config.load().id_scheme);
+            if !self.db.slug_exists(&id).await? {
+                return Ok(id);
+            }
+            tracing::debug!("File ID {} is already a slug, retrying", id);
+        }
+        Err(AppError::Internal(anyhow::anyhow!("Couldn't generate a file ID that isn't a slug")))
+    }
+
+    /// Check a requested post slug's format and availability
+    async fn validate_slug(&self, slug: &str) -> Result<()> {
+        if slug.len() < MIN_SLUG_LENGTH || slug.len() > MAX_SLUG_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "slug must be between {} and {} characters",
+                MIN_SLUG_LENGTH, MAX_SLUG_LENGTH
+            )));
+        }
+        if !slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            || slug.starts_with('-')
+            || slug.ends_with('-')
+        {
+            return Err(AppError::BadRequest(
+                "slug may only contain lowercase letters, digits and inner hyphens".to_string()
+            ));
+        }
+        // Slugs shaped like IDs could shadow (or be shadowed by) real posts
+        if uuid::Uuid::parse_str(slug).is_ok() {
+            return Err(AppError::BadRequest("slug must not be a UUID".to_string()));
+        }
+        if self.db.resolve_id(slug).await?.is_some() {
+            return Err(AppError::Conflict("This slug is already taken".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Metadata for a live file or post, without touching the blob or using up a download
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
+    pub async fn file_metadata(&self, file_id: &str) -> Result<FileRecord> {
+        let file = self
+            .db
+            .get_file(file_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+
+        self.ensure_not_blocked(&file).await?;
+
+        if file.downloads_remaining == Some(0) {
+            return Err(AppError::Gone("This file has reached its download limit".to_string()));
+        }
+
+        Ok(file)
+    }
+
+    /// Re-hash a stored blob and compare it with the hash recorded at upload
+    ///
+    /// Returns the file, the hash of the blob as stored now, and its size.
+    pub async fn verify_file(&self, file_id: &str) -> Result<(FileRecord, String, u64)> {
+        let file = self.file_metadata(file_id).await?;
+
+        if file.get_post_type() == PostType::Post {
+            return Err(AppError::BadRequest(
+                "Posts are stored in the database and can't be verified".to_string()
+            ));
+        }
+
+        let (_, mut blob) = self.storage.stream(&file.storage_path).await?;
+        let mut hasher = blake3::Hasher::new();
+        let mut size = 0u64;
+        while let Some(chunk) = blob.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            hasher.update(&chunk);
+        }
+        let actual_hash = hasher.finalize().to_hex().to_string();
+
+        if actual_hash != file.blake3_hash {
+            tracing::error!(
+                "Integrity check failed for {}: expected {}, found {}",
+                file.id,
+                file.blake3_hash,
+                actual_hash
+            );
+        }
+
+        Ok((file, actual_hash, size))
+    }
+
+    /// Refuse to serve content whose hash was added to the denylist after upload,
+    /// or that is under a legal hold
+    async fn ensure_not_blocked(&self, file: &FileRecord) -> Result<()> {
+        if self.db.is_hash_blocked(&file.blake3_hash).await? {
+            return Err(AppError::Gone("This content has been removed by the operator".to_string()));
+        }
+        self.ensure_not_quarantined(&file.id).await
+    }
+
+    /// Quarantined files are kept exactly as they are until the hold is released
+    async fn ensure_not_quarantined(&self, file_id: &str) -> Result<()> {
+        if self.db.is_quarantined(file_id).await? {
+            return Err(AppError::UnavailableForLegalReasons(
+                "This content is unavailable while under review by the operator".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Place a legal hold on a file (admin only)
+    pub async fn quarantine_file(&self, file_id: &str, reason: Option<&str>) -> Result<()> {
+        if !self.db.quarantine_file(file_id, reason).await? {
+            return Err(AppError::NotFound);
+        }
+        tracing::warn!("🔒 Admin quarantined {}", file_id);
+        self.db.audit(AuditActor::Admin, "file.quarantine", Some(file_id), reason).await;
+        Ok(())
+    }
+
+    /// Release a legal hold; the file expires normally again (admin only)
+    pub async fn release_quarantine(&self, file_id: &str) -> Result<()> {
+        if !self.db.release_quarantine(file_id).await? {
+            return Err(AppError::NotFound);
+        }
+        tracing::warn!("🔓 Admin released quarantine on {}", file_id);
+        self.db.audit(AuditActor::Admin, "file.release", Some(file_id), None).await;
+        Ok(())
     }
 
     /// Delete file with token verification
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
     pub async fn delete_file(&self, file_id: &str, deletion_token: &str) -> Result<bool> {
         // Get file metadata first
         let file = self
@@ -154,6 +770,7 @@ impl FileService {
             .get_file(file_id)
             .await?
             .ok_or(AppError::NotFound)?;
+        self.ensure_not_quarantined(file_id).await?;
 
         // Verify deletion token
         let deleted = self.db.delete_file(file_id, deletion_token).await?;
@@ -162,37 +779,286 @@ impl FileService {
             return Err(AppError::InvalidDeletionToken);
         }
 
-        // Securely delete file from disk
-        if let Err(e) = fs::remove_file(&file.storage_path).await {
-            tracing::error!("Failed to delete file from disk: {}", e);
+        // Securely delete blob from storage (posts live in the database)
+        if file.get_post_type() == PostType::File {
+            if let Err(e) = self.delete_blob(&file.storage_path, file.size_bytes).await {
+                tracing::error!("Failed to delete file from storage: {}", e);
+            }
         }
 
         tracing::info!("Deleted file {}", file_id);
+        self.webhooks.send(WebhookEvent::new(WebhookEventKind::Deleted, file_id, &file.post_type, file.size_bytes));
         Ok(true)
     }
 
+    /// Extend or shorten a file's lifetime with its deletion token, up to the caller's limits
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
+    pub async fn update_expiry(
+        &self,
+        file_id: &str,
+        deletion_token: &str,
+        expiry_hours: i64,
+        limits: UploadLimits,
+    ) -> Result<chrono::DateTime<Utc>> {
+        if expiry_hours < 0 {
+            return Err(AppError::BadRequest("expiry_hours must not be negative".to_string()));
+        }
+
+        // Already-expired files can't be revived
+        self.db.get_file(file_id).await?.ok_or(AppError::NotFound)?;
+        self.ensure_not_quarantined(file_id).await?;
+
+        if !self.db.verify_deletion_token(file_id, deletion_token).await? {
+            return Err(AppError::InvalidDeletionToken);
+        }
+
+        let expires_at = Utc::now() + Duration::hours(expiry_hours.min(limits.max_expiry_hours));
+        self.db.update_expiry(file_id, expires_at).await?;
+
+        tracing::info!("Updated expiry of {} to {}", file_id, expires_at);
+        Ok(expires_at)
+    }
+
+    /// Register (or replace) an uploader's expiry warning
+    pub async fn set_expiry_warning(
+        &self,
+        file_id: &str,
+        deletion_token: &str,
+        callback_url: Option<String>,
+        hours_before: i64,
+    ) -> Result<ExpiryWarningStatus> {
+        if hours_before < 1 {
+            return Err(AppError::BadRequest("hours_before must be at least 1".to_string()));
+        }
+        if let Some(url) = &callback_url {
+            // SECURITY: The server makes requests to these URLs, so they're opt-in for operators
+            if !self.config.load().expiry_callbacks_enabled {
+                return Err(AppError::BadRequest(
+                    "Callback URLs are disabled on this server; poll the expiry warning instead".to_string(),
+                ));
+            }
+            if !url.starts_with("https://") || url.len() > MAX_CALLBACK_URL_LENGTH {
+                return Err(AppError::BadRequest(format!(
+                    "callback_url must be an https:// URL of at most {} characters",
+                    MAX_CALLBACK_URL_LENGTH
+                )));
+            }
+        }
+
+        self.db.get_file(file_id).await?.ok_or(AppError::NotFound)?;
+        if !self.db.verify_deletion_token(file_id, deletion_token).await? {
+            return Err(AppError::InvalidDeletionToken);
+        }
+
+        self.db
+            .set_expiry_warning(&ExpiryWarning {
+                file_id: file_id.to_string(),
+                callback_url,
+                hours_before,
+                notified_at: None,
+            })
+            .await?;
+
+        tracing::info!("Registered expiry warning for {} ({}h before)", file_id, hours_before);
+        self.expiry_warning_status(file_id, deletion_token).await
+    }
+
+    /// Current expiry and warning state of a file (for uploaders polling instead of using a callback)
+    pub async fn expiry_warning_status(&self, file_id: &str, deletion_token: &str) -> Result<ExpiryWarningStatus> {
+        let file = self.db.get_file(file_id).await?.ok_or(AppError::NotFound)?;
+        if !self.db.verify_deletion_token(file_id, deletion_token).await? {
+            return Err(AppError::InvalidDeletionToken);
+        }
+
+        let warning = self.db.get_expiry_warning(file_id).await?;
+        let warning_due = warning.as_ref().is_some_and(|w| {
+            !file.is_permanent && Utc::now() >= file.expires_at - Duration::hours(w.hours_before)
+        });
+
+        Ok(ExpiryWarningStatus {
+            file_id: file.id,
+            expires_at: file.expires_at,
+            is_permanent: file.is_permanent,
+            hours_before: warning.as_ref().map(|w| w.hours_before),
+            callback_url: warning.as_ref().and_then(|w| w.callback_url.clone()),
+            warning_due,
+            notified_at: warning.and_then(|w| w.notified_at),
+        })
+    }
+
+    pub async fn remove_expiry_warning(&self, file_id: &str, deletion_token: &str) -> Result<()> {
+        self.db.get_file(file_id).await?.ok_or(AppError::NotFound)?;
+        if !self.db.verify_deletion_token(file_id, deletion_token).await? {
+            return Err(AppError::InvalidDeletionToken);
+        }
+        if !self.db.delete_expiry_warning(file_id).await? {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Call back uploaders whose files are about to expire (run by the cleanup task)
+    ///
+    /// Callbacks are signed like webhooks, keyed with the file's deletion token.
+    pub async fn send_expiry_warnings(&self) -> Result<u64> {
+        if !self.config.load().expiry_callbacks_enabled {
+            return Ok(0);
+        }
+
+        let due = self.db.due_expiry_warnings().await?;
+        for warning in &due {
+            let mut event = WebhookEvent::new(
+                WebhookEventKind::Expiring,
+                &warning.file_id,
+                &warning.post_type,
+                warning.size_bytes,
+            );
+            event.expires_at = Some(warning.expires_at);
+            self.webhooks.send_to(warning.callback_url.clone(), Some(warning.deletion_token.clone()), event);
+            self.db.mark_expiry_warning_sent(&warning.file_id).await?;
+        }
+        Ok(due.len() as u64)
+    }
+
+    /// Delete a file without a deletion token (admin only)
+    #[tracing::instrument(level = "debug", skip_all, fields(file_id = %file_id))]
+    pub async fn force_delete_file(&self, file_id: &str) -> Result<()> {
+        if self.db.is_quarantined(file_id).await? {
+            return Err(AppError::Conflict("Release the quarantine before deleting this file".to_string()));
+        }
+        let deleted = self
+            .db
+            .force_delete_file(file_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+
+        if let Err(e) = self.delete_blob_of(&deleted).await {
+            tracing::error!("Failed to delete file from storage: {}", e);
+        }
+
+        tracing::warn!("Admin force-deleted {} {}", deleted.post_type, file_id);
+        self.db.audit(AuditActor::Admin, "file.delete", Some(file_id), Some(&deleted.post_type)).await;
+        self.notify(WebhookEventKind::AdminDeleted, &deleted);
+        Ok(())
+    }
+
     /// Cleanup expired files (run periodically)
-    pub async fn cleanup_expired(&self) -> Result<u64> {
-        // Get expired file records
-        let count = self.db.cleanup_expired().await?;
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn cleanup_expired(&self) -> Result<CleanupReport> {
+        let mut report = CleanupReport::default();
+
+        // Remove expired records, then their blobs (securely, if configured)
+        let (count, expired_files) = self.db.cleanup_expired().await?;
+        report.expired = count;
+        for deleted in &expired_files {
+            match self.delete_blob_of(deleted).await {
+                Ok(()) => report.bytes_reclaimed += deleted.blob_bytes().unwrap_or(0) as u64,
+                Err(e) => tracing::error!("Failed to delete expired blob {}: {}", deleted.storage_path, e),
+            }
+            self.notify(WebhookEventKind::Expired, deleted);
+        }
+
+        // Discard abandoned resumable uploads and their partial blobs
+        let sessions = self.db.cleanup_expired_upload_sessions().await?;
+        report.upload_sessions = sessions.len() as u64;
+        for id in &sessions {
+            if let Ok(path) = self.upload_session_path(id) {
+                let size = fs::metadata(&path).await.map(|meta| meta.len()).unwrap_or(0);
+                if crate::storage::shred(&path, self.config.load().secure_delete_passes).await.is_ok() {
+                    report.bytes_reclaimed += size;
+                }
+            }
+        }
 
         if count > 0 {
-            tracing::info!("Cleaned up {} expired files", count);
+            self.db
+                .audit(AuditActor::System, "cleanup.expire", None, Some(&format!("{} expired files and pastes", count)))
+                .await;
         }
 
-        Ok(count)
+        Ok(report)
+    }
+
+    /// Delete blobs no file refers to and staged uploads nobody finished
+    ///
+    /// Only objects older than the upload timeout (plus an hour) are touched, so a
+    /// blob committed moments before its database row is never mistaken for an orphan.
+    pub async fn collect_orphans(&self) -> Result<OrphanReport> {
+        let mut report = OrphanReport::default();
+        let (upload_dir, grace) = {
+            let config = self.config.load();
+            (config.upload_dir.clone(), config.upload_timeout + std::time::Duration::from_secs(3600))
+        };
+        let cutoff = Utc::now() - Duration::from_std(grace).map_err(anyhow::Error::from)?;
+
+        // Older records store absolute paths, so compare file names
+        let referenced: HashSet<String> = self
+            .db
+            .list_storage_paths()
+            .await?
+            .iter()
+            .filter_map(|path| std::path::Path::new(path).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+
+        for object in self.storage.list().await? {
+            if object.last_modified >= cutoff || referenced.contains(&object.key) {
+                continue;
+            }
+            // Orphans were never counted towards the quota, so storage_used stays as it is
+            match self.storage.delete(&object.key).await {
+                Ok(()) => {
+                    report.blobs += 1;
+                    report.bytes_reclaimed += object.size;
+                }
+                Err(e) => tracing::error!("Failed to delete orphaned blob {}: {}", object.key, e),
+            }
+        }
+
+        // Uploads are staged here whatever the backend
+        let mut entries = fs::read_dir(&upload_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let is_staged = entry.file_name().to_string_lossy().starts_with(".upload-");
+            let meta = entry.metadata().await?;
+            let modified: chrono::DateTime<Utc> = meta.modified()?.into();
+            if is_staged && meta.is_file() && modified < cutoff && fs::remove_file(entry.path()).await.is_ok() {
+                report.stale_uploads += 1;
+                report.bytes_reclaimed += meta.len();
+            }
+        }
+
+        if report.blobs > 0 || report.stale_uploads > 0 {
+            self.db
+                .audit(
+                    AuditActor::System,
+                    "cleanup.orphans",
+                    None,
+                    Some(&format!("{} orphaned blobs, {} stale uploads", report.blobs, report.stale_uploads)),
+                )
+                .await;
+        }
+
+        Ok(report)
     }
 
     /// View a post (with all appended content)
-    pub async fn view_post(&self, post_id: &str) -> Result<PostViewResponse> {
+    ///
+    /// Counts as a view once per `viewer` per day; views from unknown addresses always count.
+    #[tracing::instrument(level = "debug", skip_all, fields(post_id = %post_id))]
+    pub async fn view_post(&self, post_id: &str, viewer: Option<IpAddr>) -> Result<PostViewResponse> {
         let file = self
             .db
             .get_file(post_id)
             .await?
             .ok_or(AppError::NotFound)?;
 
-        // Increment view count
-        self.db.increment_view_count(post_id).await?;
+        self.ensure_not_blocked(&file).await?;
+
+        let analytics = self.config.load().analytics_enabled;
+        let counted = analytics && viewer.is_none_or(|ip| self.unique_views.first_view(post_id, ip));
+        if counted {
+            self.db.increment_view_count(post_id).await?;
+        }
 
         let post_type = file.get_post_type();
 
@@ -223,12 +1089,31 @@ impl FileService {
             is_permanent: file.is_permanent,
             expires_at: if file.is_permanent { None } else { Some(file.expires_at) },
             uploaded_at: file.uploaded_at,
-            view_count: file.view_count + 1, // +1 because we just incremented
+            view_count: analytics.then_some(file.view_count + counted as i64),
+            is_locked: post_type == PostType::Post && file.post_append_key.is_none(),
+            slug: file.slug,
             content,
         })
     }
 
+    /// Load a post and all of its entries for archiving (doesn't count as a view)
+    #[tracing::instrument(level = "debug", skip_all, fields(post_id = %post_id))]
+    pub async fn post_with_content(&self, post_id: &str) -> Result<(FileRecord, Vec<PostContent>)> {
+        let file = self.db.get_file(post_id).await?.ok_or(AppError::NotFound)?;
+        if file.get_post_type() != PostType::Post {
+            return Err(AppError::BadRequest(
+                "This is a file, not a post. Use /api/files/{id} endpoint instead.".to_string()
+            ));
+        }
+        self.ensure_not_blocked(&file).await?;
+
+        let content = self.db.get_post_content(post_id).await?;
+        Ok((file, content))
+    }
+
     /// Append content to a post (requires append key)
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(level = "debug", skip_all, fields(post_id = %post_id))]
     pub async fn append_to_post(
         &self,
         post_id: &str,
@@ -239,38 +1124,484 @@ impl FileService {
         file_extension: Option<String>,
         file_size: Option<i64>,
     ) -> Result<i64> {
-        // Verify the post exists and append key is valid
-        if !self.db.verify_append_key(post_id, append_key).await? {
+        // Verify the post exists and the master or a collaborator append key is valid
+        if !self.db.verify_append_key(post_id, append_key).await?
+            && !self.db.verify_collaborator_key(post_id, append_key).await?
+        {
+            self.ensure_not_locked(post_id).await?;
             return Err(AppError::InvalidDeletionToken); // Reuse this error type
         }
 
         // Get next content order
         let order = self.db.get_next_content_order(post_id).await?;
+        let config = self.config.load();
 
         // SECURITY: Limit number of appends to prevent memory exhaustion
-        if order >= MAX_POST_CONTENT_ENTRIES {
+        if order >= config.max_post_entries {
             return Err(AppError::BadRequest(format!(
                 "Maximum post content limit reached ({} entries)",
-                MAX_POST_CONTENT_ENTRIES
+                config.max_post_entries
             )));
         }
 
+        // Bound the size before decoding anything
+        if base64_decoded_len(&content_encrypted) > config.max_post_entry_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Post entry exceeds the maximum of {} bytes",
+                config.max_post_entry_bytes
+            )));
+        }
+
+        // Garbage entries would break every client rendering the post; measure the
+        // decoded entry instead of trusting the declared size
+        let size_bytes = BASE64
+            .decode(&content_encrypted)
+            .map_err(|e| AppError::InvalidField {
+                field: "content",
+                message: format!("content is not valid base64: {}", e),
+            })?
+            .len() as u64;
+
+        // SECURITY: Cap the post as a whole so many medium appends can't grow the database without bound
+        let post_bytes = self.db.get_post_content_bytes(post_id).await? as u64;
+        if post_bytes + size_bytes > config.max_post_total_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Post is full ({} of {} bytes used)",
+                post_bytes, config.max_post_total_bytes
+            )));
+        }
+        // Encryption only adds bytes, so the plaintext can't be larger than the entry
+        if let Some(claimed) = file_size {
+            if claimed < 0 || claimed as u64 > size_bytes {
+                return Err(AppError::InvalidField {
+                    field: "file_size",
+                    message: format!("file_size {} doesn't match the entry ({} encrypted bytes)", claimed, size_bytes),
+                });
+            }
+        }
+
         // Default to markdown if not specified
         let content_type = content_type.unwrap_or_else(|| "markdown".to_string());
+        let content_kind: PostContentType = content_type.parse().unwrap_or_default();
+
+        let file_extension = normalize_file_extension(file_extension)?;
+        if content_kind == PostContentType::File {
+            config
+                .content_policy
+                .check(mime_type.as_deref(), file_extension.as_deref())
+                .map_err(AppError::UnsupportedMediaType)?;
+        }
+
+        // Record the measured size for attachments (and whenever one was declared)
+        let file_size = (file_size.is_some() || content_kind == PostContentType::File)
+            .then_some(size_bytes as i64);
+
+        let entry = PostContentView {
+            content_encrypted,
+            appended_at: Utc::now(),
+            order,
+            content_type: content_kind,
+            mime_type,
+            file_extension,
+            file_size,
+        };
 
         // Add content
         self.db.add_post_content(
             post_id,
-            &content_encrypted,
+            &entry.content_encrypted,
             order,
             &content_type,
-            mime_type.as_deref(),
-            file_extension.as_deref(),
-            file_size,
+            entry.mime_type.as_deref(),
+            entry.file_extension.as_deref(),
+            entry.file_size,
         ).await?;
 
         tracing::info!("Appended {} content to post {} (order: {})", content_type, post_id, order);
 
+        self.post_events.publish(post_id, PostEvent::Appended {
+            order,
+            content_type,
+            appended_at: entry.appended_at,
+            entry: Arc::new(entry),
+        });
+
         Ok(order)
     }
+
+    /// Permanently disable appends to a post (requires append key)
+    pub async fn lock_post(&self, post_id: &str, append_key: &str) -> Result<()> {
+        if !self.db.verify_append_key(post_id, append_key).await? {
+            self.ensure_not_locked(post_id).await?;
+            return Err(AppError::InvalidDeletionToken);
+        }
+
+        // Lost a race with another lock request: the post is locked either way
+        self.db.lock_post(post_id).await?;
+        self.post_events.publish(post_id, PostEvent::Locked);
+
+        tracing::info!("Locked post {}", post_id);
+        Ok(())
+    }
+
+    /// Replace a post's append key, authorized by the current key or the deletion token
+    pub async fn rotate_append_key(
+        &self,
+        post_id: &str,
+        append_key: Option<&str>,
+        deletion_token: Option<&str>,
+    ) -> Result<String> {
+        let authorized = match (append_key, deletion_token) {
+            (Some(key), None) => self.db.verify_append_key(post_id, key).await?,
+            (None, Some(token)) => self.db.verify_deletion_token(post_id, token).await?,
+            _ => {
+                return Err(AppError::BadRequest(
+                    "Provide exactly one of append_key or deletion_token".to_string(),
+                ))
+            }
+        };
+        if !authorized {
+            self.ensure_not_locked(post_id).await?;
+            return Err(AppError::InvalidDeletionToken);
+        }
+
+        let new_key = FileRecord::generate_append_key();
+        if !self.db.rotate_append_key(post_id, &new_key).await? {
+            // Valid deletion token, but the post is locked (or not a post at all)
+            self.ensure_not_locked(post_id).await?;
+            return Err(AppError::BadRequest("Only posts have append keys".to_string()));
+        }
+
+        tracing::info!("Rotated append key for post {}", post_id);
+        Ok(new_key)
+    }
+
+    /// Issue a collaborator append key (requires the master append key)
+    pub async fn create_post_key(
+        &self,
+        post_id: &str,
+        master_key: &str,
+        label: Option<String>,
+    ) -> Result<(PostKey, String)> {
+        self.verify_master_key(post_id, master_key).await?;
+
+        if label.as_ref().is_some_and(|l| l.chars().count() > MAX_POST_KEY_LABEL_LENGTH) {
+            return Err(AppError::BadRequest(format!(
+                "Label must be at most {} characters",
+                MAX_POST_KEY_LABEL_LENGTH
+            )));
+        }
+        if self.db.count_post_keys(post_id).await? >= MAX_POST_KEYS {
+            return Err(AppError::BadRequest(format!(
+                "Maximum collaborator key limit reached ({} keys)",
+                MAX_POST_KEYS
+            )));
+        }
+
+        let key = PostKey {
+            id: uuid::Uuid::new_v4().to_string(),
+            post_id: post_id.to_string(),
+            label,
+            created_at: Utc::now().timestamp(),
+        };
+        let append_key = FileRecord::generate_append_key();
+        self.db.create_post_key(&key, &append_key).await?;
+
+        tracing::info!("Issued collaborator key for post {}", post_id);
+        Ok((key, append_key))
+    }
+
+    /// List collaborator keys (requires the master append key)
+    pub async fn list_post_keys(&self, post_id: &str, master_key: &str) -> Result<Vec<PostKey>> {
+        self.verify_master_key(post_id, master_key).await?;
+        self.db.list_post_keys(post_id).await
+    }
+
+    /// Revoke a collaborator key (requires the master append key)
+    pub async fn revoke_post_key(&self, post_id: &str, master_key: &str, key_id: &str) -> Result<()> {
+        self.verify_master_key(post_id, master_key).await?;
+
+        if !self.db.revoke_post_key(post_id, key_id).await? {
+            return Err(AppError::NotFound);
+        }
+
+        tracing::info!("Revoked collaborator key for post {}", post_id);
+        Ok(())
+    }
+
+    async fn verify_master_key(&self, post_id: &str, master_key: &str) -> Result<()> {
+        if !self.db.verify_append_key(post_id, master_key).await? {
+            self.ensure_not_locked(post_id).await?;
+            return Err(AppError::InvalidDeletionToken);
+        }
+        Ok(())
+    }
+
+    /// Subscribe to live updates for a post
+    pub async fn subscribe_post_events(
+        &self,
+        post_id: &str,
+    ) -> Result<tokio::sync::broadcast::Receiver<PostEvent>> {
+        let file = self.db.get_file(post_id).await?.ok_or(AppError::NotFound)?;
+        if file.get_post_type() != PostType::Post {
+            return Err(AppError::BadRequest("Only posts have live updates".to_string()));
+        }
+        self.ensure_not_blocked(&file).await?;
+
+        Ok(self.post_events.subscribe(post_id))
+    }
+
+    /// Explain a failed append key check on a post that has been locked
+    async fn ensure_not_locked(&self, post_id: &str) -> Result<()> {
+        if let Some(file) = self.db.get_file(post_id).await? {
+            if file.get_post_type() == PostType::Post && file.post_append_key.is_none() {
+                return Err(AppError::Conflict("This post is locked".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the storage backend answers (health check)
+    pub async fn check_storage(&self) -> Result<()> {
+        self.storage.check().await
+    }
+
+    /// Remove every stored blob (test mode wipe)
+    pub async fn delete_all_blobs(&self) -> Result<()> {
+        self.storage_used.store(0, Ordering::Relaxed);
+        self.storage.delete_all().await
+    }
+
+    /// Path of the partial blob for a resumable upload session
+    fn upload_session_path(&self, session_id: &str) -> Result<PathBuf> {
+        let upload_dir_canonical = PathBuf::from(&self.config.load().upload_dir).canonicalize()?;
+        let path = upload_dir_canonical.join(format!(".tus-{}.part", session_id));
+
+        // SECURITY: Validate path doesn't escape upload directory
+        if !path.starts_with(&upload_dir_canonical) {
+            return Err(AppError::BadRequest("Invalid file path".to_string()));
+        }
+
+        Ok(path)
+    }
+
+    /// Create a resumable upload session with an empty partial blob
+    pub async fn create_upload_session(
+        &self,
+        upload_length: i64,
+        mut options: UploadSessionOptions,
+        limits: UploadLimits,
+    ) -> Result<UploadSession> {
+        if upload_length < 0 || upload_length as u64 > limits.max_upload_bytes {
+            return Err(AppError::FileTooLarge {
+                max_mb: limits.max_upload_bytes / (1024 * 1024),
+            });
+        }
+        // Reject now rather than after the whole blob has been uploaded
+        options.file_extension = normalize_file_extension(options.file_extension)?;
+        self.config
+            .load()
+            .content_policy
+            .check(options.mime_type.as_deref(), options.file_extension.as_deref())
+            .map_err(AppError::UnsupportedMediaType)?;
+        if options.max_downloads.is_some_and(|n| n < 1) {
+            return Err(AppError::BadRequest("max_downloads must be at least 1".to_string()));
+        }
+        self.ensure_disk_space(upload_length as u64)?;
+
+        let now = Utc::now();
+        let session = UploadSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            upload_length,
+            upload_offset: 0,
+            filename_encrypted: options.filename_encrypted,
+            mime_type: options.mime_type,
+            file_extension: options.file_extension,
+            expiry_hours: options.expiry_hours,
+            is_permanent: options.is_permanent,
+            max_downloads: options.max_downloads,
+            created_at: now.timestamp(),
+            expires_at: (now + Duration::hours(TUS_SESSION_EXPIRY_HOURS)).timestamp(),
+            file_id: None,
+            deletion_token: None,
+        };
+
+        fs::File::create(self.upload_session_path(&session.id)?).await?;
+        self.db.create_upload_session(&session).await?;
+
+        tracing::info!("Created resumable upload session ({} bytes declared)", upload_length);
+
+        Ok(session)
+    }
+
+    pub async fn get_upload_session(&self, session_id: &str) -> Result<UploadSession> {
+        self.db
+            .get_upload_session(session_id)
+            .await?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Append a chunk to a resumable upload session
+    ///
+    /// `offset` must match the bytes already received. Once the declared length is
+    /// reached the blob is hashed and committed through `store_file`, and the
+    /// returned session carries the resulting file ID and deletion token.
+    pub async fn append_upload_chunk<S, E>(
+        &self,
+        session_id: &str,
+        offset: i64,
+        mut chunks: S,
+        limits: UploadLimits,
+    ) -> Result<(UploadSession, Option<FileRecord>)>
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let _lock = self.lock_upload_session(session_id)?;
+        let mut session = self.get_upload_session(session_id).await?;
+
+        if session.is_complete() {
+            return Err(AppError::Conflict("Upload already complete".to_string()));
+        }
+        if offset != session.upload_offset {
+            return Err(AppError::Conflict(format!(
+                "Upload-Offset {} does not match current offset {}",
+                offset, session.upload_offset
+            )));
+        }
+        // Disk may have filled up since the session was created
+        self.ensure_disk_space((session.upload_length - session.upload_offset) as u64)?;
+
+        let path = self.upload_session_path(session_id)?;
+        let mut file = fs::OpenOptions::new().append(true).open(&path).await?;
+
+        // Persist progress even if the connection drops mid-chunk, so the client can resume
+        let mut received = session.upload_offset;
+        let mut stream_error = None;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    stream_error = Some(e.to_string());
+                    break;
+                }
+            };
+
+            if received + chunk.len() as i64 > session.upload_length {
+                stream_error = Some("Chunk exceeds declared Upload-Length".to_string());
+                break;
+            }
+
+            file.write_all(&chunk).await?;
+            received += chunk.len() as i64;
+        }
+        file.flush().await?;
+        file.sync_all().await?;
+
+        self.db.update_upload_offset(session_id, received).await?;
+        session.upload_offset = received;
+
+        if let Some(e) = stream_error {
+            return Err(AppError::BadRequest(format!("Failed to read upload chunk: {}", e)));
+        }
+
+        if session.upload_offset < session.upload_length {
+            return Ok((session, None));
+        }
+
+        // Upload complete: hash the assembled blob and commit it as a regular file
+        let hash_path = path.clone();
+        let blake3_hash = tokio::task::spawn_blocking(move || -> std::io::Result<String> {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_reader(std::fs::File::open(hash_path)?)?;
+            Ok(hasher.finalize().to_hex().to_string())
+        })
+        .await
+        .map_err(|e| AppError::Internal(e.into()))??;
+
+        // Commit a second link to the blob, so the part file survives a failed commit
+        // (quota, blocked content...) and a PATCH at the final offset can retry it
+        let staged_path = path.with_file_name(format!(".upload-{}.part", uuid::Uuid::new_v4()));
+        fs::hard_link(&path, &staged_path).await?;
+        let staged = StagedUpload {
+            path: staged_path,
+            size_bytes: session.upload_length,
+            blake3_hash,
+        };
+
+        let file_record = self
+            .store_file(
+                staged,
+                session.filename_encrypted.clone(),
+                session.mime_type.clone(),
+                session.expiry_hours,
+                PostType::File,
+                session.is_permanent,
+                session.file_extension.clone(),
+                session.max_downloads,
+                None,
+                limits,
+            )
+            .await?;
+        if let Err(e) = fs::remove_file(&path).await {
+            tracing::warn!("Failed to remove the part file of upload session {}: {}", session_id, e);
+        }
+
+        self.db
+            .complete_upload_session(session_id, &file_record.id, &file_record.deletion_token)
+            .await?;
+        session.file_id = Some(file_record.id.clone());
+        session.deletion_token = Some(file_record.deletion_token.clone());
+
+        Ok((session, Some(file_record)))
+    }
+
+    fn lock_upload_session(&self, session_id: &str) -> Result<SessionLock<'_>> {
+        let mut sessions = self
+            .active_sessions
+            .lock()
+            .map_err(|_| AppError::Internal(anyhow::anyhow!("Upload session lock poisoned")))?;
+
+        if !sessions.insert(session_id.to_string()) {
+            return Err(AppError::Conflict(
+                "Another request is already writing to this upload".to_string(),
+            ));
+        }
+
+        Ok(SessionLock {
+            sessions: &self.active_sessions,
+            id: session_id.to_string(),
+        })
+    }
+}
+
+/// Bytes a well-formed base64 string encodes (4 characters per 3 bytes, less any padding)
+fn base64_decoded_len(encoded: &str) -> u64 {
+    let encoded = encoded.trim_end_matches('=');
+    let len = encoded.len() as u64;
+    len / 4 * 3 + (len % 4) * 3 / 4
+}
+
+/// Normalise a declared file extension to `.ext` (lowercase ASCII letters and digits)
+///
+/// Extensions are echoed into Content-Disposition, so anything else is refused.
+pub fn normalize_file_extension(extension: Option<String>) -> Result<Option<String>> {
+    let Some(extension) = extension else {
+        return Ok(None);
+    };
+    let bare = extension.trim();
+    let bare = bare.strip_prefix('.').unwrap_or(bare);
+    if bare.is_empty() {
+        return Ok(None);
+    }
+    if bare.len() > MAX_FILE_EXTENSION_LENGTH || !bare.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::InvalidField {
+            field: "file_extension",
+            message: format!(
+                "file_extension must be a dot followed by at most {} letters or digits",
+                MAX_FILE_EXTENSION_LENGTH
+            ),
+        });
+    }
+    Ok(Some(format!(".{}", bare.to_ascii_lowercase())))
 }
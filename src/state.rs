@@ -0,0 +1,85 @@
+use crate::bans::BanList;
+use crate::captcha::Captcha;
+use crate::config::{Config, SharedConfig};
+use crate::constants::ADMIN_MESSAGE_SETTING;
+use crate::database::Database;
+use crate::geoip::GeoIp;
+use crate::pages::PageCache;
+use crate::pow::ProofOfWork;
+use crate::services::FileService;
+use crate::stats::StatsCache;
+use crate::storage;
+use crate::tor::TorExits;
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+
+/// Shared application state handed to every handler via `with_state`
+///
+/// Built once at startup so all requests share a single SQLite pool
+/// instead of opening a fresh one per request.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: SharedConfig,
+    pub db: Database,
+    pub file_service: Arc<FileService>,
+    pub pages: Arc<PageCache>,
+    pub stats: Arc<StatsCache>,
+    pub bans: Arc<BanList>,
+    /// Upload country policy (GEOIP_DATABASE)
+    pub geoip: Option<Arc<GeoIp>>,
+    pub tor_exits: Arc<TorExits>,
+    pub pow: Arc<ProofOfWork>,
+    /// Upload captcha (CAPTCHA_PROVIDER)
+    pub captcha: Option<Arc<Captcha>>,
+}
+
+impl AppState {
+    pub fn new(config: Config, db: Database) -> anyhow::Result<Self> {
+        let storage = storage::from_config(&config)?;
+        let geoip = config.geoip.as_ref().map(GeoIp::open).transpose()?.map(Arc::new);
+        let captcha = config.captcha.clone().map(Captcha::new).transpose()?.map(Arc::new);
+        let config = Arc::new(ArcSwap::from_pointee(config));
+        let file_service = Arc::new(FileService::new(config.clone(), db.clone(), storage));
+
+        Ok(Self {
+            config,
+            db,
+            file_service,
+            pages: Arc::new(PageCache::default()),
+            stats: Arc::new(StatsCache::default()),
+            bans: Arc::new(BanList::default()),
+            geoip,
+            tor_exits: Arc::new(TorExits::default()),
+            pow: Arc::new(ProofOfWork::default()),
+            captcha,
+        })
+    }
+
+    /// Apply the admin message stored through the admin API, if any (at startup)
+    pub async fn load_stored_admin_message(&self) -> anyhow::Result<()> {
+        if let Some(message) = self.db.get_setting(ADMIN_MESSAGE_SETTING).await? {
+            self.set_admin_message(Some(message).filter(|m| !m.is_empty()));
+        }
+        Ok(())
+    }
+
+    /// Replace the admin message until the next restart (persisting it is the caller's job)
+    ///
+    /// Swapping the config also invalidates the rendered pages, which show the message.
+    pub fn set_admin_message(&self, message: Option<String>) {
+        let mut config = Config::clone(&self.config.load());
+        config.admin_message = message;
+        config.admin_message_from_db = true;
+        self.config.store(Arc::new(config));
+    }
+
+    /// Reload the reloadable subset of the config (see `Config::reloaded`)
+    ///
+    /// In-flight requests keep the snapshot they started with.
+    pub fn reload_config(&self) -> anyhow::Result<()> {
+        let reloaded = self.config.load().reloaded()?;
+        self.config.store(Arc::new(reloaded));
+        tracing::info!("🔄 Configuration reloaded");
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+//! Cached public statistics
+//!
+//! Gathering stats takes several full-table aggregates plus a statvfs, so a
+//! background task recomputes them every STATS_CACHE_SECS and `/api/stats`
+//! serves the latest copy.
+
+use crate::error::Result;
+use crate::models::StatsResponse;
+use crate::state::AppState;
+use arc_swap::ArcSwapOption;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Stats as of `computed_at`
+pub struct CachedStats {
+    pub stats: StatsResponse,
+    pub computed_at: Instant,
+}
+
+#[derive(Default)]
+pub struct StatsCache {
+    latest: ArcSwapOption<CachedStats>,
+}
+
+impl StatsCache {
+    /// The cached stats if they are no older than `max_age`
+    pub fn get(&self, max_age: Duration) -> Option<Arc<CachedStats>> {
+        self.latest
+            .load_full()
+            .filter(|cached| cached.computed_at.elapsed() <= max_age)
+    }
+
+    /// Recompute and store the stats
+    pub async fn refresh(&self, db: &crate::database::Database, analytics: bool) -> Result<Arc<CachedStats>> {
+        let cached = Arc::new(CachedStats {
+            stats: crate::handlers::gather_stats(db, analytics).await?,
+            computed_at: Instant::now(),
+        });
+        self.latest.store(Some(cached.clone()));
+        Ok(cached)
+    }
+}
+
+/// Background task keeping the stats cache warm (not started when caching is off)
+pub async fn start_refresh_task(state: AppState, every: Duration) {
+    let mut interval = tokio::time::interval(every);
+    tracing::info!("📊 Refreshing stats every {}s", every.as_secs());
+    loop {
+        interval.tick().await;
+        let analytics = state.config.load().analytics_enabled;
+        if let Err(e) = state.stats.refresh(&state.db, analytics).await {
+            tracing::error!("❌ Refreshing stats failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! Pluggable storage for encrypted blobs
+//!
+//! Uploads are always staged on local disk first (so they can be hashed and
+//! deduplicated); the backend only decides where committed blobs live.
+//! Storage keys are what `files.storage_path` records.
+
+use crate::config::{Config, StorageConfig};
+use crate::constants::SHRED_CHUNK_SIZE;
+use crate::error::{AppError, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::stream::{BoxStream, StreamExt, TryStreamExt};
+use object_store::{aws::AmazonS3Builder, path::Path as ObjectPath, GetOptions, GetRange, ObjectStore};
+use rand::RngCore;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+/// Stream of encrypted blob chunks
+pub type BlobStream = BoxStream<'static, std::io::Result<Bytes>>;
+
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Commit a fully written local file under `key` (the local file is consumed)
+    async fn put(&self, key: &str, local_path: &Path) -> Result<()>;
+
+    /// Read a whole blob into memory (downloads should use `stream`)
+    #[allow(dead_code)]
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// Open a blob for streaming, returning its size in bytes
+    async fn stream(&self, key: &str) -> Result<(u64, BlobStream)>;
+
+    /// Stream the bytes `range` of a blob (the caller checks the range against its size)
+    async fn stream_range(&self, key: &str, range: Range<u64>) -> Result<BlobStream>;
+
+    /// Remove a blob (missing blobs are not an error)
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Remove every stored blob (test mode wipe)
+    async fn delete_all(&self) -> Result<()>;
+
+    /// Confirm the backend is reachable (health check)
+    async fn check(&self) -> Result<()>;
+
+    /// Every committed blob (orphan collection)
+    async fn list(&self) -> Result<Vec<StoredObject>>;
+}
+
+/// A blob as the backend sees it
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Build the backend selected by `STORAGE_BACKEND`
+pub fn from_config(config: &Config) -> anyhow::Result<Arc<dyn StorageBackend>> {
+    match &config.storage {
+        StorageConfig::Local => Ok(Arc::new(LocalStorage::new(
+            &config.upload_dir,
+            config.secure_delete_passes,
+        ))),
+        StorageConfig::S3 { bucket, region, endpoint, allow_http } => {
+            if config.secure_delete_passes > 0 {
+                tracing::warn!("⚠️  SECURE_DELETE_PASSES has no effect on S3 storage; objects are deleted normally");
+            }
+
+            tracing::info!("Using S3 storage backend (bucket: {})", bucket);
+            let store = s3_store(bucket, region, endpoint.as_deref(), *allow_http)?;
+            Ok(Arc::new(S3Storage { store }))
+        }
+    }
+}
+
+/// Client for an S3-compatible bucket
+///
+/// Credentials come from the standard AWS_* environment variables.
+pub fn s3_store(
+    bucket: &str,
+    region: &str,
+    endpoint: Option<&str>,
+    allow_http: bool,
+) -> anyhow::Result<Arc<dyn ObjectStore>> {
+    let mut builder = AmazonS3Builder::from_env()
+        .with_bucket_name(bucket)
+        .with_region(region)
+        .with_allow_http(allow_http);
+    if let Some(endpoint) = endpoint {
+        builder = builder.with_endpoint(endpoint);
+    }
+    Ok(Arc::new(builder.build()?))
+}
+
+/// Space on the filesystem holding a path, in bytes
+pub struct DiskSpace {
+    pub total: u64,
+    pub free: u64,
+    /// Free space usable by unprivileged processes (excludes root-reserved blocks)
+    pub available: u64,
+}
+
+pub fn disk_space(path: impl AsRef<Path>) -> std::io::Result<DiskSpace> {
+    let stats = nix::sys::statvfs::statvfs(path.as_ref()).map_err(std::io::Error::from)?;
+    let block_size = stats.fragment_size() as u64;
+
+    Ok(DiskSpace {
+        total: stats.blocks() as u64 * block_size,
+        free: stats.blocks_free() as u64 * block_size,
+        available: stats.blocks_available() as u64 * block_size,
+    })
+}
+
+/// Overwrite a file with random data `passes` times, then unlink it
+///
+/// Each pass is synced to disk before the next starts. This only helps on
+/// filesystems that overwrite in place; copy-on-write filesystems, SSD wear
+/// levelling and snapshots can still retain old contents. Files with other
+/// hard links (a committed resumable upload) are only unlinked.
+pub async fn shred(path: &Path, passes: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    if passes > 0 && fs::metadata(path).await?.nlink() == 1 {
+        let mut file = fs::OpenOptions::new().write(true).open(path).await?;
+        let len = file.metadata().await?.len();
+        let mut buf = vec![0u8; SHRED_CHUNK_SIZE];
+
+        for _ in 0..passes {
+            file.rewind().await?;
+            let mut remaining = len;
+            while remaining > 0 {
+                let n = remaining.min(buf.len() as u64) as usize;
+                rand::thread_rng().fill_bytes(&mut buf[..n]);
+                file.write_all(&buf[..n]).await?;
+                remaining -= n as u64;
+            }
+            file.sync_all().await?;
+        }
+    }
+
+    fs::remove_file(path).await
+}
+
+/// Delete a blob once `stream` has been read to the end or dropped
+///
+/// For the last allowed download: the blob can't be removed (let alone shredded)
+/// while it is still being sent.
+pub fn delete_after(storage: Arc<dyn StorageBackend>, key: String, stream: BlobStream) -> BlobStream {
+    struct DeleteOnDrop {
+        storage: Arc<dyn StorageBackend>,
+        key: String,
+    }
+
+    impl Drop for DeleteOnDrop {
+        fn drop(&mut self) {
+            let storage = self.storage.clone();
+            let key = std::mem::take(&mut self.key);
+            // Without a runtime (shutdown) the blob is left to orphan collection
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(async move {
+                    if let Err(e) = storage.delete(&key).await {
+                        tracing::error!("Failed to delete exhausted file from storage: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    let guard = DeleteOnDrop { storage, key };
+    stream
+        .map(move |chunk| {
+            let _ = &guard;
+            chunk
+        })
+        .boxed()
+}
+
+/// Blobs stored as files in the upload directory
+pub struct LocalStorage {
+    upload_dir: PathBuf,
+    /// Secure delete overwrite passes (0 = plain unlink)
+    shred_passes: u32,
+}
+
+impl LocalStorage {
+    pub fn new(upload_dir: &str, shred_passes: u32) -> Self {
+        Self {
+            upload_dir: PathBuf::from(upload_dir),
+            shred_passes,
+        }
+    }
+
+    /// Resolve a storage key to a path inside the upload directory
+    ///
+    /// Older records store absolute paths rather than bare keys; both are accepted.
+    fn resolve(&self, key: &str) -> Result<PathBuf> {
+        let upload_dir_canonical = self.upload_dir.canonicalize()?;
+        let path = upload_dir_canonical.join(key);
+
+        // SECURITY: Validate path doesn't escape upload directory
+        if key.contains("..") || !path.starts_with(&upload_dir_canonical) {
+            return Err(AppError::BadRequest("Invalid file path".to_string()));
+        }
+
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(&self, key: &str, local_path: &Path) -> Result<()> {
+        fs::rename(local_path, self.resolve(key)?).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.resolve(key)?).await?)
+    }
+
+    async fn stream(&self, key: &str) -> Result<(u64, BlobStream)> {
+        let file = fs::File::open(self.resolve(key)?).await?;
+        let size = file.metadata().await?.len();
+        Ok((size, ReaderStream::new(file).boxed()))
+    }
+
+    async fn stream_range(&self, key: &str, range: Range<u64>) -> Result<BlobStream> {
+        let mut file = fs::File::open(self.resolve(key)?).await?;
+        file.seek(std::io::SeekFrom::Start(range.start)).await?;
+        Ok(ReaderStream::new(file.take(range.end - range.start)).boxed())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match shred(&self.resolve(key)?, self.shred_passes).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_all(&self) -> Result<()> {
+        // Blob by blob so they are shredded, leaving uploads being staged alone
+        for object in self.list().await? {
+            self.delete(&object.key).await?;
+        }
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<()> {
+        if !fs::metadata(&self.upload_dir).await?.is_dir() {
+            return Err(AppError::Internal(anyhow::anyhow!("upload directory is not a directory")));
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        let mut entries = fs::read_dir(&self.upload_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let key = entry.file_name().to_string_lossy().into_owned();
+            let meta = entry.metadata().await?;
+            // Dotfiles are staged uploads and restore scratch space, not blobs
+            if key.starts_with('.') || !meta.is_file() {
+                continue;
+            }
+            objects.push(StoredObject {
+                key,
+                size: meta.len(),
+                last_modified: meta.modified()?.into(),
+            });
+        }
+        Ok(objects)
+    }
+}
+
+/// Blobs stored in an S3-compatible bucket
+pub struct S3Storage {
+    store: Arc<dyn ObjectStore>,
+}
+
+fn object_error(e: object_store::Error) -> AppError {
+    match e {
+        object_store::Error::NotFound { .. } => AppError::NotFound,
+        e => AppError::Internal(e.into()),
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, key: &str, local_path: &Path) -> Result<()> {
+        // Multipart upload straight from the staged file, never buffering the whole blob
+        let mut source = fs::File::open(local_path).await?;
+        let mut writer = object_store::buffered::BufWriter::new(self.store.clone(), ObjectPath::from(key));
+        tokio::io::copy(&mut source, &mut writer).await?;
+        writer.shutdown().await?;
+
+        fs::remove_file(local_path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let result = self.store.get(&ObjectPath::from(key)).await.map_err(object_error)?;
+        let bytes = result.bytes().await.map_err(object_error)?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn stream(&self, key: &str) -> Result<(u64, BlobStream)> {
+        let result = self.store.get(&ObjectPath::from(key)).await.map_err(object_error)?;
+        let size = result.meta.size as u64;
+        let stream = result
+            .into_stream()
+            .map_err(std::io::Error::other)
+            .boxed();
+        Ok((size, stream))
+    }
+
+    async fn stream_range(&self, key: &str, range: Range<u64>) -> Result<BlobStream> {
+        let options = GetOptions {
+            range: Some(GetRange::Bounded(range.start as usize..range.end as usize)),
+            ..Default::default()
+        };
+        let result = self.store.get_opts(&ObjectPath::from(key), options).await.map_err(object_error)?;
+        Ok(result.into_stream().map_err(std::io::Error::other).boxed())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self.store.delete(&ObjectPath::from(key)).await {
+            Err(e) if !matches!(e, object_store::Error::NotFound { .. }) => Err(object_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_all(&self) -> Result<()> {
+        let keys = self
+            .store
+            .list(None)
+            .map_ok(|meta| meta.location)
+            .boxed();
+        self.store
+            .delete_stream(keys)
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(object_error)?;
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<()> {
+        // Any answer from the bucket will do; a missing key still proves it is reachable
+        match self.store.head(&ObjectPath::from(".dogbox-health")).await {
+            Err(e) if !matches!(e, object_store::Error::NotFound { .. }) => Err(object_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<StoredObject>> {
+        self.store
+            .list(None)
+            .map_ok(|meta| StoredObject {
+                key: meta.location.to_string(),
+                size: meta.size as u64,
+                last_modified: meta.last_modified,
+            })
+            .try_collect()
+            .await
+            .map_err(object_error)
+    }
+}
@@ -0,0 +1,165 @@
+//! Logging setup, OpenTelemetry trace export and Sentry error reporting
+//!
+//! Logging is set up before the config is read, so the OTLP layer starts out
+//! empty and gets filled in once OTLP_ENDPOINT is known. Spans come from the
+//! request TraceLayer plus the instrumented service and database methods.
+
+use crate::config::Config;
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{Request, Response},
+};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use std::time::Duration;
+use tracing::Span;
+use tracing_subscriber::{layer::SubscriberExt, registry::Registry, reload, util::SubscriberInitExt, Layer};
+
+type OtelLayer = Option<Box<dyn Layer<Registry> + Send + Sync>>;
+
+/// Slot in the subscriber stack for the OTLP layer
+pub type OtelHandle = reload::Handle<OtelLayer, Registry>;
+
+/// Log output format (LOG_FORMAT)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines (the default)
+    Text,
+    /// One JSON object per line, for Loki/ELK
+    Json,
+}
+
+impl LogFormat {
+    /// Read LOG_FORMAT, which has to come from the environment since logging
+    /// starts before the config file is loaded
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("LOG_FORMAT").as_deref() {
+            Ok("text") | Err(_) => Ok(Self::Text),
+            Ok("json") => Ok(Self::Json),
+            Ok(other) => anyhow::bail!("Unknown LOG_FORMAT '{}' (expected 'text' or 'json')", other),
+        }
+    }
+}
+
+/// Install the global subscriber, with an empty slot for the OTLP layer
+pub fn init_logging(format: LogFormat) -> OtelHandle {
+    let (otel_layer, otel_handle) = reload::Layer::new(None);
+    let registry = tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "dogbox=debug,tower_http=debug,axum=trace".into()),
+        );
+    match format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        // Event fields sit at the top level; the request span's fields go under "span"
+        LogFormat::Json => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false),
+            )
+            .init(),
+    }
+    otel_handle
+}
+
+/// Span for one HTTP request. The field names are stable so log pipelines can
+/// index them: `method`, `route` (the matched route pattern, so IDs don't blow up
+/// cardinality), `request_id`.
+pub fn make_request_span(request: &Request<Body>) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        route = %route,
+        request_id = %request_id,
+        otel.name = %format!("{} {}", request.method(), route),
+    )
+}
+
+/// Completion event with the stable `status` and `latency_ms` fields
+pub fn log_response(response: &Response<Body>, latency: Duration, _span: &Span) {
+    tracing::info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "request completed"
+    );
+}
+
+/// Start exporting traces if OTLP_ENDPOINT is set. The returned provider must be
+/// shut down on exit to flush spans still queued.
+pub fn init(config: &Config, handle: &OtelHandle) -> anyhow::Result<Option<SdkTracerProvider>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpBinary)
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.otlp_service_name.clone())
+                .build(),
+        )
+        .build();
+
+    let tracer = provider.tracer("dogbox");
+    handle.modify(|layer| {
+        *layer = Some(tracing_opentelemetry::layer().with_tracer(tracer).boxed());
+    })?;
+
+    tracing::info!("🔭 Exporting traces to {} as {}", endpoint, config.otlp_service_name);
+    Ok(Some(provider))
+}
+
+/// Start reporting errors and panics to Sentry if SENTRY_DSN is set. Events are
+/// sent while the returned guard is alive and flushed when it is dropped.
+pub fn init_sentry(config: &Config) -> Option<sentry::ClientInitGuard> {
+    let dsn = config.sentry_dsn.as_deref()?;
+    let guard = sentry::init((
+        dsn,
+        sentry::ClientOptions {
+            release: sentry::release_name!(),
+            environment: config.sentry_environment.clone().map(Into::into),
+            // Never attach client IPs or headers; uploaders stay anonymous
+            send_default_pii: false,
+            ..Default::default()
+        },
+    ));
+    tracing::info!("🐛 Reporting errors to Sentry");
+    Some(guard)
+}
+
+/// Send an internal error to Sentry, tagged with the current request
+/// (a no-op when Sentry isn't configured)
+pub fn report_error(err: &(dyn std::error::Error + 'static)) {
+    let request = crate::middleware::current_request();
+    sentry::with_scope(
+        |scope| {
+            if let Some(request) = request {
+                scope.set_tag("request_id", request.id);
+                scope.set_tag("method", request.method);
+                scope.set_tag("route", request.route);
+            }
+        },
+        || sentry::capture_error(err),
+    );
+}
@@ -0,0 +1,61 @@
+//! Tor exit node list for TOR_EXIT_POLICY
+//!
+//! The list is downloaded at startup and every TOR_EXIT_LIST_REFRESH_MINS. A failed
+//! download keeps the previous list, so a Tor Project outage doesn't open the gate.
+
+use crate::constants::TOR_EXIT_LIST_TIMEOUT_SECS;
+use crate::state::AppState;
+use arc_swap::ArcSwap;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct TorExits {
+    exits: ArcSwap<HashSet<IpAddr>>,
+}
+
+impl TorExits {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.exits.load().contains(&ip.to_canonical())
+    }
+
+    /// Download the exit list and replace the current one, returning its length
+    pub async fn refresh(&self, url: &str) -> anyhow::Result<usize> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(TOR_EXIT_LIST_TIMEOUT_SECS))
+            .user_agent(concat!("dogbox/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let body = client.get(url).send().await?.error_for_status()?.text().await?;
+
+        // Bulk lists have one address per line; exit-addresses has "ExitAddress <ip> <date>"
+        let exits: HashSet<IpAddr> = body
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_whitespace().find_map(|token| token.parse().ok()))
+            .collect();
+        if exits.is_empty() {
+            anyhow::bail!("no addresses in the exit list");
+        }
+
+        let count = exits.len();
+        self.exits.store(std::sync::Arc::new(exits));
+        Ok(count)
+    }
+}
+
+/// Background task keeping the exit list current (first download right away)
+pub async fn start_refresh_task(state: AppState) {
+    let (url, every) = {
+        let config = state.config.load();
+        (config.tor_exit_list_url.clone(), config.tor_exit_list_refresh)
+    };
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match state.tor_exits.refresh(&url).await {
+            Ok(count) => tracing::info!("🧅 Loaded {} Tor exit addresses", count),
+            Err(e) => tracing::error!("❌ Downloading the Tor exit list failed: {}", e),
+        }
+    }
+}
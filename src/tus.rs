@@ -0,0 +1,294 @@
+//! tus resumable upload protocol (https://tus.io/protocols/resumable-upload)
+//!
+//! Implements the core protocol plus the `creation` extension so clients on flaky
+//! connections can resume multi-gigabyte encrypted uploads instead of restarting.
+//! Completed uploads become regular files; the file ID and deletion token are
+//! returned in `Dogbox-*` headers on the final PATCH (and on HEAD afterwards).
+
+use crate::constants::TUS_VERSION;
+use crate::error::{AppError, Result};
+use crate::middleware::TrustedClient;
+use crate::models::UploadSession;
+use crate::services::UploadSessionOptions;
+use crate::state::AppState;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+const DOGBOX_FILE_ID: HeaderName = HeaderName::from_static("dogbox-file-id");
+const DOGBOX_DELETION_TOKEN: HeaderName = HeaderName::from_static("dogbox-deletion-token");
+const DOGBOX_URL: HeaderName = HeaderName::from_static("dogbox-url");
+
+/// Base headers sent on every tus response
+fn tus_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers
+}
+
+/// Rejection for clients speaking a different protocol version (None if supported)
+fn version_mismatch(headers: &HeaderMap) -> Option<Response> {
+    match headers.get(&TUS_RESUMABLE).and_then(|v| v.to_str().ok()) {
+        Some(TUS_VERSION) => None,
+        _ => {
+            let mut response_headers = tus_headers();
+            response_headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+            Some((StatusCode::PRECONDITION_FAILED, response_headers).into_response())
+        }
+    }
+}
+
+fn parse_i64_header(headers: &HeaderMap, name: &HeaderName) -> Result<i64> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| AppError::BadRequest(format!("Missing or invalid {} header", name)))
+}
+
+/// Parse `Upload-Metadata` ("key base64value,key2 base64value2") into upload options
+fn parse_upload_metadata(headers: &HeaderMap) -> Result<UploadSessionOptions> {
+    let mut options = UploadSessionOptions::default();
+
+    let Some(raw) = headers.get(&UPLOAD_METADATA) else {
+        return Ok(options);
+    };
+    let raw = raw
+        .to_str()
+        .map_err(|_| AppError::BadRequest("Invalid Upload-Metadata header".to_string()))?;
+
+    for pair in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = match pair.split_once(' ') {
+            Some((key, encoded)) => {
+                let decoded = BASE64
+                    .decode(encoded.trim())
+                    .map_err(|_| AppError::BadRequest(format!("Invalid base64 in Upload-Metadata for {}", key)))?;
+                let value = String::from_utf8(decoded)
+                    .map_err(|_| AppError::BadRequest(format!("Invalid UTF-8 in Upload-Metadata for {}", key)))?;
+                (key, value)
+            }
+            None => (pair, String::new()),
+        };
+
+        match key {
+            "filename" => options.filename_encrypted = Some(value),
+            "mime_type" | "filetype" => options.mime_type = Some(value),
+            "file_extension" => options.file_extension = Some(value),
+            "expiry_hours" => {
+                options.expiry_hours = Some(value.parse().map_err(|_| {
+                    AppError::BadRequest("Invalid expiry_hours value".to_string())
+                })?);
+            }
+            "is_permanent" => {
+                options.is_permanent = value.parse().map_err(|_| {
+                    AppError::BadRequest("Invalid is_permanent value".to_string())
+                })?;
+            }
+            "max_downloads" => {
+                options.max_downloads = Some(value.parse().map_err(|_| {
+                    AppError::BadRequest("Invalid max_downloads value".to_string())
+                })?);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(options)
+}
+
+/// Headers describing a session's progress (and result, once complete)
+fn session_headers(session: &UploadSession) -> HeaderMap {
+    let mut headers = tus_headers();
+    headers.insert(UPLOAD_OFFSET, session.upload_offset.into());
+    headers.insert(UPLOAD_LENGTH, session.upload_length.into());
+
+    if let Some(expires) = chrono::DateTime::from_timestamp(session.expires_at, 0) {
+        if let Ok(value) = expires.format("%a, %d %b %Y %H:%M:%S GMT").to_string().parse() {
+            headers.insert(UPLOAD_EXPIRES, value);
+        }
+    }
+
+    if let (Some(file_id), Some(token)) = (&session.file_id, &session.deletion_token) {
+        if let (Ok(id_value), Ok(token_value), Ok(url_value)) = (
+            file_id.parse(),
+            token.parse(),
+            format!("/f/{}", file_id).parse(),
+        ) {
+            headers.insert(DOGBOX_FILE_ID, id_value);
+            headers.insert(DOGBOX_DELETION_TOKEN, token_value);
+            headers.insert(DOGBOX_URL, url_value);
+        }
+    }
+
+    headers
+}
+
+/// Advertise tus protocol support
+#[utoipa::path(
+    options,
+    path = "/api/tus",
+    tag = "dogbox.moe",
+    responses(
+        (status = 204, description = "Supported tus version, extensions and maximum size")
+    )
+)]
+pub async fn options(
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+) -> impl IntoResponse {
+    let mut headers = tus_headers();
+    headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+    headers.insert(TUS_EXTENSION, HeaderValue::from_static("creation"));
+    headers.insert(
+        TUS_MAX_SIZE,
+        state.file_service.upload_limits(trusted.is_some()).max_upload_bytes.into(),
+    );
+    (StatusCode::NO_CONTENT, headers)
+}
+
+/// Create a resumable upload session
+///
+/// Requires `Upload-Length`. Optional `Upload-Metadata` keys: filename, mime_type,
+/// file_extension, expiry_hours, is_permanent, max_downloads.
+#[utoipa::path(
+    post,
+    path = "/api/tus",
+    tag = "dogbox.moe",
+    params(
+        ("Tus-Resumable" = String, Header, description = "Protocol version (1.0.0)"),
+        ("Upload-Length" = i64, Header, description = "Total size of the encrypted blob"),
+        ("Upload-Metadata" = Option<String>, Header, description = "Comma-separated key/base64-value pairs")
+    ),
+    responses(
+        (status = 201, description = "Upload session created (see Location header)"),
+        (status = 412, description = "Unsupported tus version"),
+        (status = 413, description = "Upload-Length exceeds maximum upload size"),
+        (status = 415, description = "Declared MIME type or extension not accepted")
+    )
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    if let Some(response) = version_mismatch(&headers) {
+        return Ok(response);
+    }
+
+    let upload_length = parse_i64_header(&headers, &UPLOAD_LENGTH)?;
+    let options = parse_upload_metadata(&headers)?;
+
+    let session = state
+        .file_service
+        .create_upload_session(upload_length, options, state.file_service.upload_limits(trusted.is_some()))
+        .await?;
+
+    let mut response_headers = session_headers(&session);
+    if let Ok(location) = format!("/api/tus/{}", session.id).parse() {
+        response_headers.insert(header::LOCATION, location);
+    }
+
+    Ok((StatusCode::CREATED, response_headers).into_response())
+}
+
+/// Get the current offset of a resumable upload
+#[utoipa::path(
+    head,
+    path = "/api/tus/{id}",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Upload session ID"),
+        ("Tus-Resumable" = String, Header, description = "Protocol version (1.0.0)")
+    ),
+    responses(
+        (status = 200, description = "Upload-Offset and Upload-Length headers"),
+        (status = 404, description = "Upload session not found or expired")
+    )
+)]
+pub async fn upload_offset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    if let Some(response) = version_mismatch(&headers) {
+        return Ok(response);
+    }
+
+    let session = state.file_service.get_upload_session(&id).await?;
+
+    Ok((StatusCode::OK, session_headers(&session)).into_response())
+}
+
+/// Upload a chunk of a resumable upload
+///
+/// Body is raw encrypted bytes (`Content-Type: application/offset+octet-stream`)
+/// starting at `Upload-Offset`.
+#[utoipa::path(
+    patch,
+    path = "/api/tus/{id}",
+    tag = "dogbox.moe",
+    params(
+        ("id" = String, Path, description = "Upload session ID"),
+        ("Tus-Resumable" = String, Header, description = "Protocol version (1.0.0)"),
+        ("Upload-Offset" = i64, Header, description = "Offset this chunk starts at")
+    ),
+    request_body(content = inline(Vec<u8>), description = "Encrypted blob chunk", content_type = "application/offset+octet-stream"),
+    responses(
+        (status = 204, description = "Chunk accepted (new Upload-Offset header; Dogbox-* headers once complete)"),
+        (status = 404, description = "Upload session not found or expired"),
+        (status = 409, description = "Offset mismatch or upload busy"),
+        (status = 415, description = "Wrong Content-Type"),
+        (status = 507, description = "Disk nearly full, or storage quota reached (on the final chunk)")
+    )
+)]
+pub async fn upload_chunk(
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response> {
+    if let Some(response) = version_mismatch(&headers) {
+        return Ok(response);
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if content_type != "application/offset+octet-stream" {
+        return Ok((StatusCode::UNSUPPORTED_MEDIA_TYPE, tus_headers()).into_response());
+    }
+
+    let offset = parse_i64_header(&headers, &UPLOAD_OFFSET)?;
+
+    let (session, file) = state
+        .file_service
+        .append_upload_chunk(
+            &id,
+            offset,
+            body.into_data_stream(),
+            state.file_service.upload_limits(trusted.is_some()),
+        )
+        .await?;
+
+    if let Some(file) = file {
+        tracing::info!("Resumable upload completed as file {}", file.id);
+    }
+
+    Ok((StatusCode::NO_CONTENT, session_headers(&session)).into_response())
+}
@@ -0,0 +1,66 @@
+//! Unique view counting without storing who viewed what
+//!
+//! A post's view count goes up once per viewer per UTC day. Viewers are
+//! remembered as SHA-256(salt, IP, post ID), and the salt is random, kept only
+//! in memory and replaced (forgetting every hash) when the day changes, so
+//! yesterday's hashes can't be linked to addresses or to today's. A restart
+//! forgets them too, which at worst counts a viewer twice.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Viewers remembered per day before starting over early (about 16 MB of hashes)
+const MAX_TRACKED_VIEWS: usize = 1_000_000;
+
+struct Window {
+    day: i64,
+    salt: [u8; 32],
+    seen: HashSet<[u8; 16]>,
+}
+
+impl Window {
+    fn new(day: i64) -> Self {
+        let mut salt = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self { day, salt, seen: HashSet::new() }
+    }
+}
+
+pub struct UniqueViews {
+    window: Mutex<Window>,
+}
+
+impl Default for UniqueViews {
+    fn default() -> Self {
+        Self { window: Mutex::new(Window::new(today())) }
+    }
+}
+
+impl UniqueViews {
+    /// Whether this is `ip`'s first view of `post_id` today (and remember it if so)
+    pub fn first_view(&self, post_id: &str, ip: IpAddr) -> bool {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        let day = today();
+        if window.day != day || window.seen.len() >= MAX_TRACKED_VIEWS {
+            *window = Window::new(day);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(window.salt);
+        hasher.update(ip.to_canonical().to_string());
+        hasher.update([0]);
+        hasher.update(post_id);
+        let digest = hasher.finalize();
+
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&digest[..16]);
+        window.seen.insert(key)
+    }
+}
+
+fn today() -> i64 {
+    chrono::Utc::now().timestamp().div_euclid(86_400)
+}
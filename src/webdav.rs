@@ -0,0 +1,411 @@
+//! Minimal WebDAV surface at /dav/ (WEBDAV_ENABLED), for file managers and scripts
+//!
+//! Every trusted API key gets its own namespace of paths, each pointing at an
+//! ordinary file upload: `PUT` stores the body through `FileService` (same limits
+//! and policies as `PUT /api/raw`, but never deduplicated, so deleting a path
+//! can't take out another upload), `GET` streams it back and `DELETE` removes
+//! it. `PROPFIND` lists the namespace so it can be mounted. Directories aren't
+//! stored; they exist while they contain files, and `MKCOL` merely succeeds.
+//! The server never sees keys here either, but it stores bodies as-is, so
+//! clients should encrypt before uploading (e.g. an rclone `crypt` remote).
+//!
+//! WebDAV clients can only do HTTP Basic, so the API key may be given as the
+//! Basic password (see `middleware::api_key`).
+
+use crate::constants::MAX_DAV_PATH_LENGTH;
+use crate::error::{AppError, Result};
+use crate::middleware::TrustedClient;
+use crate::models::{DavEntry, FileRecord};
+use crate::services::{StagedUpload, UploadLimits};
+use crate::state::AppState;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Methods answered under /dav/
+const ALLOW: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, MKCOL";
+
+/// Everything but unreserved characters is escaped in hrefs
+const HREF_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Whether a request path is under /dav/
+pub fn is_dav_path(path: &str) -> bool {
+    path == "/dav" || path.starts_with("/dav/")
+}
+
+/// Handle any WebDAV request (404 unless WEBDAV_ENABLED)
+pub async fn handler(
+    State(state): State<AppState>,
+    trusted: Option<Extension<TrustedClient>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response> {
+    if !state.config.load().webdav_enabled {
+        return Err(AppError::NotFound);
+    }
+    // Clients probe for DAV support before they authenticate
+    if method == Method::OPTIONS {
+        return Ok(options());
+    }
+    let Some(Extension(trusted)) = trusted else {
+        return Ok(unauthorized());
+    };
+    let path = namespace_path(uri.path())?;
+    let key_id = trusted.key_id.as_str();
+
+    match method.as_str() {
+        "GET" | "HEAD" => get(&state, key_id, &path, method == Method::HEAD).await,
+        "PUT" => put(&state, key_id, &path, &headers, body).await,
+        "DELETE" => delete(&state, key_id, &path).await,
+        "PROPFIND" => propfind(&state, key_id, &path, &headers).await,
+        "MKCOL" => mkcol(&state, key_id, &path).await,
+        _ => Ok(method_not_allowed()),
+    }
+}
+
+/// The request's path within the namespace: percent-decoded, without `/dav/` or a trailing slash
+fn namespace_path(uri_path: &str) -> Result<String> {
+    let raw = uri_path.strip_prefix("/dav").unwrap_or_default();
+    let decoded = percent_decode_str(raw)
+        .decode_utf8()
+        .map_err(|_| AppError::BadRequest("Path is not valid UTF-8".to_string()))?;
+    let path = decoded.trim_matches('/');
+
+    if !path.is_empty() {
+        check_path(path)?;
+    }
+    Ok(path.to_string())
+}
+
+/// Reject paths that couldn't be mounted: too long, or with empty, `.`, `..` or control character segments
+pub(crate) fn check_path(path: &str) -> Result<()> {
+    if path.len() > MAX_DAV_PATH_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "Path exceeds the maximum of {} bytes",
+            MAX_DAV_PATH_LENGTH
+        )));
+    }
+    let invalid_segment = |segment: &str| {
+        segment.is_empty() || segment == "." || segment == ".." || segment.chars().any(char::is_control)
+    };
+    if path.split('/').any(invalid_segment) {
+        return Err(AppError::BadRequest("Invalid path".to_string()));
+    }
+    Ok(())
+}
+
+async fn get(state: &AppState, key_id: &str, path: &str, head: bool) -> Result<Response> {
+    let entry = state.db.get_dav_entry(key_id, path).await?.ok_or(AppError::NotFound)?;
+
+    let mut headers = HeaderMap::new();
+    let content_type = entry.mime_type.as_deref().unwrap_or("application/octet-stream");
+    if let Ok(value) = HeaderValue::from_str(content_type) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", entry.blake3_hash)) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&http_date(entry.created_at)) {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+
+    if head {
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(entry.size_bytes));
+        return Ok((headers, Body::empty()).into_response());
+    }
+    let (_, size, blob) = state.file_service.retrieve_file(&entry.file_id).await?;
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+    Ok((headers, Body::from_stream(blob)).into_response())
+}
+
+async fn put(state: &AppState, key_id: &str, path: &str, headers: &HeaderMap, body: Body) -> Result<Response> {
+    if path.is_empty() {
+        return Ok(method_not_allowed());
+    }
+    check_target(state, key_id, path).await?;
+
+    let limits = state.file_service.upload_limits(true);
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Some(length) = content_length.filter(|length| *length > limits.max_upload_bytes) {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Content-Length {} exceeds maximum upload size of {} bytes",
+            length, limits.max_upload_bytes
+        )));
+    }
+
+    let mut writer = state.file_service.begin_upload(content_length.unwrap_or(0), limits).await?;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AppError::BadRequest(format!("Failed to read file data: {}", e)))?;
+        writer.write_chunk(&chunk).await?;
+    }
+    let upload = writer.finish().await?;
+
+    let mime_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let (_, replaced) = store(state, key_id, path, upload, mime_type, limits).await?;
+    Ok(if replaced { StatusCode::NO_CONTENT } else { StatusCode::CREATED }.into_response())
+}
+
+async fn delete(state: &AppState, key_id: &str, path: &str) -> Result<Response> {
+    if path.is_empty() {
+        return Ok(method_not_allowed());
+    }
+    if state.db.get_dav_entry(key_id, path).await?.is_none() {
+        // Deleting a directory deletes what's in it
+        let entries = state.db.list_dav_entries(key_id, &format!("{}/", path)).await?;
+        if entries.is_empty() {
+            return Err(AppError::NotFound);
+        }
+        for entry in entries {
+            remove(state, key_id, &entry.path).await?;
+        }
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    remove(state, key_id, path).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Directories only exist through the files in them, so there is nothing to create
+async fn mkcol(state: &AppState, key_id: &str, path: &str) -> Result<Response> {
+    if path.is_empty() || state.db.get_dav_entry(key_id, path).await?.is_some() {
+        return Ok(method_not_allowed());
+    }
+    Ok(StatusCode::CREATED.into_response())
+}
+
+/// `Depth: 0` describes the resource itself, anything else adds its children
+async fn propfind(state: &AppState, key_id: &str, path: &str, headers: &HeaderMap) -> Result<Response> {
+    let depth_zero = headers.get("depth").and_then(|v| v.to_str().ok()) == Some("0");
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n");
+    if let Some(entry) = state.db.get_dav_entry(key_id, path).await? {
+        write_file_response(&mut xml, &entry);
+    } else {
+        let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+        let entries = state.db.list_dav_entries(key_id, &prefix).await?;
+        if entries.is_empty() && !path.is_empty() {
+            return Err(AppError::NotFound);
+        }
+
+        let modified = entries.iter().map(|entry| entry.created_at).max();
+        write_directory_response(&mut xml, &prefix, modified);
+        if !depth_zero {
+            // Direct children: files, and the first path segment of anything deeper
+            let mut directories: BTreeMap<&str, i64> = BTreeMap::new();
+            for entry in &entries {
+                match entry.path[prefix.len()..].split_once('/') {
+                    Some((directory, _)) => {
+                        let latest = directories.entry(directory).or_default();
+                        *latest = (*latest).max(entry.created_at);
+                    }
+                    None => write_file_response(&mut xml, entry),
+                }
+            }
+            for (directory, modified) in directories {
+                write_directory_response(&mut xml, &format!("{}{}/", prefix, directory), Some(modified));
+            }
+        }
+    }
+    xml.push_str("</D:multistatus>\n");
+
+    Ok((
+        StatusCode::MULTI_STATUS,
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        xml,
+    )
+        .into_response())
+}
+
+fn write_file_response(xml: &mut String, entry: &DavEntry) {
+    let name = entry.path.rsplit('/').next().unwrap_or_default();
+    let _ = writeln!(
+        xml,
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>\
+         <D:displayname>{}</D:displayname><D:resourcetype/>\
+         <D:getcontentlength>{}</D:getcontentlength><D:getcontenttype>{}</D:getcontenttype>\
+         <D:getetag>\"{}\"</D:getetag><D:getlastmodified>{}</D:getlastmodified>\
+         <D:creationdate>{}</D:creationdate>\
+         </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        href(&entry.path),
+        escape(name),
+        entry.size_bytes,
+        escape(entry.mime_type.as_deref().unwrap_or("application/octet-stream")),
+        entry.blake3_hash,
+        http_date(entry.created_at),
+        rfc3339(entry.created_at),
+    );
+}
+
+/// `prefix` is the directory's path with a trailing slash (empty for the root)
+fn write_directory_response(xml: &mut String, prefix: &str, modified: Option<i64>) {
+    let name = prefix.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    let modified = modified
+        .map(|modified| format!("<D:getlastmodified>{}</D:getlastmodified>", http_date(modified)))
+        .unwrap_or_default();
+    let _ = writeln!(
+        xml,
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>\
+         <D:displayname>{}</D:displayname><D:resourcetype><D:collection/></D:resourcetype>{}\
+         </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        href(prefix),
+        escape(name),
+        modified,
+    );
+}
+
+/// Check that a file can be stored at `path`: it mustn't be a directory or lie below a file
+pub(crate) async fn check_target(state: &AppState, key_id: &str, path: &str) -> Result<()> {
+    if is_directory(state, key_id, path).await? {
+        return Err(AppError::Conflict(format!("{} is a directory", path)));
+    }
+    for (end, _) in path.match_indices('/') {
+        if state.db.get_dav_entry(key_id, &path[..end]).await?.is_some() {
+            return Err(AppError::Conflict(format!("{} is a file", &path[..end])));
+        }
+    }
+    Ok(())
+}
+
+/// Commit a staged upload and point `path` at it, releasing the file it replaces
+///
+/// Shared with the S3 API, whose objects live in the same namespace. Returns the
+/// stored file and whether the path existed before.
+pub(crate) async fn store(
+    state: &AppState,
+    key_id: &str,
+    path: &str,
+    upload: StagedUpload,
+    mime_type: Option<String>,
+    limits: UploadLimits,
+) -> Result<(FileRecord, bool)> {
+    // The extension is only used for download filenames, so odd ones are dropped rather than rejected
+    let file_extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .and_then(|(_, extension)| crate::services::normalize_file_extension(Some(extension.to_string())).ok())
+        .flatten();
+
+    let file = state
+        .file_service
+        .store_namespace_file(upload, mime_type, file_extension, limits)
+        .await?;
+
+    let previous = state.db.put_dav_entry(key_id, path, &file.id).await?;
+    if let Some(previous) = previous.as_deref() {
+        release_file(state, previous).await;
+    }
+    tracing::debug!("Stored {} in the namespace of API key {} as file {}", path, key_id, file.id);
+    Ok((file, previous.is_some()))
+}
+
+/// Unlink `path` and release its file; returns whether it existed
+pub(crate) async fn remove(state: &AppState, key_id: &str, path: &str) -> Result<bool> {
+    match state.db.remove_dav_entry(key_id, path).await? {
+        Some(file_id) => {
+            release_file(state, &file_id).await;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Whether `path` is an (implied) directory of the namespace
+async fn is_directory(state: &AppState, key_id: &str, path: &str) -> Result<bool> {
+    Ok(!state.db.list_dav_entries(key_id, &format!("{}/", path)).await?.is_empty())
+}
+
+/// Delete a file that was replaced or unlinked, unless another path still points at it
+///
+/// Paths stored while namespace uploads were still deduplicated may share a file,
+/// and files the namespace didn't create are never deleted.
+async fn release_file(state: &AppState, file_id: &str) {
+    let result = async {
+        if state.db.dav_entry_exists(file_id).await? || !state.db.is_dav_file(file_id).await? {
+            return Ok(());
+        }
+        if let Some(file) = state.db.get_file(file_id).await? {
+            state.file_service.delete_file(&file.id, &file.deletion_token).await?;
+        }
+        Ok::<_, AppError>(())
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("Failed to delete file {} unlinked over WebDAV: {}", file_id, e);
+    }
+}
+
+fn options() -> Response {
+    (
+        [
+            (header::HeaderName::from_static("dav"), "1"),
+            (header::ALLOW, ALLOW),
+            // Windows' WebDAV redirector won't write without it
+            (header::HeaderName::from_static("ms-author-via"), "DAV"),
+        ],
+        StatusCode::OK,
+    )
+        .into_response()
+}
+
+fn method_not_allowed() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, ALLOW)]).into_response()
+}
+
+/// 401 asking for Basic credentials, so file managers prompt for the key
+fn unauthorized() -> Response {
+    let mut response = AppError::Unauthorized("API key required".to_string()).into_response();
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::from_static("Basic realm=\"dogbox WebDAV\", charset=\"UTF-8\""),
+    );
+    response
+}
+
+fn href(path: &str) -> String {
+    let mut href = String::from("/dav/");
+    for (i, segment) in path.split('/').enumerate() {
+        if i > 0 {
+            href.push('/');
+        }
+        href.extend(utf8_percent_encode(segment, HREF_SEGMENT));
+    }
+    href
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn timestamp(unix: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(unix, 0).unwrap_or_default()
+}
+
+pub(crate) fn http_date(unix: i64) -> String {
+    timestamp(unix).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn rfc3339(unix: i64) -> String {
+    timestamp(unix).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
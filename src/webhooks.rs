@@ -0,0 +1,144 @@
+//! Webhook notifications for file events
+//!
+//! Events are POSTed as JSON to WEBHOOK_URL in the background, so a slow or
+//! failing receiver never holds up the request that caused the event. With
+//! WEBHOOK_SECRET set, each delivery carries `X-Dogbox-Signature: sha256=<hex>`,
+//! an HMAC-SHA256 of the raw body. Payloads only contain what the server already
+//! knows: IDs, sizes and timestamps, never keys or client IPs.
+
+use crate::config::SharedConfig;
+use crate::constants::{WEBHOOK_MAX_ATTEMPTS, WEBHOOK_TIMEOUT_SECS};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::Duration;
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    /// Upload committed (files, posts and completed resumable uploads)
+    Uploaded,
+    /// Deleted with its deletion token
+    Deleted,
+    /// Deleted by the admin
+    AdminDeleted,
+    /// Removed by the cleanup task after expiring
+    Expired,
+    /// Removed to make room under STORAGE_FULL_POLICY=evict
+    Evicted,
+    /// Uploader's expiry warning (sent to their own callback URL)
+    Expiring,
+}
+
+impl WebhookEventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Uploaded => "uploaded",
+            Self::Deleted => "deleted",
+            Self::AdminDeleted => "admin_deleted",
+            Self::Expired => "expired",
+            Self::Evicted => "evicted",
+            Self::Expiring => "expiring",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    pub event: WebhookEventKind,
+    pub file_id: String,
+    pub post_type: String,
+    pub size_bytes: i64,
+    /// Unix timestamp the file expires at (expiry warnings only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Unix timestamp of the event
+    pub timestamp: i64,
+}
+
+impl WebhookEvent {
+    pub fn new(event: WebhookEventKind, file_id: &str, post_type: &str, size_bytes: i64) -> Self {
+        Self {
+            event,
+            file_id: file_id.to_string(),
+            post_type: post_type.to_string(),
+            size_bytes,
+            expires_at: None,
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+pub struct Webhooks {
+    config: SharedConfig,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn new(config: SharedConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .user_agent(concat!("dogbox/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("webhook HTTP client");
+        Self { config, client }
+    }
+
+    /// Queue an event for delivery (no-op when WEBHOOK_URL is unset)
+    pub fn send(&self, event: WebhookEvent) {
+        let config = self.config.load();
+        let Some(url) = config.webhook_url.clone() else {
+            return;
+        };
+        self.send_to(url, config.webhook_secret.clone(), event);
+    }
+
+    /// Queue an event for delivery to a specific URL
+    pub fn send_to(&self, url: String, secret: Option<String>, event: WebhookEvent) {
+        let client = self.client.clone();
+
+        tokio::spawn(async move {
+            let body = match serde_json::to_vec(&event) {
+                Ok(body) => body,
+                Err(e) => {
+                    tracing::error!("Failed to serialize webhook event: {}", e);
+                    return;
+                }
+            };
+            let signature = secret.map(|secret| sign(secret.as_bytes(), &body));
+
+            for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+                let mut request = client
+                    .post(&url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header("X-Dogbox-Event", event.event.as_str())
+                    .body(body.clone());
+                if let Some(signature) = &signature {
+                    request = request.header("X-Dogbox-Signature", signature);
+                }
+
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) => return,
+                    Err(e) if attempt < WEBHOOK_MAX_ATTEMPTS => {
+                        tracing::debug!("Webhook delivery attempt {} failed: {}", attempt, e);
+                        tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "🪝 Dropped {} webhook for {} after {} attempts: {}",
+                            event.event.as_str(), event.file_id, attempt, e
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// `sha256=<hex>` HMAC of the request body
+fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
@@ -0,0 +1,75 @@
+//! Crawler, security contact and other `/.well-known/` files
+//!
+//! `/robots.txt` and `/.well-known/security.txt` are generated from the config
+//! (ROBOTS_TXT / ROBOTS_DISALLOW and SECURITY_CONTACT / SECURITY_POLICY), so
+//! operators don't have to ship their own static files. Anything else under
+//! `/.well-known/` (assetlinks.json, apple-app-site-association, ...) is served
+//! from WELL_KNOWN_DIR when it is set.
+
+use crate::config::Config;
+use crate::constants::SECURITY_TXT_EXPIRY_DAYS;
+use crate::state::AppState;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, SecondsFormat, Utc};
+use tower::ServiceExt;
+use tower_http::services::ServeDir;
+
+const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+
+pub async fn robots_txt(State(state): State<AppState>) -> Response {
+    let robots = state.config.load().robots_txt.clone();
+    ([(header::CONTENT_TYPE, TEXT_PLAIN)], robots).into_response()
+}
+
+/// `/.well-known/*`: the generated security.txt, then files from WELL_KNOWN_DIR
+pub async fn serve(State(state): State<AppState>, request: Request) -> Response {
+    let config = state.config.load_full();
+    let path = request.uri().path().strip_prefix("/.well-known").unwrap_or_default().to_string();
+
+    if path == "/security.txt" {
+        if let Some(body) = security_txt(&config) {
+            return ([(header::CONTENT_TYPE, TEXT_PLAIN)], body).into_response();
+        }
+    }
+
+    let Some(dir) = &config.well_known_dir else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // ServeDir resolves the (still percent-encoded) path itself and refuses `..`
+    let Ok(uri) = path.parse::<Uri>() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let (mut parts, body) = request.into_parts();
+    parts.uri = uri;
+    match ServeDir::new(dir).oneshot(Request::from_parts(parts, body)).await {
+        Ok(response) => response.map(Body::new),
+        Err(never) => match never {},
+    }
+}
+
+/// RFC 9116 security.txt, when a contact is configured
+fn security_txt(config: &Config) -> Option<String> {
+    let security = config.security_txt.as_ref()?;
+
+    let mut body = String::new();
+    for contact in &security.contacts {
+        body.push_str(&format!("Contact: {}\n", contact));
+    }
+    // Generated from the live config, so it is as current as the config itself
+    let expires = Utc::now() + Duration::days(SECURITY_TXT_EXPIRY_DAYS);
+    body.push_str(&format!("Expires: {}\n", expires.to_rfc3339_opts(SecondsFormat::Secs, true)));
+    if let Some(policy) = &security.policy {
+        body.push_str(&format!("Policy: {}\n", policy));
+    }
+    if let Some(public_url) = &config.public_url {
+        body.push_str(&format!("Canonical: {}/.well-known/security.txt\n", public_url));
+    }
+    body.push_str("Preferred-Languages: en\n");
+
+    Some(body)
+}
@@ -214,7 +214,7 @@
                 infoFooter.style.cssText = 'margin-top: 20px; padding-top: 15px; border-top: 2px solid #e5e7eb; color: #666; font-size: 0.9em;';
                 infoFooter.innerHTML = `
                     <strong>Post Info:</strong><br/>
-                    📊 Views: ${postData.view_count}<br/>
+                    ${postData.view_count !== undefined ? `📊 Views: ${postData.view_count}<br/>` : ''}
                     📅 Created: ${new Date(postData.uploaded_at).toLocaleString()}<br/>
                     ${postData.is_permanent ? '♾️ Permanent (never expires)' : `⏱️ Expires: ${new Date(postData.expires_at).toLocaleString()}`}
                 `;
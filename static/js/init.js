@@ -7,19 +7,17 @@
 async function initializePage() {
     try {
         // Load navbar
-        const response = await fetch("/static/navbar.html");
+        const response = await fetch("/navbar");
         const html = await response.text();
         const navbarPlaceholder = document.getElementById("navbar-placeholder");
 
         if (navbarPlaceholder) {
             navbarPlaceholder.innerHTML = html;
 
-            // Set logo and site name from config
+            // Set logo from config (the site name is rendered by the server)
             if (window.DogboxConfig) {
                 const logoEl = document.getElementById('navbar-logo');
-                const siteNameEl = document.getElementById('navbar-sitename');
                 if (logoEl) logoEl.textContent = DogboxConfig.logo;
-                if (siteNameEl) siteNameEl.textContent = DogboxConfig.siteName;
             }
 
             // Initialize mobile menu toggle (since scripts in innerHTML don't execute)
@@ -61,7 +59,98 @@ function initializeNavbarToggle() {
     }
 }
 
+/**
+ * Private instances (PRIVATE_MODE) reject API requests without an access key.
+ * Attach the key remembered in localStorage to same-origin API calls, and ask
+ * for one when the server says it's missing or invalid.
+ */
+const ACCESS_KEY_STORAGE = 'dogbox_access_key';
+
+function installAccessKeyFetch() {
+    const originalFetch = window.fetch.bind(window);
+
+    window.fetch = async function(input, init = {}) {
+        const url = new URL(typeof input === 'string' ? input : input.url, window.location.href);
+        if (url.origin !== window.location.origin || !url.pathname.startsWith('/api/')) {
+            return originalFetch(input, init);
+        }
+
+        const send = (key) => {
+            const headers = new Headers(init.headers || {});
+            if (key) headers.set('X-Access-Key', key);
+            return originalFetch(input, { ...init, headers });
+        };
+
+        // Upload invites (/?upload_token=...) authorize the upload itself
+        const uploadToken = new URLSearchParams(window.location.search).get('upload_token');
+        if (uploadToken && url.pathname === '/api/upload') {
+            const headers = new Headers(init.headers || {});
+            headers.set('X-Upload-Token', uploadToken);
+            init = { ...init, headers };
+        }
+
+        // Captcha widget on upload pages (CAPTCHA_PROVIDER): each token is good for one upload
+        const captchaToken = document.querySelector('[name="cf-turnstile-response"], [name="h-captcha-response"]')?.value;
+        const isUpload = ['POST', 'PUT'].includes((init.method || 'GET').toUpperCase());
+        if (captchaToken && isUpload) {
+            const headers = new Headers(init.headers || {});
+            headers.set('X-Captcha-Token', captchaToken);
+            init = { ...init, headers };
+            window.turnstile?.reset();
+            window.hcaptcha?.reset();
+        }
+
+        let response = await send(localStorage.getItem(ACCESS_KEY_STORAGE));
+        // Streamed bodies can't be replayed, so only retry simple requests
+        if (response.status === 401 && !(init.body instanceof ReadableStream)) {
+            const key = window.prompt('This dogbox instance is private. Enter your access key:');
+            if (key) {
+                localStorage.setItem(ACCESS_KEY_STORAGE, key.trim());
+                response = await send(key.trim());
+            }
+        }
+        // Uploads from Tor exits may need a proof-of-work (TOR_EXIT_POLICY=pow)
+        if (response.status === 428 && !(init.body instanceof ReadableStream)) {
+            const solution = await solveProofOfWork();
+            if (solution) {
+                const headers = new Headers(init.headers || {});
+                headers.set('X-Pow-Solution', solution);
+                init = { ...init, headers };
+                response = await send(localStorage.getItem(ACCESS_KEY_STORAGE));
+            }
+        }
+        return response;
+    };
+}
+
+/**
+ * Solve a challenge from /api/pow/challenge: find a counter such that
+ * SHA-256("<challenge>:<counter>") starts with `difficulty` zero bits.
+ */
+async function solveProofOfWork() {
+    const response = await fetch('/api/pow/challenge');
+    if (!response.ok) return null;
+    const { challenge, difficulty } = await response.json();
+
+    const encoder = new TextEncoder();
+    for (let counter = 0; ; counter++) {
+        const solution = `${challenge}:${counter}`;
+        const hash = new Uint8Array(await crypto.subtle.digest('SHA-256', encoder.encode(solution)));
+        if (leadingZeroBits(hash) >= difficulty) return solution;
+    }
+}
+
+function leadingZeroBits(hash) {
+    let bits = 0;
+    for (const byte of hash) {
+        if (byte !== 0) return bits + Math.clz32(byte) - 24;
+        bits += 8;
+    }
+    return bits;
+}
+
 // Export for use in browser
 if (typeof window !== 'undefined') {
+    installAccessKeyFetch();
     window.initializePage = initializePage;
 }
@@ -73,6 +73,84 @@
                 ctx.stroke();
             }
 
+            function renderHistoryChart(snapshots) {
+                const canvas = document.getElementById("historyCanvas");
+                if (!canvas) return;
+
+                const ctx = canvas.getContext("2d");
+                ctx.clearRect(0, 0, canvas.width, canvas.height);
+
+                if (snapshots.length < 2) {
+                    ctx.fillStyle = "#666";
+                    ctx.font = "16px sans-serif";
+                    ctx.textAlign = "center";
+                    ctx.fillText("Not enough history yet (recorded hourly)", canvas.width / 2, canvas.height / 2);
+                    return;
+                }
+
+                const padding = 50;
+                const chartWidth = canvas.width - padding * 2;
+                const chartHeight = canvas.height - padding * 2;
+                const first = snapshots[0].created_at;
+                const span = Math.max(snapshots[snapshots.length - 1].created_at - first, 1);
+
+                // Each series is scaled to its own maximum so both fit on one chart
+                const series = [
+                    { key: "total_uploads", label: "Uploads", color: "#667eea" },
+                    { key: "storage_bytes", label: "Storage", color: "#764ba2" },
+                ];
+                series.forEach((s, i) => {
+                    const max = Math.max(...snapshots.map(snap => snap[s.key]), 1);
+                    ctx.strokeStyle = s.color;
+                    ctx.lineWidth = 2;
+                    ctx.beginPath();
+                    snapshots.forEach((snap, j) => {
+                        const x = padding + ((snap.created_at - first) / span) * chartWidth;
+                        const y = canvas.height - padding - (snap[s.key] / max) * chartHeight;
+                        if (j === 0) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+                    });
+                    ctx.stroke();
+
+                    // Legend
+                    ctx.fillStyle = s.color;
+                    ctx.font = "12px sans-serif";
+                    ctx.textAlign = "left";
+                    ctx.fillText(s.label, padding + i * 90, padding - 15);
+                });
+
+                // Axes
+                ctx.strokeStyle = "#ccc";
+                ctx.lineWidth = 1;
+                ctx.beginPath();
+                ctx.moveTo(padding, padding);
+                ctx.lineTo(padding, canvas.height - padding);
+                ctx.lineTo(canvas.width - padding, canvas.height - padding);
+                ctx.stroke();
+
+                // Date range under the X-axis
+                ctx.fillStyle = "#666";
+                ctx.font = "11px sans-serif";
+                ctx.textAlign = "left";
+                ctx.fillText(new Date(first * 1000).toLocaleDateString(), padding, canvas.height - padding + 18);
+                ctx.textAlign = "right";
+                ctx.fillText(
+                    new Date(snapshots[snapshots.length - 1].created_at * 1000).toLocaleDateString(),
+                    canvas.width - padding,
+                    canvas.height - padding + 18
+                );
+            }
+
+            async function loadHistory() {
+                try {
+                    const response = await fetch("/api/stats/history?days=30");
+                    if (!response.ok) throw new Error("Failed to fetch");
+                    const data = await response.json();
+                    renderHistoryChart(data.snapshots);
+                } catch (err) {
+                    console.error("Error loading stats history:", err);
+                }
+            }
+
             async function loadStats() {
                 const loading = document.getElementById("loading");
                 const error = document.getElementById("error");
@@ -89,25 +167,34 @@
                     const data = await response.json();
 
                     // Update values
-                    document.getElementById("total-uploads").textContent = data.total_uploads.toLocaleString();
-                    document.getElementById("total-posts").textContent = data.total_posts.toLocaleString();
-                    document.getElementById("total-files").textContent = data.total_files.toLocaleString();
-                    document.getElementById("total-dogpastes").textContent = data.total_dogpastes.toLocaleString();
-                    document.getElementById("dogpaste-views").textContent = data.dogpaste_views.toLocaleString();
-                    document.getElementById("permanent-count").textContent = data.permanent_count.toLocaleString();
-                    document.getElementById("temporary-count").textContent = data.temporary_count.toLocaleString();
-                    document.getElementById("total-views").textContent = data.total_views.toLocaleString();
+                    const { uploads, posts, dogpastes, disk } = data;
+                    document.getElementById("total-uploads").textContent = uploads.total.toLocaleString();
+                    document.getElementById("total-posts").textContent = posts.count.toLocaleString();
+                    document.getElementById("total-files").textContent = uploads.files.toLocaleString();
+                    document.getElementById("total-dogpastes").textContent = dogpastes.count.toLocaleString();
+                    // View totals are left out when the instance disables analytics
+                    for (const [id, value] of [["dogpaste-views", dogpastes.views], ["total-views", uploads.views]]) {
+                        const element = document.getElementById(id);
+                        if (value === undefined) {
+                            element.closest(".stat-card").style.display = "none";
+                        } else {
+                            element.textContent = value.toLocaleString();
+                        }
+                    }
+                    document.getElementById("permanent-count").textContent = uploads.permanent.toLocaleString();
+                    document.getElementById("temporary-count").textContent = uploads.temporary.toLocaleString();
                     document.getElementById("storage-mb").textContent =
-                        data.storage_mb.toFixed(2) + " MB";
+                        (uploads.storage_mb + dogpastes.storage_mb).toFixed(2) + " MB";
                     document.getElementById("disk-total").textContent =
-                        data.disk_total_gb.toFixed(1) + " GB";
+                        disk.total_gb.toFixed(1) + " GB";
                     document.getElementById("disk-used").textContent =
-                        data.disk_used_gb.toFixed(1) + " GB";
+                        disk.used_gb.toFixed(1) + " GB";
                     document.getElementById("disk-free").textContent =
-                        data.disk_free_gb.toFixed(1) + " GB";
+                        disk.free_gb.toFixed(1) + " GB";
 
                     // Render file extension chart
-                    renderExtensionChart(data.file_extensions);
+                    renderExtensionChart(uploads.file_extensions);
+                    loadHistory();
 
                     // Show stats
                     loading.style.display = "none";